@@ -3,9 +3,10 @@ use std::time::{Duration, Instant};
 use erupt::vk;
 use raw_window_handle::HasRawWindowHandle;
 use reify::{
-    graph::{ImageInfo, ImageSize, RenderGraphBuilder},
+    graph::{ImageInfo, ImageSize, RenderGraph, RenderGraphBuilder},
     pass::{ClearColorValue, RenderPass},
-    Instance, MemoryConfig,
+    DeviceLimits, DeviceSelector, Display, DisplayConfig, GraphicsPipelineInfo, Instance,
+    MemoryConfig,
 };
 use shaderc::{Compiler, ShaderKind};
 use winit::{
@@ -32,7 +33,10 @@ impl RenderPass for TrianglePass {
     }
 }
 
-fn build_graph() {
+fn build_graph(display: &Display, limits: &DeviceLimits) -> RenderGraph {
+    // The final image is presented directly, so it must match the swapchain.
+    let format = display.info().surface_format.format;
+
     let mut graph = RenderGraphBuilder::new();
 
     let mut pass_a = graph.add_render_pass("pass A", TrianglePass);
@@ -41,7 +45,12 @@ fn build_graph() {
             "pass A color",
             ImageInfo {
                 size: ImageSize::SAME_AS_SWAPCHAIN,
-                format: vk::Format::B8G8R8A8_SRGB,
+                format,
+                view_formats: Vec::new(),
+                samples: vk::SampleCountFlagBits::_1,
+                mip_levels: 1,
+                array_layers: 1,
+                view_mask: 0,
             },
             None,
         )
@@ -55,7 +64,12 @@ fn build_graph() {
             "pass B color",
             ImageInfo {
                 size: ImageSize::SAME_AS_SWAPCHAIN,
-                format: vk::Format::B8G8R8A8_SRGB,
+                format,
+                view_formats: Vec::new(),
+                samples: vk::SampleCountFlagBits::_1,
+                mip_levels: 1,
+                array_layers: 1,
+                view_mask: 0,
             },
             None,
         )
@@ -69,7 +83,12 @@ fn build_graph() {
             "pass C color",
             ImageInfo {
                 size: ImageSize::SAME_AS_SWAPCHAIN,
-                format: vk::Format::B8G8R8A8_SRGB,
+                format,
+                view_formats: Vec::new(),
+                samples: vk::SampleCountFlagBits::_1,
+                mip_levels: 1,
+                array_layers: 1,
+                view_mask: 0,
             },
             Some(a_out),
         )
@@ -77,9 +96,7 @@ fn build_graph() {
     pass_c.finish();
 
     graph.set_final_image(c_out).unwrap();
-    // graph.build().unwrap();
-
-    todo!();
+    graph.build(display.info(), limits).unwrap()
 }
 
 pub fn main() {
@@ -91,17 +108,14 @@ pub fn main() {
     let instance = Instance::create("reify", 0);
     let _debug_messenger = instance.create_debug_messenger();
     let surface = instance.create_surface(window.raw_window_handle());
-    let phys_device = match instance
-        .enumerate_physical_devices(
-            &surface,
-            MemoryConfig {
-                min_host_memory: 128 * 1024 * 1024,
-                min_device_memory: 128 * 1024 * 1024,
-            },
-        )
-        .into_iter()
-        .find(|_phys| true)
-    {
+    let phys_device = match instance.select_physical_device(
+        Some(&surface),
+        MemoryConfig {
+            min_host_memory: 128 * 1024 * 1024,
+            min_device_memory: 128 * 1024 * 1024,
+        },
+        &DeviceSelector::new().require_presentation(),
+    ) {
         Some(phys) => phys,
         None => panic!("no suitable device"),
     };
@@ -115,8 +129,10 @@ pub fn main() {
                 width: phys_size.width,
                 height: phys_size.height,
             },
+            DisplayConfig::default(),
         )
-    };
+    }
+    .expect("failed to create display");
 
     let mem_types = phys_device.memory_types();
     println!("Memory types: {:#?}", mem_types);
@@ -133,8 +149,15 @@ pub fn main() {
         .compile_into_spirv(frag_glsl, ShaderKind::Fragment, "frag.glsl", "main", None)
         .unwrap();
 
-    let pipeline =
-        unsafe { device.create_pipeline(vert_spv.as_binary(), frag_spv.as_binary(), &display) };
+    let pipeline = unsafe {
+        device.create_pipeline(
+            vert_spv.as_binary(),
+            frag_spv.as_binary(),
+            &GraphicsPipelineInfo::new(),
+            &display,
+        )
+    }
+    .expect("failed to create pipeline");
 
     {
         let pipeline_read = pipeline.read_inner();
@@ -151,7 +174,12 @@ pub fn main() {
             Event::MainEventsCleared => {
                 if last_frame.elapsed() > Duration::from_micros(16666) {
                     last_frame = Instant::now();
-                    display.draw();
+                    match display.draw() {
+                        Ok(()) => (),
+                        // A resize event will follow.
+                        Err(reify::Error::SwapchainOutOfDate) => return,
+                        Err(e) => panic!("failed to draw: {}", e),
+                    }
                     log::debug!("frame {}: draw complete.", frames);
                     frames += 1;
                 } else {
@@ -164,6 +192,21 @@ pub fn main() {
                 event,
             } => match event {
                 WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
+                WindowEvent::Resized(size) => {
+                    display
+                        .resize(vk::Extent2D {
+                            width: size.width,
+                            height: size.height,
+                        })
+                        .expect("failed to resize display");
+
+                    let pipeline_read = pipeline.read_inner();
+                    display.rebuild_framebuffers(pipeline_read.render_pass());
+                    display.record_command_buffers(
+                        pipeline_read.render_pass(),
+                        pipeline_read.pipeline(),
+                    );
+                }
                 _ => (),
             },
             _ => (),
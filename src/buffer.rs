@@ -0,0 +1,829 @@
+//! Buffers and buffer memory.
+
+use std::{fmt, marker::PhantomData, mem, ptr::NonNull};
+
+use erupt::vk;
+
+use crate::{
+    mem::AllocationKind,
+    util::{self, ErrorOnDrop},
+    vks::{self, VkObject},
+    Device, Error, IndexType,
+};
+
+/// The minimum capacity, in elements, of a newly created buffer.
+const MIN_CAPACITY: usize = 64;
+
+#[derive(Default)]
+pub struct DroppedBuffer;
+
+impl fmt::Display for DroppedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Buffer must be manually destroyed with .destroy_with()")
+    }
+}
+
+#[derive(Default)]
+pub struct DroppedTypedBuffer;
+
+impl fmt::Display for DroppedTypedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TypedBuffer must be manually destroyed with .destroy_with()")
+    }
+}
+
+#[derive(Default)]
+pub struct DroppedStreamBuffer;
+
+impl fmt::Display for DroppedStreamBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StreamBuffer must be manually destroyed with .destroy_with()")
+    }
+}
+
+#[derive(Default)]
+pub struct DroppedFrameArrayBuffer;
+
+impl fmt::Display for DroppedFrameArrayBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FrameArrayBuffer must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// The kind of memory backing a buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemoryLocation {
+    /// Device-local memory, which is fastest for the GPU to access. Contents
+    /// must be written with transfer commands, e.g. copied from a
+    /// host-visible buffer.
+    Device,
+    /// Host-visible memory, which is persistently mapped and written directly
+    /// by the host.
+    Host,
+}
+
+/// Describes a buffer to create with [`Device::create_buffer`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferCreateInfo {
+    /// The size of the buffer in bytes.
+    pub size: vk::DeviceSize,
+    pub usage: vk::BufferUsageFlags,
+    pub location: MemoryLocation,
+    /// A name for the buffer, used in diagnostics.
+    pub label: Option<String>,
+}
+
+impl BufferCreateInfo {
+    pub fn new(
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        location: MemoryLocation,
+    ) -> BufferCreateInfo {
+        BufferCreateInfo {
+            size,
+            usage,
+            location,
+            label: None,
+        }
+    }
+
+    pub fn label<S: Into<String>>(mut self, label: S) -> BufferCreateInfo {
+        self.label = Some(label.into());
+        self
+    }
+}
+
+/// A buffer with its own memory allocation.
+///
+/// A buffer's lifecycle is as follows:
+/// 1. Create the buffer with [`Device::create_buffer`].
+/// 2. Upload its contents. Host-visible buffers are written directly with
+///    [`Buffer::write`]; device-local buffers are filled by recording a copy
+///    from a host-visible buffer.
+/// 3. Bind [`Buffer::raw`] in command buffers, e.g. with
+///    `PipelineInner::bind_vertex_buffers`.
+/// 4. Once no pending commands refer to it, destroy the buffer with
+///    [`Buffer::destroy_with`].
+pub struct Buffer {
+    bomb: ErrorOnDrop<DroppedBuffer>,
+
+    buffer: vks::Buffer,
+    memory: vks::DeviceMemory,
+    memory_type: u32,
+    /// The size of `memory` in bytes, which may exceed `size`.
+    allocation_size: vk::DeviceSize,
+    /// The mapped memory, if the buffer is host-visible.
+    ptr: Option<NonNull<u8>>,
+    /// The capacity of the buffer in bytes.
+    size: vk::DeviceSize,
+    label: Option<String>,
+}
+
+impl Buffer {
+    pub(crate) fn create(device: &Device, info: &BufferCreateInfo) -> vks::VkResult<Buffer> {
+        let device_read = device.inner.read();
+        let memory_types = device_read.phys_device.memory_types();
+
+        let create_info = vk::BufferCreateInfoBuilder::new()
+            .size(info.size)
+            .usage(info.usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let mut buffer = unsafe { device_read.raw.create_buffer(&create_info)? };
+
+        let requirements = unsafe { device_read.raw.get_buffer_memory_requirements(&buffer) };
+        let type_index = match info.location {
+            MemoryLocation::Device => memory_types.device_type_index(),
+            MemoryLocation::Host => memory_types.host_type_index(),
+        };
+        if requirements.memory_type_bits & (1 << type_index) == 0 {
+            unsafe { device_read.raw.destroy_buffer(buffer) };
+            return Err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+        }
+
+        let allocate_info = vk::MemoryAllocateInfoBuilder::new()
+            .allocation_size(requirements.size)
+            .memory_type_index(type_index);
+
+        let result =
+            unsafe { device_read.raw.allocate_memory(&allocate_info) }.and_then(|mut memory| {
+                let bound = unsafe { device_read.raw.bind_buffer_memory(&mut buffer, &memory, 0) };
+                let mapped = bound.and_then(|()| match info.location {
+                    MemoryLocation::Device => Ok(None),
+                    MemoryLocation::Host => unsafe {
+                        device_read
+                            .raw
+                            .map_memory(&mut memory, 0, vk::WHOLE_SIZE)
+                            .map(Some)
+                    },
+                });
+
+                match mapped {
+                    Ok(ptr) => Ok((memory, ptr)),
+                    Err(e) => {
+                        unsafe { device_read.raw.free_memory(memory) };
+                        Err(e)
+                    }
+                }
+            });
+
+        match result {
+            Ok((memory, ptr)) => {
+                device_read.memory_stats.record(
+                    AllocationKind::Buffer,
+                    type_index,
+                    requirements.size,
+                );
+
+                Ok(Buffer {
+                    bomb: ErrorOnDrop::new(DroppedBuffer),
+                    buffer,
+                    memory,
+                    memory_type: type_index,
+                    allocation_size: requirements.size,
+                    ptr: ptr.map(|ptr| {
+                        NonNull::new(ptr as *mut u8).expect("vkMapMemory returned null")
+                    }),
+                    size: info.size,
+                    label: info.label.clone(),
+                })
+            }
+            Err(e) => {
+                unsafe { device_read.raw.destroy_buffer(buffer) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the underlying buffer handle.
+    pub fn raw(&self) -> &vks::Buffer {
+        &self.buffer
+    }
+
+    /// Returns the size of the buffer in bytes.
+    pub fn size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn location(&self) -> MemoryLocation {
+        match self.ptr {
+            Some(_) => MemoryLocation::Host,
+            None => MemoryLocation::Device,
+        }
+    }
+
+    /// Returns a pointer to the start of the buffer's mapped memory, or
+    /// `None` if the buffer is not host-visible.
+    ///
+    /// Writes through the pointer to non-coherent memory must be flushed
+    /// before they are visible to the device.
+    pub fn mapped_ptr(&self) -> Option<NonNull<u8>> {
+        self.ptr
+    }
+
+    /// Writes `data` to the buffer at byte offset `offset`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the buffer was created with.
+    /// - No pending command may access the written range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is not host-visible, or if the written range
+    /// extends past the end of the buffer.
+    pub unsafe fn write<T: bytemuck::Pod>(
+        &mut self,
+        device: &Device,
+        offset: vk::DeviceSize,
+        data: &[T],
+    ) -> vks::VkResult<()> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let ptr = self.ptr.expect("buffer is not host-visible");
+        assert!(
+            offset
+                .checked_add(bytes.len() as vk::DeviceSize)
+                .map_or(false, |end| end <= self.size),
+            "write of {} bytes at offset {} overflows buffer of size {}",
+            bytes.len(),
+            offset,
+            self.size
+        );
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                ptr.as_ptr().add(offset as usize),
+                bytes.len(),
+            );
+        }
+
+        let device_read = device.inner.read();
+        if !device_read.phys_device.memory_types().host_is_coherent() && !bytes.is_empty() {
+            // Flushing the whole mapping avoids rounding to the atom size.
+            unsafe {
+                device_read
+                    .raw
+                    .flush_mapped_memory_range(&self.memory, 0, vk::WHOLE_SIZE)?
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Reads `len` bytes from the buffer at byte offset `offset`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the buffer was created with.
+    /// - All device writes to the read range must have completed and been made
+    ///   available to the host.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is not host-visible, or if the read range extends
+    /// past the end of the buffer.
+    pub unsafe fn read(
+        &self,
+        device: &Device,
+        offset: vk::DeviceSize,
+        len: usize,
+    ) -> vks::VkResult<Vec<u8>> {
+        let ptr = self.ptr.expect("buffer is not host-visible");
+        assert!(
+            offset
+                .checked_add(len as vk::DeviceSize)
+                .map_or(false, |end| end <= self.size),
+            "read of {} bytes at offset {} overflows buffer of size {}",
+            len,
+            offset,
+            self.size
+        );
+
+        let device_read = device.inner.read();
+        if !device_read.phys_device.memory_types().host_is_coherent() && len != 0 {
+            // Invalidating the whole mapping avoids rounding to the atom size.
+            unsafe {
+                device_read
+                    .raw
+                    .invalidate_mapped_memory_range(&self.memory, 0, vk::WHOLE_SIZE)?
+            };
+        }
+
+        let mut data = vec![0; len];
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                ptr.as_ptr().add(offset as usize),
+                data.as_mut_ptr(),
+                len,
+            );
+        }
+
+        Ok(data)
+    }
+
+    /// Destroys the buffer and frees its memory.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the buffer was created with.
+    /// - All submitted commands that refer to the buffer must have completed
+    ///   execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+
+        let device_read = device.inner.read();
+        unsafe {
+            device_read.raw.destroy_buffer(self.buffer);
+            // Freeing memory implicitly unmaps it.
+            device_read.raw.free_memory(self.memory);
+        }
+        device_read.memory_stats.release(
+            AllocationKind::Buffer,
+            self.memory_type,
+            self.allocation_size,
+        );
+    }
+}
+
+/// An element type which may be used as a vertex index.
+pub trait IndexElement: bytemuck::Pod {
+    const INDEX_TYPE: IndexType;
+}
+
+impl IndexElement for u16 {
+    const INDEX_TYPE: IndexType = IndexType::U16;
+}
+
+impl IndexElement for u32 {
+    const INDEX_TYPE: IndexType = IndexType::U32;
+}
+
+/// A buffer holding a fixed-length array of `T`.
+///
+/// The buffer tracks its element count and stride, so offsets and sizes need
+/// not be computed by hand. Uniform buffers pad each element to the device's
+/// minimum uniform buffer offset alignment, so any element may be bound on
+/// its own.
+pub struct TypedBuffer<T: bytemuck::Pod> {
+    bomb: ErrorOnDrop<DroppedTypedBuffer>,
+
+    buffer: Option<Buffer>,
+    len: usize,
+    stride: vk::DeviceSize,
+
+    phantom: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> TypedBuffer<T> {
+    /// Creates a buffer with room for `len` elements.
+    pub fn create(
+        device: &Device,
+        len: usize,
+        usage: vk::BufferUsageFlags,
+        location: MemoryLocation,
+    ) -> Result<TypedBuffer<T>, Error> {
+        let elem_size = mem::size_of::<T>() as vk::DeviceSize;
+        let stride = if usage.contains(vk::BufferUsageFlags::UNIFORM_BUFFER) {
+            let align = device
+                .physical_device()
+                .properties()
+                .limits
+                .min_uniform_buffer_offset_alignment
+                .max(1);
+            // An overflowing size is rejected by `create_buffer`.
+            util::align_up(elem_size, align).unwrap_or(u64::MAX)
+        } else {
+            elem_size
+        };
+
+        // Zero-sized buffers are invalid.
+        let size = stride
+            .checked_mul(len as vk::DeviceSize)
+            .unwrap_or(u64::MAX)
+            .max(1);
+        let buffer = device.create_buffer(&BufferCreateInfo::new(size, usage, location))?;
+
+        Ok(TypedBuffer {
+            bomb: ErrorOnDrop::new(DroppedTypedBuffer),
+            buffer: Some(buffer),
+            len,
+            stride,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Creates a host-visible buffer containing `data`.
+    pub fn from_slice(
+        device: &Device,
+        usage: vk::BufferUsageFlags,
+        data: &[T],
+    ) -> Result<TypedBuffer<T>, Error> {
+        let mut buffer = TypedBuffer::create(device, data.len(), usage, MemoryLocation::Host)?;
+
+        // Safety: the buffer is new, so no commands refer to it.
+        if let Err(e) = unsafe { buffer.write(device, 0, data) } {
+            unsafe { buffer.destroy_with(device) };
+            return Err(e.into());
+        }
+
+        Ok(buffer)
+    }
+
+    /// Returns the underlying buffer.
+    pub fn buffer(&self) -> &Buffer {
+        self.buffer.as_ref().unwrap()
+    }
+
+    /// Returns the number of elements in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the distance in bytes between consecutive elements.
+    pub fn stride(&self) -> vk::DeviceSize {
+        self.stride
+    }
+
+    /// Returns the byte offset of the element at `index`.
+    pub fn offset_of(&self, index: usize) -> vk::DeviceSize {
+        self.stride * index as vk::DeviceSize
+    }
+
+    /// Returns a descriptor covering the single element at `index`, e.g. for
+    /// binding one uniform block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn descriptor_info(&self, index: usize) -> vk::DescriptorBufferInfo {
+        assert!(index < self.len, "index {} out of bounds", index);
+
+        vk::DescriptorBufferInfo {
+            // Safety: the handle does not outlive the buffer.
+            buffer: unsafe { *self.buffer().raw().handle() },
+            offset: self.offset_of(index),
+            range: mem::size_of::<T>() as vk::DeviceSize,
+        }
+    }
+
+    /// Writes `data` to the elements starting at `first`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the buffer was created with.
+    /// - No pending command may access the written elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer is not host-visible, or if the written elements
+    /// extend past the end of the buffer.
+    pub unsafe fn write(&mut self, device: &Device, first: usize, data: &[T]) -> vks::VkResult<()> {
+        assert!(
+            first + data.len() <= self.len,
+            "write of {} elements at index {} overflows buffer of length {}",
+            data.len(),
+            first,
+            self.len
+        );
+
+        let stride = self.stride;
+        let offset = self.offset_of(first);
+        let buffer = self.buffer.as_mut().unwrap();
+        if stride == mem::size_of::<T>() as vk::DeviceSize {
+            unsafe { buffer.write(device, offset, data) }
+        } else {
+            for (i, elem) in data.iter().enumerate() {
+                let elem_offset = offset + stride * i as vk::DeviceSize;
+                unsafe { buffer.write(device, elem_offset, std::slice::from_ref(elem))? };
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Binds the buffer as the vertex buffer for input binding `binding`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - The buffer must have been created with `VERTEX_BUFFER` usage.
+    pub unsafe fn bind_vertex(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        binding: u32,
+    ) {
+        // Safety: the handle does not outlive the call.
+        let raw = unsafe { *self.buffer().raw().handle() };
+        unsafe { device.cmd_bind_vertex_buffers(command_buffer, binding, &[raw], &[0]) };
+    }
+
+    /// Destroys the buffer and frees its memory.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the buffer was created with.
+    /// - All submitted commands that refer to the buffer must have completed
+    ///   execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+
+        if let Some(buffer) = self.buffer.take() {
+            unsafe { buffer.destroy_with(device) };
+        }
+    }
+}
+
+impl<T: IndexElement> TypedBuffer<T> {
+    /// Binds the buffer as the index buffer for subsequent indexed draws.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - The buffer must have been created with `INDEX_BUFFER` usage.
+    pub unsafe fn bind_index(&self, device: &vks::Device, command_buffer: &mut vks::CommandBuffer) {
+        unsafe {
+            device.cmd_bind_index_buffer(
+                command_buffer,
+                self.buffer().raw(),
+                0,
+                T::INDEX_TYPE.into(),
+            )
+        };
+    }
+}
+
+/// A growable array of `T`, rewritten by the host every frame.
+///
+/// This covers the common pattern of building an array of structs on the CPU
+/// each frame, such as a light list, and reading it on the GPU. Each frame in
+/// flight has its own buffer, so writing one frame's data never races with the
+/// GPU reading another's. Buffers grow as needed and are never shrunk.
+///
+/// Buffers are allocated in host-visible memory and written directly, without
+/// a staging copy.
+pub struct FrameArrayBuffer<T: bytemuck::Pod> {
+    bomb: ErrorOnDrop<DroppedFrameArrayBuffer>,
+
+    usage: vk::BufferUsageFlags,
+    frames: Vec<Option<Buffer>>,
+    lens: Vec<usize>,
+    current: usize,
+
+    phantom: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> FrameArrayBuffer<T> {
+    /// Creates an empty buffer set. No Vulkan objects are created until the
+    /// first call to `write`.
+    pub fn new(frames_in_flight: usize, usage: vk::BufferUsageFlags) -> FrameArrayBuffer<T> {
+        assert!(frames_in_flight > 0);
+
+        FrameArrayBuffer {
+            bomb: ErrorOnDrop::default(),
+            usage,
+            frames: (0..frames_in_flight).map(|_| None).collect(),
+            lens: vec![0; frames_in_flight],
+            current: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Begins a new frame, selecting the buffer for `frame_index`.
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.current = frame_index % self.frames.len();
+    }
+
+    /// Replaces the current frame's contents with `data`, growing the buffer
+    /// if necessary.
+    ///
+    /// Growing the buffer replaces it, so previously returned handles from
+    /// `buffer` must be rebound.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to the current frame's buffer must
+    ///   have completed execution.
+    pub unsafe fn write(&mut self, device: &Device, data: &[T]) -> vks::VkResult<()> {
+        let required = mem::size_of_val(data) as vk::DeviceSize;
+
+        let slot = &mut self.frames[self.current];
+        if slot.as_ref().map_or(true, |buf| buf.size() < required) {
+            if let Some(old) = slot.take() {
+                unsafe { old.destroy_with(device) };
+            }
+
+            let capacity = data.len().max(MIN_CAPACITY).next_power_of_two();
+            let size = (capacity * mem::size_of::<T>()) as vk::DeviceSize;
+            let info = BufferCreateInfo::new(size, self.usage, MemoryLocation::Host);
+            *slot = Some(Buffer::create(device, &info)?);
+
+            // Arm the bomb once the buffer set owns a Vulkan object.
+            self.bomb.arm();
+        }
+
+        unsafe { slot.as_mut().unwrap().write(device, 0, data)? };
+
+        self.lens[self.current] = data.len();
+        Ok(())
+    }
+
+    /// Returns the current frame's buffer, or `None` if nothing has been
+    /// written to it yet.
+    pub fn buffer(&self) -> Option<&vks::Buffer> {
+        self.frames[self.current].as_ref().map(Buffer::raw)
+    }
+
+    /// Returns the number of elements last written to the current frame's
+    /// buffer.
+    pub fn len(&self) -> usize {
+        self.lens[self.current]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Destroys the buffers for all frames.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to any of the buffers must have
+    ///   completed execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+        for buf in self.frames.drain(..).flatten() {
+            unsafe { buf.destroy_with(device) };
+        }
+    }
+}
+
+/// A region of a [`StreamBuffer`] written during the current frame.
+#[derive(Copy, Clone, Debug)]
+pub struct StreamAllocation {
+    pub buffer: vk::Buffer,
+    /// The byte offset of the data within `buffer`.
+    pub offset: vk::DeviceSize,
+    /// The size of the data in bytes.
+    pub size: vk::DeviceSize,
+}
+
+impl StreamAllocation {
+    /// Returns a descriptor covering the allocation.
+    pub fn descriptor_info(&self) -> vk::DescriptorBufferInfo {
+        vk::DescriptorBufferInfo {
+            buffer: self.buffer,
+            offset: self.offset,
+            range: self.size,
+        }
+    }
+}
+
+/// A ring of host-visible buffers for transient per-frame data.
+///
+/// Small, short-lived data such as uniforms or debug line vertices is
+/// appended to the current frame's buffer, and the whole buffer is reused
+/// once the frame is no longer in flight. Each frame in flight has a buffer
+/// of fixed capacity; allocations beyond it fail rather than grow, so
+/// returned handles remain valid for the whole frame.
+pub struct StreamBuffer {
+    bomb: ErrorOnDrop<DroppedStreamBuffer>,
+
+    frames: Vec<Buffer>,
+    alignment: vk::DeviceSize,
+    current: usize,
+    cursor: vk::DeviceSize,
+}
+
+impl StreamBuffer {
+    /// Creates a stream buffer with `capacity` bytes for each of
+    /// `frames_in_flight` frames.
+    ///
+    /// Allocations are aligned to the device's minimum offset alignment for
+    /// the uniform and storage usages in `usage`.
+    pub fn create(
+        device: &Device,
+        frames_in_flight: usize,
+        capacity: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+    ) -> vks::VkResult<StreamBuffer> {
+        assert!(frames_in_flight > 0);
+        assert!(capacity > 0);
+
+        let limits = device.physical_device().properties().limits;
+        let mut alignment = 4;
+        if usage.contains(vk::BufferUsageFlags::UNIFORM_BUFFER) {
+            alignment = alignment.max(limits.min_uniform_buffer_offset_alignment);
+        }
+        if usage.contains(vk::BufferUsageFlags::STORAGE_BUFFER) {
+            alignment = alignment.max(limits.min_storage_buffer_offset_alignment);
+        }
+
+        let mut frames = Vec::with_capacity(frames_in_flight);
+        for i in 0..frames_in_flight {
+            let info = BufferCreateInfo::new(capacity, usage, MemoryLocation::Host)
+                .label(format!("stream buffer (frame {})", i));
+            match Buffer::create(device, &info) {
+                Ok(buffer) => frames.push(buffer),
+                Err(e) => {
+                    for buffer in frames {
+                        unsafe { buffer.destroy_with(device) };
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(StreamBuffer {
+            bomb: ErrorOnDrop::new(DroppedStreamBuffer),
+            frames,
+            alignment,
+            current: 0,
+            cursor: 0,
+        })
+    }
+
+    /// Begins a new frame, discarding all allocations previously made in the
+    /// buffer for `frame_index`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to the buffer for `frame_index`
+    ///   must have completed execution.
+    pub unsafe fn begin_frame(&mut self, frame_index: usize) {
+        self.current = frame_index % self.frames.len();
+        self.cursor = 0;
+    }
+
+    /// Copies `data` into the current frame's buffer, returning where it was
+    /// written.
+    pub fn allocate<T: bytemuck::Pod>(
+        &mut self,
+        device: &Device,
+        data: &[T],
+    ) -> Result<StreamAllocation, Error> {
+        let size = mem::size_of_val(data) as vk::DeviceSize;
+        let alignment = self.alignment.max(mem::align_of::<T>() as vk::DeviceSize);
+        let offset = util::align_up(self.cursor, alignment).unwrap_or(u64::MAX);
+
+        let buffer = &mut self.frames[self.current];
+        if offset
+            .checked_add(size)
+            .map_or(true, |end| end > buffer.size())
+        {
+            return Err(Error::StreamBufferFull {
+                requested: size,
+                available: buffer.size().saturating_sub(offset),
+            });
+        }
+
+        // Safety: allocations never overlap, and the range was released by
+        // `begin_frame`.
+        unsafe { buffer.write(device, offset, data)? };
+        self.cursor = offset + size;
+
+        Ok(StreamAllocation {
+            // Safety: the handle does not outlive the buffer.
+            buffer: unsafe { *buffer.raw().handle() },
+            offset,
+            size,
+        })
+    }
+
+    /// Returns the number of bytes allocated from the current frame's buffer.
+    pub fn used(&self) -> vk::DeviceSize {
+        self.cursor
+    }
+
+    /// Destroys the buffers for all frames.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to any of the buffers must have
+    ///   completed execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+        for buffer in self.frames.drain(..) {
+            unsafe { buffer.destroy_with(device) };
+        }
+    }
+}
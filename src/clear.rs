@@ -0,0 +1,324 @@
+//! Clearing images and filling buffers outside of render passes.
+
+use erupt::vk;
+
+use crate::{
+    pass::{ClearColorValue, ClearDepthStencilValue},
+    vks, Error,
+};
+
+/// The pipeline stages and memory accesses to which the writes of a recorded
+/// transfer command are made available.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AccessScope {
+    pub stage_mask: vk::PipelineStageFlags,
+    pub access_mask: vk::AccessFlags,
+}
+
+impl AccessScope {
+    /// Reads by the host, e.g. of a readback buffer.
+    pub const HOST_READ: AccessScope = AccessScope {
+        stage_mask: vk::PipelineStageFlags::HOST,
+        access_mask: vk::AccessFlags::HOST_READ,
+    };
+
+    pub const fn new(
+        stage_mask: vk::PipelineStageFlags,
+        access_mask: vk::AccessFlags,
+    ) -> AccessScope {
+        AccessScope {
+            stage_mask,
+            access_mask,
+        }
+    }
+}
+
+/// The maximum number of bytes written by a single `vkCmdUpdateBuffer`.
+const MAX_UPDATE_SIZE: usize = 65536;
+
+/// Returns an error if `len` bytes may not be written at `offset` by
+/// `vkCmdUpdateBuffer`.
+fn check_update(offset: vk::DeviceSize, len: usize) -> Result<(), Error> {
+    if offset & 3 != 0 || len & 3 != 0 || len == 0 {
+        return Err(Error::MisalignedBufferUpdate { offset, len });
+    }
+
+    if len > MAX_UPDATE_SIZE {
+        return Err(Error::BufferUpdateTooLarge {
+            len,
+            max: MAX_UPDATE_SIZE,
+        });
+    }
+
+    Ok(())
+}
+
+/// Waits for all previous commands and transitions `range` of `image` for
+/// writing by a clear, discarding its contents.
+unsafe fn begin_clear(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    image: &vks::Image,
+    range: vk::ImageSubresourceRange,
+) {
+    let barrier = vks::ImageMemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .old_layout(vk::ImageLayout::UNDEFINED)
+        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(range);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            // Safety: Produced raw handles do not outlive the block.
+            &[barrier.into_inner()],
+        );
+    }
+}
+
+/// Transitions `range` of `image` from the clear to its final layout and
+/// makes the writes available to `dst_scope`.
+unsafe fn end_clear(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    image: &vks::Image,
+    range: vk::ImageSubresourceRange,
+    final_layout: vk::ImageLayout,
+    dst_scope: AccessScope,
+) {
+    let barrier = vks::ImageMemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(dst_scope.access_mask)
+        .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+        .new_layout(final_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(range);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier.into_inner()],
+        );
+    }
+}
+
+/// Records commands to clear `range` of a color image to `value`.
+///
+/// The clear waits for all previously recorded commands, and the previous
+/// contents of `range` are discarded. On completion, `range` is in
+/// `final_layout`, and the writes are made available to `dst_scope`.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `image` must have been created with `TRANSFER_DST` usage and a color
+///   format, and must contain `range`.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn clear_color_image(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    image: &vks::Image,
+    value: ClearColorValue,
+    range: vk::ImageSubresourceRange,
+    final_layout: vk::ImageLayout,
+    dst_scope: AccessScope,
+) {
+    unsafe {
+        begin_clear(device, cmdbuf, image, range);
+        device.cmd_clear_color_image(
+            cmdbuf,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &value.into(),
+            &[range.into_builder()],
+        );
+        end_clear(device, cmdbuf, image, range, final_layout, dst_scope);
+    }
+}
+
+/// Records commands to clear `range` of a depth/stencil image to `value`.
+///
+/// Synchronization is as for [`clear_color_image`].
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `image` must have been created with `TRANSFER_DST` usage and a
+///   depth/stencil format, and must contain `range`.
+/// - The aspects of `range` must be present in the image's format.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn clear_depth_stencil_image(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    image: &vks::Image,
+    value: ClearDepthStencilValue,
+    range: vk::ImageSubresourceRange,
+    final_layout: vk::ImageLayout,
+    dst_scope: AccessScope,
+) {
+    unsafe {
+        begin_clear(device, cmdbuf, image, range);
+        device.cmd_clear_depth_stencil_image(
+            cmdbuf,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &value.into(),
+            &[range.into_builder()],
+        );
+        end_clear(device, cmdbuf, image, range, final_layout, dst_scope);
+    }
+}
+
+/// Records a global barrier between all previous memory writes and the
+/// transfer writes of a buffer update.
+unsafe fn begin_buffer_write(device: &vks::Device, cmdbuf: &mut vks::CommandBuffer) {
+    let barrier = vk::MemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+/// Makes the transfer writes of a buffer update available to `dst_scope`.
+unsafe fn end_buffer_write(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    dst_scope: AccessScope,
+) {
+    let barrier = vk::MemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(dst_scope.access_mask);
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            vk::DependencyFlags::empty(),
+            &[barrier],
+            &[],
+            &[],
+        );
+    }
+}
+
+/// Records commands to fill `size` bytes of a buffer at `offset` with
+/// repeated copies of `data`.
+///
+/// `size` may be `vk::WHOLE_SIZE` to fill to the end of the buffer. The fill
+/// waits for all previously recorded commands, and on completion the writes
+/// are made available to `dst_scope`.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `buffer` must have been created with `TRANSFER_DST` usage.
+/// - `offset` must be a multiple of 4 and less than the buffer's size.
+/// - Unless `size` is `vk::WHOLE_SIZE`, it must be a multiple of 4 and
+///   `offset + size` must not exceed the buffer's size.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn fill_buffer(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    buffer: &vks::Buffer,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    data: u32,
+    dst_scope: AccessScope,
+) {
+    unsafe {
+        begin_buffer_write(device, cmdbuf);
+        device.cmd_fill_buffer(cmdbuf, buffer, offset, size, data);
+        end_buffer_write(device, cmdbuf, dst_scope);
+    }
+}
+
+/// Records commands to write `data` to a buffer at `offset`.
+///
+/// The data is copied into the command buffer, so this is intended for small
+/// updates. Synchronization is as for [`fill_buffer`].
+///
+/// Returns [`Error::MisalignedBufferUpdate`] if `offset` or `data.len()` is
+/// not a multiple of 4 or `data` is empty, and
+/// [`Error::BufferUpdateTooLarge`] if `data` is larger than 65536 bytes. No
+/// commands are recorded in either case.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `buffer` must have been created with `TRANSFER_DST` usage.
+/// - `offset + data.len()` must not exceed the buffer's size.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn update_buffer(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    buffer: &vks::Buffer,
+    offset: vk::DeviceSize,
+    data: &[u8],
+    dst_scope: AccessScope,
+) -> Result<(), Error> {
+    check_update(offset, data.len())?;
+
+    unsafe {
+        begin_buffer_write(device, cmdbuf);
+        device.cmd_update_buffer(cmdbuf, buffer, offset, data);
+        end_buffer_write(device, cmdbuf, dst_scope);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_must_be_aligned_and_bounded() {
+        assert!(check_update(0, 4).is_ok());
+        assert!(check_update(256, MAX_UPDATE_SIZE).is_ok());
+
+        let misaligned = |offset, len| {
+            matches!(
+                check_update(offset, len),
+                Err(Error::MisalignedBufferUpdate { .. })
+            )
+        };
+        assert!(misaligned(0, 0));
+        assert!(misaligned(2, 4));
+        assert!(misaligned(0, 6));
+
+        assert!(matches!(
+            check_update(0, MAX_UPDATE_SIZE + 4),
+            Err(Error::BufferUpdateTooLarge {
+                len: 65540,
+                max: MAX_UPDATE_SIZE,
+            })
+        ));
+    }
+}
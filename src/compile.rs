@@ -0,0 +1,227 @@
+//! Background compilation of graphics pipelines.
+
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use parking_lot::Mutex;
+
+use crate::{Device, Error, GraphicsPipelineInfo, Pipeline, PipelineTarget};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The most worker threads a device spawns for pipeline compilation.
+const MAX_COMPILE_THREADS: usize = 4;
+
+/// A pool of worker threads which compile pipelines off the calling thread.
+///
+/// Dropping the pool detaches its workers, which exit once the queued jobs
+/// are done; [`CompilePool::shutdown`] also waits for them.
+pub(crate) struct CompilePool {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl CompilePool {
+    /// Spawns a pool with one worker per available core, up to
+    /// `MAX_COMPILE_THREADS`.
+    pub(crate) fn new() -> CompilePool {
+        let threads = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(MAX_COMPILE_THREADS);
+        CompilePool::with_threads(threads)
+    }
+
+    fn with_threads(threads: usize) -> CompilePool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads)
+            .map(|index| {
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("reify-compile-{}", index))
+                    .spawn(move || loop {
+                        // The lock is released before running the job, so
+                        // other workers may take the next one.
+                        let job = receiver.lock().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("failed to spawn pipeline compile thread")
+            })
+            .collect();
+
+        CompilePool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Queues a job to run on a worker thread.
+    pub(crate) fn spawn(&self, job: Job) {
+        // Workers only exit once the sender is dropped.
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(job)
+            .expect("pipeline compile threads exited");
+    }
+
+    /// Waits for all queued jobs to finish and stops the workers.
+    pub(crate) fn shutdown(mut self) {
+        drop(self.sender.take());
+
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                log::error!("A pipeline compile thread panicked.");
+            }
+        }
+    }
+}
+
+/// A graphics pipeline being compiled in the background.
+///
+/// Returned by [`Device::compile_pipeline`]. Dropping it discards the
+/// pipeline once compiled.
+pub struct PendingPipeline {
+    receiver: mpsc::Receiver<Result<Pipeline, Error>>,
+}
+
+impl PendingPipeline {
+    /// Compiles a pipeline on one of the device's compile threads.
+    pub(crate) fn spawn(
+        device: &Device,
+        vert_spv: Vec<u32>,
+        frag_spv: Vec<u32>,
+        info: GraphicsPipelineInfo,
+        target: PipelineTarget,
+    ) -> PendingPipeline {
+        let (sender, receiver) = mpsc::sync_channel(1);
+
+        let job_device = device.clone();
+        let job = Box::new(move || {
+            let result = unsafe {
+                job_device.create_pipeline_for_target(&vert_spv, &frag_spv, &info, target)
+            };
+            // The receiver may have been dropped, discarding the pipeline.
+            let _ = sender.send(result);
+        });
+
+        let device_read = device.read_inner();
+        device_read
+            .compile_pool
+            .lock()
+            .get_or_insert_with(CompilePool::new)
+            .spawn(job);
+
+        PendingPipeline { receiver }
+    }
+
+    /// Returns the compiled pipeline, or `None` if compilation has not
+    /// finished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if compilation panicked.
+    pub fn try_take(&self) -> Option<Result<Pipeline, Error>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => panic!("pipeline compilation panicked"),
+        }
+    }
+
+    /// Blocks until the pipeline is compiled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if compilation panicked.
+    pub fn wait(self) -> Result<Pipeline, Error> {
+        self.receiver.recv().expect("pipeline compilation panicked")
+    }
+}
+
+/// The pipeline used for a draw, which may be replaced by one compiling in
+/// the background.
+///
+/// Until the first pipeline is compiled, [`PipelineSlot::current`] returns
+/// `None` and draws using the slot should be skipped. When a new variant is
+/// compiled, e.g. after a shader is edited, the previous pipeline remains
+/// current until the new one is ready.
+#[derive(Default)]
+pub struct PipelineSlot {
+    current: Option<Pipeline>,
+    pending: Option<PendingPipeline>,
+}
+
+impl PipelineSlot {
+    pub fn new() -> PipelineSlot {
+        PipelineSlot::default()
+    }
+
+    /// Starts compiling a pipeline to replace the current one.
+    ///
+    /// Any replacement which is still compiling is discarded.
+    pub fn compile(
+        &mut self,
+        device: &Device,
+        vert_spv: Vec<u32>,
+        frag_spv: Vec<u32>,
+        info: GraphicsPipelineInfo,
+        target: PipelineTarget,
+    ) {
+        self.pending = Some(device.compile_pipeline(vert_spv, frag_spv, info, target));
+    }
+
+    /// Returns the pipeline to draw with, if one has been compiled.
+    pub fn current(&self) -> Option<&Pipeline> {
+        self.current.as_ref()
+    }
+
+    /// Returns `true` if a replacement pipeline is compiling.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Makes the pending pipeline current if it has finished compiling.
+    ///
+    /// Returns the replaced pipeline, if any. It must be kept alive until all
+    /// command buffers which use it have completed execution. If compilation
+    /// failed, the current pipeline is kept and the error is returned.
+    pub fn poll(&mut self) -> Result<Option<Pipeline>, Error> {
+        let result = match self.pending.as_ref().and_then(PendingPipeline::try_take) {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        self.pending = None;
+
+        Ok(self.current.replace(result?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn shutdown_runs_queued_jobs() {
+        let pool = CompilePool::with_threads(2);
+        let count = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..16 {
+            let count = count.clone();
+            pool.spawn(Box::new(move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            }));
+        }
+
+        pool.shutdown();
+        assert_eq!(count.load(Ordering::SeqCst), 16);
+    }
+}
@@ -0,0 +1,539 @@
+//! Copies between images and buffers.
+
+use std::iter;
+
+use arrayvec::ArrayVec;
+use erupt::vk;
+
+use crate::{clear::AccessScope, vks};
+
+/// Returns the optimal-tiling features a blit from `src` to `dst` with
+/// `filter` requires of each format but which are missing, as `(source,
+/// destination)`.
+pub(crate) fn missing_blit_features(
+    src: vk::FormatFeatureFlags,
+    dst: vk::FormatFeatureFlags,
+    filter: vk::Filter,
+) -> (vk::FormatFeatureFlags, vk::FormatFeatureFlags) {
+    let mut src_required = vk::FormatFeatureFlags::BLIT_SRC;
+    if filter == vk::Filter::LINEAR {
+        src_required |= vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR;
+    }
+
+    (src_required - src, vk::FormatFeatureFlags::BLIT_DST - dst)
+}
+
+/// Returns the subresource range covering `layers`.
+fn layers_to_range(layers: vk::ImageSubresourceLayers) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask: layers.aspect_mask,
+        base_mip_level: layers.mip_level,
+        level_count: 1,
+        base_array_layer: layers.base_array_layer,
+        layer_count: layers.layer_count,
+    }
+}
+
+fn image_barrier<'a>(
+    image: &vks::Image,
+    range: vk::ImageSubresourceRange,
+    (src_access_mask, old_layout): (vk::AccessFlags, vk::ImageLayout),
+    (dst_access_mask, new_layout): (vk::AccessFlags, vk::ImageLayout),
+) -> vks::ImageMemoryBarrierBuilder<'a> {
+    vks::ImageMemoryBarrierBuilder::new()
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(range)
+}
+
+unsafe fn pipeline_barrier<'a>(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    src_stage_mask: vk::PipelineStageFlags,
+    dst_stage_mask: vk::PipelineStageFlags,
+    memory_barriers: &[vk::MemoryBarrierBuilder<'_>],
+    image_barriers: impl IntoIterator<Item = vks::ImageMemoryBarrierBuilder<'a>>,
+) {
+    // Safety: Produced raw handles do not outlive the call.
+    let image_barriers = image_barriers
+        .into_iter()
+        .map(|b| unsafe { b.into_inner() })
+        .collect::<ArrayVec<_, 2>>();
+
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            src_stage_mask,
+            dst_stage_mask,
+            vk::DependencyFlags::empty(),
+            memory_barriers,
+            &[],
+            &image_barriers,
+        );
+    }
+}
+
+/// Describes a copy between two images, recorded by [`copy_image`].
+#[derive(Copy, Clone)]
+pub struct ImageCopyInfo<'a> {
+    pub src: &'a vks::Image,
+    /// The layout of the copied subresources of `src`. They are returned to
+    /// this layout after the copy.
+    pub src_layout: vk::ImageLayout,
+    pub dst: &'a vks::Image,
+    /// The layout of the copied subresources of `dst` before the copy.
+    pub dst_layout: vk::ImageLayout,
+    /// The layout the copied subresources of `dst` are left in.
+    pub dst_final_layout: vk::ImageLayout,
+    pub region: vk::ImageCopy,
+}
+
+/// Describes a blit between two images, recorded by [`blit_image`].
+#[derive(Copy, Clone)]
+pub struct ImageBlitInfo<'a> {
+    pub src: &'a vks::Image,
+    /// The layout of the blitted subresources of `src`. They are returned to
+    /// this layout after the blit.
+    pub src_layout: vk::ImageLayout,
+    pub dst: &'a vks::Image,
+    /// The layout of the blitted subresources of `dst` before the blit.
+    pub dst_layout: vk::ImageLayout,
+    /// The layout the blitted subresources of `dst` are left in.
+    pub dst_final_layout: vk::ImageLayout,
+    pub region: vk::ImageBlit,
+    /// The filter applied when the source and destination regions differ in
+    /// size.
+    pub filter: vk::Filter,
+}
+
+/// Describes a copy from a buffer to an image, recorded by
+/// [`copy_buffer_to_image`].
+#[derive(Copy, Clone)]
+pub struct BufferToImageCopyInfo<'a> {
+    pub src: &'a vks::Buffer,
+    pub dst: &'a vks::Image,
+    /// The layout of the copied subresource of `dst` before the copy.
+    pub dst_layout: vk::ImageLayout,
+    /// The layout the copied subresource of `dst` is left in.
+    pub dst_final_layout: vk::ImageLayout,
+    pub region: vk::BufferImageCopy,
+}
+
+/// Describes a copy from an image to a buffer, recorded by
+/// [`copy_image_to_buffer`].
+#[derive(Copy, Clone)]
+pub struct ImageToBufferCopyInfo<'a> {
+    pub src: &'a vks::Image,
+    /// The layout of the copied subresource of `src`. It is returned to this
+    /// layout after the copy.
+    pub src_layout: vk::ImageLayout,
+    pub dst: &'a vks::Buffer,
+    pub region: vk::BufferImageCopy,
+}
+
+/// Records commands to copy `info.region` from `info.src` to `info.dst`.
+///
+/// The copy waits for all previously recorded commands. `src` is returned to
+/// `src_layout`, and the copied region of `dst` is left in `dst_final_layout`
+/// with the writes made available to `dst_scope`. If `dst_layout` is
+/// `UNDEFINED`, the previous contents of the region are discarded.
+///
+/// The images' formats must be size-compatible; no conversion is performed.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `src` must have been created with `TRANSFER_SRC` usage, and `dst` with
+///   `TRANSFER_DST` usage.
+/// - `src` and `dst` must be different images containing the regions
+///   described by `region`.
+/// - The copied subresources of `src` must be in `src_layout`, which must
+///   not be `UNDEFINED`, and those of `dst` in `dst_layout`.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn copy_image(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    info: &ImageCopyInfo<'_>,
+    dst_scope: AccessScope,
+) {
+    let ImageCopyInfo {
+        src,
+        src_layout,
+        dst,
+        dst_layout,
+        dst_final_layout,
+        region,
+    } = *info;
+    let src_range = layers_to_range(region.src_subresource);
+    let dst_range = layers_to_range(region.dst_subresource);
+    let transfer_src = (
+        vk::AccessFlags::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+    let transfer_dst = (
+        vk::AccessFlags::TRANSFER_WRITE,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    );
+
+    unsafe {
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            &[],
+            ArrayVec::from([
+                image_barrier(
+                    src,
+                    src_range,
+                    (vk::AccessFlags::MEMORY_WRITE, src_layout),
+                    transfer_src,
+                ),
+                image_barrier(
+                    dst,
+                    dst_range,
+                    (vk::AccessFlags::MEMORY_WRITE, dst_layout),
+                    transfer_dst,
+                ),
+            ]),
+        );
+
+        device.cmd_copy_image(
+            cmdbuf,
+            src,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region.into_builder()],
+        );
+
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            &[],
+            ArrayVec::from([
+                image_barrier(
+                    src,
+                    src_range,
+                    (vk::AccessFlags::empty(), transfer_src.1),
+                    (vk::AccessFlags::empty(), src_layout),
+                ),
+                image_barrier(
+                    dst,
+                    dst_range,
+                    transfer_dst,
+                    (dst_scope.access_mask, dst_final_layout),
+                ),
+            ]),
+        );
+    }
+}
+
+/// Records commands to blit `info.region` from `info.src` to `info.dst`,
+/// scaling and converting formats as needed.
+///
+/// `region` selects the mip level, array layers and bounds of both the
+/// source and destination, so a blit may cover a sub-rectangle of either
+/// image. Reversed offsets mirror the image. Synchronization is as for
+/// [`copy_image`].
+///
+/// Use [`PhysicalDevice::check_blit_support`] to check that the formats
+/// support blitting with `filter`.
+///
+/// [`PhysicalDevice::check_blit_support`]: crate::PhysicalDevice::check_blit_support
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `src` must have been created with `TRANSFER_SRC` usage, and `dst` with
+///   `TRANSFER_DST` usage.
+/// - `src` and `dst` must be different images containing the regions
+///   described by `region`.
+/// - The source format must support `BLIT_SRC`, and additionally
+///   `SAMPLED_IMAGE_FILTER_LINEAR` if `filter` is `LINEAR`. The destination
+///   format must support `BLIT_DST`.
+/// - The blitted subresources of `src` must be in `src_layout`, which must
+///   not be `UNDEFINED`, and those of `dst` in `dst_layout`.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn blit_image(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    info: &ImageBlitInfo<'_>,
+    dst_scope: AccessScope,
+) {
+    let ImageBlitInfo {
+        src,
+        src_layout,
+        dst,
+        dst_layout,
+        dst_final_layout,
+        region,
+        filter,
+    } = *info;
+    let src_range = layers_to_range(region.src_subresource);
+    let dst_range = layers_to_range(region.dst_subresource);
+    let transfer_src = (
+        vk::AccessFlags::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+    let transfer_dst = (
+        vk::AccessFlags::TRANSFER_WRITE,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    );
+
+    unsafe {
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            &[],
+            ArrayVec::from([
+                image_barrier(
+                    src,
+                    src_range,
+                    (vk::AccessFlags::MEMORY_WRITE, src_layout),
+                    transfer_src,
+                ),
+                image_barrier(
+                    dst,
+                    dst_range,
+                    (vk::AccessFlags::MEMORY_WRITE, dst_layout),
+                    transfer_dst,
+                ),
+            ]),
+        );
+
+        device.cmd_blit_image(
+            cmdbuf,
+            src,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region.into_builder()],
+            filter,
+        );
+
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            &[],
+            ArrayVec::from([
+                image_barrier(
+                    src,
+                    src_range,
+                    (vk::AccessFlags::empty(), transfer_src.1),
+                    (vk::AccessFlags::empty(), src_layout),
+                ),
+                image_barrier(
+                    dst,
+                    dst_range,
+                    transfer_dst,
+                    (dst_scope.access_mask, dst_final_layout),
+                ),
+            ]),
+        );
+    }
+}
+
+/// Records commands to copy `info.region` from `info.src` into `info.dst`,
+/// e.g. to upload texture data.
+///
+/// Synchronization is as for the destination of [`copy_image`]. Writes to
+/// `src` by previously recorded commands are made visible to the copy.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `src` must have been created with `TRANSFER_SRC` usage, and `dst` with
+///   `TRANSFER_DST` usage.
+/// - `src` and `dst` must contain the regions described by `region`.
+/// - The copied subresource of `dst` must be in `dst_layout`.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn copy_buffer_to_image(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    info: &BufferToImageCopyInfo<'_>,
+    dst_scope: AccessScope,
+) {
+    let BufferToImageCopyInfo {
+        src,
+        dst,
+        dst_layout,
+        dst_final_layout,
+        region,
+    } = *info;
+    let range = layers_to_range(region.image_subresource);
+    let transfer_dst = (
+        vk::AccessFlags::TRANSFER_WRITE,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    );
+    let buffer_barrier = vk::MemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
+    unsafe {
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            &[buffer_barrier],
+            iter::once(image_barrier(
+                dst,
+                range,
+                (vk::AccessFlags::MEMORY_WRITE, dst_layout),
+                transfer_dst,
+            )),
+        );
+
+        device.cmd_copy_buffer_to_image(
+            cmdbuf,
+            src,
+            dst,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[region.into_builder()],
+        );
+
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            &[],
+            iter::once(image_barrier(
+                dst,
+                range,
+                transfer_dst,
+                (dst_scope.access_mask, dst_final_layout),
+            )),
+        );
+    }
+}
+
+/// Records commands to copy `info.region` from `info.src` into `info.dst`,
+/// e.g. to read an image back to the host.
+///
+/// The copy waits for all previously recorded commands. `src` is returned to
+/// `src_layout`, and the writes to `dst` are made available to `dst_scope`.
+/// For host readback, this is [`AccessScope::HOST_READ`].
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `src` must have been created with `TRANSFER_SRC` usage, and `dst` with
+///   `TRANSFER_DST` usage.
+/// - `src` and `dst` must contain the regions described by `region`.
+/// - The copied subresource of `src` must be in `src_layout`, which must not
+///   be `UNDEFINED`.
+/// - `cmdbuf` must be in the recording state, outside of a render pass.
+pub unsafe fn copy_image_to_buffer(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    info: &ImageToBufferCopyInfo<'_>,
+    dst_scope: AccessScope,
+) {
+    let ImageToBufferCopyInfo {
+        src,
+        src_layout,
+        dst,
+        region,
+    } = *info;
+    let range = layers_to_range(region.image_subresource);
+    let transfer_src = (
+        vk::AccessFlags::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+    let before_write = vk::MemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE);
+    let after_write = vk::MemoryBarrierBuilder::new()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(dst_scope.access_mask);
+
+    unsafe {
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            &[before_write],
+            iter::once(image_barrier(
+                src,
+                range,
+                (vk::AccessFlags::MEMORY_WRITE, src_layout),
+                transfer_src,
+            )),
+        );
+
+        device.cmd_copy_image_to_buffer(
+            cmdbuf,
+            src,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            dst,
+            &[region.into_builder()],
+        );
+
+        pipeline_barrier(
+            device,
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            &[after_write],
+            iter::once(image_barrier(
+                src,
+                range,
+                (vk::AccessFlags::empty(), transfer_src.1),
+                (vk::AccessFlags::empty(), src_layout),
+            )),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_blits_require_filtering() {
+        let blit_src = vk::FormatFeatureFlags::BLIT_SRC;
+        let blit_dst = vk::FormatFeatureFlags::BLIT_DST;
+        let none = vk::FormatFeatureFlags::empty();
+
+        assert_eq!(
+            missing_blit_features(blit_src, blit_dst, vk::Filter::NEAREST),
+            (none, none)
+        );
+        assert_eq!(
+            missing_blit_features(blit_src, blit_dst, vk::Filter::LINEAR),
+            (vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR, none)
+        );
+        assert_eq!(
+            missing_blit_features(none, none, vk::Filter::NEAREST),
+            (blit_src, blit_dst)
+        );
+    }
+
+    #[test]
+    fn layers_cover_a_single_level() {
+        let range = layers_to_range(vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            mip_level: 3,
+            base_array_layer: 2,
+            layer_count: 4,
+        });
+
+        assert_eq!(range.aspect_mask, vk::ImageAspectFlags::DEPTH);
+        assert_eq!((range.base_mip_level, range.level_count), (3, 1));
+        assert_eq!((range.base_array_layer, range.layer_count), (2, 4));
+    }
+}
@@ -1,9 +1,11 @@
 use std::{
     ffi::CStr,
     fmt::{self, Write},
+    panic::{self, AssertUnwindSafe},
 };
 
 use erupt::vk;
+use parking_lot::RwLock;
 
 use crate::{vks, Instance};
 
@@ -137,6 +139,73 @@ where
 }
 
 const DEBUG_MESSAGE_INIT_CAPACITY: usize = 128;
+
+/// A message reported by the driver or a layer, e.g. a validation error.
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    pub severity: vk::DebugUtilsMessageSeverityFlagBitsEXT,
+    pub ty: vk::DebugUtilsMessageTypeFlagsEXT,
+    /// The numeric message ID. Zero if the message has no ID.
+    pub id_number: i32,
+    /// The message ID name, e.g. a validation `VUID`.
+    pub id_name: Option<String>,
+    /// The full message text, including queue, command buffer and object
+    /// labels.
+    pub text: String,
+}
+
+/// A user-installed handler for debug messages.
+pub type DebugCallback = Box<dyn Fn(&DebugMessage) + Send + Sync>;
+
+/// Configures which debug messages are reported, and how.
+pub struct DebugMessengerConfig {
+    /// The message severities to report. Verbose messages are excluded by
+    /// default.
+    pub severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    /// The message types to report.
+    pub types: vk::DebugUtilsMessageTypeFlagsEXT,
+    /// Numeric IDs of messages to suppress.
+    pub muted_ids: Vec<i32>,
+    /// ID names of messages to suppress.
+    pub muted_names: Vec<String>,
+    /// Called for each reported message. If `None`, messages are written to
+    /// the `log` crate at a level matching their severity.
+    pub callback: Option<DebugCallback>,
+}
+
+impl Default for DebugMessengerConfig {
+    fn default() -> Self {
+        DebugMessengerConfig {
+            severity: vk::DebugUtilsMessageSeverityFlagsEXT::all()
+                - vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE_EXT,
+            types: vk::DebugUtilsMessageTypeFlagsEXT::all(),
+            muted_ids: Vec::new(),
+            muted_names: Vec::new(),
+            callback: None,
+        }
+    }
+}
+
+/// Shared between a messenger and its callback through the user data pointer.
+struct MessengerState {
+    muted_ids: RwLock<Vec<i32>>,
+    muted_names: RwLock<Vec<String>>,
+    callback: Option<DebugCallback>,
+}
+
+impl MessengerState {
+    fn is_muted(&self, message: &DebugMessage) -> bool {
+        if self.muted_ids.read().contains(&message.id_number) {
+            return true;
+        }
+
+        match &message.id_name {
+            Some(name) => self.muted_names.read().contains(name),
+            None => false,
+        }
+    }
+}
+
 unsafe extern "system" fn debug_utils_messenger_callback(
     severity: vk::DebugUtilsMessageSeverityFlagBitsEXT,
     ty: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -149,43 +218,62 @@ unsafe extern "system" fn debug_utils_messenger_callback(
         return vk::FALSE;
     }
 
-    if let Err(e) = debug_utils_messenger_callback_impl(severity, ty, callback_data, user_data) {
-        log::error!("debug message formatting failed: {}", e);
+    let message = match unsafe { format_debug_message(severity, ty, callback_data) } {
+        Ok(m) => m,
+        Err(e) => {
+            log::error!("debug message formatting failed: {}", e);
+            return vk::FALSE;
+        }
+    };
+
+    // Safety: the state outlives the messenger.
+    let state = unsafe { &*(user_data as *const MessengerState) };
+    if state.is_muted(&message) {
+        return vk::FALSE;
+    }
+
+    match state.callback.as_ref() {
+        Some(callback) => {
+            // Unwinding into the driver is undefined behavior.
+            let result = panic::catch_unwind(AssertUnwindSafe(|| callback(&message)));
+            if result.is_err() {
+                log::error!("debug message callback panicked");
+            }
+        }
+        None => log_debug_message(&message),
     }
 
     vk::FALSE
 }
 
-fn debug_utils_messenger_callback_impl(
+/// Collects the contents of a debug message.
+///
+/// # Safety
+///
+/// `callback_data` must point to a valid `vk::DebugUtilsMessengerCallbackDataEXT`.
+unsafe fn format_debug_message(
     severity: vk::DebugUtilsMessageSeverityFlagBitsEXT,
     ty: vk::DebugUtilsMessageTypeFlagsEXT,
     callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _user_data: *mut std::ffi::c_void,
-) -> fmt::Result {
+) -> Result<DebugMessage, fmt::Error> {
     let callback_data = unsafe { *callback_data };
 
-    let severity = match severity {
-        vk::DebugUtilsMessageSeverityFlagBitsEXT::ERROR_EXT => log::Level::Error,
-        vk::DebugUtilsMessageSeverityFlagBitsEXT::WARNING_EXT => log::Level::Warn,
-        vk::DebugUtilsMessageSeverityFlagBitsEXT::INFO_EXT => log::Level::Info,
-        vk::DebugUtilsMessageSeverityFlagBitsEXT::VERBOSE_EXT => log::Level::Trace,
-        _ => log::Level::Warn,
-    };
-
     let mut log_message = String::with_capacity(DEBUG_MESSAGE_INIT_CAPACITY);
 
     write!(&mut log_message, "{:?} ", ty)?;
 
     let msg_id_num = callback_data.message_id_number;
-    if callback_data.p_message_id_name.is_null() {
+    let id_name = if callback_data.p_message_id_name.is_null() {
         // "[Message MESSAGE_ID]"
         write!(&mut log_message, "[Message 0x{:X}] : ", msg_id_num)?;
+        None
     } else {
         // "[MESSAGE_NAME (MESSAGE_ID)]"
         let msg_name_cstr = unsafe { CStr::from_ptr(callback_data.p_message_id_name) };
         log_message.write_str("[")?;
         format_cstr(&mut log_message, msg_name_cstr)?;
         write!(&mut log_message, " (0x{:X})] : ", msg_id_num)?;
+        Some(msg_name_cstr.to_string_lossy().into_owned())
     };
 
     if !callback_data.p_message.is_null() {
@@ -216,14 +304,33 @@ fn debug_utils_messenger_callback_impl(
         )?;
     }
 
-    log::log!(severity, "{}", log_message);
+    Ok(DebugMessage {
+        severity,
+        ty,
+        id_number: msg_id_num,
+        id_name,
+        text: log_message,
+    })
+}
 
-    Ok(())
+fn log_debug_message(message: &DebugMessage) {
+    let level = match message.severity {
+        vk::DebugUtilsMessageSeverityFlagBitsEXT::ERROR_EXT => log::Level::Error,
+        vk::DebugUtilsMessageSeverityFlagBitsEXT::WARNING_EXT => log::Level::Warn,
+        vk::DebugUtilsMessageSeverityFlagBitsEXT::INFO_EXT => log::Level::Info,
+        vk::DebugUtilsMessageSeverityFlagBitsEXT::VERBOSE_EXT => log::Level::Trace,
+        _ => log::Level::Warn,
+    };
+
+    log::log!(level, "{}", message.text);
 }
 
 pub struct DebugMessenger {
     instance: Instance,
     messenger: Option<vks::DebugUtilsMessengerEXT>,
+    // Boxed so the callback's pointer remains valid if the messenger moves.
+    // Dropped after the messenger is destroyed.
+    state: Box<MessengerState>,
 }
 
 impl Drop for DebugMessenger {
@@ -241,12 +348,34 @@ impl Drop for DebugMessenger {
 
 impl DebugMessenger {
     /// Initializes a new `DebugUtils`.
+    ///
+    /// Messages of the instance's debug severities are written to the `log`
+    /// crate.
     pub fn new(instance: Instance) -> DebugMessenger {
+        let severity = instance.read_inner().debug_severity();
+        DebugMessenger::with_config(
+            instance,
+            DebugMessengerConfig {
+                severity,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Initializes a new `DebugUtils` which reports messages as configured.
+    pub fn with_config(instance: Instance, config: DebugMessengerConfig) -> DebugMessenger {
+        let state = Box::new(MessengerState {
+            muted_ids: RwLock::new(config.muted_ids),
+            muted_names: RwLock::new(config.muted_names),
+            callback: config.callback,
+        });
+
         let debug_ext_info = vk::DebugUtilsMessengerCreateInfoEXTBuilder::new()
             .flags(vk::DebugUtilsMessengerCreateFlagsEXT::empty())
-            .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
-            .message_type(vk::DebugUtilsMessageTypeFlagsEXT::all())
-            .pfn_user_callback(Some(debug_utils_messenger_callback));
+            .message_severity(config.severity)
+            .message_type(config.types)
+            .pfn_user_callback(Some(debug_utils_messenger_callback))
+            .user_data(&*state as *const MessengerState as *mut std::ffi::c_void);
 
         // Safety: messenger is destroyed in Drop impl.
         let messenger = unsafe {
@@ -260,8 +389,39 @@ impl DebugMessenger {
         DebugMessenger {
             instance,
             messenger: Some(messenger),
+            state,
+        }
+    }
+
+    /// Suppresses messages with the numeric ID `id`.
+    pub fn mute_id(&self, id: i32) {
+        let mut muted = self.state.muted_ids.write();
+        if !muted.contains(&id) {
+            muted.push(id);
         }
     }
+
+    /// Suppresses messages with the ID name `name`, e.g. a validation `VUID`.
+    pub fn mute_name<S>(&self, name: S)
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        let mut muted = self.state.muted_names.write();
+        if !muted.contains(&name) {
+            muted.push(name);
+        }
+    }
+
+    /// Stops suppressing messages with the numeric ID `id`.
+    pub fn unmute_id(&self, id: i32) {
+        self.state.muted_ids.write().retain(|&muted| muted != id);
+    }
+
+    /// Stops suppressing messages with the ID name `name`.
+    pub fn unmute_name(&self, name: &str) {
+        self.state.muted_names.write().retain(|muted| muted != name);
+    }
 }
 
 #[cfg(test)]
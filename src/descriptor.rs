@@ -0,0 +1,769 @@
+//! Descriptor set layouts, pools and per-frame allocation.
+
+use std::{collections::HashMap, fmt};
+
+use erupt::{vk, ExtendableFromConst};
+
+use crate::{
+    util::ErrorOnDrop,
+    vks::{self, VkObject},
+    Device, Error,
+};
+
+/// A single binding within a descriptor set layout.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorBinding {
+    /// The binding number, as declared in the shader.
+    pub binding: u32,
+    /// The type of descriptor bound at this binding.
+    pub ty: vk::DescriptorType,
+    /// The number of descriptors in the binding. Values greater than one
+    /// declare an array.
+    pub count: u32,
+    /// The shader stages which may access the binding.
+    pub stages: vk::ShaderStageFlags,
+}
+
+/// Describes the layout of a descriptor set.
+///
+/// Layouts are deduplicated by the device, so two pipelines declaring the
+/// same layout share a single `VkDescriptorSetLayout`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DescriptorSetLayoutInfo {
+    bindings: Vec<DescriptorBinding>,
+    update_after_bind: bool,
+}
+
+impl DescriptorSetLayoutInfo {
+    pub fn new() -> DescriptorSetLayoutInfo {
+        DescriptorSetLayoutInfo::default()
+    }
+
+    /// Adds a binding to the layout.
+    pub fn binding(
+        mut self,
+        binding: u32,
+        ty: vk::DescriptorType,
+        count: u32,
+        stages: vk::ShaderStageFlags,
+    ) -> DescriptorSetLayoutInfo {
+        self.bindings.push(DescriptorBinding {
+            binding,
+            ty,
+            count,
+            stages,
+        });
+        self
+    }
+
+    /// Allows descriptors in sets with this layout to be partially bound and
+    /// updated after the set is bound.
+    ///
+    /// Only sampler, sampled image, combined image sampler and storage buffer
+    /// bindings may be updated after the set is bound; bindings of other types
+    /// are only partially bound. Layouts with dynamic uniform or storage
+    /// buffer bindings cannot be created.
+    ///
+    /// Sets with such a layout must be allocated from a pool created with
+    /// `UPDATE_AFTER_BIND`, and the device must have been created with
+    /// bindless descriptors enabled.
+    pub fn update_after_bind(mut self) -> DescriptorSetLayoutInfo {
+        self.update_after_bind = true;
+        self
+    }
+
+    pub fn bindings(&self) -> &[DescriptorBinding] {
+        &self.bindings
+    }
+
+    pub fn is_update_after_bind(&self) -> bool {
+        self.update_after_bind
+    }
+}
+
+/// Returns the binding flags of a binding of type `ty` in an
+/// update-after-bind layout.
+///
+/// Updating after bind is only allowed for the descriptor types whose
+/// features are enabled along with bindless descriptors.
+fn update_after_bind_flags(ty: vk::DescriptorType) -> vk::DescriptorBindingFlags {
+    let flags = vk::DescriptorBindingFlags::PARTIALLY_BOUND
+        | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+
+    match ty {
+        vk::DescriptorType::SAMPLER
+        | vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+        | vk::DescriptorType::SAMPLED_IMAGE
+        | vk::DescriptorType::STORAGE_BUFFER => {
+            flags | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+        }
+        _ => flags,
+    }
+}
+
+/// Returns whether a binding of type `ty` may appear in an update-after-bind
+/// layout.
+///
+/// Dynamic buffers are forbidden in such layouts even without the
+/// `UPDATE_AFTER_BIND` binding flag.
+fn allowed_in_update_after_bind(ty: vk::DescriptorType) -> bool {
+    !matches!(
+        ty,
+        vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+    )
+}
+
+/// A device-wide cache of descriptor set layouts.
+#[derive(Default)]
+pub(crate) struct DescriptorSetLayoutCache {
+    layouts: HashMap<DescriptorSetLayoutInfo, vks::DescriptorSetLayout>,
+}
+
+impl DescriptorSetLayoutCache {
+    /// Returns the raw handle of the layout described by `info`, creating it
+    /// if it does not yet exist.
+    ///
+    /// Returns `ERROR_VALIDATION_FAILED_EXT` if `info` is update-after-bind
+    /// and has a dynamic buffer binding.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device that owns all layouts in the cache.
+    /// - The returned handle must not be used after the cache is destroyed.
+    pub unsafe fn get_or_create(
+        &mut self,
+        device: &vks::Device,
+        info: &DescriptorSetLayoutInfo,
+    ) -> vks::VkResult<vk::DescriptorSetLayout> {
+        if let Some(layout) = self.layouts.get(info) {
+            return Ok(unsafe { *layout.handle() });
+        }
+
+        if info.update_after_bind
+            && !info
+                .bindings
+                .iter()
+                .all(|b| allowed_in_update_after_bind(b.ty))
+        {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+
+        let bindings = info
+            .bindings
+            .iter()
+            .map(|b| {
+                vk::DescriptorSetLayoutBindingBuilder::new()
+                    .binding(b.binding)
+                    .descriptor_type(b.ty)
+                    .descriptor_count(b.count)
+                    .stage_flags(b.stages)
+            })
+            .collect::<Vec<_>>();
+
+        let binding_flags = info
+            .bindings
+            .iter()
+            .map(|b| update_after_bind_flags(b.ty))
+            .collect::<Vec<_>>();
+        let binding_flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfoBuilder::new()
+            .binding_flags(&binding_flags);
+
+        let mut create_info = vk::DescriptorSetLayoutCreateInfoBuilder::new().bindings(&bindings);
+        if info.update_after_bind {
+            create_info = create_info
+                .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+                .extend_from(&binding_flags_info);
+        }
+        let layout = unsafe { device.create_descriptor_set_layout(&create_info)? };
+        let raw = unsafe { *layout.handle() };
+        self.layouts.insert(info.clone(), layout);
+
+        Ok(raw)
+    }
+
+    /// Destroys all cached layouts.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device that owns all layouts in the cache.
+    /// - No pipeline layouts or descriptor sets created from the cached
+    ///   layouts may be in use.
+    pub unsafe fn destroy_with(&mut self, device: &vks::Device) {
+        for (_, layout) in self.layouts.drain() {
+            unsafe { device.destroy_descriptor_set_layout(layout) };
+        }
+    }
+}
+
+/// The number of descriptor sets which can be allocated from a single pool.
+const SETS_PER_POOL: u32 = 256;
+
+/// The number of descriptors of each type reserved per pool, as a multiple of
+/// `SETS_PER_POOL`.
+const POOL_RATIOS: &[(vk::DescriptorType, f32)] = &[
+    (vk::DescriptorType::SAMPLER, 0.5),
+    (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4.0),
+    (vk::DescriptorType::SAMPLED_IMAGE, 4.0),
+    (vk::DescriptorType::STORAGE_IMAGE, 1.0),
+    (vk::DescriptorType::UNIFORM_BUFFER, 2.0),
+    (vk::DescriptorType::STORAGE_BUFFER, 2.0),
+    (vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC, 1.0),
+    (vk::DescriptorType::STORAGE_BUFFER_DYNAMIC, 1.0),
+    (vk::DescriptorType::INPUT_ATTACHMENT, 0.5),
+];
+
+#[derive(Default)]
+pub struct DroppedDescriptorAllocator;
+
+impl fmt::Display for DroppedDescriptorAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DescriptorAllocator must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// A growable descriptor set allocator.
+///
+/// Descriptor sets are allocated from a list of pools. When the current pool
+/// is exhausted, a new one is created. Individual sets are never freed;
+/// instead, all sets are released at once with `reset`.
+pub struct DescriptorAllocator {
+    bomb: ErrorOnDrop<DroppedDescriptorAllocator>,
+
+    /// Pools which have been reset and may be reused.
+    free_pools: Vec<vks::DescriptorPool>,
+    /// Pools with outstanding allocations. The last pool is the current one.
+    used_pools: Vec<vks::DescriptorPool>,
+}
+
+impl Default for DescriptorAllocator {
+    fn default() -> Self {
+        DescriptorAllocator::new()
+    }
+}
+
+impl DescriptorAllocator {
+    pub fn new() -> DescriptorAllocator {
+        DescriptorAllocator {
+            bomb: ErrorOnDrop::default(),
+            free_pools: Vec::new(),
+            used_pools: Vec::new(),
+        }
+    }
+
+    unsafe fn create_pool(device: &vks::Device) -> vks::VkResult<vks::DescriptorPool> {
+        let pool_sizes = POOL_RATIOS
+            .iter()
+            .map(|&(ty, ratio)| {
+                vk::DescriptorPoolSizeBuilder::new()
+                    ._type(ty)
+                    .descriptor_count((ratio * SETS_PER_POOL as f32) as u32)
+            })
+            .collect::<Vec<_>>();
+
+        let create_info = vk::DescriptorPoolCreateInfoBuilder::new()
+            .max_sets(SETS_PER_POOL)
+            .pool_sizes(&pool_sizes);
+
+        unsafe { device.create_descriptor_pool(&create_info) }
+    }
+
+    unsafe fn next_pool(&mut self, device: &vks::Device) -> vks::VkResult<()> {
+        let pool = match self.free_pools.pop() {
+            Some(p) => p,
+            None => unsafe { DescriptorAllocator::create_pool(device)? },
+        };
+
+        self.use_pool(pool);
+
+        Ok(())
+    }
+
+    /// Makes `pool` the pool that new sets are allocated from.
+    fn use_pool(&mut self, pool: vks::DescriptorPool) {
+        // The allocator owns a Vulkan object from here on. Pools are only
+        // released by `destroy_with`, so the bomb stays armed across resets.
+        self.bomb.arm();
+        self.used_pools.push(pool);
+    }
+
+    /// Moves all used pools to the free list once they have been reset.
+    fn recycle_pools(&mut self) {
+        self.free_pools.append(&mut self.used_pools);
+    }
+
+    /// Allocates a descriptor set with the given layout.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device that owns `layout` and all pools owned by
+    ///   this allocator.
+    /// - The returned descriptor set must not be used after the next call to
+    ///   `reset` or `destroy_with`.
+    pub(crate) unsafe fn allocate_raw(
+        &mut self,
+        device: &vks::Device,
+        layout: vk::DescriptorSetLayout,
+    ) -> vks::VkResult<vks::DescriptorSet> {
+        if self.used_pools.is_empty() {
+            unsafe { self.next_pool(device)? };
+        }
+
+        let set_layouts = &[layout];
+        let mut retried = false;
+        loop {
+            let pool = self.used_pools.last_mut().unwrap();
+            let allocate_info = vks::DescriptorSetAllocateInfoBuilder::new()
+                .descriptor_pool(pool)
+                .set_layouts(set_layouts);
+
+            match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+                Ok(sets) => return Ok(sets.into_iter().next().unwrap()),
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL)
+                    if !retried =>
+                {
+                    retried = true;
+                    unsafe { self.next_pool(device)? };
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Releases all descriptor sets allocated by this allocator.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to descriptor sets allocated by
+    ///   this allocator must have completed execution.
+    pub unsafe fn reset(&mut self, device: &Device) -> vks::VkResult<()> {
+        let device_read = device.inner.read();
+
+        for pool in self.used_pools.iter_mut() {
+            unsafe { device_read.raw.reset_descriptor_pool(pool)? };
+        }
+        self.recycle_pools();
+
+        Ok(())
+    }
+
+    /// Destroys all pools owned by the allocator.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to descriptor sets allocated by
+    ///   this allocator must have completed execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        let device_read = device.inner.read();
+
+        self.bomb.disarm();
+        for pool in self.used_pools.drain(..).chain(self.free_pools.drain(..)) {
+            unsafe { device_read.raw.destroy_descriptor_pool(pool) };
+        }
+    }
+}
+
+/// A set of descriptor allocators, one per frame in flight.
+///
+/// Descriptor sets allocated for a frame remain valid until the same frame
+/// slot is begun again, at which point its allocator is reset.
+pub struct DescriptorArena {
+    frames: Vec<DescriptorAllocator>,
+    current: usize,
+}
+
+impl DescriptorArena {
+    pub fn new(frames_in_flight: usize) -> DescriptorArena {
+        assert!(frames_in_flight > 0);
+
+        DescriptorArena {
+            frames: (0..frames_in_flight)
+                .map(|_| DescriptorAllocator::new())
+                .collect(),
+            current: 0,
+        }
+    }
+
+    /// Begins a new frame, releasing all descriptor sets previously allocated
+    /// in the same frame slot.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands from the last frame which used `frame_index`
+    ///   must have completed execution.
+    pub unsafe fn begin_frame(&mut self, device: &Device, frame_index: usize) -> vks::VkResult<()> {
+        self.current = frame_index % self.frames.len();
+        unsafe { self.frames[self.current].reset(device) }
+    }
+
+    /// Returns the allocator for the current frame.
+    pub fn allocator(&mut self) -> &mut DescriptorAllocator {
+        &mut self.frames[self.current]
+    }
+
+    /// Destroys the allocators for all frames.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to descriptor sets allocated from
+    ///   the arena must have completed execution.
+    pub unsafe fn destroy_with(self, device: &Device) {
+        for allocator in self.frames {
+            unsafe { allocator.destroy_with(device) };
+        }
+    }
+}
+
+/// The binding of the sampled image array in a [`BindlessTable`].
+pub const BINDLESS_IMAGE_BINDING: u32 = 0;
+/// The binding of the storage buffer array in a [`BindlessTable`].
+pub const BINDLESS_BUFFER_BINDING: u32 = 1;
+
+/// An index into one of the descriptor arrays of a [`BindlessTable`].
+///
+/// Shaders use the index to select a descriptor from the array, typically
+/// after receiving it in a push constant or buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BindlessIndex(pub u32);
+
+/// Slot bookkeeping for one descriptor array of a bindless table.
+struct BindlessSlots<K> {
+    capacity: u32,
+    next: u32,
+    free: Vec<u32>,
+    indices: HashMap<K, u32>,
+}
+
+impl<K: Copy + Eq + std::hash::Hash> BindlessSlots<K> {
+    fn new(capacity: u32) -> BindlessSlots<K> {
+        BindlessSlots {
+            capacity,
+            next: 0,
+            free: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    /// Returns the slot of `key` and whether it was newly assigned.
+    fn insert(&mut self, key: K) -> Option<(u32, bool)> {
+        if let Some(&index) = self.indices.get(&key) {
+            return Some((index, false));
+        }
+
+        let index = match self.free.pop() {
+            Some(i) => i,
+            None if self.next < self.capacity => {
+                self.next += 1;
+                self.next - 1
+            }
+            None => return None,
+        };
+        self.indices.insert(key, index);
+
+        Some((index, true))
+    }
+
+    fn remove(&mut self, key: K) -> Option<u32> {
+        let index = self.indices.remove(&key)?;
+        self.free.push(index);
+        Some(index)
+    }
+}
+
+#[derive(Default)]
+pub struct DroppedBindlessTable;
+
+impl fmt::Display for DroppedBindlessTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BindlessTable must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// A single long-lived descriptor set holding arrays of sampled images and
+/// storage buffers, indexed from shaders by [`BindlessIndex`].
+///
+/// Combined image samplers are bound at [`BINDLESS_IMAGE_BINDING`] and
+/// storage buffers at [`BINDLESS_BUFFER_BINDING`]. Adding the same resource
+/// twice returns the same index. Slots freed by `remove_*` are reused.
+///
+/// Requires a device created with [`DeviceDescriptor::bindless`] set.
+///
+/// [`DeviceDescriptor::bindless`]: crate::DeviceDescriptor::bindless
+pub struct BindlessTable {
+    bomb: ErrorOnDrop<DroppedBindlessTable>,
+
+    pool: vks::DescriptorPool,
+    set: vks::DescriptorSet,
+    images: BindlessSlots<(vk::ImageView, vk::Sampler)>,
+    buffers: BindlessSlots<vk::Buffer>,
+}
+
+impl BindlessTable {
+    /// Creates a bindless table with room for `image_capacity` sampled images
+    /// and `buffer_capacity` storage buffers.
+    pub fn create(
+        device: &Device,
+        image_capacity: u32,
+        buffer_capacity: u32,
+    ) -> Result<BindlessTable, Error> {
+        assert!(image_capacity > 0);
+        assert!(buffer_capacity > 0);
+
+        let device_read = device.inner.read();
+        if !device_read.is_bindless_enabled() {
+            return Err(Error::BindlessUnsupported);
+        }
+
+        let info = BindlessTable::layout_info_with(image_capacity, buffer_capacity);
+        let layout = unsafe {
+            device_read
+                .descriptor_set_layouts
+                .lock()
+                .get_or_create(&device_read.raw, &info)?
+        };
+
+        let pool_sizes = [
+            vk::DescriptorPoolSizeBuilder::new()
+                ._type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(image_capacity),
+            vk::DescriptorPoolSizeBuilder::new()
+                ._type(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(buffer_capacity),
+        ];
+        let create_info = vk::DescriptorPoolCreateInfoBuilder::new()
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .max_sets(1)
+            .pool_sizes(&pool_sizes);
+        let mut pool = unsafe { device_read.raw.create_descriptor_pool(&create_info)? };
+
+        let set_layouts = &[layout];
+        let allocate_info = vks::DescriptorSetAllocateInfoBuilder::new()
+            .descriptor_pool(&mut pool)
+            .set_layouts(set_layouts);
+        let set = match unsafe { device_read.raw.allocate_descriptor_sets(&allocate_info) } {
+            Ok(sets) => sets.into_iter().next().unwrap(),
+            Err(e) => {
+                unsafe { device_read.raw.destroy_descriptor_pool(pool) };
+                return Err(e.into());
+            }
+        };
+
+        Ok(BindlessTable {
+            bomb: ErrorOnDrop::new(DroppedBindlessTable),
+            pool,
+            set,
+            images: BindlessSlots::new(image_capacity),
+            buffers: BindlessSlots::new(buffer_capacity),
+        })
+    }
+
+    fn layout_info_with(image_capacity: u32, buffer_capacity: u32) -> DescriptorSetLayoutInfo {
+        DescriptorSetLayoutInfo::new()
+            .binding(
+                BINDLESS_IMAGE_BINDING,
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                image_capacity,
+                vk::ShaderStageFlags::ALL,
+            )
+            .binding(
+                BINDLESS_BUFFER_BINDING,
+                vk::DescriptorType::STORAGE_BUFFER,
+                buffer_capacity,
+                vk::ShaderStageFlags::ALL,
+            )
+            .update_after_bind()
+    }
+
+    /// Returns the layout of the table's descriptor set, for use in pipeline
+    /// layouts.
+    pub fn layout_info(&self) -> DescriptorSetLayoutInfo {
+        BindlessTable::layout_info_with(self.images.capacity, self.buffers.capacity)
+    }
+
+    /// Returns the table's descriptor set.
+    pub fn set(&self) -> vk::DescriptorSet {
+        unsafe { *self.set.handle() }
+    }
+
+    /// Adds a combined image sampler to the table and returns its index.
+    ///
+    /// The image must be in `SHADER_READ_ONLY_OPTIMAL` layout when accessed.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `view` and `sampler` must be valid handles associated with `device`.
+    /// - `view` and `sampler` must not be destroyed while they remain in the
+    ///   table.
+    pub unsafe fn add_sampled_image(
+        &mut self,
+        device: &Device,
+        view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Result<BindlessIndex, Error> {
+        let (index, new) = self
+            .images
+            .insert((view, sampler))
+            .ok_or(Error::BindlessTableFull("image"))?;
+
+        if new {
+            let image_info = [vk::DescriptorImageInfoBuilder::new()
+                .sampler(sampler)
+                .image_view(view)
+                .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+            let write = vk::WriteDescriptorSetBuilder::new()
+                .dst_set(self.set())
+                .dst_binding(BINDLESS_IMAGE_BINDING)
+                .dst_array_element(index)
+                .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .image_info(&image_info);
+            unsafe { device.update_descriptor_sets(&[write]) };
+        }
+
+        Ok(BindlessIndex(index))
+    }
+
+    /// Adds a storage buffer to the table and returns its index.
+    ///
+    /// The whole buffer is bound.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `buffer` must be a valid handle associated with `device`.
+    /// - `buffer` must not be destroyed while it remains in the table.
+    pub unsafe fn add_storage_buffer(
+        &mut self,
+        device: &Device,
+        buffer: vk::Buffer,
+    ) -> Result<BindlessIndex, Error> {
+        let (index, new) = self
+            .buffers
+            .insert(buffer)
+            .ok_or(Error::BindlessTableFull("buffer"))?;
+
+        if new {
+            let buffer_info = [vk::DescriptorBufferInfoBuilder::new()
+                .buffer(buffer)
+                .offset(0)
+                .range(vk::WHOLE_SIZE)];
+            let write = vk::WriteDescriptorSetBuilder::new()
+                .dst_set(self.set())
+                .dst_binding(BINDLESS_BUFFER_BINDING)
+                .dst_array_element(index)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&buffer_info);
+            unsafe { device.update_descriptor_sets(&[write]) };
+        }
+
+        Ok(BindlessIndex(index))
+    }
+
+    /// Removes a combined image sampler from the table, returning its former
+    /// index so that it may be reused.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - No pending command buffer may access the removed index.
+    pub unsafe fn remove_sampled_image(
+        &mut self,
+        view: vk::ImageView,
+        sampler: vk::Sampler,
+    ) -> Option<BindlessIndex> {
+        self.images.remove((view, sampler)).map(BindlessIndex)
+    }
+
+    /// Removes a storage buffer from the table, returning its former index
+    /// so that it may be reused.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - No pending command buffer may access the removed index.
+    pub unsafe fn remove_storage_buffer(&mut self, buffer: vk::Buffer) -> Option<BindlessIndex> {
+        self.buffers.remove(buffer).map(BindlessIndex)
+    }
+
+    /// Destroys the table.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to the table's descriptor set must
+    ///   have completed execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+        unsafe { device.inner.read().raw.destroy_descriptor_pool(self.pool) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_pool(raw: u64) -> vks::DescriptorPool {
+        unsafe { vks::DescriptorPool::new(vk::DescriptorPool(raw)) }
+    }
+
+    #[test]
+    fn reset_then_allocate_keeps_bomb_armed() {
+        let mut alloc = DescriptorAllocator::new();
+        assert!(!alloc.bomb.is_armed());
+
+        alloc.use_pool(fake_pool(1));
+        assert!(alloc.bomb.is_armed());
+
+        // Reset, then allocate again from the recycled pool.
+        alloc.recycle_pools();
+        assert!(alloc.used_pools.is_empty());
+        let pool = alloc.free_pools.pop().unwrap();
+        alloc.use_pool(pool);
+        assert!(alloc.bomb.is_armed());
+        assert_eq!(alloc.used_pools.len(), 1);
+
+        alloc.bomb.disarm();
+    }
+
+    #[test]
+    fn update_after_bind_only_for_enabled_types() {
+        use vk::DescriptorBindingFlags as F;
+
+        for ty in [
+            vk::DescriptorType::SAMPLER,
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            vk::DescriptorType::SAMPLED_IMAGE,
+            vk::DescriptorType::STORAGE_BUFFER,
+        ] {
+            assert!(update_after_bind_flags(ty).contains(F::UPDATE_AFTER_BIND));
+        }
+
+        for ty in [
+            vk::DescriptorType::UNIFORM_BUFFER,
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+            vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+            vk::DescriptorType::STORAGE_IMAGE,
+            vk::DescriptorType::INPUT_ATTACHMENT,
+        ] {
+            let flags = update_after_bind_flags(ty);
+            assert!(!flags.contains(F::UPDATE_AFTER_BIND));
+            assert!(flags.contains(F::PARTIALLY_BOUND));
+        }
+
+        assert!(allowed_in_update_after_bind(
+            vk::DescriptorType::UNIFORM_BUFFER
+        ));
+        assert!(!allowed_in_update_after_bind(
+            vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+        ));
+        assert!(!allowed_in_update_after_bind(
+            vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+        ));
+    }
+}
@@ -1,14 +1,17 @@
-use std::cmp;
+use std::{cmp, collections::VecDeque, ffi::CStr, sync::Arc};
 
 use arrayvec::ArrayVec;
 use erupt::vk;
 
 use crate::{
+    mem::AllocationKind,
+    util,
     vks::{self, VkObject},
-    Device,
+    Buffer, BufferCreateInfo, Device, DeviceInner, Error, FormatExt, MemoryLocation,
+    PipelineTarget,
 };
 
-const MAX_FRAMES_IN_FLIGHT: usize = 2;
+const MAX_FRAMES_IN_FLIGHT: usize = 3;
 const SWAPCHAIN_CHOOSES_EXTENT: vk::Extent2D = vk::Extent2D {
     width: u32::MAX,
     height: u32::MAX,
@@ -24,15 +27,61 @@ pub struct FrameInFlight {
 
     // Only used if graphics and present queues are from different families.
     present_queue_ownership: vks::Semaphore,
+
+    // Allocated on the first capture of this frame.
+    capture_commands: Option<vks::CommandBuffer>,
 }
 
 impl FrameInFlight {
+    unsafe fn create(device: &vks::Device) -> vks::VkResult<FrameInFlight> {
+        let fence_create_info =
+            vk::FenceCreateInfoBuilder::new().flags(vk::FenceCreateFlags::SIGNALED);
+        let in_flight = unsafe { device.create_fence(&fence_create_info)? };
+
+        let semaphore_create_info = vk::SemaphoreCreateInfoBuilder::new();
+        let mut semaphores = ArrayVec::<_, 3>::new();
+        for _ in 0..3 {
+            match unsafe { device.create_semaphore(&semaphore_create_info) } {
+                Ok(semaphore) => semaphores.push(semaphore),
+                Err(e) => unsafe {
+                    device.destroy_fence(in_flight);
+                    for semaphore in semaphores {
+                        device.destroy_semaphore(semaphore);
+                    }
+                    return Err(e);
+                },
+            }
+        }
+
+        let mut semaphores = semaphores.into_iter();
+        Ok(FrameInFlight {
+            in_flight,
+            image_available: semaphores.next().unwrap(),
+            render_complete: semaphores.next().unwrap(),
+            present_queue_ownership: semaphores.next().unwrap(),
+            capture_commands: None,
+        })
+    }
+
+    /// Signals the in-flight fence again after a submission that would have
+    /// signaled it failed, so that waiting on the frame does not block forever.
+    unsafe fn resignal_fence(&mut self, device: &vks::Device, queue: &mut vks::Queue) {
+        // An empty submission signals the fence once prior work on the queue
+        // completes.
+        if let Err(e) = unsafe { device.queue_submit(queue, &[], Some(&mut self.in_flight)) } {
+            log::error!("Failed to signal in-flight fence: {}", e);
+        }
+    }
+
     fn destroy_with(self, device: &vks::Device) {
         let FrameInFlight {
             in_flight,
             image_available,
             render_complete,
             present_queue_ownership,
+            // Command buffers are destroyed automatically along with their
+            // owning pools.
+            capture_commands: _,
         } = self;
 
         unsafe {
@@ -49,19 +98,142 @@ pub struct SwapchainImage {
     present_commands: vks::CommandBuffer,
     graphics_commands: vks::CommandBuffer,
     framebuffer: Option<vks::Framebuffer>,
+    // Rendered to and resolved into `image` when multisampling is enabled.
+    multisample: Option<MultisampleTarget>,
+    view: vks::ImageView,
+    image: vks::Image,
+}
+
+/// A multisampled color image which is resolved into a swapchain image.
+struct MultisampleTarget {
     view: vks::ImageView,
     image: vks::Image,
+    memory: vks::DeviceMemory,
+    memory_type: u32,
+    allocation_size: vk::DeviceSize,
+}
+
+impl MultisampleTarget {
+    unsafe fn create(
+        device: &Device,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlagBits,
+    ) -> Result<MultisampleTarget, Error> {
+        let device_read = device.inner.read();
+
+        let create_info = vk::ImageCreateInfoBuilder::new()
+            .image_type(vk::ImageType::_2D)
+            .format(format)
+            .extent(util::extent_2d_to_3d(extent))
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(samples)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            // The contents never leave the render pass, so the image may be
+            // backed by lazily allocated memory on tiled GPUs.
+            .usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+
+        let mut image = unsafe { device_read.raw.create_image(&create_info)? };
+
+        let requirements = unsafe { device_read.raw.get_image_memory_requirements(&image) };
+        let type_index = device_read
+            .phys_device
+            .memory_types()
+            .transient_type_index(requirements.memory_type_bits);
+        assert!(
+            requirements.memory_type_bits & (1 << type_index) != 0,
+            "multisample image is not compatible with device-local memory",
+        );
+
+        let allocate_info = vk::MemoryAllocateInfoBuilder::new()
+            .allocation_size(requirements.size)
+            .memory_type_index(type_index);
+        let memory = match unsafe { device_read.raw.allocate_memory(&allocate_info) } {
+            Ok(memory) => memory,
+            Err(e) => {
+                unsafe { device_read.raw.destroy_image(image) };
+                return Err(e.into());
+            }
+        };
+
+        if let Err(e) = unsafe { device_read.raw.bind_image_memory(&mut image, &memory, 0) } {
+            unsafe {
+                device_read.raw.destroy_image(image);
+                device_read.raw.free_memory(memory);
+            }
+            return Err(e.into());
+        }
+        device_read.memory_stats.record(
+            AllocationKind::TransientImage,
+            type_index,
+            requirements.size,
+        );
+
+        let view = unsafe {
+            device_read.raw.create_image_view(
+                &vks::ImageViewCreateInfoBuilder::new()
+                    .image(&image)
+                    .view_type(vk::ImageViewType::_2D)
+                    .format(format)
+                    .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR),
+            )
+        };
+        let view = match view {
+            Ok(view) => view,
+            Err(e) => {
+                unsafe {
+                    device_read.raw.destroy_image(image);
+                    device_read.raw.free_memory(memory);
+                }
+                device_read.memory_stats.release(
+                    AllocationKind::TransientImage,
+                    type_index,
+                    requirements.size,
+                );
+                return Err(e.into());
+            }
+        };
+
+        Ok(MultisampleTarget {
+            view,
+            image,
+            memory,
+            memory_type: type_index,
+            allocation_size: requirements.size,
+        })
+    }
+
+    fn destroy_with(self, device: &DeviceInner) {
+        unsafe {
+            device.raw.destroy_image_view(self.view);
+            device.raw.destroy_image(self.image);
+            device.raw.free_memory(self.memory);
+        }
+        device.memory_stats.release(
+            AllocationKind::TransientImage,
+            self.memory_type,
+            self.allocation_size,
+        );
+    }
 }
 
 impl SwapchainImage {
-    fn destroy_with(self, device: &vks::Device) {
+    /// Destroys the image's framebuffer and view.
+    ///
+    /// Command buffers are destroyed automatically along with their owning
+    /// pools, so they are returned to the caller for reuse.
+    fn destroy_with(self, device: &DeviceInner) -> CommandBufferPair {
         let SwapchainImage {
             framebuffer,
+            multisample,
             view,
-            // Command buffers are destroyed automatically along with their
-            // owning pools.
-            present_commands: _,
-            graphics_commands: _,
+            present_commands,
+            graphics_commands,
             // Swapchain images are destroyed automatically along with their
             // owning swapchain.
             image: _,
@@ -69,9 +241,225 @@ impl SwapchainImage {
 
         unsafe {
             if let Some(fb) = framebuffer {
-                device.destroy_framebuffer(fb);
+                device.raw.destroy_framebuffer(fb);
+            }
+            device.raw.destroy_image_view(view);
+        }
+
+        if let Some(target) = multisample {
+            target.destroy_with(device);
+        }
+
+        CommandBufferPair {
+            graphics: graphics_commands,
+            present: present_commands,
+        }
+    }
+}
+
+/// Command buffers used to render to and present a swapchain image.
+struct CommandBufferPair {
+    graphics: vks::CommandBuffer,
+    present: vks::CommandBuffer,
+}
+
+/// Selects the swapchain extent for the given surface capabilities.
+fn choose_image_extent(
+    surf_caps: &vk::SurfaceCapabilitiesKHR,
+    phys_window_extent: vk::Extent2D,
+) -> vk::Extent2D {
+    if surf_caps.current_extent.width == SWAPCHAIN_CHOOSES_EXTENT.width
+        && surf_caps.current_extent.height == SWAPCHAIN_CHOOSES_EXTENT.height
+    {
+        phys_window_extent
+    } else {
+        surf_caps.current_extent
+    }
+}
+
+/// A swapchain presentation mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PresentModePreference {
+    /// Presents images immediately, without vertical sync. May tear.
+    Immediate,
+    /// Replaces the queued image with each new one, presenting the latest
+    /// image at vertical blank. Does not tear.
+    Mailbox,
+    /// Queues images for presentation at vertical blank. Does not tear, and is
+    /// supported by all implementations.
+    Fifo,
+    /// Like `Fifo`, but presents late images immediately. May tear when the
+    /// application misses a vertical blank, which suits variable refresh rate
+    /// displays.
+    FifoRelaxed,
+}
+
+impl PresentModePreference {
+    /// Returns the preference corresponding to a Vulkan presentation mode, if
+    /// any.
+    pub fn from_vk(mode: vk::PresentModeKHR) -> Option<PresentModePreference> {
+        match mode {
+            vk::PresentModeKHR::IMMEDIATE_KHR => Some(PresentModePreference::Immediate),
+            vk::PresentModeKHR::MAILBOX_KHR => Some(PresentModePreference::Mailbox),
+            vk::PresentModeKHR::FIFO_KHR => Some(PresentModePreference::Fifo),
+            vk::PresentModeKHR::FIFO_RELAXED_KHR => Some(PresentModePreference::FifoRelaxed),
+            _ => None,
+        }
+    }
+}
+
+impl From<PresentModePreference> for vk::PresentModeKHR {
+    fn from(pref: PresentModePreference) -> Self {
+        match pref {
+            PresentModePreference::Immediate => vk::PresentModeKHR::IMMEDIATE_KHR,
+            PresentModePreference::Mailbox => vk::PresentModeKHR::MAILBOX_KHR,
+            PresentModePreference::Fifo => vk::PresentModeKHR::FIFO_KHR,
+            PresentModePreference::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED_KHR,
+        }
+    }
+}
+
+/// Selects the first preferred presentation mode supported by the surface,
+/// falling back to FIFO.
+fn choose_present_mode(
+    preferences: &[PresentModePreference],
+    supported: &[vk::PresentModeKHR],
+) -> vk::PresentModeKHR {
+    preferences
+        .iter()
+        .map(|&pref| vk::PresentModeKHR::from(pref))
+        .find(|pm| supported.contains(pm))
+        // Implementations are required to support FIFO.
+        .unwrap_or(vk::PresentModeKHR::FIFO_KHR)
+}
+
+/// A candidate swapchain surface format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SurfaceFormatPreference {
+    pub format: vk::Format,
+    pub color_space: vk::ColorSpaceKHR,
+}
+
+impl SurfaceFormatPreference {
+    pub const fn new(
+        format: vk::Format,
+        color_space: vk::ColorSpaceKHR,
+    ) -> SurfaceFormatPreference {
+        SurfaceFormatPreference {
+            format,
+            color_space,
+        }
+    }
+
+    fn matches(&self, surface_format: &vk::SurfaceFormatKHR) -> bool {
+        surface_format.format == self.format && surface_format.color_space == self.color_space
+    }
+}
+
+/// The color encoding a renderer expects of the swapchain it outputs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OutputEncoding {
+    /// Shaders output linear color, which the swapchain encodes to sRGB on
+    /// write. This requires an `_SRGB` swapchain format.
+    LinearToSrgb,
+    /// Shaders output sRGB-encoded color, which the swapchain stores as-is.
+    /// This requires a `_UNORM` swapchain format.
+    EncodedSrgb,
+    /// Shaders output PQ-encoded BT.2020 color for HDR10 displays.
+    Hdr10,
+    /// Shaders output linear scRGB color, which may exceed [0, 1].
+    ExtendedLinear,
+}
+
+impl OutputEncoding {
+    /// Returns the surface formats which provide this encoding, in order of
+    /// preference.
+    pub fn surface_formats(self) -> Vec<SurfaceFormatPreference> {
+        use vk::{ColorSpaceKHR as Cs, Format as F};
+
+        let prefs: &[(vk::Format, vk::ColorSpaceKHR)] = match self {
+            OutputEncoding::LinearToSrgb => &[
+                (F::B8G8R8A8_SRGB, Cs::SRGB_NONLINEAR_KHR),
+                (F::R8G8B8A8_SRGB, Cs::SRGB_NONLINEAR_KHR),
+                (F::A8B8G8R8_SRGB_PACK32, Cs::SRGB_NONLINEAR_KHR),
+            ],
+            OutputEncoding::EncodedSrgb => &[
+                (F::B8G8R8A8_UNORM, Cs::SRGB_NONLINEAR_KHR),
+                (F::R8G8B8A8_UNORM, Cs::SRGB_NONLINEAR_KHR),
+                (F::A2B10G10R10_UNORM_PACK32, Cs::SRGB_NONLINEAR_KHR),
+            ],
+            OutputEncoding::Hdr10 => &[
+                (F::A2B10G10R10_UNORM_PACK32, Cs::HDR10_ST2084_EXT),
+                (F::A2R10G10B10_UNORM_PACK32, Cs::HDR10_ST2084_EXT),
+            ],
+            OutputEncoding::ExtendedLinear => {
+                &[(F::R16G16B16A16_SFLOAT, Cs::EXTENDED_SRGB_LINEAR_EXT)]
             }
-            device.destroy_image_view(view);
+        };
+
+        prefs
+            .iter()
+            .map(|&(format, color_space)| SurfaceFormatPreference::new(format, color_space))
+            .collect()
+    }
+}
+
+/// Configuration values for a [`Display`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayConfig {
+    /// Presentation modes to use, in order of preference.
+    ///
+    /// The first mode supported by the surface is selected. If none are
+    /// supported, `Fifo` is used, as all implementations must support it.
+    pub present_modes: Vec<PresentModePreference>,
+
+    /// Surface formats to use, in order of preference.
+    ///
+    /// The first format supported by the surface is selected. If none are
+    /// supported, the first format reported by the surface is used.
+    pub surface_formats: Vec<SurfaceFormatPreference>,
+
+    /// Usage flags requested for swapchain images in addition to
+    /// `COLOR_ATTACHMENT`.
+    ///
+    /// For example, `STORAGE` allows a compute shader to write the swapchain
+    /// image directly. Flags which the surface or the selected format do not
+    /// support are dropped with a warning; check [`DisplayInfo::image_usage`]
+    /// for the usage actually granted.
+    pub extra_image_usage: vk::ImageUsageFlags,
+
+    /// The number of samples per pixel to render with.
+    ///
+    /// If greater than one, pipelines render to multisampled images which are
+    /// resolved into the swapchain images at the end of each render pass. If
+    /// the device does not support the sample count, a single sample is used.
+    pub samples: vk::SampleCountFlagBits,
+
+    /// The number of frames which may be recorded and submitted before
+    /// waiting for the GPU. Must be between 1 and 3 inclusive, or
+    /// [`Display::create`] returns [`Error::InvalidFramesInFlight`].
+    pub frames_in_flight: usize,
+}
+
+impl DisplayConfig {
+    /// Replaces the preferred surface formats with those providing
+    /// `encoding`.
+    ///
+    /// For a render graph, pass the graph's `output_encoding`.
+    pub fn output_encoding(mut self, encoding: OutputEncoding) -> DisplayConfig {
+        self.surface_formats = encoding.surface_formats();
+        self
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            present_modes: vec![PresentModePreference::Mailbox],
+            surface_formats: OutputEncoding::LinearToSrgb.surface_formats(),
+            extra_image_usage: vk::ImageUsageFlags::empty(),
+            samples: vk::SampleCountFlagBits::_1,
+            frames_in_flight: 2,
         }
     }
 }
@@ -79,18 +467,178 @@ impl SwapchainImage {
 pub struct DisplayInfo {
     pub min_image_count: u32,
     pub surface_format: vk::SurfaceFormatKHR,
+    /// All surface formats supported by the surface.
+    pub supported_surface_formats: Vec<vk::SurfaceFormatKHR>,
     pub image_extent: vk::Extent2D,
+    /// The usage flags of the swapchain images.
+    pub image_usage: vk::ImageUsageFlags,
+    /// The number of samples per pixel rendered.
+    pub samples: vk::SampleCountFlagBits,
     pub present_mode: vk::PresentModeKHR,
+    /// All presentation modes supported by the surface.
+    pub supported_present_modes: Vec<vk::PresentModeKHR>,
+}
+
+/// Mastering display and content light level metadata for HDR output.
+///
+/// Chromaticities are given as CIE 1931 xy coordinates, luminance in nits.
+#[derive(Copy, Clone, Debug)]
+pub struct HdrMetadata {
+    pub display_primary_red: vk::XYColorEXT,
+    pub display_primary_green: vk::XYColorEXT,
+    pub display_primary_blue: vk::XYColorEXT,
+    pub white_point: vk::XYColorEXT,
+    /// The maximum luminance of the mastering display.
+    pub max_luminance: f32,
+    /// The minimum luminance of the mastering display.
+    pub min_luminance: f32,
+    /// The maximum content light level (MaxCLL).
+    pub max_content_light_level: f32,
+    /// The maximum frame-average light level (MaxFALL).
+    pub max_frame_average_light_level: f32,
+}
+
+fn is_hdr_color_space(color_space: vk::ColorSpaceKHR) -> bool {
+    matches!(
+        color_space,
+        vk::ColorSpaceKHR::HDR10_ST2084_EXT
+            | vk::ColorSpaceKHR::HDR10_HLG_EXT
+            | vk::ColorSpaceKHR::DOLBYVISION_EXT
+            | vk::ColorSpaceKHR::BT2020_LINEAR_EXT
+            | vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT
+            | vk::ColorSpaceKHR::EXTENDED_SRGB_NONLINEAR_EXT
+    )
+}
+
+/// A monitor attached directly to a physical device.
+///
+/// Direct displays are presented to without a windowing system, using
+/// [`Display::create_direct`].
+#[derive(Clone, Debug)]
+pub struct DirectDisplay {
+    raw: vk::DisplayKHR,
+    name: Option<String>,
+    physical_resolution: vk::Extent2D,
+    modes: Vec<DirectDisplayMode>,
+}
+
+impl DirectDisplay {
+    /// Enumerates the displays attached to the physical device of `device`.
+    ///
+    /// Displays which are currently driven by a windowing system are
+    /// typically not reported.
+    pub fn enumerate(device: &Device) -> Result<Vec<DirectDisplay>, Error> {
+        let device_read = device.inner.read();
+        let instance_read = device_read.instance.read_inner();
+        let phys = &device_read.phys_device.inner.raw;
+
+        let props = unsafe {
+            instance_read
+                .handle
+                .get_physical_device_display_properties_khr(phys)?
+        };
+
+        props
+            .into_iter()
+            .map(|props| {
+                let modes = unsafe {
+                    instance_read
+                        .handle
+                        .get_display_mode_properties_khr(phys, props.display)?
+                }
+                .into_iter()
+                .map(|mode| DirectDisplayMode {
+                    raw: mode.display_mode,
+                    visible_region: mode.parameters.visible_region,
+                    refresh_rate: mode.parameters.refresh_rate,
+                })
+                .collect();
+
+                let name = (!props.display_name.is_null()).then(|| {
+                    // Safety: the name is a valid NUL-terminated string.
+                    unsafe { CStr::from_ptr(props.display_name) }
+                        .to_string_lossy()
+                        .into_owned()
+                });
+
+                Ok(DirectDisplay {
+                    raw: props.display,
+                    name,
+                    physical_resolution: props.physical_resolution,
+                    modes,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the human-readable name of the display, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the native resolution of the display.
+    pub fn physical_resolution(&self) -> vk::Extent2D {
+        self.physical_resolution
+    }
+
+    /// Returns the built-in modes of the display.
+    pub fn modes(&self) -> &[DirectDisplayMode] {
+        &self.modes
+    }
+}
+
+/// A resolution and refresh rate supported by a [`DirectDisplay`].
+#[derive(Copy, Clone, Debug)]
+pub struct DirectDisplayMode {
+    raw: vk::DisplayModeKHR,
+    visible_region: vk::Extent2D,
+    refresh_rate: u32,
+}
+
+impl DirectDisplayMode {
+    /// Returns the visible region of the display in this mode.
+    pub fn extent(&self) -> vk::Extent2D {
+        self.visible_region
+    }
+
+    /// Returns the refresh rate of this mode, in millihertz.
+    pub fn refresh_rate_mhz(&self) -> u32 {
+        self.refresh_rate
+    }
+}
+
+/// The contents of a swapchain image, copied back to host memory.
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    /// Tightly packed texel data, in row-major order.
+    pub data: Vec<u8>,
+}
+
+/// A capture whose copy has been submitted, but not yet read back.
+struct PendingCapture {
+    frame_index: usize,
+    format: vk::Format,
+    extent: vk::Extent2D,
+    buffer: Buffer,
 }
 
 pub struct Display {
     info: DisplayInfo,
+    hdr_metadata: Option<HdrMetadata>,
 
     current_frame: u64,
 
     frames: ArrayVec<FrameInFlight, MAX_FRAMES_IN_FLIGHT>,
     images: Vec<SwapchainImage>,
     image_frames: Vec<Option<usize>>,
+    // Command buffers left over after the swapchain shrinks.
+    spare_command_buffers: Vec<CommandBufferPair>,
+
+    capture_requested: bool,
+    pending_captures: ArrayVec<PendingCapture, MAX_FRAMES_IN_FLIGHT>,
+    captures: VecDeque<Capture>,
 
     swapchain: Option<vks::SwapchainKHR>,
     surface: Option<vks::SurfaceKHR>,
@@ -101,11 +649,26 @@ impl Drop for Display {
     fn drop(&mut self) {
         let device_read = self.device.inner.read();
 
-        // TODO
-        // self.frame.destroy_with(&device_read.raw);
+        // Wait for all frames to finish before destroying their resources.
+        let fences = self
+            .frames
+            .iter()
+            .map(|frame| unsafe { *frame.in_flight.handle() })
+            .collect::<ArrayVec<_, MAX_FRAMES_IN_FLIGHT>>();
+        if let Err(e) = unsafe { device_read.raw.wait_for_fences(&fences, true, None) } {
+            log::error!("Failed to wait for frames in flight: {}", e);
+        }
+
+        for pending in self.pending_captures.drain(..) {
+            unsafe { pending.buffer.destroy_with(&self.device) };
+        }
+
+        for frame in self.frames.drain(..) {
+            frame.destroy_with(&device_read.raw);
+        }
 
         for si in self.images.drain(..) {
-            si.destroy_with(&device_read.raw)
+            si.destroy_with(&device_read);
         }
 
         if let Some(swapchain) = self.swapchain.take() {
@@ -125,42 +688,39 @@ impl Drop for Display {
 }
 
 impl Display {
-    // Safety: device and surface must be from same instance
+    /// Creates a display which presents to `surface`.
+    ///
+    /// The display takes ownership of `surface`, which is destroyed if
+    /// creation fails.
+    ///
+    /// # Safety
+    ///
+    /// `device` and `surface` must be from the same instance.
     pub unsafe fn create(
         device: &Device,
         mut surface: vks::SurfaceKHR,
         phys_window_extent: vk::Extent2D,
-    ) -> Display {
+        config: DisplayConfig,
+    ) -> Result<Display, Error> {
         let device_read = device.inner.read();
         let instance_read = device_read.instance.read_inner();
 
-        let (surf_caps, surf_formats, surf_present_modes) = unsafe {
-            let instance_handle = &instance_read.handle;
-            let phys = &device_read.phys_device.inner.raw;
+        if !(1..=MAX_FRAMES_IN_FLIGHT).contains(&config.frames_in_flight) {
+            unsafe { instance_read.handle.destroy_surface(surface) };
+            return Err(Error::InvalidFramesInFlight {
+                requested: config.frames_in_flight,
+                max: MAX_FRAMES_IN_FLIGHT,
+            });
+        }
 
-            let surface_supported = instance_handle
-                .get_physical_device_surface_support_khr(
-                    &phys,
-                    device.present_family_id(),
-                    &surface,
-                )
-                .expect("failed to verify physical device surface support");
+        let queried = unsafe { Display::query_surface(device, &surface) };
 
-            if !surface_supported {
-                panic!("Surface not supported with this device.");
+        let (surf_caps, surf_formats, surf_present_modes) = match queried {
+            Ok(q) => q,
+            Err(e) => {
+                unsafe { instance_read.handle.destroy_surface(surface) };
+                return Err(e);
             }
-
-            (
-                instance_handle
-                    .get_physical_device_surface_capabilities_khr(phys, &surface)
-                    .expect("failed to query surface capabilities"),
-                instance_handle
-                    .get_physical_device_surface_formats_khr(phys, &surface)
-                    .expect("failed to query surface formats"),
-                instance_handle
-                    .get_physical_device_surface_present_modes_khr(phys, &surface)
-                    .expect("failed to query surface presentation modes"),
-            )
         };
 
         let min_image_count = {
@@ -184,189 +744,749 @@ impl Display {
             "surface must support IDENTITY_KHR transform",
         );
 
-        let surface_format = *surf_formats
-            .iter()
-            .find(|sf| {
-                sf.format == vk::Format::B8G8R8A8_SRGB
-                    && sf.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR_KHR
-            })
-            .unwrap_or(&surf_formats[0]);
+        let surface_format = *config
+            .surface_formats
+            .iter()
+            .find_map(|pref| surf_formats.iter().find(|sf| pref.matches(sf)))
+            .unwrap_or(&surf_formats[0]);
+
+        log::info!(
+            "Using surface format {:?} with color space {:?}",
+            surface_format.format,
+            surface_format.color_space
+        );
+
+        let image_extent = choose_image_extent(&surf_caps, phys_window_extent);
+
+        let image_usage = {
+            let mut supported = surf_caps.supported_usage_flags;
+
+            // Storage usage also requires support from the image format.
+            let format_features = device_read
+                .phys_device
+                .format_properties(surface_format.format)
+                .optimal_tiling_features;
+            if !format_features.contains(vk::FormatFeatureFlags::STORAGE_IMAGE) {
+                supported.remove(vk::ImageUsageFlags::STORAGE);
+            }
+
+            let unsupported = config.extra_image_usage - supported;
+            if !unsupported.is_empty() {
+                log::warn!(
+                    "Swapchain image usage {:?} is not supported; ignoring.",
+                    unsupported
+                );
+            }
+
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | (config.extra_image_usage & supported)
+        };
+
+        let samples = {
+            let supported = device_read
+                .phys_device
+                .properties()
+                .limits
+                .framebuffer_color_sample_counts;
+            if supported.contains(config.samples.bitmask()) {
+                config.samples
+            } else {
+                log::warn!(
+                    "Sample count {:?} is not supported; using a single sample.",
+                    config.samples
+                );
+                vk::SampleCountFlagBits::_1
+            }
+        };
+
+        let present_mode = choose_present_mode(&config.present_modes, &surf_present_modes);
+
+        log::info!("Using presentation mode {:?}", present_mode);
+
+        let mut create_info = vks::SwapchainCreateInfo {
+            flags: vk::SwapchainCreateFlagsKHR::empty(),
+            surface: &mut surface,
+            min_image_count,
+            image_format: surface_format.format,
+            image_color_space: surface_format.color_space,
+            image_extent,
+            image_array_layers: 1,
+            image_usage,
+            image_sharing_mode: vk::SharingMode::EXCLUSIVE,
+            queue_family_indices: &[],
+            pre_transform: vk::SurfaceTransformFlagBitsKHR::IDENTITY_KHR,
+            composite_alpha: vk::CompositeAlphaFlagBitsKHR::OPAQUE_KHR,
+            present_mode,
+            clipped: true,
+            old_swapchain: None,
+        };
+
+        let swapchain = match unsafe { device_read.raw.create_swapchain_khr(&mut create_info) } {
+            Ok(swapchain) => swapchain,
+            Err(e) => {
+                unsafe { instance_read.handle.destroy_surface(surface) };
+                return Err(e.into());
+            }
+        };
+
+        log::info!("Successfully created swapchain.");
+
+        let mut spare_command_buffers = Vec::new();
+        let swapchain_images = unsafe {
+            Display::create_swapchain_images(
+                device,
+                &swapchain,
+                surface_format.format,
+                image_extent,
+                samples,
+                &mut spare_command_buffers,
+            )
+        };
+        let swapchain_images = match swapchain_images {
+            Ok(images) => images,
+            Err(e) => {
+                unsafe {
+                    device_read.raw.destroy_swapchain_khr(swapchain);
+                    instance_read.handle.destroy_surface(surface);
+                }
+                return Err(e);
+            }
+        };
+        let mut image_frames = Vec::with_capacity(swapchain_images.len());
+        image_frames.resize(swapchain_images.len(), None);
+
+        let info = DisplayInfo {
+            min_image_count,
+            surface_format,
+            supported_surface_formats: surf_formats,
+            image_extent,
+            image_usage,
+            samples,
+            present_mode,
+            supported_present_modes: surf_present_modes,
+        };
+
+        // From here on, the display owns all created objects and destroys
+        // them when dropped, including on failure.
+        let mut display = Display {
+            info,
+            hdr_metadata: None,
+            current_frame: 0,
+            frames: ArrayVec::new(),
+            images: swapchain_images,
+            image_frames,
+            spare_command_buffers,
+            capture_requested: false,
+            pending_captures: ArrayVec::new(),
+            captures: VecDeque::new(),
+            swapchain: Some(swapchain),
+            surface: Some(surface),
+            device: device.clone(),
+        };
+
+        for _ in 0..config.frames_in_flight {
+            let frame = unsafe { FrameInFlight::create(&device_read.raw)? };
+            display.frames.push(frame);
+        }
+
+        Ok(display)
+    }
+
+    /// Queries the capabilities, formats and presentation modes of `surface`,
+    /// failing if it cannot be presented to by `device`.
+    ///
+    /// # Safety
+    ///
+    /// `device` and `surface` must be from the same instance.
+    unsafe fn query_surface(
+        device: &Device,
+        surface: &vks::SurfaceKHR,
+    ) -> Result<
+        (
+            vk::SurfaceCapabilitiesKHR,
+            Vec<vk::SurfaceFormatKHR>,
+            Vec<vk::PresentModeKHR>,
+        ),
+        Error,
+    > {
+        let device_read = device.inner.read();
+        let instance_read = device_read.instance.read_inner();
+        let instance_handle = &instance_read.handle;
+        let phys = &device_read.phys_device.inner.raw;
+
+        unsafe {
+            let supported = instance_handle.get_physical_device_surface_support_khr(
+                &phys,
+                device.present_family_id(),
+                surface,
+            )?;
+            if !supported {
+                return Err(Error::SurfaceNotSupported);
+            }
+
+            Ok((
+                instance_handle.get_physical_device_surface_capabilities_khr(phys, surface)?,
+                instance_handle.get_physical_device_surface_formats_khr(phys, surface)?,
+                instance_handle.get_physical_device_surface_present_modes_khr(phys, surface)?,
+            ))
+        }
+    }
+
+    /// Creates a display which presents directly to `display` in `mode`,
+    /// without a windowing system.
+    ///
+    /// The first display plane which can show `display` and is not in use
+    /// by another display is selected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mode` is not one of the modes of `display`.
+    pub fn create_direct(
+        device: &Device,
+        display: &DirectDisplay,
+        mode: &DirectDisplayMode,
+        config: DisplayConfig,
+    ) -> Result<Display, Error> {
+        assert!(
+            display.modes.iter().any(|m| m.raw == mode.raw),
+            "mode does not belong to display"
+        );
+
+        let surface = {
+            let device_read = device.inner.read();
+            let instance_read = device_read.instance.read_inner();
+            let instance = &instance_read.handle;
+            let phys = &device_read.phys_device.inner.raw;
+
+            let planes =
+                unsafe { instance.get_physical_device_display_plane_properties_khr(phys)? };
+
+            let mut selected = None;
+            for (plane_index, plane) in (0..).zip(planes.iter()) {
+                if !plane.current_display.is_null() && plane.current_display != display.raw {
+                    continue;
+                }
+
+                let supported = unsafe {
+                    instance.get_display_plane_supported_displays_khr(phys, plane_index)?
+                };
+                if supported.contains(&display.raw) {
+                    selected = Some((plane_index, plane.current_stack_index));
+                    break;
+                }
+            }
+            let (plane_index, plane_stack_index) = selected.ok_or(Error::NoDisplayPlane)?;
+
+            let caps = unsafe {
+                instance.get_display_plane_capabilities_khr(phys, mode.raw, plane_index)?
+            };
+            let alpha_mode = [
+                vk::DisplayPlaneAlphaFlagBitsKHR::OPAQUE_KHR,
+                vk::DisplayPlaneAlphaFlagBitsKHR::GLOBAL_KHR,
+                vk::DisplayPlaneAlphaFlagBitsKHR::PER_PIXEL_KHR,
+                vk::DisplayPlaneAlphaFlagBitsKHR::PER_PIXEL_PREMULTIPLIED_KHR,
+            ]
+            .iter()
+            .copied()
+            .find(|&alpha| caps.supported_alpha.contains(alpha.bitmask()))
+            .ok_or(Error::NoDisplayPlane)?;
+
+            log::info!(
+                "Presenting directly to display {:?} on plane {}",
+                display.name().unwrap_or("<unnamed>"),
+                plane_index
+            );
+
+            let create_info = vk::DisplaySurfaceCreateInfoKHRBuilder::new()
+                .flags(vk::DisplaySurfaceCreateFlagsKHR::empty())
+                .display_mode(mode.raw)
+                .plane_index(plane_index)
+                .plane_stack_index(plane_stack_index)
+                .transform(vk::SurfaceTransformFlagBitsKHR::IDENTITY_KHR)
+                .global_alpha(1.0)
+                .alpha_mode(alpha_mode)
+                .image_extent(mode.visible_region);
+
+            unsafe { instance.create_display_plane_surface_khr(&create_info)? }
+        };
+
+        // Safety: the surface was created from the device's instance.
+        unsafe { Display::create(device, surface, mode.visible_region, config) }
+    }
+
+    /// Creates views and command buffers for each image in `swapchain`.
+    ///
+    /// Command buffers are taken from `command_buffers` where possible; any
+    /// additional command buffers are newly allocated. On failure, the
+    /// command buffers are left in `command_buffers`.
+    ///
+    /// # Safety
+    ///
+    /// `swapchain` must be associated with `device`.
+    unsafe fn create_swapchain_images(
+        device: &Device,
+        swapchain: &vks::SwapchainKHR,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlagBits,
+        command_buffers: &mut Vec<CommandBufferPair>,
+    ) -> Result<Vec<SwapchainImage>, Error> {
+        let device_read = device.inner.read();
+
+        let images = unsafe { device_read.raw.get_swapchain_images_khr(swapchain)? };
+
+        log::info!("Retrieved {} images from swapchain.", images.len());
+
+        // Allocate command buffers for any images beyond those that can be
+        // reused.
+        let needed = images.len().saturating_sub(command_buffers.len());
+        if needed > 0 {
+            let graphics_command_buffers = {
+                let graphics_command_pool = device.graphics_command_pool();
+                let mut pool_mut = graphics_command_pool.get_mut()?;
+                let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
+                    .command_pool(&mut *pool_mut)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(needed as u32);
+
+                unsafe { device_read.raw.allocate_command_buffers(&allocate_info)? }
+            };
+
+            let present_command_buffers = {
+                let present_command_pool = device.present_command_pool();
+                let mut pool_mut = present_command_pool.get_mut()?;
+                let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
+                    .command_pool(&mut *pool_mut)
+                    .level(vk::CommandBufferLevel::PRIMARY)
+                    .command_buffer_count(needed as u32);
+
+                unsafe { device_read.raw.allocate_command_buffers(&allocate_info)? }
+            };
+
+            command_buffers.extend(
+                graphics_command_buffers
+                    .into_iter()
+                    .zip(present_command_buffers)
+                    .map(|(graphics, present)| CommandBufferPair { graphics, present }),
+            );
+        }
+
+        let mut swapchain_images = Vec::with_capacity(images.len());
+        for image in images {
+            let created =
+                unsafe { Display::create_swapchain_image(device, &image, format, extent, samples) };
+            match created {
+                Ok((view, multisample)) => {
+                    let commands = command_buffers.pop().unwrap();
+                    swapchain_images.push(SwapchainImage {
+                        present_commands: commands.present,
+                        graphics_commands: commands.graphics,
+                        framebuffer: None,
+                        multisample,
+                        view,
+                        image,
+                    });
+                }
+                Err(e) => {
+                    for si in swapchain_images {
+                        command_buffers.push(si.destroy_with(&device_read));
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(swapchain_images)
+    }
+
+    /// Creates the view and multisample target of a single swapchain image.
+    ///
+    /// # Safety
+    ///
+    /// `image` must be a swapchain image associated with `device`.
+    unsafe fn create_swapchain_image(
+        device: &Device,
+        image: &vks::Image,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlagBits,
+    ) -> Result<(vks::ImageView, Option<MultisampleTarget>), Error> {
+        let device_read = device.inner.read();
+
+        let view = unsafe {
+            device_read.raw.create_image_view(
+                &vks::ImageViewCreateInfoBuilder::new()
+                    .flags(vk::ImageViewCreateFlags::empty())
+                    .image(image)
+                    .view_type(vk::ImageViewType::_2D)
+                    .format(format)
+                    .components(vk::ComponentMapping {
+                        r: vk::ComponentSwizzle::IDENTITY,
+                        g: vk::ComponentSwizzle::IDENTITY,
+                        b: vk::ComponentSwizzle::IDENTITY,
+                        a: vk::ComponentSwizzle::IDENTITY,
+                    })
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        base_mip_level: 0,
+                        level_count: 1,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    }),
+            )?
+        };
+
+        if samples == vk::SampleCountFlagBits::_1 {
+            return Ok((view, None));
+        }
+
+        match unsafe { MultisampleTarget::create(device, format, extent, samples) } {
+            Ok(target) => Ok((view, Some(target))),
+            Err(e) => {
+                unsafe { device_read.raw.destroy_image_view(view) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Recreates the swapchain to match a new window size.
+    ///
+    /// Pipelines and render passes created for this display remain valid, but
+    /// framebuffers and command buffers are discarded: `rebuild_framebuffers`
+    /// and `record_command_buffers` must be called again before the next
+    /// `draw`.
+    ///
+    /// This blocks until the graphics and present queues are idle.
+    pub fn resize(&mut self, phys_window_extent: vk::Extent2D) -> Result<(), Error> {
+        if self.is_suspended() {
+            log::debug!("Ignoring resize of suspended display.");
+            return Ok(());
+        }
+
+        let device_read = self.device.inner.read();
+
+        let surf_caps = unsafe {
+            device_read
+                .instance
+                .read_inner()
+                .handle
+                .get_physical_device_surface_capabilities_khr(
+                    &device_read.phys_device.inner.raw,
+                    self.surface.as_ref().unwrap(),
+                )?
+        };
+
+        let image_extent = choose_image_extent(&surf_caps, phys_window_extent);
+        if image_extent.width == 0 || image_extent.height == 0 {
+            // The window is minimized; keep the current swapchain until it is
+            // restored.
+            log::debug!("Ignoring resize to zero-sized extent.");
+            return Ok(());
+        }
+
+        drop(device_read);
+        self.recreate_swapchain(image_extent, self.info.present_mode)
+    }
+
+    /// Returns the presentation modes supported by the surface.
+    pub fn supported_present_modes(&self) -> impl Iterator<Item = PresentModePreference> + '_ {
+        self.info
+            .supported_present_modes
+            .iter()
+            .copied()
+            .filter_map(PresentModePreference::from_vk)
+    }
+
+    /// Returns the presentation mode currently in use.
+    pub fn present_mode(&self) -> PresentModePreference {
+        // Only modes with a corresponding preference are ever selected.
+        PresentModePreference::from_vk(self.info.present_mode).unwrap()
+    }
+
+    /// Selects a new presentation mode, recreating the swapchain if it
+    /// changes.
+    ///
+    /// As with [`DisplayConfig::present_modes`], the first mode supported by
+    /// the surface is selected, falling back to `Fifo`. If the swapchain is
+    /// recreated, `rebuild_framebuffers` and `record_command_buffers` must be
+    /// called again before the next `draw`.
+    pub fn set_present_modes(
+        &mut self,
+        preferences: &[PresentModePreference],
+    ) -> Result<(), Error> {
+        let present_mode = choose_present_mode(preferences, &self.info.supported_present_modes);
+        if present_mode == self.info.present_mode {
+            return Ok(());
+        }
+
+        if self.is_suspended() {
+            // Applied when the swapchain is recreated on resume.
+            self.info.present_mode = present_mode;
+            return Ok(());
+        }
+
+        self.recreate_swapchain(self.info.image_extent, present_mode)?;
+        log::info!("Using presentation mode {:?}", present_mode);
+        Ok(())
+    }
+
+    /// Returns `true` if the display has no surface to present to.
+    ///
+    /// A display is suspended by [`Display::suspend`], or when drawing finds
+    /// that its surface was lost. Drawing fails with
+    /// [`Error::DisplaySuspended`] until the display is resumed with a new
+    /// surface.
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Releases the swapchain and surface, e.g. because the application was
+    /// suspended and its native window destroyed.
+    ///
+    /// Pipelines and render passes created for this display remain valid.
+    /// Framebuffers and command buffers are discarded, as with `resize`.
+    ///
+    /// This blocks until the graphics and present queues are idle. If
+    /// waiting fails, the display is left unchanged.
+    pub fn suspend(&mut self) -> Result<(), Error> {
+        if self.is_suspended() {
+            return Ok(());
+        }
+
+        let device = self.device.clone();
+        Display::wait_queues_idle(&device)?;
+
+        let device_read = device.inner.read();
+        for si in self.images.drain(..) {
+            let commands = si.destroy_with(&device_read);
+            self.spare_command_buffers.push(commands);
+        }
+        self.image_frames.clear();
+
+        if let Some(swapchain) = self.swapchain.take() {
+            unsafe { device_read.raw.destroy_swapchain_khr(swapchain) };
+        }
+
+        if let Some(surface) = self.surface.take() {
+            unsafe {
+                device_read
+                    .instance
+                    .read_inner()
+                    .handle
+                    .destroy_surface(surface)
+            };
+        }
+
+        log::info!("Suspended display.");
+        Ok(())
+    }
+
+    /// Resumes a suspended display, presenting to `surface`.
+    ///
+    /// The display takes ownership of `surface`, which is destroyed if
+    /// resuming fails. The surface must support the display's current surface
+    /// format. As after `resize`, `rebuild_framebuffers` and
+    /// `record_command_buffers` must be called again before the next `draw`.
+    ///
+    /// # Safety
+    ///
+    /// `surface` must be from the same instance as the display's device.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the display is not suspended.
+    pub unsafe fn resume(
+        &mut self,
+        surface: vks::SurfaceKHR,
+        phys_window_extent: vk::Extent2D,
+    ) -> Result<(), Error> {
+        assert!(self.is_suspended(), "display is not suspended");
+
+        let queried = unsafe { Display::query_surface(&self.device, &surface) }.and_then(
+            |(caps, formats, present_modes)| {
+                let current = &self.info.surface_format;
+                if formats
+                    .iter()
+                    .any(|f| f.format == current.format && f.color_space == current.color_space)
+                {
+                    Ok((caps, formats, present_modes))
+                } else {
+                    Err(Error::SurfaceNotSupported)
+                }
+            },
+        );
+
+        let (surf_caps, surf_formats, surf_present_modes) = match queried {
+            Ok(q) => q,
+            Err(e) => {
+                let device_read = self.device.inner.read();
+                unsafe {
+                    device_read
+                        .instance
+                        .read_inner()
+                        .handle
+                        .destroy_surface(surface)
+                };
+                return Err(e);
+            }
+        };
+
+        let present_mode = choose_present_mode(&[self.present_mode()], &surf_present_modes);
+        let image_extent = choose_image_extent(&surf_caps, phys_window_extent);
+
+        self.info.supported_surface_formats = surf_formats;
+        self.info.supported_present_modes = surf_present_modes;
+        self.surface = Some(surface);
+        if let Err(e) = self.recreate_swapchain(image_extent, present_mode) {
+            // Stay suspended; the surface is owned by the display.
+            let surface = self.surface.take().unwrap();
+            let device_read = self.device.inner.read();
+            unsafe {
+                device_read
+                    .instance
+                    .read_inner()
+                    .handle
+                    .destroy_surface(surface)
+            };
+            return Err(e);
+        }
+
+        log::info!("Resumed display.");
+        Ok(())
+    }
+
+    /// Suspends the display if drawing found that its surface was lost.
+    fn handle_draw_error(&mut self, error: Error) -> Error {
+        if let Error::SurfaceLost = error {
+            log::warn!("Display surface was lost; suspending.");
+            if let Err(e) = self.suspend() {
+                log::error!("Failed to suspend display: {}", e);
+            }
+        }
+
+        error
+    }
+
+    /// Blocks until the graphics and present queues of `device` are idle.
+    fn wait_queues_idle(device: &Device) -> Result<(), Error> {
+        let device_read = device.inner.read();
+        for queue in [device.graphics_queue(), device.present_queue()] {
+            unsafe {
+                device_read
+                    .raw
+                    .queue_wait_idle(&mut queue.write_inner().raw)?
+            };
+        }
+
+        Ok(())
+    }
 
-        let image_extent = if surf_caps.current_extent.width == SWAPCHAIN_CHOOSES_EXTENT.width
-            && surf_caps.current_extent.height == SWAPCHAIN_CHOOSES_EXTENT.height
-        {
-            phys_window_extent
-        } else {
-            surf_caps.current_extent
-        };
+    /// Replaces the swapchain with a new one with the given parameters.
+    ///
+    /// If the display has no swapchain, e.g. on resume, a new one is created.
+    /// This blocks until the graphics and present queues are idle.
+    ///
+    /// If creating the new swapchain fails, the old one is still retired, so
+    /// the display is left without a swapchain and drawing fails with
+    /// [`Error::SwapchainOutOfDate`] until the swapchain is recreated.
+    fn recreate_swapchain(
+        &mut self,
+        image_extent: vk::Extent2D,
+        present_mode: vk::PresentModeKHR,
+    ) -> Result<(), Error> {
+        let device = self.device.clone();
+        let device_read = device.inner.read();
 
-        let present_mode = if surf_present_modes
-            .iter()
-            .any(|&pm| pm == vk::PresentModeKHR::MAILBOX_KHR)
-        {
-            vk::PresentModeKHR::MAILBOX_KHR
-        } else {
-            // Implementations are required to support FIFO.
-            vk::PresentModeKHR::FIFO_KHR
-        };
+        // Ensure no swapchain resources are in use.
+        Display::wait_queues_idle(&device)?;
 
+        let mut old_swapchain = self.swapchain.take();
         let mut create_info = vks::SwapchainCreateInfo {
             flags: vk::SwapchainCreateFlagsKHR::empty(),
-            surface: &mut surface,
-            min_image_count,
-            image_format: surface_format.format,
-            image_color_space: surface_format.color_space,
+            surface: self.surface.as_mut().unwrap(),
+            min_image_count: self.info.min_image_count,
+            image_format: self.info.surface_format.format,
+            image_color_space: self.info.surface_format.color_space,
             image_extent,
             image_array_layers: 1,
-            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            image_usage: self.info.image_usage,
             image_sharing_mode: vk::SharingMode::EXCLUSIVE,
             queue_family_indices: &[],
             pre_transform: vk::SurfaceTransformFlagBitsKHR::IDENTITY_KHR,
             composite_alpha: vk::CompositeAlphaFlagBitsKHR::OPAQUE_KHR,
             present_mode,
             clipped: true,
-            old_swapchain: None,
+            old_swapchain: old_swapchain.as_mut(),
         };
 
-        let swapchain = unsafe { device_read.raw.create_swapchain_khr(&mut create_info) }
-            .expect("failed to create swapchain");
+        let created = unsafe { device_read.raw.create_swapchain_khr(&mut create_info) };
 
-        log::info!("Successfully created swapchain.");
-
-        let images = unsafe { device_read.raw.get_swapchain_images_khr(&swapchain) }
-            .expect("failed to get swapchain images");
+        // The old swapchain is retired even if creation failed.
+        for si in self.images.drain(..) {
+            let commands = si.destroy_with(&device_read);
+            self.spare_command_buffers.push(commands);
+        }
+        self.image_frames.clear();
 
-        log::info!("Retrieved {} images from swapchain.", images.len());
+        if let Some(old_swapchain) = old_swapchain {
+            unsafe { device_read.raw.destroy_swapchain_khr(old_swapchain) };
+        }
 
-        let image_views = images
-            .iter()
-            .map(|img| unsafe {
-                device_read.raw.create_image_view(
-                    &vks::ImageViewCreateInfoBuilder::new()
-                        .flags(vk::ImageViewCreateFlags::empty())
-                        .image(img)
-                        .view_type(vk::ImageViewType::_2D)
-                        .format(surface_format.format)
-                        .components(vk::ComponentMapping {
-                            r: vk::ComponentSwizzle::IDENTITY,
-                            g: vk::ComponentSwizzle::IDENTITY,
-                            b: vk::ComponentSwizzle::IDENTITY,
-                            a: vk::ComponentSwizzle::IDENTITY,
-                        })
-                        .subresource_range(vk::ImageSubresourceRange {
-                            aspect_mask: vk::ImageAspectFlags::COLOR,
-                            base_mip_level: 0,
-                            level_count: 1,
-                            base_array_layer: 0,
-                            layer_count: 1,
-                        }),
-                )
-            })
-            .collect::<Result<Vec<_>, _>>()
-            .unwrap();
+        let swapchain = created?;
 
-        let graphics_command_buffers = {
-            let graphics_command_pool = device.graphics_command_pool();
-            let mut pool_mut = graphics_command_pool
-                .get_mut()
-                .expect("failed to acquire command pool");
-            let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
-                .command_pool(&mut *pool_mut)
-                .level(vk::CommandBufferLevel::PRIMARY)
-                .command_buffer_count(image_views.len() as u32);
+        log::info!(
+            "Recreated swapchain with extent {}x{}.",
+            image_extent.width,
+            image_extent.height
+        );
 
-            unsafe { device_read.raw.allocate_command_buffers(&allocate_info) }
-                .expect("failed to allocate command buffers")
+        let images = unsafe {
+            Display::create_swapchain_images(
+                &device,
+                &swapchain,
+                self.info.surface_format.format,
+                image_extent,
+                self.info.samples,
+                &mut self.spare_command_buffers,
+            )
         };
-
-        let present_command_buffers = {
-            let present_command_pool = device.present_command_pool();
-            let mut pool_mut = present_command_pool
-                .get_mut()
-                .expect("failed to acquire command pool");
-            let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
-                .command_pool(&mut *pool_mut)
-                .level(vk::CommandBufferLevel::PRIMARY)
-                .command_buffer_count(image_views.len() as u32);
-
-            unsafe { device_read.raw.allocate_command_buffers(&allocate_info) }
-                .expect("failed to allocate command buffers")
+        self.images = match images {
+            Ok(images) => images,
+            Err(e) => {
+                unsafe { device_read.raw.destroy_swapchain_khr(swapchain) };
+                return Err(e);
+            }
         };
+        drop(device_read);
 
-        let swapchain_images = images
-            .into_iter()
-            .zip(image_views)
-            .zip(graphics_command_buffers)
-            .zip(present_command_buffers)
-            .map(
-                |(((image, view), graphics_commands), present_commands)| SwapchainImage {
-                    present_commands,
-                    graphics_commands,
-                    framebuffer: None,
-                    view,
-                    image,
-                },
-            )
-            .collect::<Vec<_>>();
-
-        let info = DisplayInfo {
-            min_image_count,
-            surface_format,
-            image_extent,
-            present_mode,
-        };
+        self.image_frames = vec![None; self.images.len()];
+        self.swapchain = Some(swapchain);
+        self.info.image_extent = image_extent;
+        self.info.present_mode = present_mode;
 
-        let mut frames = ArrayVec::new();
-        for _ in 0..MAX_FRAMES_IN_FLIGHT {
-            let fence_create_info =
-                vk::FenceCreateInfoBuilder::new().flags(vk::FenceCreateFlags::SIGNALED);
-            let in_flight = unsafe { device_read.raw.create_fence(&fence_create_info) }
-                .expect("failed to create in_flight fence");
-
-            let semaphore_create_info = vk::SemaphoreCreateInfoBuilder::new();
-            let image_available =
-                unsafe { device_read.raw.create_semaphore(&semaphore_create_info) }
-                    .expect("failed to create image_available semaphore");
-            let render_complete =
-                unsafe { device_read.raw.create_semaphore(&semaphore_create_info) }
-                    .expect("failed to create render_complete semaphore");
-            let present_queue_ownership =
-                unsafe { device_read.raw.create_semaphore(&semaphore_create_info) }
-                    .expect("failed to create present_queue_ownership semaphore");
-
-            frames.push(FrameInFlight {
-                in_flight,
-                image_available,
-                render_complete,
-                present_queue_ownership,
-            });
+        // HDR metadata is associated with the swapchain, so it must be set
+        // again.
+        if let Some(metadata) = self.hdr_metadata {
+            self.set_hdr_metadata(metadata);
         }
 
-        let mut image_frames = Vec::with_capacity(swapchain_images.len());
-        image_frames.resize(swapchain_images.len(), None);
-
-        Display {
-            info,
-            current_frame: 0,
-            frames,
-            images: swapchain_images,
-            image_frames,
-            swapchain: Some(swapchain),
-            surface: Some(surface),
-            device: device.clone(),
-        }
+        Ok(())
     }
 
     pub fn rebuild_framebuffers(&mut self, render_pass: &vks::RenderPass) {
         for image in self.images.iter_mut() {
             unsafe {
-                // Safety: raw handle does not outlive the block.
-                let attachments = &[*image.view.handle()];
+                // Safety: raw handles do not outlive the block.
+                let attachments = match &image.multisample {
+                    // The multisampled image is resolved into the swapchain
+                    // image.
+                    Some(target) => vec![*target.view.handle(), *image.view.handle()],
+                    None => vec![*image.view.handle()],
+                };
 
                 let create_info = vks::FramebufferCreateInfoBuilder::new()
                     .flags(vk::FramebufferCreateFlags::empty())
                     .render_pass(render_pass)
-                    .attachments(attachments)
+                    .attachments(&attachments)
                     .width(self.info.image_extent.width)
                     .height(self.info.image_extent.height)
                     .layers(1);
@@ -413,14 +1533,6 @@ impl Display {
             // The EXTERNAL -> 0 subpass dependency was omitted, so insert a
             // barrier to perform the layout transition here.
 
-            let subresource_range = vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                base_mip_level: 0,
-                level_count: vk::REMAINING_MIP_LEVELS,
-                base_array_layer: 0,
-                layer_count: vk::REMAINING_ARRAY_LAYERS,
-            };
-
             let pre_render_barrier = vks::ImageMemoryBarrierBuilder::new()
                 .src_access_mask(vk::AccessFlags::empty())
                 .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
@@ -431,7 +1543,7 @@ impl Display {
                 .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
                 .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
                 .image(&image.image)
-                .subresource_range(subresource_range);
+                .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR);
 
             unsafe {
                 device_read.raw.cmd_pipeline_barrier(
@@ -472,6 +1584,20 @@ impl Display {
                 .raw
                 .cmd_bind_pipeline(cmdbuf, vk::PipelineBindPoint::GRAPHICS, pipeline);
 
+            let viewport = vk::ViewportBuilder::new()
+                .x(0.0)
+                .y(0.0)
+                .width(self.info.image_extent.width as f32)
+                .height(self.info.image_extent.height as f32)
+                .min_depth(0.0)
+                .max_depth(1.0);
+            device_read.raw.cmd_set_viewport(cmdbuf, 0, &[viewport]);
+
+            let scissor = vk::Rect2DBuilder::new()
+                .offset(vk::Offset2D { x: 0, y: 0 })
+                .extent(self.info.image_extent);
+            device_read.raw.cmd_set_scissor(cmdbuf, 0, &[scissor]);
+
             device_read.raw.cmd_draw(cmdbuf, 3, 1, 0, 0);
             device_read.raw.cmd_end_render_pass(cmdbuf);
         }
@@ -480,14 +1606,6 @@ impl Display {
             // Need to perform a QFOT from the graphics queue to the present
             // queue. Layout transition can occur simultaneously.
 
-            let subresource_range = vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::COLOR,
-                base_mip_level: 0,
-                level_count: vk::REMAINING_MIP_LEVELS,
-                base_array_layer: 0,
-                layer_count: vk::REMAINING_ARRAY_LAYERS,
-            };
-
             let post_render_barrier = vks::ImageMemoryBarrierBuilder::new()
                 // Make all writes to the color attachment available.
                 .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
@@ -499,7 +1617,7 @@ impl Display {
                 .src_queue_family_index(self.device.graphics_family_id())
                 .dst_queue_family_index(self.device.present_family_id())
                 .image(&image.image)
-                .subresource_range(subresource_range);
+                .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR);
 
             unsafe {
                 device_read.raw.cmd_pipeline_barrier(
@@ -556,14 +1674,6 @@ impl Display {
                         .expect("failed to begin presentation command buffer");
                 }
 
-                let subresource_range = vk::ImageSubresourceRange {
-                    aspect_mask: vk::ImageAspectFlags::COLOR,
-                    base_mip_level: 0,
-                    level_count: vk::REMAINING_MIP_LEVELS,
-                    base_array_layer: 0,
-                    layer_count: vk::REMAINING_ARRAY_LAYERS,
-                };
-
                 unsafe {
                     let acquire_attachment_barrier = vk::ImageMemoryBarrierBuilder::new()
                         // No access masks needed: graphics command buffer
@@ -576,7 +1686,7 @@ impl Display {
                         .src_queue_family_index(self.device.graphics_family_id())
                         .dst_queue_family_index(self.device.present_family_id())
                         .image(*image.image.handle())
-                        .subresource_range(subresource_range);
+                        .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR);
 
                     device_read.raw.cmd_pipeline_barrier(
                         cmdbuf,
@@ -597,22 +1707,409 @@ impl Display {
         }
     }
 
-    pub fn draw(&mut self) {
-        log::trace!("drawing");
+    /// Renders and presents the next frame.
+    ///
+    /// Returns [`Error::SwapchainOutOfDate`] if the display must be resized
+    /// before drawing can continue. To present several displays with one
+    /// queue operation, use [`Display::draw_all`] instead.
+    pub fn draw(&mut self) -> Result<(), Error> {
+        let image_index = self.submit_frame().map_err(|e| self.handle_draw_error(e))?;
+
+        let device = self.device.clone();
+        let device_read = device.read_inner();
+        let present_queue = device.present_queue();
+        let mut present_queue_write = present_queue.write_inner();
+
+        let presented = unsafe {
+            let wait_semaphores = &[self.present_wait_semaphore()];
+            let swapchains = &[*self.swapchain.as_mut().unwrap().handle_mut()];
+            let image_indices = &[image_index];
+
+            let present_info = vk::PresentInfoKHRBuilder::new()
+                .wait_semaphores(wait_semaphores)
+                .swapchains(swapchains)
+                .image_indices(image_indices);
+
+            log::trace!("presenting swapchain image");
+            device_read
+                .raw
+                .queue_present_khr(&mut present_queue_write.raw, &present_info)
+        };
+
+        drop(present_queue_write);
+        drop(device_read);
+        presented.map_err(|e| self.handle_draw_error(e.into()))?;
+
+        self.current_frame += 1;
+        Ok(())
+    }
+
+    /// Renders and presents the next frame of several displays at once.
+    ///
+    /// All swapchain images are presented with a single queue operation, so
+    /// windows showing related content are updated together. Returns the
+    /// result of drawing each display, in order. A display which fails does
+    /// not prevent the others from being presented.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the displays do not all belong to the same device.
+    pub fn draw_all(displays: &mut [&mut Display]) -> Vec<Result<(), Error>> {
+        let device = match displays.first() {
+            Some(display) => display.device.clone(),
+            None => return Vec::new(),
+        };
+        assert!(
+            displays
+                .iter()
+                .all(|d| Arc::ptr_eq(&d.device.inner, &device.inner)),
+            "displays must belong to the same device"
+        );
+
+        let mut results = displays
+            .iter_mut()
+            .map(|display| display.submit_frame().map(Some))
+            .collect::<Vec<_>>();
+
+        let presented = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, res)| match res {
+                Ok(Some(index)) => Some((i, *index)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if !presented.is_empty() {
+            let device_read = device.read_inner();
+            let present_queue = device.present_queue();
+            let mut present_queue_write = present_queue.write_inner();
+
+            let mut wait_semaphores = Vec::with_capacity(presented.len());
+            let mut swapchains = Vec::with_capacity(presented.len());
+            let mut image_indices = Vec::with_capacity(presented.len());
+            for &(i, index) in presented.iter() {
+                let display = &mut *displays[i];
+                unsafe {
+                    // Safety: raw handles do not outlive the function.
+                    wait_semaphores.push(display.present_wait_semaphore());
+                    swapchains.push(*display.swapchain.as_mut().unwrap().handle_mut());
+                }
+                image_indices.push(index);
+            }
+            let mut present_results = vec![vk::Result::SUCCESS; presented.len()];
+
+            let present_info = vk::PresentInfoKHRBuilder::new()
+                .wait_semaphores(&wait_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices)
+                .results(&mut present_results);
+
+            log::trace!("presenting {} swapchain images", presented.len());
+            let overall = unsafe {
+                device_read
+                    .raw
+                    .queue_present_khr(&mut present_queue_write.raw, &present_info)
+            };
+
+            // If presentation failed outright, the per-swapchain results may
+            // not have been written.
+            let any_failed = present_results.iter().any(|r| r.0 < 0);
+            for (&(i, _), &result) in presented.iter().zip(present_results.iter()) {
+                results[i] = match overall {
+                    Err(e) if !any_failed => Err(e.into()),
+                    _ if result.0 < 0 => Err(result.into()),
+                    _ => Ok(None),
+                };
+            }
+        }
+
+        displays
+            .iter_mut()
+            .zip(results)
+            .map(|(display, result)| match result {
+                Ok(_) => {
+                    display.current_frame += 1;
+                    Ok(())
+                }
+                Err(e) => Err(display.handle_draw_error(e)),
+            })
+            .collect()
+    }
+
+    /// Requests that the next frame drawn be copied back to host memory.
+    ///
+    /// Once the frame has finished rendering, the copy is returned by
+    /// [`Display::take_capture`]. Capturing requires `TRANSFER_SRC` swapchain
+    /// image usage, which may be requested through
+    /// [`DisplayConfig::extra_image_usage`], and graphics and present queues
+    /// from the same family.
+    pub fn request_capture(&mut self) -> Result<(), Error> {
+        let graphics_present_differ =
+            self.device.graphics_family_id() != self.device.present_family_id();
+        if graphics_present_differ
+            || !self
+                .info
+                .image_usage
+                .contains(vk::ImageUsageFlags::TRANSFER_SRC)
+        {
+            return Err(Error::CaptureUnsupported);
+        }
+
+        self.capture_requested = true;
+        Ok(())
+    }
+
+    /// Returns the oldest completed capture, if any.
+    ///
+    /// This does not block. Captures of frames which are still rendering are
+    /// returned by later calls.
+    pub fn take_capture(&mut self) -> Result<Option<Capture>, Error> {
+        self.collect_captures(None)?;
+        Ok(self.captures.pop_front())
+    }
+
+    /// Reads back the captures of frames which have finished rendering.
+    ///
+    /// If `wait_frame` is given, the capture of that frame is waited on.
+    fn collect_captures(&mut self, wait_frame: Option<usize>) -> Result<(), Error> {
+        let device = self.device.clone();
+
+        let mut i = 0;
+        while i < self.pending_captures.len() {
+            let frame_index = self.pending_captures[i].frame_index;
+            let in_flight = &self.frames[frame_index].in_flight;
+
+            let complete = {
+                let device_read = device.read_inner();
+                if wait_frame == Some(frame_index) {
+                    unsafe {
+                        device_read
+                            .raw
+                            .wait_for_fences(&[*in_flight.handle()], true, None)
+                    }?;
+                    true
+                } else {
+                    let status = unsafe { device_read.raw.get_fence_status(in_flight) }?;
+                    matches!(status, vks::FenceStatus::Signaled)
+                }
+            };
+
+            if !complete {
+                i += 1;
+                continue;
+            }
+
+            let pending = self.pending_captures.remove(i);
+            let len = pending.buffer.size() as usize;
+            let data = unsafe { pending.buffer.read(&device, 0, len) };
+            unsafe { pending.buffer.destroy_with(&device) };
+
+            self.captures.push_back(Capture {
+                format: pending.format,
+                extent: pending.extent,
+                data: data?,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records a copy of swapchain image `image_index` into a new host buffer,
+    /// returning the command buffer to submit after rendering.
+    fn record_capture(
+        &mut self,
+        frame_index: usize,
+        image_index: usize,
+    ) -> Result<vk::CommandBuffer, Error> {
+        let format = self.info.surface_format.format;
+        let extent = self.info.image_extent;
+        let texel_size = format
+            .block_size()
+            .expect("swapchain format has no known texel size");
+        let size = texel_size as vk::DeviceSize
+            * extent.width as vk::DeviceSize
+            * extent.height as vk::DeviceSize;
+
+        let buffer_info = BufferCreateInfo::new(
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::Host,
+        )
+        .label("swapchain capture");
+        let buffer = self.device.create_buffer(&buffer_info)?;
+
+        // The display owns the buffer from here on, even if recording fails.
+        self.pending_captures.push(PendingCapture {
+            frame_index,
+            format,
+            extent,
+            buffer,
+        });
+        let buffer = self.pending_captures.last().unwrap().buffer.raw();
+
         let device_read = self.device.read_inner();
+        let frame = &mut self.frames[frame_index];
+        if frame.capture_commands.is_none() {
+            let graphics_command_pool = self.device.graphics_command_pool();
+            let mut pool_mut = graphics_command_pool
+                .get_mut()
+                .expect("failed to acquire command pool");
+            let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
+                .command_pool(&mut *pool_mut)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+
+            let mut allocated =
+                unsafe { device_read.raw.allocate_command_buffers(&allocate_info) }?;
+            frame.capture_commands = allocated.pop();
+        }
+        let cmdbuf = frame.capture_commands.as_mut().unwrap();
+        let image = &self.images[image_index].image;
+
+        let begin_info = vk::CommandBufferBeginInfoBuilder::new()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        unsafe {
+            device_read.raw.begin_command_buffer(cmdbuf, &begin_info)?;
+
+            let pre_copy_barrier = vks::ImageMemoryBarrierBuilder::new()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR);
+
+            device_read.raw.cmd_pipeline_barrier(
+                cmdbuf,
+                // Wait for rendering to finish writing the image.
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                // Safety: Produced raw handles do not outlive the block.
+                &[pre_copy_barrier.into_inner()],
+            );
+
+            let region = vk::BufferImageCopyBuilder::new()
+                .buffer_offset(0)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(vk::ImageSubresourceLayers {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    mip_level: 0,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                });
+
+            device_read.raw.cmd_copy_image_to_buffer(
+                cmdbuf,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[region],
+            );
+
+            let post_copy_barrier = vks::ImageMemoryBarrierBuilder::new()
+                .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .dst_access_mask(vk::AccessFlags::empty())
+                .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(util::IMAGE_SUBRESOURCE_RANGE_FULL_COLOR);
+
+            // Make the copied data visible to the host.
+            let buffer_barrier = vk::BufferMemoryBarrierBuilder::new()
+                .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .dst_access_mask(vk::AccessFlags::HOST_READ)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(*buffer.handle())
+                .offset(0)
+                .size(vk::WHOLE_SIZE);
+
+            device_read.raw.cmd_pipeline_barrier(
+                cmdbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[buffer_barrier],
+                // Safety: Produced raw handles do not outlive the block.
+                &[post_copy_barrier.into_inner()],
+            );
+
+            device_read.raw.end_command_buffer(cmdbuf)?;
+
+            // Safety: the handle does not outlive the frame's submission.
+            Ok(*cmdbuf.handle_mut())
+        }
+    }
+
+    /// Returns the semaphore which presentation of the current frame must
+    /// wait on.
+    ///
+    /// # Safety
+    ///
+    /// The returned handle must not outlive the frame.
+    unsafe fn present_wait_semaphore(&mut self) -> vk::Semaphore {
+        let graphics_present_differ =
+            self.device.graphics_family_id() != self.device.present_family_id();
+        let frame_index = self.frame_index();
+        let frame = &mut self.frames[frame_index];
+
+        unsafe {
+            if graphics_present_differ {
+                *frame.present_queue_ownership.handle_mut()
+            } else {
+                *frame.render_complete.handle_mut()
+            }
+        }
+    }
+
+    /// Acquires the next swapchain image and submits the current frame's
+    /// commands, returning the index of the image to present.
+    fn submit_frame(&mut self) -> Result<u32, Error> {
+        log::trace!("drawing");
+        if self.is_suspended() {
+            return Err(Error::DisplaySuspended);
+        }
+        if self.swapchain.is_none() {
+            // A previous attempt to recreate the swapchain failed.
+            return Err(Error::SwapchainOutOfDate);
+        }
+
+        let frame_index = self.frame_index();
+
+        // Read back the frame's capture before its memory is reused.
+        self.collect_captures(Some(frame_index))?;
+
+        let device = self.device.clone();
+        let device_read = device.read_inner();
 
         let graphics_present_differ =
             self.device.graphics_family_id() != self.device.present_family_id();
 
-        let frame = &mut self.frames[self.current_frame as usize % MAX_FRAMES_IN_FLIGHT];
+        let frame = &mut self.frames[frame_index];
 
         // Wait for the next frame to become available.
         unsafe {
             device_read
                 .raw
                 .wait_for_fences(&[*frame.in_flight.handle()], true, None)
-        }
-        .expect("failed to wait for frame to become available");
+        }?;
 
         // Acquire an image from the swapchain.
         let acquired = unsafe {
@@ -622,25 +2119,29 @@ impl Display {
                 Some(&mut frame.image_available),
                 None,
             )
-        }
-        .expect("failed to acquire next swapchain image");
+        }?;
 
         // Wait for any previous operations on the acquired image to complete.
         drop(frame);
-        if let Some(frame_index) = self.image_frames[acquired.index as usize] {
+        if let Some(image_frame) = self.image_frames[acquired.index as usize] {
             unsafe {
                 device_read.raw.wait_for_fences(
-                    &[*self.frames[frame_index % MAX_FRAMES_IN_FLIGHT]
-                        .in_flight
-                        .handle()],
+                    &[*self.frames[image_frame].in_flight.handle()],
                     true,
                     None,
                 )
-            }
-            .expect("failed to wait for previous image frame");
+            }?;
         }
+        self.image_frames[acquired.index as usize] = Some(frame_index);
+
+        let capture_commands = if self.capture_requested {
+            self.capture_requested = false;
+            Some(self.record_capture(frame_index, acquired.index as usize)?)
+        } else {
+            None
+        };
 
-        let frame = &mut self.frames[self.current_frame as usize % MAX_FRAMES_IN_FLIGHT];
+        let frame = &mut self.frames[frame_index];
         let graphics_queue = self.device.graphics_queue();
         let mut graphics_queue_write = graphics_queue.write_inner();
 
@@ -651,36 +2152,49 @@ impl Display {
             let wait_semaphores = &[*frame.image_available.handle_mut()];
             // Only block rendering once ready to output to the color attachment.
             let wait_dst_stage_mask = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
-            let command_buffers = &[*self.images[acquired.index as usize]
-                .graphics_commands
-                .handle_mut()];
+            let mut command_buffers = ArrayVec::<_, 2>::new();
+            command_buffers.push(
+                *self.images[acquired.index as usize]
+                    .graphics_commands
+                    .handle_mut(),
+            );
+            // The capture copy follows rendering, and is complete before the
+            // image is presented.
+            command_buffers.extend(capture_commands);
             let signal_semaphores = &[*frame.render_complete.handle_mut()];
 
             let submit_info = vk::SubmitInfoBuilder::new()
                 .wait_semaphores(wait_semaphores)
                 .wait_dst_stage_mask(wait_dst_stage_mask)
-                .command_buffers(command_buffers)
+                .command_buffers(&command_buffers)
                 .signal_semaphores(signal_semaphores);
 
             let submits = &[submit_info];
 
             // If the image will be presented on another queue, don't signal the
             // frame-in-flight fence here.
-            let signal_fence = if graphics_present_differ {
-                None
+            let submitted = if graphics_present_differ {
+                log::trace!("submitting draw commands");
+                device_read
+                    .raw
+                    .queue_submit(&mut graphics_queue_write.raw, submits, None)
             } else {
                 device_read
                     .raw
-                    .reset_fences(&[*frame.in_flight.handle_mut()])
-                    .expect("failed to reset in-flight fence");
-                Some(&mut frame.in_flight)
-            };
+                    .reset_fences(&[*frame.in_flight.handle_mut()])?;
 
-            log::trace!("submitting draw commands");
-            device_read
-                .raw
-                .queue_submit(&mut graphics_queue_write.raw, submits, signal_fence)
-                .expect("failed to submit graphics command buffer");
+                log::trace!("submitting draw commands");
+                let submitted = device_read.raw.queue_submit(
+                    &mut graphics_queue_write.raw,
+                    submits,
+                    Some(&mut frame.in_flight),
+                );
+                if submitted.is_err() {
+                    frame.resignal_fence(&device_read.raw, &mut graphics_queue_write.raw);
+                }
+                submitted
+            };
+            submitted?;
         }
 
         let present_queue = self.device.present_queue();
@@ -707,48 +2221,100 @@ impl Display {
 
                 device_read
                     .raw
-                    .reset_fences(&[*frame.in_flight.handle_mut()])
-                    .expect("failed to reset in-flight fence");
+                    .reset_fences(&[*frame.in_flight.handle_mut()])?;
 
                 log::trace!("submitting presentation queue acquire");
-                device_read
-                    .raw
-                    .queue_submit(
-                        &mut present_queue_write.raw,
-                        submits,
-                        Some(&mut frame.in_flight),
-                    )
-                    .expect("failed to submit command buffer");
+                let submitted = device_read.raw.queue_submit(
+                    &mut present_queue_write.raw,
+                    submits,
+                    Some(&mut frame.in_flight),
+                );
+                if submitted.is_err() {
+                    frame.resignal_fence(&device_read.raw, &mut present_queue_write.raw);
+                }
+                submitted?;
             }
         }
 
-        unsafe {
-            let present_wait_semaphore = if graphics_present_differ {
-                *frame.present_queue_ownership.handle_mut()
-            } else {
-                *frame.render_complete.handle_mut()
-            };
-
-            let wait_semaphores = &[present_wait_semaphore];
-            let swapchains = &[*self.swapchain.as_mut().unwrap().handle_mut()];
-            let image_indices = &[acquired.index];
+        Ok(acquired.index)
+    }
 
-            let present_info = vk::PresentInfoKHRBuilder::new()
-                .wait_semaphores(wait_semaphores)
-                .swapchains(swapchains)
-                .image_indices(image_indices);
+    pub fn info(&self) -> &DisplayInfo {
+        &self.info
+    }
 
-            log::trace!("presenting swapchain image");
-            device_read
-                .raw
-                .queue_present_khr(&mut present_queue_write.raw, &present_info)
-                .expect("failed to present swapchain image");
+    /// Returns the color attachment that pipelines drawing to this display
+    /// render to.
+    pub fn pipeline_target(&self) -> PipelineTarget {
+        PipelineTarget {
+            format: self.info.surface_format.format,
+            samples: self.info.samples,
         }
+    }
 
-        self.current_frame += 1;
+    /// Returns the number of frames which may be in flight at once.
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
     }
 
-    pub fn info(&self) -> &DisplayInfo {
-        &self.info
+    /// Returns the index of the frame slot used by the next call to `draw`.
+    ///
+    /// This is in the range `0..frames_in_flight()` and is suitable for
+    /// indexing per-frame resources, such as with
+    /// [`DescriptorArena::begin_frame`](crate::DescriptorArena::begin_frame).
+    pub fn frame_index(&self) -> usize {
+        (self.current_frame % self.frames.len() as u64) as usize
+    }
+
+    /// Returns the HDR metadata most recently set on the display.
+    pub fn hdr_metadata(&self) -> Option<&HdrMetadata> {
+        self.hdr_metadata.as_ref()
+    }
+
+    /// Sets the HDR metadata sent to the presentation engine.
+    ///
+    /// The metadata only takes effect if the swapchain uses an HDR color space
+    /// and the device supports `VK_EXT_hdr_metadata`; otherwise a warning is
+    /// logged and the metadata is stored but not applied. Without a
+    /// swapchain, the metadata is applied when the swapchain is recreated.
+    pub fn set_hdr_metadata(&mut self, metadata: HdrMetadata) {
+        self.hdr_metadata = Some(metadata);
+
+        let swapchain = match self.swapchain.as_mut() {
+            Some(swapchain) => swapchain,
+            None => return,
+        };
+
+        let color_space = self.info.surface_format.color_space;
+        if !is_hdr_color_space(color_space) {
+            log::warn!(
+                "Swapchain color space {:?} is not HDR; ignoring HDR metadata",
+                color_space
+            );
+            return;
+        }
+
+        let device_read = self.device.inner.read();
+        let hdr_metadata_ext = unsafe { CStr::from_ptr(vk::EXT_HDR_METADATA_EXTENSION_NAME) };
+        if !device_read.is_extension_enabled(hdr_metadata_ext) {
+            log::warn!("VK_EXT_hdr_metadata is not enabled; ignoring HDR metadata");
+            return;
+        }
+
+        let metadata_info = vk::HdrMetadataEXTBuilder::new()
+            .display_primary_red(metadata.display_primary_red)
+            .display_primary_green(metadata.display_primary_green)
+            .display_primary_blue(metadata.display_primary_blue)
+            .white_point(metadata.white_point)
+            .max_luminance(metadata.max_luminance)
+            .min_luminance(metadata.min_luminance)
+            .max_content_light_level(metadata.max_content_light_level)
+            .max_frame_average_light_level(metadata.max_frame_average_light_level);
+
+        unsafe {
+            device_read
+                .raw
+                .set_hdr_metadata_ext(swapchain, &metadata_info);
+        }
     }
 }
@@ -0,0 +1,100 @@
+//! Errors returned by the public API.
+
+use erupt::vk;
+use thiserror::Error;
+
+use crate::{graph::RenderGraphError, LimitExceeded, QueryKind, SamplerError};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The logical device was lost, e.g. due to a driver crash or timeout.
+    ///
+    /// The device and all objects created from it must be recreated.
+    #[error("The device was lost.")]
+    DeviceLost,
+    #[error("Out of host memory.")]
+    OutOfHostMemory,
+    #[error("Out of device memory.")]
+    OutOfDeviceMemory,
+    /// The surface is no longer available, e.g. because its window was
+    /// destroyed.
+    #[error("The surface was lost.")]
+    SurfaceLost,
+    /// The surface has changed such that the swapchain must be recreated.
+    #[error("The swapchain is out of date and must be recreated.")]
+    SwapchainOutOfDate,
+    /// The display has no surface, e.g. because the application was
+    /// suspended, and must be resumed before drawing.
+    #[error("The display is suspended.")]
+    DisplaySuspended,
+    #[error("The surface cannot be presented to by this device.")]
+    SurfaceNotSupported,
+    #[error("No display plane can present to the selected display.")]
+    NoDisplayPlane,
+    #[error(
+        "Capture requires TRANSFER_SRC swapchain image usage and \
+         graphics and present queues from the same family."
+    )]
+    CaptureUnsupported,
+    #[error("The graphics queue does not support timestamp queries.")]
+    TimestampsUnsupported,
+    /// A GPU profiler must have room for at least one scope, and its queries
+    /// must fit in a query pool.
+    #[error("A GPU profiler cannot hold {0} scopes.")]
+    InvalidProfilerCapacity(u32),
+    #[error("The device does not support {0:?} queries.")]
+    QueryUnsupported(QueryKind),
+    /// An object could not be destroyed because other handles to it remain.
+    ///
+    /// The object is destroyed once the remaining handles are dropped.
+    #[error("Cannot destroy the {object}: {references} other references remain.")]
+    StillInUse {
+        object: &'static str,
+        references: usize,
+    },
+    #[error("Stream buffer allocation of {requested} bytes exceeds the {available} bytes remaining this frame.")]
+    StreamBufferFull { requested: u64, available: u64 },
+    #[error("Format {format:?} lacks the {missing:?} features required for blitting.")]
+    BlitUnsupported {
+        format: vk::Format,
+        missing: vk::FormatFeatureFlags,
+    },
+    #[error("Bindless descriptors were not enabled at device creation.")]
+    BindlessUnsupported,
+    #[error("The bindless table has no free {0} slots.")]
+    BindlessTableFull(&'static str),
+    /// `vkCmdUpdateBuffer` writes must be non-empty and 4-byte aligned.
+    #[error(
+        "Buffer update of {len} bytes at offset {offset} must be non-empty and 4-byte aligned."
+    )]
+    MisalignedBufferUpdate { offset: vk::DeviceSize, len: usize },
+    #[error("Buffer update of {len} bytes exceeds the maximum of {max} bytes.")]
+    BufferUpdateTooLarge { len: usize, max: usize },
+    #[error("frames_in_flight must be between 1 and {max}, got {requested}.")]
+    InvalidFramesInFlight { requested: usize, max: usize },
+    #[error("{0}")]
+    LimitExceeded(#[from] LimitExceeded),
+    /// A graphics pipeline's blend states do not match its render pass.
+    #[error("Expected {expected} color blend attachments, got {got}.")]
+    ColorBlendAttachmentCount { expected: usize, got: usize },
+    #[error("Invalid sampler: {0}")]
+    Sampler(#[from] SamplerError),
+    #[error("Render graph validation failed: {0}")]
+    Graph(#[from] RenderGraphError),
+    /// Any other Vulkan error.
+    #[error("Vulkan error: {0}")]
+    Vulkan(vk::Result),
+}
+
+impl From<vk::Result> for Error {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_DEVICE_LOST => Error::DeviceLost,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY => Error::OutOfHostMemory,
+            vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => Error::OutOfDeviceMemory,
+            vk::Result::ERROR_SURFACE_LOST_KHR => Error::SurfaceLost,
+            vk::Result::ERROR_OUT_OF_DATE_KHR => Error::SwapchainOutOfDate,
+            other => Error::Vulkan(other),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Image format queries.
+
+use erupt::vk;
+
+/// Extension methods for [`vk::Format`].
+///
+/// Only the formats defined by core Vulkan 1.0 are described. Queries on other
+/// formats (such as multi-planar YCbCr formats) return `None` or treat the
+/// format as a single-plane color format.
+pub trait FormatExt: Copy {
+    /// Returns the image aspects present in the format.
+    fn aspects(self) -> vk::ImageAspectFlags;
+
+    /// Returns `true` if the format has a depth component.
+    fn is_depth(&self) -> bool {
+        self.aspects().contains(vk::ImageAspectFlags::DEPTH)
+    }
+
+    /// Returns `true` if the format has a stencil component.
+    fn is_stencil(&self) -> bool {
+        self.aspects().contains(vk::ImageAspectFlags::STENCIL)
+    }
+
+    /// Returns `true` if the format is block-compressed.
+    fn is_compressed(&self) -> bool {
+        matches!(self.block_extent(), Some(extent) if extent != (1, 1))
+    }
+
+    /// Returns `true` if the format uses sRGB nonlinear encoding.
+    fn is_srgb(&self) -> bool;
+
+    /// Returns the size in bytes of a texel block.
+    ///
+    /// For uncompressed formats, a block is a single texel. The sizes of
+    /// combined depth/stencil formats are nominal, as their memory layout is
+    /// implementation-defined.
+    fn block_size(self) -> Option<u32>;
+
+    /// Returns the width and height in texels of a texel block.
+    fn block_extent(self) -> Option<(u32, u32)>;
+
+    /// Returns the sRGB equivalent of a UNORM format.
+    fn to_srgb(self) -> Option<Self>;
+
+    /// Returns the UNORM equivalent of an sRGB format.
+    fn to_unorm(self) -> Option<Self>;
+}
+
+// Core formats are numbered contiguously, so ranges of raw values are used to
+// avoid spelling out every variant.
+
+/// The 8-bit-per-channel formats with an sRGB variant, from `R8_UNORM` to
+/// `A8B8G8R8_SRGB_PACK32`. Each group of seven formats runs from UNORM to SRGB.
+const SRGB_CAPABLE_8BIT: std::ops::RangeInclusive<i32> = 9..=57;
+const SRGB_8BIT_OFFSET: i32 = 6;
+
+fn is_compressed_srgb_pair_unorm(raw: i32) -> bool {
+    match raw {
+        // BC1_RGB, BC1_RGBA, BC2, BC3
+        131 | 133 | 135 | 137 => true,
+        // BC7
+        145 => true,
+        // ETC2_R8G8B8, ETC2_R8G8B8A1, ETC2_R8G8B8A8
+        147 | 149 | 151 => true,
+        // ASTC_4x4 through ASTC_12x12
+        157..=184 => (raw - 157) % 2 == 0,
+        _ => false,
+    }
+}
+
+impl FormatExt for vk::Format {
+    fn aspects(self) -> vk::ImageAspectFlags {
+        match self {
+            vk::Format::D16_UNORM | vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D32_SFLOAT => {
+                vk::ImageAspectFlags::DEPTH
+            }
+            vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+            vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT => {
+                vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+            }
+            _ => vk::ImageAspectFlags::COLOR,
+        }
+    }
+
+    fn is_srgb(&self) -> bool {
+        let raw = self.0;
+        if SRGB_CAPABLE_8BIT.contains(&raw) {
+            (raw - SRGB_CAPABLE_8BIT.start()) % 7 == SRGB_8BIT_OFFSET
+        } else {
+            is_compressed_srgb_pair_unorm(raw - 1)
+        }
+    }
+
+    fn block_size(self) -> Option<u32> {
+        let size = match self.0 {
+            // R4G4_UNORM_PACK8
+            1 => 1,
+            // 16-bit packed formats
+            2..=8 => 2,
+            // R8
+            9..=15 => 1,
+            // R8G8
+            16..=22 => 2,
+            // R8G8B8, B8G8R8
+            23..=36 => 3,
+            // R8G8B8A8, B8G8R8A8, A8B8G8R8, A2R10G10B10, A2B10G10R10
+            37..=69 => 4,
+            // R16
+            70..=76 => 2,
+            // R16G16
+            77..=83 => 4,
+            // R16G16B16
+            84..=90 => 6,
+            // R16G16B16A16
+            91..=97 => 8,
+            // R32
+            98..=100 => 4,
+            // R32G32
+            101..=103 => 8,
+            // R32G32B32
+            104..=106 => 12,
+            // R32G32B32A32
+            107..=109 => 16,
+            // R64
+            110..=112 => 8,
+            // R64G64
+            113..=115 => 16,
+            // R64G64B64
+            116..=118 => 24,
+            // R64G64B64A64
+            119..=121 => 32,
+            // B10G11R11_UFLOAT, E5B9G9R9_UFLOAT
+            122 | 123 => 4,
+            // D16_UNORM
+            124 => 2,
+            // X8_D24_UNORM, D32_SFLOAT
+            125 | 126 => 4,
+            // S8_UINT
+            127 => 1,
+            // D16_UNORM_S8_UINT
+            128 => 3,
+            // D24_UNORM_S8_UINT
+            129 => 4,
+            // D32_SFLOAT_S8_UINT
+            130 => 5,
+            // BC1
+            131..=134 => 8,
+            // BC2, BC3
+            135..=138 => 16,
+            // BC4
+            139 | 140 => 8,
+            // BC5, BC6H, BC7
+            141..=146 => 16,
+            // ETC2_R8G8B8, ETC2_R8G8B8A1
+            147..=150 => 8,
+            // ETC2_R8G8B8A8
+            151 | 152 => 16,
+            // EAC_R11
+            153 | 154 => 8,
+            // EAC_R11G11
+            155 | 156 => 16,
+            // ASTC
+            157..=184 => 16,
+            _ => return None,
+        };
+
+        Some(size)
+    }
+
+    fn block_extent(self) -> Option<(u32, u32)> {
+        const ASTC_BLOCKS: [(u32, u32); 14] = [
+            (4, 4),
+            (5, 4),
+            (5, 5),
+            (6, 5),
+            (6, 6),
+            (8, 5),
+            (8, 6),
+            (8, 8),
+            (10, 5),
+            (10, 6),
+            (10, 8),
+            (10, 10),
+            (12, 10),
+            (12, 12),
+        ];
+
+        match self.0 {
+            1..=130 => Some((1, 1)),
+            // BC, ETC2 and EAC
+            131..=156 => Some((4, 4)),
+            raw @ 157..=184 => Some(ASTC_BLOCKS[((raw - 157) / 2) as usize]),
+            _ => None,
+        }
+    }
+
+    fn to_srgb(self) -> Option<vk::Format> {
+        let raw = self.0;
+        if SRGB_CAPABLE_8BIT.contains(&raw) {
+            ((raw - SRGB_CAPABLE_8BIT.start()) % 7 == 0).then(|| vk::Format(raw + SRGB_8BIT_OFFSET))
+        } else {
+            is_compressed_srgb_pair_unorm(raw).then(|| vk::Format(raw + 1))
+        }
+    }
+
+    fn to_unorm(self) -> Option<vk::Format> {
+        let raw = self.0;
+        if SRGB_CAPABLE_8BIT.contains(&raw) {
+            ((raw - SRGB_CAPABLE_8BIT.start()) % 7 == SRGB_8BIT_OFFSET)
+                .then(|| vk::Format(raw - SRGB_8BIT_OFFSET))
+        } else {
+            is_compressed_srgb_pair_unorm(raw - 1).then(|| vk::Format(raw - 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aspects() {
+        assert_eq!(
+            vk::Format::B8G8R8A8_SRGB.aspects(),
+            vk::ImageAspectFlags::COLOR
+        );
+        assert!(vk::Format::D32_SFLOAT.is_depth());
+        assert!(!vk::Format::D32_SFLOAT.is_stencil());
+        assert!(vk::Format::S8_UINT.is_stencil());
+        assert!(vk::Format::D24_UNORM_S8_UINT.is_depth());
+        assert!(vk::Format::D24_UNORM_S8_UINT.is_stencil());
+    }
+
+    #[test]
+    fn block_size() {
+        assert_eq!(vk::Format::R8_UNORM.block_size(), Some(1));
+        assert_eq!(vk::Format::R8G8B8_SRGB.block_size(), Some(3));
+        assert_eq!(vk::Format::B8G8R8A8_UNORM.block_size(), Some(4));
+        assert_eq!(vk::Format::A2B10G10R10_UINT_PACK32.block_size(), Some(4));
+        assert_eq!(vk::Format::R16G16B16A16_SFLOAT.block_size(), Some(8));
+        assert_eq!(vk::Format::R32G32B32_SFLOAT.block_size(), Some(12));
+        assert_eq!(vk::Format::R64G64B64A64_SFLOAT.block_size(), Some(32));
+        assert_eq!(vk::Format::BC1_RGB_UNORM_BLOCK.block_size(), Some(8));
+        assert_eq!(vk::Format::BC7_SRGB_BLOCK.block_size(), Some(16));
+        assert_eq!(vk::Format::ASTC_12X12_SRGB_BLOCK.block_size(), Some(16));
+        assert_eq!(vk::Format::UNDEFINED.block_size(), None);
+    }
+
+    #[test]
+    fn block_extent() {
+        assert_eq!(vk::Format::R8G8B8A8_UNORM.block_extent(), Some((1, 1)));
+        assert_eq!(vk::Format::BC3_UNORM_BLOCK.block_extent(), Some((4, 4)));
+        assert_eq!(
+            vk::Format::EAC_R11G11_SNORM_BLOCK.block_extent(),
+            Some((4, 4))
+        );
+        assert_eq!(
+            vk::Format::ASTC_5X4_UNORM_BLOCK.block_extent(),
+            Some((5, 4))
+        );
+        assert_eq!(
+            vk::Format::ASTC_10X8_SRGB_BLOCK.block_extent(),
+            Some((10, 8))
+        );
+        assert_eq!(
+            vk::Format::ASTC_12X12_UNORM_BLOCK.block_extent(),
+            Some((12, 12))
+        );
+
+        assert!(!vk::Format::D32_SFLOAT.is_compressed());
+        assert!(vk::Format::BC1_RGBA_SRGB_BLOCK.is_compressed());
+    }
+
+    #[test]
+    fn srgb_pairs() {
+        let pairs = [
+            (vk::Format::R8_UNORM, vk::Format::R8_SRGB),
+            (vk::Format::R8G8_UNORM, vk::Format::R8G8_SRGB),
+            (vk::Format::R8G8B8_UNORM, vk::Format::R8G8B8_SRGB),
+            (vk::Format::B8G8R8_UNORM, vk::Format::B8G8R8_SRGB),
+            (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+            (vk::Format::B8G8R8A8_UNORM, vk::Format::B8G8R8A8_SRGB),
+            (
+                vk::Format::A8B8G8R8_UNORM_PACK32,
+                vk::Format::A8B8G8R8_SRGB_PACK32,
+            ),
+            (
+                vk::Format::BC1_RGB_UNORM_BLOCK,
+                vk::Format::BC1_RGB_SRGB_BLOCK,
+            ),
+            (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+            (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+            (
+                vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK,
+                vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK,
+            ),
+            (
+                vk::Format::ASTC_8X6_UNORM_BLOCK,
+                vk::Format::ASTC_8X6_SRGB_BLOCK,
+            ),
+        ];
+
+        for (unorm, srgb) in pairs.iter().copied() {
+            assert!(!unorm.is_srgb());
+            assert!(srgb.is_srgb());
+            assert_eq!(unorm.to_srgb(), Some(srgb));
+            assert_eq!(srgb.to_unorm(), Some(unorm));
+            assert_eq!(unorm.to_unorm(), None);
+            assert_eq!(srgb.to_srgb(), None);
+        }
+
+        assert_eq!(vk::Format::R8_SNORM.to_srgb(), None);
+        assert_eq!(vk::Format::BC4_UNORM_BLOCK.to_srgb(), None);
+        assert_eq!(vk::Format::R16_UNORM.to_srgb(), None);
+    }
+}
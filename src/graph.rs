@@ -6,27 +6,117 @@
 //! image) is represented by consuming the original image and producing a new
 //! one.
 
-use std::{collections::VecDeque, convert::TryInto, fmt, time::Instant};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    convert::TryInto,
+    fmt,
+    ops::Range,
+    time::Instant,
+};
 
 use arrayvec::ArrayVec;
 use erupt::vk;
+use fixedbitset::FixedBitSet;
 use petgraph::{
     graph::NodeIndex,
     visit::{EdgeRef, IntoEdgeReferences, IntoNodeReferences, NodeRef},
-    Directed,
+    Directed, Direction,
 };
 use thiserror::Error;
 use tinyvec::TinyVec;
 
 use crate::{
+    display::{DisplayInfo, OutputEncoding},
+    format::FormatExt,
     frame::FrameContext,
-    pass::{ClearColorValue, ClearDepthStencilValue, RenderPass},
-    util::SmallSet,
+    limits::{DeviceLimits, LimitExceeded},
+    pass::{ClearColorValue, ClearDepthStencilValue, ComputePipeline, RenderPass},
+    util::{self, SmallSet},
     vks,
 };
 
 type PassGraph = petgraph::Graph<RenderPassId, DependencyType, Directed, u16>;
 
+/// Topologically sorts the pass graph, preferring passes with higher priority.
+///
+/// Among the passes whose dependencies have all been scheduled, the pass with
+/// the highest priority is scheduled next, with ties broken in favor of the
+/// pass added to the graph first. A pass's priority is raised to that of its
+/// highest-priority dependent, so that the dependencies of a high-priority
+/// pass are scheduled early as well. Returns a node on a cycle if the graph is
+/// not acyclic.
+fn prioritized_toposort<F>(
+    graph: &PassGraph,
+    priority: F,
+) -> Result<Vec<NodeIndex<u16>>, NodeIndex<u16>>
+where
+    F: Fn(RenderPassId) -> i32,
+{
+    let mut in_degree = graph
+        .node_indices()
+        .map(|idx| graph.edges_directed(idx, Direction::Incoming).count())
+        .collect::<Vec<_>>();
+
+    let mut effective = graph
+        .node_indices()
+        .map(|idx| priority(graph[idx]))
+        .collect::<Vec<_>>();
+    // Cycles are reported below, where priorities no longer matter.
+    if let Ok(order) = petgraph::algo::toposort(graph, None) {
+        for &idx in order.iter().rev() {
+            for dependent in graph.neighbors(idx) {
+                effective[idx.index()] = effective[idx.index()].max(effective[dependent.index()]);
+            }
+        }
+    }
+
+    let key = |idx: NodeIndex<u16>| {
+        let pass_id = graph[idx];
+        (effective[idx.index()], Reverse(pass_id.id), idx)
+    };
+
+    let mut ready = graph
+        .node_indices()
+        .filter(|idx| in_degree[idx.index()] == 0)
+        .map(key)
+        .collect::<BinaryHeap<_>>();
+
+    let mut ordered = Vec::with_capacity(graph.node_count());
+    while let Some((_, _, idx)) = ready.pop() {
+        ordered.push(idx);
+
+        for edge in graph.edges(idx) {
+            let target = edge.target();
+            in_degree[target.index()] -= 1;
+            if in_degree[target.index()] == 0 {
+                ready.push(key(target));
+            }
+        }
+    }
+
+    if ordered.len() < graph.node_count() {
+        // Every unscheduled node is on or downstream of a cycle; walk
+        // backwards along unscheduled edges until a node repeats.
+        let mut visited = vec![false; graph.node_count()];
+        let mut node = graph
+            .node_indices()
+            .find(|idx| in_degree[idx.index()] > 0)
+            .unwrap();
+        while !visited[node.index()] {
+            visited[node.index()] = true;
+            node = graph
+                .neighbors_directed(node, Direction::Incoming)
+                .find(|pred| in_degree[pred.index()] > 0)
+                .unwrap();
+        }
+
+        return Err(node);
+    }
+
+    Ok(ordered)
+}
+
 #[derive(Debug)]
 pub enum AccessType {
     Consume,
@@ -75,6 +165,143 @@ pub enum RenderGraphError {
         r_id: ResourceId,
         p_name: String,
     },
+    #[error(
+        "Resource {r_name:?} (ID = {r_id}) cannot be viewed as {format:?}. \
+         View formats must be the image format or one of its declared view formats."
+    )]
+    IncompatibleViewFormat {
+        r_name: String,
+        r_id: ResourceId,
+        format: vk::Format,
+    },
+    #[error(
+        "Resource {r_name:?} (ID = {r_id}) has format {format:?}, \
+         but motion vectors must use MOTION_VECTOR_FORMAT."
+    )]
+    NotMotionVectors {
+        r_name: String,
+        r_id: ResourceId,
+        format: vk::Format,
+    },
+    #[error(
+        "Layers {layers:?} are out of range for resource {r_name:?} (ID = {r_id}), \
+         which has {array_layers} layers."
+    )]
+    LayersOutOfRange {
+        r_name: String,
+        r_id: ResourceId,
+        layers: Range<u32>,
+        array_layers: u32,
+    },
+    #[error(
+        "Subresources {subresources:?} are out of range for resource {r_name:?} (ID = {r_id})."
+    )]
+    SubresourcesOutOfRange {
+        r_name: String,
+        r_id: ResourceId,
+        subresources: Subresources,
+    },
+    #[error(
+        "Render pass {pass_name:?} reads subresources of resource {r_name:?} \
+         (ID = {r_id}) which it also writes."
+    )]
+    OverlappingSubresources {
+        pass_name: String,
+        r_name: String,
+        r_id: ResourceId,
+    },
+    #[error("Alias alignment {0} is not a power of two.")]
+    InvalidAlignment(vk::DeviceSize),
+    #[error("Resource {r_name:?} (ID = {r_id}) is a 3D image and cannot have array layers.")]
+    LayeredVolume { r_name: String, r_id: ResourceId },
+    #[error(
+        "View mask {view_mask:#b} of resource {r_name:?} (ID = {r_id}) \
+         selects views beyond its {array_layers} layers."
+    )]
+    InvalidViewMask {
+        r_name: String,
+        r_id: ResourceId,
+        view_mask: u32,
+        array_layers: u32,
+    },
+    #[error(
+        "Render pass {pass_name:?} has a viewport or scissor which is empty or \
+         extends beyond its attachments."
+    )]
+    InvalidViewport { pass_name: String },
+    #[error("Resource {r_name:?} (ID = {r_id}) is not multisampled and cannot be resolved.")]
+    NotMultisampled { r_name: String, r_id: ResourceId },
+    #[error(
+        "Resource {r_name:?} (ID = {r_id}) is discarded by render pass {writer:?}, \
+         but is later read by render pass {reader:?}."
+    )]
+    DiscardedContentsRead {
+        r_name: String,
+        r_id: ResourceId,
+        writer: String,
+        reader: String,
+    },
+    #[error(
+        "The final image {r_name:?} (ID = {r_id}) is discarded by render pass {writer:?} \
+         and cannot be presented."
+    )]
+    DiscardedFinalImage {
+        r_name: String,
+        r_id: ResourceId,
+        writer: String,
+    },
+    #[error(
+        "The final image {r_name:?} has format {format:?}, but the display's swapchain \
+         format is {swapchain_format:?}."
+    )]
+    SwapchainFormatMismatch {
+        r_name: String,
+        format: vk::Format,
+        swapchain_format: vk::Format,
+    },
+    #[error(
+        "The display's surface format {surface_format:?} does not provide the graph's \
+         output encoding {encoding:?}."
+    )]
+    OutputEncodingMismatch {
+        encoding: OutputEncoding,
+        surface_format: vk::SurfaceFormatKHR,
+    },
+    #[error(
+        "The final image {r_name:?} is written with {usage:?} usage, which the display's \
+         swapchain images lack; request it with `DisplayConfig::extra_image_usage`."
+    )]
+    SwapchainUsageMissing {
+        r_name: String,
+        usage: vk::ImageUsageFlags,
+    },
+    #[error("{name:?}: {source}")]
+    LimitExceeded {
+        /// The name of the offending resource or render pass.
+        name: String,
+        #[source]
+        source: LimitExceeded,
+    },
+    #[error(
+        "Render pass {pass_name:?} cannot be disabled, as resource {res_name:?} \
+         (ID = {res_id}) is used by other passes and is not passed through."
+    )]
+    RequiredPass {
+        pass_name: String,
+        res_name: String,
+        res_id: ResourceId,
+    },
+    #[error(
+        "Subgraph resource {r_name:?} (ID = {r_id}) is produced within the subgraph \
+         and cannot be mapped onto a resource of the parent graph."
+    )]
+    ProducedSubgraphInput { r_name: String, r_id: ResourceId },
+    #[error("Resource {r_name:?} (ID = {r_id}) is already consumed by render pass {p_name:?}.")]
+    AlreadyConsumed {
+        r_name: String,
+        r_id: ResourceId,
+        p_name: String,
+    },
     #[error("Final image size must be SAME_AS_SWAPCHAIN.")]
     FinalImageSize(ImageSize),
     #[error("Render graph has no swapchain image.")]
@@ -144,6 +371,34 @@ impl RelativeExtent {
         height: 1.0,
         depth: 1.0,
     };
+
+    pub fn new(width: f32, height: f32, depth: f32) -> RelativeExtent {
+        RelativeExtent {
+            width,
+            height,
+            depth,
+        }
+    }
+
+    /// Returns a relative extent which scales width and height uniformly.
+    pub fn scale(scale: f32) -> RelativeExtent {
+        RelativeExtent::new(scale, scale, 1.0)
+    }
+
+    /// Scales `extent` by this relative extent.
+    ///
+    /// Each dimension is rounded to the nearest integer and is at least 1.
+    fn apply(&self, extent: vk::Extent3D) -> vk::Extent3D {
+        fn scale_dim(dim: u32, scale: f32) -> u32 {
+            ((dim as f32 * scale).round() as u32).max(1)
+        }
+
+        vk::Extent3D {
+            width: scale_dim(extent.width, self.width),
+            height: scale_dim(extent.height, self.height),
+            depth: scale_dim(extent.depth, self.depth),
+        }
+    }
 }
 
 impl Default for RelativeExtent {
@@ -156,11 +411,175 @@ impl Default for RelativeExtent {
     }
 }
 
+/// A rectangle relative to the extent of a render pass's attachments, where
+/// `(0, 0)` is the top-left corner and `(1, 1)` the bottom-right.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RelativeRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl RelativeRect {
+    pub const FULL: Self = RelativeRect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> RelativeRect {
+        RelativeRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns `true` if the rectangle is non-empty and lies within the
+    /// attachments.
+    fn is_valid(&self) -> bool {
+        let in_unit = |v: f32| (0.0..=1.0).contains(&v);
+
+        self.width > 0.0
+            && self.height > 0.0
+            && in_unit(self.x)
+            && in_unit(self.y)
+            && in_unit(self.x + self.width)
+            && in_unit(self.y + self.height)
+    }
+
+    /// Scales the rectangle to `extent`.
+    ///
+    /// Edges are rounded to the nearest texel, so rectangles which share an
+    /// edge, e.g. the halves of a split screen, tile the extent exactly.
+    pub fn apply(&self, extent: vk::Extent2D) -> vk::Rect2D {
+        let edge = |v: f32, dim: u32| (v * dim as f32).round() as i32;
+        let (x0, x1) = (
+            edge(self.x, extent.width),
+            edge(self.x + self.width, extent.width),
+        );
+        let (y0, y1) = (
+            edge(self.y, extent.height),
+            edge(self.y + self.height, extent.height),
+        );
+
+        vk::Rect2D {
+            offset: vk::Offset2D { x: x0, y: y0 },
+            extent: vk::Extent2D {
+                width: (x1 - x0) as u32,
+                height: (y1 - y0) as u32,
+            },
+        }
+    }
+
+    fn intersect(&self, other: &RelativeRect) -> RelativeRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        RelativeRect {
+            x,
+            y,
+            width: ((self.x + self.width).min(other.x + other.width) - x).max(0.0),
+            height: ((self.y + self.height).min(other.y + other.height) - y).max(0.0),
+        }
+    }
+}
+
+impl Default for RelativeRect {
+    fn default() -> Self {
+        RelativeRect::FULL
+    }
+}
+
+/// The viewport transform and scissor of a render pass, e.g. to draw one
+/// player's view in split-screen or picture-in-picture rendering.
+///
+/// By default, a render pass covers its attachments entirely.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PassViewport {
+    /// The viewport rectangle, which is also the pass's render area.
+    pub rect: RelativeRect,
+    /// The scissor rectangle, clipped to `rect`. If `None`, the scissor
+    /// matches `rect`.
+    pub scissor: Option<RelativeRect>,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl PassViewport {
+    pub fn new(rect: RelativeRect) -> PassViewport {
+        PassViewport {
+            rect,
+            scissor: None,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+
+    pub fn scissor(mut self, scissor: RelativeRect) -> PassViewport {
+        self.scissor = Some(scissor);
+        self
+    }
+
+    pub fn depth_range(mut self, min_depth: f32, max_depth: f32) -> PassViewport {
+        self.min_depth = min_depth;
+        self.max_depth = max_depth;
+        self
+    }
+
+    fn is_valid(&self) -> bool {
+        let depth_valid =
+            (0.0..=1.0).contains(&self.min_depth) && (0.0..=1.0).contains(&self.max_depth);
+
+        let scissor_valid = match self.scissor {
+            Some(s) => s.is_valid(),
+            None => true,
+        };
+
+        depth_valid && self.rect.is_valid() && scissor_valid
+    }
+
+    /// Returns the render area and scissor rectangle for attachments of the
+    /// given extent.
+    pub fn render_area(&self, extent: vk::Extent2D) -> (vk::Rect2D, vk::Rect2D) {
+        let scissor = match self.scissor {
+            Some(s) => self.rect.intersect(&s),
+            None => self.rect,
+        };
+
+        (self.rect.apply(extent), scissor.apply(extent))
+    }
+
+    /// Returns the viewport transform for attachments of the given extent.
+    pub fn viewport(&self, extent: vk::Extent2D) -> vk::Viewport {
+        let area = self.rect.apply(extent);
+
+        vk::Viewport {
+            x: area.offset.x as f32,
+            y: area.offset.y as f32,
+            width: area.extent.width as f32,
+            height: area.extent.height as f32,
+            min_depth: self.min_depth,
+            max_depth: self.max_depth,
+        }
+    }
+}
+
+impl Default for PassViewport {
+    fn default() -> Self {
+        PassViewport::new(RelativeRect::FULL)
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum ImageSize {
     Absolute(vk::Extent3D),
     RelativeToSwapchain(RelativeExtent),
     RelativeToInput(RelativeExtent),
+    /// Relative to the size of another image in the graph.
+    RelativeToImage(ResourceId, RelativeExtent),
 }
 
 impl PartialEq for ImageSize {
@@ -172,6 +591,7 @@ impl PartialEq for ImageSize {
             }
             (RelativeToSwapchain(a), RelativeToSwapchain(b)) => a == b,
             (RelativeToInput(a), RelativeToInput(b)) => a == b,
+            (RelativeToImage(a, ra), RelativeToImage(b, rb)) => a == b && ra == rb,
             _ => false,
         }
     }
@@ -179,16 +599,193 @@ impl PartialEq for ImageSize {
 
 impl ImageSize {
     pub const SAME_AS_SWAPCHAIN: Self = ImageSize::RelativeToSwapchain(RelativeExtent::ONE);
+
+    /// Computes the concrete extent of an image.
+    ///
+    /// For sizes relative to an input image or to another graph image,
+    /// `input_extent` is the extent of that image. Returns `None` if such a
+    /// size is resolved and `input_extent` is `None`.
+    pub fn resolve(
+        &self,
+        swapchain_extent: vk::Extent2D,
+        input_extent: Option<vk::Extent3D>,
+    ) -> Option<vk::Extent3D> {
+        match self {
+            ImageSize::Absolute(extent) => Some(*extent),
+            ImageSize::RelativeToSwapchain(rel) => Some(rel.apply(vk::Extent3D {
+                width: swapchain_extent.width,
+                height: swapchain_extent.height,
+                depth: 1,
+            })),
+            ImageSize::RelativeToInput(rel) | ImageSize::RelativeToImage(_, rel) => {
+                input_extent.map(|extent| rel.apply(extent))
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct ImageInfo {
     pub size: ImageSize,
     pub format: vk::Format,
+    /// Additional formats the image may be viewed with, such as the sRGB
+    /// equivalent of a UNORM format.
+    ///
+    /// If non-empty, the image is created with `MUTABLE_FORMAT` and an
+    /// explicit format list. Each format must be size-compatible with
+    /// `format`.
+    pub view_formats: Vec<vk::Format>,
+    /// The number of samples per texel.
+    pub samples: vk::SampleCountFlagBits,
+    /// The number of mip levels.
+    pub mip_levels: u32,
+    /// The number of array layers, e.g. six for a cube map or two for stereo
+    /// rendering.
+    pub array_layers: u32,
+    /// The layers rendered with multiview when the image is a color
+    /// attachment, one bit per layer. If zero, multiview is not used.
+    pub view_mask: u32,
+}
+
+impl ImageInfo {
+    /// Returns the info of a swapchain-sized motion vector image.
+    pub fn motion_vectors() -> ImageInfo {
+        ImageInfo {
+            size: ImageSize::SAME_AS_SWAPCHAIN,
+            format: MOTION_VECTOR_FORMAT,
+            view_formats: Vec::new(),
+            samples: vk::SampleCountFlagBits::_1,
+            mip_levels: 1,
+            array_layers: 1,
+            view_mask: 0,
+        }
+    }
+
+    /// Returns the image creation flags implied by the info.
+    pub fn create_flags(&self) -> vk::ImageCreateFlags {
+        if self.view_formats.is_empty() {
+            vk::ImageCreateFlags::empty()
+        } else {
+            vk::ImageCreateFlags::MUTABLE_FORMAT
+        }
+    }
+
+    /// Returns the type of the image, given its resolved extent.
+    ///
+    /// Images with a depth greater than one are 3D.
+    pub fn image_type(&self, extent: vk::Extent3D) -> vk::ImageType {
+        if extent.depth > 1 {
+            vk::ImageType::_3D
+        } else {
+            vk::ImageType::_2D
+        }
+    }
+
+    /// Returns the type of a view of the whole image, given its resolved
+    /// extent.
+    pub fn view_type(&self, extent: vk::Extent3D) -> vk::ImageViewType {
+        if extent.depth > 1 {
+            vk::ImageViewType::_3D
+        } else if self.array_layers > 1 {
+            vk::ImageViewType::_2D_ARRAY
+        } else {
+            vk::ImageViewType::_2D
+        }
+    }
+
+    /// Returns the subresource range covering all of the image's mip levels
+    /// and array layers.
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        self.all_subresources().to_vk(self.format.aspects())
+    }
+
+    /// Returns the subresources making up the whole image.
+    pub fn all_subresources(&self) -> Subresources {
+        Subresources {
+            mip_levels: 0..self.mip_levels,
+            array_layers: 0..self.array_layers,
+        }
+    }
+
+    /// Returns `true` if every view in `view_mask` is a layer of the image.
+    pub fn view_mask_is_valid(&self) -> bool {
+        self.array_layers >= 32 || self.view_mask >> self.array_layers == 0
+    }
+
+    /// Returns `true` if the image may be viewed with `format`.
+    pub fn supports_view_format(&self, format: vk::Format) -> bool {
+        format == self.format || self.view_formats.contains(&format)
+    }
+}
+
+/// A range of mip levels and array layers of an image.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Subresources {
+    pub mip_levels: Range<u32>,
+    pub array_layers: Range<u32>,
+}
+
+impl Subresources {
+    /// Returns the subresources making up every layer of one mip level.
+    pub fn mip(level: u32, array_layers: u32) -> Subresources {
+        Subresources {
+            mip_levels: level..level + 1,
+            array_layers: 0..array_layers,
+        }
+    }
+
+    /// Returns `true` if the two ranges share any subresource.
+    pub fn overlaps(&self, other: &Subresources) -> bool {
+        let overlap = |a: &Range<u32>, b: &Range<u32>| a.start < b.end && b.start < a.end;
+        overlap(&self.mip_levels, &other.mip_levels)
+            && overlap(&self.array_layers, &other.array_layers)
+    }
+
+    /// Returns `true` if the ranges are non-empty and lie within `info`.
+    pub fn is_within(&self, info: &ImageInfo) -> bool {
+        !self.mip_levels.is_empty()
+            && !self.array_layers.is_empty()
+            && self.mip_levels.end <= info.mip_levels
+            && self.array_layers.end <= info.array_layers
+    }
+
+    pub fn to_vk(&self, aspect_mask: vk::ImageAspectFlags) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange {
+            aspect_mask,
+            base_mip_level: self.mip_levels.start,
+            level_count: self.mip_levels.len() as u32,
+            base_array_layer: self.array_layers.start,
+            layer_count: self.array_layers.len() as u32,
+        }
+    }
+}
+
+/// The format of motion vector images.
+///
+/// Each texel holds the offset, in normalized `[0, 1]` texture coordinates,
+/// from the texel's position in the current frame to the position of the same
+/// surface point in the previous frame. That is, a consumer finds the previous
+/// frame's sample at `uv + motion`.
+pub const MOTION_VECTOR_FORMAT: vk::Format = vk::Format::R16G16_SFLOAT;
+
+/// GLSL helpers for writing motion vectors.
+///
+/// `reify_motion_vector` takes the clip-space positions of a vertex in the
+/// current and previous frames, both without jitter, and returns the value to
+/// write to a motion vector attachment.
+pub const MOTION_VECTOR_GLSL: &str = "\
+vec2 reify_motion_vector(vec4 current_clip, vec4 previous_clip) {
+    vec2 current_uv = current_clip.xy / current_clip.w * 0.5 + 0.5;
+    vec2 previous_uv = previous_clip.xy / previous_clip.w * 0.5 + 0.5;
+    return previous_uv - current_uv;
 }
+";
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct BufferInfo {}
+pub struct BufferInfo {
+    pub size: vk::DeviceSize,
+    pub usage: vk::BufferUsageFlags,
+}
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ResourceTypeTag {
@@ -202,6 +799,207 @@ pub enum ResourceType {
     Buffer(BufferInfo),
 }
 
+/// A report of the opportunities for overlapping render pass execution.
+///
+/// Render passes are currently recorded serially on a single queue. This
+/// report describes how much of that serialization is imposed by the graph's
+/// dependencies, as a guide to the benefit of concurrent scheduling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlapReport {
+    /// The number of render passes in the graph.
+    pub pass_count: usize,
+    /// The number of passes in the longest chain of dependent passes.
+    ///
+    /// With unlimited concurrency, the graph could execute in this many
+    /// serial steps rather than `pass_count`.
+    pub critical_path_len: usize,
+    /// The names of each pair of passes with no dependency between them,
+    /// which could execute concurrently.
+    pub independent_pairs: Vec<(String, String)>,
+}
+
+impl fmt::Display for OverlapReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} render passes, critical path of {} passes",
+            self.pass_count, self.critical_path_len
+        )?;
+
+        for (a, b) in self.independent_pairs.iter() {
+            writeln!(f, "  {:?} and {:?} are independent", a, b)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The span of render passes over which a resource is live.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceLifetime {
+    pub id: ResourceId,
+    pub name: String,
+    /// The position in execution order of the pass which produces the
+    /// resource.
+    pub first_use: usize,
+    /// The position in execution order of the last pass which reads or
+    /// consumes the resource.
+    pub last_use: usize,
+    /// The estimated size of the resource in bytes, or `None` if it cannot be
+    /// determined from the graph alone.
+    pub size: Option<vk::DeviceSize>,
+}
+
+/// The lifetimes of the transient resources in a render graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LifetimeReport {
+    /// The names of the render passes, in execution order.
+    pub passes: Vec<String>,
+    pub resources: Vec<ResourceLifetime>,
+}
+
+impl LifetimeReport {
+    /// Returns the largest total size of the resources live during any one
+    /// render pass, assuming no aliasing.
+    pub fn peak_size(&self) -> vk::DeviceSize {
+        (0..self.passes.len())
+            .map(|pos| {
+                self.resources
+                    .iter()
+                    .filter(|res| (res.first_use..=res.last_use).contains(&pos))
+                    .filter_map(|res| res.size)
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Serializes the report as JSON, e.g. for plotting as a Gantt chart.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"passes\":[");
+        for (i, pass) in self.passes.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_json_string(&mut out, pass);
+        }
+
+        out.push_str("],\"resources\":[");
+        for (i, res) in self.resources.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!("{{\"id\":{},\"name\":", res.id.id));
+            write_json_string(&mut out, &res.name);
+            out.push_str(&format!(
+                ",\"first_use\":{},\"last_use\":{},\"size\":",
+                res.first_use, res.last_use
+            ));
+            match res.size {
+                Some(size) => out.push_str(&size.to_string()),
+                None => out.push_str("null"),
+            }
+            out.push('}');
+        }
+        out.push_str("]}");
+
+        out
+    }
+}
+
+/// The placement of resources sharing storage within an aliased memory
+/// block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AliasPlacement {
+    /// The resources placed at this location. Each is consumed by the next.
+    pub resources: Vec<ResourceId>,
+    /// The byte offset of the resources within the memory block.
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    /// The position in execution order of the first pass which uses the
+    /// resources.
+    pub first_use: usize,
+    /// The position in execution order of the last pass which uses the
+    /// resources.
+    pub last_use: usize,
+}
+
+/// A plan for aliasing transient resources within one memory block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AliasPlan {
+    /// The size in bytes of the memory block.
+    pub size: vk::DeviceSize,
+    pub placements: Vec<AliasPlacement>,
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Estimates the memory used by an image with the given extent.
+///
+/// Only the base mip level is counted, and alignment and padding are ignored.
+fn estimate_image_size(info: &ImageInfo, extent: vk::Extent3D) -> Option<vk::DeviceSize> {
+    let block_size = info.format.block_size()? as vk::DeviceSize;
+    let (block_width, block_height) = info.format.block_extent()?;
+    let blocks_wide = util::ceil_div(extent.width.into(), block_width.into());
+    let blocks_high = util::ceil_div(extent.height.into(), block_height.into());
+
+    Some(
+        blocks_wide
+            * blocks_high
+            * extent.depth as vk::DeviceSize
+            * info.array_layers as vk::DeviceSize
+            * block_size
+            * info.samples.0 as vk::DeviceSize,
+    )
+}
+
+/// The resources and render passes of a subgraph spliced into a render graph.
+///
+/// Returned by [`RenderGraphBuilder::add_subgraph`], this translates IDs from
+/// the subgraph's builder into IDs in the parent graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subgraph {
+    resources: Vec<ResourceId>,
+    passes: Vec<RenderPassId>,
+}
+
+impl Subgraph {
+    /// Returns the parent graph's ID for a resource of the subgraph.
+    pub fn resource(&self, id: ResourceId) -> Option<ResourceId> {
+        self.resources.get(id.id as usize).copied()
+    }
+
+    /// Returns the parent graph's ID for a render pass of the subgraph.
+    pub fn render_pass(&self, id: RenderPassId) -> Option<RenderPassId> {
+        self.passes.get(id.id as usize).copied()
+    }
+}
+
+/// A summary of an image resource in the render graph.
+///
+/// This is intended for debugging tools which allow inspecting intermediate
+/// images.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageDescription<'a> {
+    pub id: ResourceId,
+    pub name: &'a str,
+    pub info: &'a ImageInfo,
+    /// The name of the render pass which produces the image, or `None` if the
+    /// image is external to the graph.
+    pub producer: Option<&'a str>,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct ResourceConsumed {
     pass: RenderPassId,
@@ -215,6 +1013,9 @@ struct Resource {
     produced_by: Option<RenderPassId>,
     read_by: TinyVec<[RenderPassId; 4]>,
     consumed_by: Option<RenderPassId>,
+    // Passes which read the value of the resource from the previous frame.
+    // These accesses do not create dependencies within a frame.
+    history_read_by: TinyVec<[RenderPassId; 4]>,
 }
 
 impl Resource {
@@ -224,6 +1025,7 @@ impl Resource {
             produced_by: None,
             read_by: TinyVec::new(),
             consumed_by: None,
+            history_read_by: TinyVec::new(),
         }
     }
 
@@ -236,6 +1038,16 @@ impl Resource {
             }),
         }
     }
+
+    fn buffer_info(&self) -> Result<&BufferInfo, RenderGraphError> {
+        match &self.ty {
+            ResourceType::Buffer(info) => Ok(info),
+            ResourceType::Image(_) => Err(RenderGraphError::IncompatibleResourceType {
+                expected: ResourceTypeTag::Buffer,
+                actual: ResourceTypeTag::Image,
+            }),
+        }
+    }
 }
 
 enum AttachmentType {
@@ -272,6 +1084,9 @@ pub struct RenderPassBuilder<'a> {
     /// The resources produced by this builder.
     produced: ArrayVec<ResourceType, MAX_PRODUCED_RESOURCES>,
     produced_names: ArrayVec<String, MAX_PRODUCED_RESOURCES>,
+    /// Images of which only some subresources are read. These become reads
+    /// when the pass is finished, unless the pass also consumes the image.
+    partial_reads: SmallSet<ResourceId, EXPECTED_READS>,
 
     graph: &'a mut RenderGraphBuilder,
     pass: RenderPassNode,
@@ -334,7 +1149,7 @@ impl<'a> RenderPassBuilder<'a> {
             });
         }
 
-        if !self.pass.reads.insert(id) {
+        if self.partial_reads.contains(&id) || !self.pass.reads.insert(id) {
             return Err(RenderGraphError::AlreadyAccessed {
                 ty: AccessType::Read,
                 r_name: name.to_owned(),
@@ -368,19 +1183,54 @@ impl<'a> RenderPassBuilder<'a> {
     pub fn add_input_attachment(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
         self.add_read(id)?;
 
-        self.pass.input_attachments.push(id);
+        self.pass.input_attachments.push(InputAttachment {
+            resource: id,
+            view_format: None,
+        });
 
         Ok(())
     }
 
-    /// Adds a color attachment to the render pass.
+    /// Adds an input attachment which is viewed with a specific format.
     ///
-    /// If `consumes` is `Some(c)`, then `c` is the ID of an image resource
-    /// whose value will be used to initialize the image resource identified by
-    /// `id`. The consumed resource may not be used again.
-    pub fn add_color_attachment<S: AsRef<str>>(
+    /// `format` must be the image's format or one of its declared
+    /// `view_formats`.
+    pub fn add_input_attachment_with_format(
         &mut self,
-        name: S,
+        id: ResourceId,
+        format: vk::Format,
+    ) -> Result<(), RenderGraphError> {
+        if !self
+            .graph
+            .resource(id)?
+            .image_info()?
+            .supports_view_format(format)
+        {
+            return Err(RenderGraphError::IncompatibleViewFormat {
+                r_name: self.graph.resource_name(id).unwrap().to_owned(),
+                r_id: id,
+                format,
+            });
+        }
+
+        self.add_read(id)?;
+
+        self.pass.input_attachments.push(InputAttachment {
+            resource: id,
+            view_format: Some(format),
+        });
+
+        Ok(())
+    }
+
+    /// Adds a color attachment to the render pass.
+    ///
+    /// If `consumes` is `Some(c)`, then `c` is the ID of an image resource
+    /// whose value will be used to initialize the image resource identified by
+    /// `id`. The consumed resource may not be used again.
+    pub fn add_color_attachment<S: AsRef<str>>(
+        &mut self,
+        name: S,
         info: ImageInfo,
         consumes: Option<ResourceId>,
     ) -> Result<ResourceId, RenderGraphError> {
@@ -391,6 +1241,345 @@ impl<'a> RenderPassBuilder<'a> {
         self.pass.color_attachments.push(ColorAttachment {
             consumed: consumes,
             produced: id,
+            resolve: None,
+            discard: false,
+            layers: None,
+            mip_level: 0,
+        });
+
+        if let Some(c) = consumes {
+            self.add_consume(c)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Adds an image sampled by the render pass's fragment shaders.
+    ///
+    /// The image is read in `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn add_sampled_image(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        self.add_sampled_image_with_stages(id, vk::PipelineStageFlags::FRAGMENT_SHADER)
+    }
+
+    /// Adds an image sampled by the render pass in the given shader stages.
+    ///
+    /// The image is read in `SHADER_READ_ONLY_OPTIMAL` layout.
+    pub fn add_sampled_image_with_stages(
+        &mut self,
+        id: ResourceId,
+        stages: vk::PipelineStageFlags,
+    ) -> Result<(), RenderGraphError> {
+        self.check_self_loop(id)?;
+        self.graph.resource(id)?.image_info()?;
+        self.add_read(id)?;
+
+        self.pass.sampled_images.push(SampledImage {
+            resource: id,
+            stages,
+            subresources: None,
+        });
+
+        Ok(())
+    }
+
+    /// Adds a read of some of an image's mip levels and array layers.
+    ///
+    /// Unlike a read of the whole image, this may be combined with consuming
+    /// the same image in this pass, provided the pass does not write the
+    /// subresources it reads. This allows e.g. a downsample pass to read mip
+    /// level N of an image and write level N + 1.
+    pub fn add_sampled_subresources(
+        &mut self,
+        id: ResourceId,
+        subresources: Subresources,
+        stages: vk::PipelineStageFlags,
+    ) -> Result<(), RenderGraphError> {
+        self.check_self_loop(id)?;
+        let info = self.graph.resource(id)?.image_info()?;
+
+        if !subresources.is_within(info) {
+            return Err(RenderGraphError::SubresourcesOutOfRange {
+                r_name: self.graph.resource_name(id).unwrap().to_owned(),
+                r_id: id,
+                subresources,
+            });
+        }
+
+        if self.pass.reads.contains(&id) || !self.partial_reads.insert(id) {
+            return Err(RenderGraphError::AlreadyAccessed {
+                ty: AccessType::Read,
+                r_name: self.graph.resource_name(id).unwrap().to_owned(),
+                r_id: id,
+                p_name: self.name.clone(),
+            });
+        }
+
+        self.pass.sampled_images.push(SampledImage {
+            resource: id,
+            stages,
+            subresources: Some(subresources),
+        });
+
+        Ok(())
+    }
+
+    /// Selects the mip level a color attachment produced by this pass
+    /// renders to.
+    pub fn select_color_attachment_mip(
+        &mut self,
+        id: ResourceId,
+        level: u32,
+    ) -> Result<(), RenderGraphError> {
+        let color_idx = self
+            .pass
+            .color_attachments
+            .iter()
+            .position(|att| att.produced == id)
+            .ok_or(RenderGraphError::NoSuchResource(id))?;
+
+        let produced_idx = (id.id - self.base_resource_id.id) as usize;
+        let info = match &self.produced[produced_idx] {
+            ResourceType::Image(info) => info,
+            ResourceType::Buffer(_) => unreachable!("color attachment is not an image"),
+        };
+
+        if level >= info.mip_levels {
+            return Err(RenderGraphError::SubresourcesOutOfRange {
+                r_name: self.produced_names[produced_idx].clone(),
+                r_id: id,
+                subresources: Subresources::mip(level, info.array_layers),
+            });
+        }
+
+        self.pass.color_attachments[color_idx].mip_level = level;
+        Ok(())
+    }
+
+    /// Adds an image whose contents from the previous frame are read by the
+    /// render pass.
+    ///
+    /// This is a back-edge in the dependency graph: the pass may read the
+    /// previous frame's value of an image that it produces in the current
+    /// frame, or of an image declared before the pass, as needed for temporal
+    /// effects. Images produced by later passes do not exist yet, so their
+    /// history cannot be read. History images persist across frames rather
+    /// than being transient.
+    pub fn add_history_image(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        let name = if id.id >= self.base_resource_id.id {
+            // The image is produced by this pass.
+            let idx = (id.id - self.base_resource_id.id) as usize;
+            match self.produced.get(idx) {
+                Some(ResourceType::Image(_)) => self.produced_names[idx].as_str(),
+                Some(ResourceType::Buffer(_)) => {
+                    return Err(RenderGraphError::IncompatibleResourceType {
+                        expected: ResourceTypeTag::Image,
+                        actual: ResourceTypeTag::Buffer,
+                    })
+                }
+                None => return Err(RenderGraphError::NoSuchResource(id)),
+            }
+        } else {
+            self.graph.resource(id)?.image_info()?;
+            self.graph.resource_name(id).unwrap()
+        };
+
+        if self.pass.history_reads.contains(&id) {
+            return Err(RenderGraphError::AlreadyAccessed {
+                ty: AccessType::Read,
+                r_name: name.to_owned(),
+                r_id: id,
+                p_name: self.name.clone(),
+            });
+        }
+
+        self.pass.history_reads.push(id);
+
+        Ok(())
+    }
+
+    /// Adds a swapchain-sized motion vector image written by the render pass.
+    ///
+    /// See [`MOTION_VECTOR_FORMAT`] for the encoding.
+    pub fn add_motion_vector_output<S: AsRef<str>>(
+        &mut self,
+        name: S,
+    ) -> Result<ResourceId, RenderGraphError> {
+        self.add_color_attachment(name, ImageInfo::motion_vectors(), None)
+    }
+
+    /// Adds a motion vector image sampled by the render pass's fragment
+    /// shaders.
+    ///
+    /// Returns an error if the image does not use [`MOTION_VECTOR_FORMAT`].
+    pub fn add_motion_vector_input(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        let format = self.graph.resource(id)?.image_info()?.format;
+        if format != MOTION_VECTOR_FORMAT {
+            return Err(RenderGraphError::NotMotionVectors {
+                r_name: self.graph.resource_name(id).unwrap().to_owned(),
+                r_id: id,
+                format,
+            });
+        }
+
+        self.add_sampled_image(id)
+    }
+
+    /// Adds an attachment into which a multisampled color attachment is
+    /// resolved at the end of the render pass.
+    ///
+    /// The resolved image has the same size and format as `source`, with a
+    /// single sample per texel. `source` must be produced by this render pass.
+    pub fn add_resolve_attachment<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        source: ResourceId,
+    ) -> Result<ResourceId, RenderGraphError> {
+        let color_idx = self
+            .pass
+            .color_attachments
+            .iter()
+            .position(|att| att.produced == source)
+            .ok_or(RenderGraphError::NoSuchResource(source))?;
+
+        let produced_idx = (source.id - self.base_resource_id.id) as usize;
+        let source_info = match &self.produced[produced_idx] {
+            ResourceType::Image(info) => info,
+            ResourceType::Buffer(_) => unreachable!("color attachment is not an image"),
+        };
+
+        if source_info.samples == vk::SampleCountFlagBits::_1 {
+            return Err(RenderGraphError::NotMultisampled {
+                r_name: self.produced_names[produced_idx].clone(),
+                r_id: source,
+            });
+        }
+
+        let info = ImageInfo {
+            samples: vk::SampleCountFlagBits::_1,
+            ..source_info.clone()
+        };
+        let id = self.add_produce(name, ResourceType::Image(info))?;
+        self.pass.color_attachments[color_idx].resolve = Some(id);
+
+        Ok(id)
+    }
+
+    /// Sets the scheduling priority of the render pass.
+    ///
+    /// When the render graph is built, passes with higher priority run before
+    /// passes with lower priority, as far as their dependencies allow. This
+    /// can be used to e.g. render shadow maps early or schedule
+    /// post-processing late. Priorities never reorder dependent passes. The
+    /// default priority is 0.
+    pub fn set_priority(&mut self, priority: i32) {
+        self.pass.priority = priority;
+    }
+
+    /// Restricts the render pass to a region of its attachments.
+    ///
+    /// The region is relative to the attachment extent, so it follows
+    /// swapchain resizes. It is checked when the graph is built.
+    pub fn set_viewport(&mut self, viewport: PassViewport) {
+        self.pass.viewport = viewport;
+    }
+
+    /// Marks the render pass as a root of the graph.
+    ///
+    /// When the graph is built, passes whose outputs never reach the final
+    /// image are culled. Root passes and their dependencies are kept
+    /// regardless, which is needed for passes with side effects outside the
+    /// graph, such as readbacks.
+    pub fn mark_root(&mut self) {
+        self.pass.root = true;
+    }
+
+    /// Restricts a color attachment produced by this pass to a range of its
+    /// array layers, e.g. to render one face of a cube shadow map.
+    ///
+    /// The other layers are passed through from the consumed image, if any.
+    pub fn select_color_attachment_layers(
+        &mut self,
+        id: ResourceId,
+        layers: Range<u32>,
+    ) -> Result<(), RenderGraphError> {
+        let color_idx = self
+            .pass
+            .color_attachments
+            .iter()
+            .position(|att| att.produced == id)
+            .ok_or(RenderGraphError::NoSuchResource(id))?;
+
+        let produced_idx = (id.id - self.base_resource_id.id) as usize;
+        let array_layers = match &self.produced[produced_idx] {
+            ResourceType::Image(info) => info.array_layers,
+            ResourceType::Buffer(_) => unreachable!("color attachment is not an image"),
+        };
+
+        if layers.is_empty() || layers.end > array_layers {
+            return Err(RenderGraphError::LayersOutOfRange {
+                r_name: self.produced_names[produced_idx].clone(),
+                r_id: id,
+                layers,
+                array_layers,
+            });
+        }
+
+        self.pass.color_attachments[color_idx].layers = Some(layers);
+        Ok(())
+    }
+
+    /// Discards the contents of a color attachment at the end of the render
+    /// pass.
+    ///
+    /// This avoids writing the attachment back to memory, e.g. for a
+    /// multisampled attachment which is only needed for its resolve. `id`
+    /// must be produced by this render pass, may not be read by any later
+    /// render pass, and may not be the final image; this is checked when the
+    /// graph is built.
+    pub fn discard_color_attachment(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        let att = self
+            .pass
+            .color_attachments
+            .iter_mut()
+            .find(|att| att.produced == id)
+            .ok_or(RenderGraphError::NoSuchResource(id))?;
+        att.discard = true;
+
+        Ok(())
+    }
+
+    /// Adds a buffer read by the render pass.
+    pub fn add_buffer_input(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        self.check_self_loop(id)?;
+        self.graph.resource(id)?.buffer_info()?;
+        self.add_read(id)?;
+
+        self.pass.buffer_inputs.push(id);
+
+        Ok(())
+    }
+
+    /// Adds a buffer written by the render pass.
+    ///
+    /// If `consumes` is `Some(c)`, then `c` is the ID of a buffer resource
+    /// whose contents will be used to initialize the buffer resource
+    /// identified by `id`. The consumed resource may not be used again.
+    pub fn add_buffer_output<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        info: BufferInfo,
+        consumes: Option<ResourceId>,
+    ) -> Result<ResourceId, RenderGraphError> {
+        if let Some(c) = consumes {
+            self.check_self_loop(c)?;
+            self.graph.resource(c)?.buffer_info()?;
+        }
+
+        let id = self.add_produce(name, ResourceType::Buffer(info)).unwrap();
+
+        self.pass.buffer_outputs.push(BufferOutput {
+            consumed: consumes,
+            produced: id,
         });
 
         if let Some(c) = consumes {
@@ -414,6 +1603,14 @@ impl<'a> RenderPassBuilder<'a> {
             self.graph.add_resource(name, res);
         }
 
+        // If the pass also consumes a partially read image, the consume
+        // orders the pass after the image's producer.
+        for read in self.partial_reads.iter().copied() {
+            if !self.pass.consumes.contains(&read) {
+                self.pass.reads.insert(read);
+            }
+        }
+
         for consume in self.pass.consumes.iter().copied() {
             self.graph.add_consume(consume, id);
         }
@@ -426,6 +1623,12 @@ impl<'a> RenderPassBuilder<'a> {
             self.graph.add_produce(write, id);
         }
 
+        for history in self.pass.history_reads.iter().copied() {
+            self.graph.resources[history.id as usize]
+                .history_read_by
+                .push(id);
+        }
+
         self.graph.passes.push(self.pass);
         self.graph.pass_names.push(self.name);
 
@@ -433,10 +1636,226 @@ impl<'a> RenderPassBuilder<'a> {
     }
 }
 
+/// Declares the resources accessed by a compute pass.
+///
+/// Images and buffers are accessed by the `COMPUTE_SHADER` stage. Images
+/// written by the pass are storage images in `GENERAL` layout.
+pub struct ComputePassBuilder<'a> {
+    inner: RenderPassBuilder<'a>,
+}
+
+impl<'a> ComputePassBuilder<'a> {
+    /// Adds a storage image written by the compute pass.
+    ///
+    /// If `consumes` is `Some(c)`, then `c` is the ID of an image resource
+    /// whose value will be used to initialize the image resource identified by
+    /// `id`. The consumed resource may not be used again.
+    ///
+    /// The image may be the graph's final image, e.g. for a compute upscaler
+    /// writing the swapchain image directly. The display must then be created
+    /// with `STORAGE` in `DisplayConfig::extra_image_usage`; the swapchain
+    /// image is written in `GENERAL` layout and transitioned for presentation
+    /// by [`RenderGraph::record_present_barrier`].
+    pub fn add_storage_image<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        info: ImageInfo,
+        consumes: Option<ResourceId>,
+    ) -> Result<ResourceId, RenderGraphError> {
+        if let Some(c) = consumes {
+            self.inner.check_self_loop(c)?;
+            self.inner.graph.resource(c)?.image_info()?;
+        }
+
+        let id = self.inner.add_produce(name, ResourceType::Image(info))?;
+
+        self.inner.pass.storage_images.push(StorageImage {
+            consumed: consumes,
+            produced: id,
+        });
+
+        if let Some(c) = consumes {
+            self.inner.add_consume(c)?;
+        }
+
+        Ok(id)
+    }
+
+    /// Adds a storage image read by the compute pass.
+    pub fn add_storage_image_input(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        self.inner.check_self_loop(id)?;
+        self.inner.graph.resource(id)?.image_info()?;
+        self.inner.add_read(id)?;
+
+        self.inner.pass.storage_inputs.push(id);
+
+        Ok(())
+    }
+
+    /// Adds an image sampled by the compute pass.
+    pub fn add_sampled_image(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        self.inner
+            .add_sampled_image_with_stages(id, vk::PipelineStageFlags::COMPUTE_SHADER)
+    }
+
+    /// Adds a read of some of an image's mip levels and array layers.
+    ///
+    /// See [`RenderPassBuilder::add_sampled_subresources`].
+    pub fn add_sampled_subresources(
+        &mut self,
+        id: ResourceId,
+        subresources: Subresources,
+    ) -> Result<(), RenderGraphError> {
+        self.inner.add_sampled_subresources(
+            id,
+            subresources,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+        )
+    }
+
+    /// Adds a buffer read by the compute pass.
+    pub fn add_buffer_input(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
+        self.inner.add_buffer_input(id)
+    }
+
+    /// Adds a buffer written by the compute pass.
+    ///
+    /// See [`RenderPassBuilder::add_buffer_output`].
+    pub fn add_buffer_output<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        info: BufferInfo,
+        consumes: Option<ResourceId>,
+    ) -> Result<ResourceId, RenderGraphError> {
+        self.inner.add_buffer_output(name, info, consumes)
+    }
+
+    /// Sets the scheduling priority of the compute pass.
+    ///
+    /// See [`RenderPassBuilder::set_priority`].
+    pub fn set_priority(&mut self, priority: i32) {
+        self.inner.set_priority(priority);
+    }
+
+    /// Marks the compute pass as a root of the graph.
+    ///
+    /// See [`RenderPassBuilder::mark_root`].
+    pub fn mark_root(&mut self) {
+        self.inner.mark_root();
+    }
+
+    pub fn finish(self) -> RenderPassId {
+        self.inner.finish()
+    }
+}
+
+#[derive(Default)]
+struct InputAttachment {
+    resource: ResourceId,
+    /// The format to view the image with, if different from its own.
+    view_format: Option<vk::Format>,
+}
+
+impl InputAttachment {
+    /// Returns the format of the attachment's image view, given the image's
+    /// description.
+    fn view_format(&self, info: &ImageInfo) -> vk::Format {
+        self.view_format.unwrap_or(info.format)
+    }
+}
+
+/// An image read with `SHADER_READ` access in `SHADER_READ_ONLY_OPTIMAL`
+/// layout.
+#[derive(Default)]
+struct SampledImage {
+    resource: ResourceId,
+    /// The shader stages which sample the image.
+    stages: vk::PipelineStageFlags,
+    /// The subresources read, or `None` for the whole image.
+    subresources: Option<Subresources>,
+}
+
 #[derive(Default)]
 struct ColorAttachment {
     consumed: Option<ResourceId>,
     produced: ResourceId,
+    /// The image the attachment is resolved into, if it is multisampled.
+    resolve: Option<ResourceId>,
+    /// Whether the attachment's contents are discarded at the end of the
+    /// render pass, i.e. stored with `AttachmentStoreOp::DONT_CARE`.
+    discard: bool,
+    /// The array layers rendered to, or `None` for all layers.
+    layers: Option<Range<u32>>,
+    /// The mip level rendered to.
+    mip_level: u32,
+}
+
+#[derive(Default)]
+struct BufferOutput {
+    consumed: Option<ResourceId>,
+    produced: ResourceId,
+}
+
+/// An image written by a compute pass in `GENERAL` layout.
+#[derive(Default)]
+struct StorageImage {
+    consumed: Option<ResourceId>,
+    produced: ResourceId,
+}
+
+/// The accesses which must be made available to later passes.
+const WRITE_ACCESS: vk::AccessFlags = vk::AccessFlags::from_bits_truncate(
+    vk::AccessFlags::SHADER_WRITE.bits()
+        | vk::AccessFlags::COLOR_ATTACHMENT_WRITE.bits()
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.bits()
+        | vk::AccessFlags::TRANSFER_WRITE.bits(),
+);
+
+/// How a pass accesses a resource.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct ResourceAccess {
+    /// The pipeline stages which access the resource.
+    stages: vk::PipelineStageFlags,
+    /// The memory accesses made by those stages.
+    access: vk::AccessFlags,
+    /// The layout of an image during the pass, or `UNDEFINED` for buffers.
+    layout: vk::ImageLayout,
+}
+
+/// The synchronization required by a dependency between two passes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct DependencyBarrier {
+    src_stages: vk::PipelineStageFlags,
+    src_access: vk::AccessFlags,
+    dst_stages: vk::PipelineStageFlags,
+    dst_access: vk::AccessFlags,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+}
+
+impl DependencyBarrier {
+    /// Returns a barrier applying this dependency's accesses and layout
+    /// transition to `range` of `image`.
+    fn image_barrier(
+        &self,
+        image: vk::Image,
+        range: vk::ImageSubresourceRange,
+    ) -> vk::ImageMemoryBarrierBuilder<'static> {
+        vk::ImageMemoryBarrierBuilder::new()
+            .src_access_mask(self.src_access)
+            .dst_access_mask(self.dst_access)
+            .old_layout(self.old_layout)
+            .new_layout(self.new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range)
+    }
+}
+
+enum PassKind {
+    Graphics(Box<dyn RenderPass>),
+    Compute(Box<dyn ComputePipeline>),
 }
 
 const EXPECTED_CONSUMES: usize = 4;
@@ -445,11 +1864,24 @@ const EXPECTED_PRODUCES: usize = 4;
 
 pub struct RenderPassNode {
     // TODO: Ideally, avoid boxing render passes.
-    pass: Box<dyn RenderPass>,
+    pass: PassKind,
 
-    input_attachments: TinyVec<[ResourceId; 4]>,
+    input_attachments: TinyVec<[InputAttachment; 4]>,
     color_attachments: TinyVec<[ColorAttachment; 4]>,
 
+    // Images accessed by compute passes in `GENERAL` layout.
+    storage_images: TinyVec<[StorageImage; 4]>,
+    storage_inputs: TinyVec<[ResourceId; 4]>,
+
+    sampled_images: TinyVec<[SampledImage; 4]>,
+
+    buffer_inputs: TinyVec<[ResourceId; 4]>,
+
+    // Resources read from the previous frame. Not included in `reads`, as
+    // they do not order passes within a frame.
+    history_reads: TinyVec<[ResourceId; 4]>,
+    buffer_outputs: TinyVec<[BufferOutput; 4]>,
+
     // Associated resources by access type.
     //
     // The number of resources used by a given pass is expected to be fairly
@@ -459,11 +1891,111 @@ pub struct RenderPassNode {
     reads: SmallSet<ResourceId, EXPECTED_READS>,
     produces: SmallSet<ResourceId, EXPECTED_PRODUCES>,
 
+    // Scheduling hint used to order passes with no dependency between them.
+    priority: i32,
+    // Disabled passes are skipped, passing consumed resources through.
+    enabled: bool,
+    // Root passes are kept even if their outputs never reach the final image.
+    root: bool,
+    viewport: PassViewport,
+
     // Index of the node in the dependency graph.
     node_idx: Option<NodeIndex<u16>>,
 }
 
-/// A dependency between render passes.
+impl RenderPassNode {
+    /// Returns how the pass accesses a resource, or `None` if it does not.
+    ///
+    /// Writes determine the layout of images which are also read, e.g. by a
+    /// downsample pass reading one mip level and writing the next.
+    fn access(&self, id: ResourceId) -> Option<ResourceAccess> {
+        use vk::{AccessFlags as A, ImageLayout as L, PipelineStageFlags as S};
+
+        let shader_stages = match self.pass {
+            PassKind::Graphics(_) => S::VERTEX_SHADER | S::FRAGMENT_SHADER,
+            PassKind::Compute(_) => S::COMPUTE_SHADER,
+        };
+        // The access to a resource consumed by a write also reads it.
+        let write = |consumed: Option<ResourceId>, write: A, read: A| {
+            if consumed == Some(id) {
+                write | read
+            } else {
+                write
+            }
+        };
+
+        let mut found: Option<ResourceAccess> = None;
+        let mut add = |stages, access, layout| {
+            let acc = found.get_or_insert(ResourceAccess {
+                stages: S::empty(),
+                access: A::empty(),
+                layout,
+            });
+            acc.stages |= stages;
+            acc.access |= access;
+        };
+
+        for att in self.color_attachments.iter() {
+            if att.produced == id || att.consumed == Some(id) {
+                add(
+                    S::COLOR_ATTACHMENT_OUTPUT,
+                    write(
+                        att.consumed,
+                        A::COLOR_ATTACHMENT_WRITE,
+                        A::COLOR_ATTACHMENT_READ,
+                    ),
+                    L::COLOR_ATTACHMENT_OPTIMAL,
+                );
+            }
+            if att.resolve == Some(id) {
+                add(
+                    S::COLOR_ATTACHMENT_OUTPUT,
+                    A::COLOR_ATTACHMENT_WRITE,
+                    L::COLOR_ATTACHMENT_OPTIMAL,
+                );
+            }
+        }
+        for img in self.storage_images.iter() {
+            if img.produced == id || img.consumed == Some(id) {
+                add(
+                    S::COMPUTE_SHADER,
+                    write(img.consumed, A::SHADER_WRITE, A::SHADER_READ),
+                    L::GENERAL,
+                );
+            }
+        }
+        for out in self.buffer_outputs.iter() {
+            if out.produced == id || out.consumed == Some(id) {
+                add(
+                    shader_stages,
+                    write(out.consumed, A::SHADER_WRITE, A::SHADER_READ),
+                    L::UNDEFINED,
+                );
+            }
+        }
+
+        if self.input_attachments.iter().any(|att| att.resource == id) {
+            add(
+                S::FRAGMENT_SHADER,
+                A::INPUT_ATTACHMENT_READ,
+                L::SHADER_READ_ONLY_OPTIMAL,
+            );
+        }
+        for img in self.sampled_images.iter().filter(|img| img.resource == id) {
+            add(img.stages, A::SHADER_READ, L::SHADER_READ_ONLY_OPTIMAL);
+        }
+        if self.storage_inputs.contains(&id) {
+            add(S::COMPUTE_SHADER, A::SHADER_READ, L::GENERAL);
+        }
+        if self.buffer_inputs.contains(&id) {
+            add(shader_stages, A::SHADER_READ, L::UNDEFINED);
+        }
+
+        found
+    }
+}
+
+/// A dependency between render passes.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum DependencyType {
     /// Render pass A produces a resource needed by render pass B.
@@ -472,6 +2004,15 @@ enum DependencyType {
     Consume(ResourceId),
 }
 
+impl DependencyType {
+    /// Returns the resource the dependency is on.
+    fn resource(self) -> ResourceId {
+        match self {
+            DependencyType::Produce(id) | DependencyType::Consume(id) => id,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct RenderGraphBuilder {
     resources: Vec<Resource>,
@@ -481,6 +2022,7 @@ pub struct RenderGraphBuilder {
     pass_names: Vec<String>,
 
     final_image: Option<ResourceId>,
+    output_encoding: Option<OutputEncoding>,
 }
 
 impl RenderGraphBuilder {
@@ -538,6 +2080,7 @@ impl RenderGraphBuilder {
             produced_by: None,
             read_by: TinyVec::new(),
             consumed_by: None,
+            history_read_by: TinyVec::new(),
         });
 
         self.resource_names.push(name.as_ref().to_owned());
@@ -550,6 +2093,507 @@ impl RenderGraphBuilder {
         self.add_resource(name, ResourceType::Image(info))
     }
 
+    #[inline]
+    pub fn add_buffer<S: AsRef<str>>(&mut self, name: S, info: BufferInfo) -> ResourceId {
+        self.add_resource(name, ResourceType::Buffer(info))
+    }
+
+    /// Adds an image whose size is derived from another image in the graph.
+    ///
+    /// The derived image's extent is always computed from the current extent
+    /// of `source`, so it stays consistent when the swapchain is resized. This
+    /// is useful for effects which work at reduced resolution, such as bloom.
+    pub fn add_derived_image<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        source: ResourceId,
+        scale: RelativeExtent,
+        format: vk::Format,
+    ) -> Result<ResourceId, RenderGraphError> {
+        self.resource(source)?.image_info()?;
+
+        let info = ImageInfo {
+            size: ImageSize::RelativeToImage(source, scale),
+            format,
+            view_formats: Vec::new(),
+            samples: vk::SampleCountFlagBits::_1,
+            mip_levels: 1,
+            array_layers: 1,
+            view_mask: 0,
+        };
+
+        Ok(self.add_image(name, info))
+    }
+
+    /// Computes the concrete extent of an image in the graph.
+    ///
+    /// Returns `Ok(None)` if the image's size, or the size of an image it is
+    /// derived from, is relative to an input image.
+    pub fn image_extent(
+        &self,
+        id: ResourceId,
+        swapchain_extent: vk::Extent2D,
+    ) -> Result<Option<vk::Extent3D>, RenderGraphError> {
+        let size = self.resource(id)?.image_info()?.size;
+
+        let source_extent = match size {
+            // Derived images are always added after their source, so this
+            // recursion terminates.
+            ImageSize::RelativeToImage(source, _) => {
+                match self.image_extent(source, swapchain_extent)? {
+                    Some(extent) => Some(extent),
+                    None => return Ok(None),
+                }
+            }
+            _ => None,
+        };
+
+        Ok(size.resolve(swapchain_extent, source_extent))
+    }
+
+    /// Returns an iterator over all image resources in the graph.
+    pub fn images(&self) -> impl Iterator<Item = ImageDescription<'_>> + '_ {
+        self.resources
+            .iter()
+            .zip(self.resource_names.iter())
+            .enumerate()
+            .filter_map(move |(idx, (res, name))| {
+                let info = res.image_info().ok()?;
+                Some(ImageDescription {
+                    id: ResourceId { id: idx as u16 },
+                    name,
+                    info,
+                    producer: res.produced_by.and_then(|p| self.render_pass_name(p)),
+                })
+            })
+    }
+
+    /// Returns an iterator over the images whose contents must persist
+    /// across frames, as they are read as history by some render pass.
+    pub fn persistent_images(&self) -> impl Iterator<Item = ResourceId> + '_ {
+        self.resources
+            .iter()
+            .enumerate()
+            .filter(|(_, res)| !res.history_read_by.is_empty())
+            .map(|(idx, _)| ResourceId { id: idx as u16 })
+    }
+
+    /// Returns an iterator over the images which are only ever accessed as
+    /// attachments.
+    ///
+    /// The contents of these images never leave the render passes which use
+    /// them, so they may be created with `TRANSIENT_ATTACHMENT` usage and
+    /// backed by lazily allocated memory. Images which consume one another
+    /// share storage, so an image is only reported if every image it consumes
+    /// or is consumed by is also attachment-only.
+    pub fn transient_attachments(&self) -> impl Iterator<Item = ResourceId> + '_ {
+        let mut transient = Vec::new();
+        for chain in self.consume_chains() {
+            if chain.iter().all(|&id| self.is_attachment_only(id)) {
+                transient.extend(chain);
+            }
+        }
+
+        transient.sort_by_key(|id| id.id);
+        transient.into_iter()
+    }
+
+    /// Groups resources into chains in which each resource is consumed by the
+    /// next. The resources in a chain share storage.
+    fn consume_chains(&self) -> Vec<Vec<ResourceId>> {
+        // Maps each consumed resource to the resource which consumes it.
+        let mut successors: Vec<Option<ResourceId>> = vec![None; self.resources.len()];
+        let mut is_successor = vec![false; self.resources.len()];
+        let outputs = self.passes.iter().flat_map(|p| {
+            let colors = p.color_attachments.iter().map(|a| (a.consumed, a.produced));
+            let storage = p.storage_images.iter().map(|s| (s.consumed, s.produced));
+            let buffers = p.buffer_outputs.iter().map(|b| (b.consumed, b.produced));
+            colors.chain(storage).chain(buffers)
+        });
+        for (consumed, produced) in outputs {
+            if let Some(consumed) = consumed {
+                successors[consumed.id as usize] = Some(produced);
+                is_successor[produced.id as usize] = true;
+            }
+        }
+
+        let mut chains = Vec::new();
+        for (root, &successor) in is_successor.iter().enumerate() {
+            if successor {
+                continue;
+            }
+
+            let mut chain = Vec::new();
+            let mut next = Some(ResourceId { id: root as u16 });
+            while let Some(id) = next {
+                chain.push(id);
+                next = successors[id.id as usize];
+            }
+            chains.push(chain);
+        }
+
+        chains
+    }
+
+    /// Plans the placement of transient resources in a single shared memory
+    /// block, such that resources whose lifetimes do not overlap may occupy
+    /// the same memory.
+    ///
+    /// Resources which consume one another share storage and are placed
+    /// together. Resources of unknown size, those which are external to the
+    /// graph or read as history, and the final image are not placed.
+    /// Offsets are aligned to `alignment`, which must be a power of two.
+    ///
+    /// Returns [`RenderGraphError::InvalidAlignment`] if it is not.
+    pub fn alias_plan(
+        &self,
+        swapchain_extent: vk::Extent2D,
+        alignment: vk::DeviceSize,
+    ) -> Result<AliasPlan, RenderGraphError> {
+        if !alignment.is_power_of_two() {
+            return Err(RenderGraphError::InvalidAlignment(alignment));
+        }
+        let report = self.lifetime_report(swapchain_extent)?;
+
+        let mut lifetimes = vec![None; self.resources.len()];
+        for res in report.resources.iter() {
+            lifetimes[res.id.id as usize] = Some(res);
+        }
+
+        // Merge each chain into a single allocation spanning all of its
+        // members' lifetimes.
+        let mut allocations = Vec::new();
+        for chain in self.consume_chains() {
+            if self.final_image.map_or(false, |id| chain.contains(&id)) {
+                continue;
+            }
+
+            let members = chain
+                .iter()
+                .map(|id| lifetimes[id.id as usize].filter(|res| res.size.is_some()))
+                .collect::<Option<Vec<_>>>();
+            let members = match members {
+                Some(m) => m,
+                None => continue,
+            };
+
+            allocations.push(AliasPlacement {
+                first_use: members.iter().map(|res| res.first_use).min().unwrap(),
+                last_use: members.iter().map(|res| res.last_use).max().unwrap(),
+                size: members.iter().filter_map(|res| res.size).max().unwrap(),
+                offset: 0,
+                resources: chain,
+            });
+        }
+
+        // Place the largest allocations first, each at the lowest offset which
+        // does not collide with a placed allocation of overlapping lifetime.
+        allocations.sort_by_key(|alloc| (Reverse(alloc.size), alloc.first_use));
+
+        let mut placed: Vec<AliasPlacement> = Vec::with_capacity(allocations.len());
+        for mut alloc in allocations {
+            let overlapping = placed
+                .iter()
+                .filter(|p| p.first_use <= alloc.last_use && alloc.first_use <= p.last_use)
+                .collect::<Vec<_>>();
+
+            let candidates = std::iter::once(0).chain(
+                overlapping
+                    .iter()
+                    .filter_map(|p| util::align_up(p.offset + p.size, alignment)),
+            );
+            alloc.offset = candidates
+                .filter(|&offset| {
+                    overlapping
+                        .iter()
+                        .all(|p| offset + alloc.size <= p.offset || p.offset + p.size <= offset)
+                })
+                .min()
+                .unwrap();
+
+            placed.push(alloc);
+        }
+
+        Ok(AliasPlan {
+            size: placed.iter().map(|p| p.offset + p.size).max().unwrap_or(0),
+            placements: placed,
+        })
+    }
+
+    fn is_attachment_only(&self, id: ResourceId) -> bool {
+        let res = &self.resources[id.id as usize];
+
+        // External images, history images and the final image are all
+        // accessed outside of the graph's render passes.
+        if res.image_info().is_err()
+            || res.produced_by.is_none()
+            || !res.history_read_by.is_empty()
+            || self.final_image == Some(id)
+        {
+            return false;
+        }
+
+        // Storage images written by compute passes cannot be transient.
+        let producer = &self.passes[res.produced_by.unwrap().id as usize];
+        if !producer
+            .color_attachments
+            .iter()
+            .any(|att| att.produced == id || att.resolve == Some(id))
+        {
+            return false;
+        }
+
+        if let Some(consumer) = res.consumed_by {
+            let consumer = &self.passes[consumer.id as usize];
+            if !consumer
+                .color_attachments
+                .iter()
+                .any(|att| att.consumed == Some(id))
+            {
+                return false;
+            }
+        }
+
+        res.read_by.iter().all(|&pass_id| {
+            let pass = &self.passes[pass_id.id as usize];
+            pass.input_attachments.iter().any(|att| att.resource == id)
+                && !pass.sampled_images.iter().any(|img| img.resource == id)
+        })
+    }
+
+    /// Splices the render passes and resources of another graph into this one.
+    ///
+    /// `inputs` maps resources of `subgraph` onto existing resources of this
+    /// graph, as `(subgraph_id, id)` pairs. Mapped resources must not be
+    /// produced within the subgraph. All other resources and render passes are
+    /// added to this graph with their names prefixed by `label`. The
+    /// subgraph's final image and output encoding are ignored.
+    ///
+    /// This allows reusable effects, such as a bloom chain, to be declared
+    /// independently of the graphs which use them.
+    pub fn add_subgraph<S: AsRef<str>>(
+        &mut self,
+        label: S,
+        mut subgraph: RenderGraphBuilder,
+        inputs: &[(ResourceId, ResourceId)],
+    ) -> Result<Subgraph, RenderGraphError> {
+        let label = label.as_ref();
+
+        // Validate the mapping before modifying the graph.
+        let mut mapped = vec![None; subgraph.resources.len()];
+        let mut consumed = Vec::new();
+        for &(sub_id, id) in inputs {
+            let sub_res = subgraph.resource(sub_id)?;
+            let res = self.resource(id)?;
+
+            if sub_res.produced_by.is_some() {
+                return Err(RenderGraphError::ProducedSubgraphInput {
+                    r_name: subgraph.resource_name(sub_id).unwrap().to_owned(),
+                    r_id: sub_id,
+                });
+            }
+
+            match (&sub_res.ty, &res.ty) {
+                (ResourceType::Image(_), ResourceType::Buffer(_)) => {
+                    return Err(RenderGraphError::IncompatibleResourceType {
+                        expected: ResourceTypeTag::Image,
+                        actual: ResourceTypeTag::Buffer,
+                    })
+                }
+                (ResourceType::Buffer(_), ResourceType::Image(_)) => {
+                    return Err(RenderGraphError::IncompatibleResourceType {
+                        expected: ResourceTypeTag::Buffer,
+                        actual: ResourceTypeTag::Image,
+                    })
+                }
+                _ => (),
+            }
+
+            if let Some(sub_pass) = sub_res.consumed_by {
+                let consumer = match res.consumed_by {
+                    Some(pass) => Some(self.render_pass_name(pass).unwrap().to_owned()),
+                    None if consumed.contains(&id) => Some(format!(
+                        "{}/{}",
+                        label,
+                        subgraph.render_pass_name(sub_pass).unwrap()
+                    )),
+                    None => None,
+                };
+
+                if let Some(p_name) = consumer {
+                    return Err(RenderGraphError::AlreadyConsumed {
+                        r_name: self.resource_name(id).unwrap().to_owned(),
+                        r_id: id,
+                        p_name,
+                    });
+                }
+
+                consumed.push(id);
+            }
+
+            mapped[sub_id.id as usize] = Some(id);
+        }
+
+        let pass_base = self.passes.len();
+        let passes = (0..subgraph.passes.len())
+            .map(|i| RenderPassId {
+                id: (pass_base + i)
+                    .try_into()
+                    .expect("render passes overflowed"),
+            })
+            .collect::<Vec<_>>();
+        let remap_pass = |id: RenderPassId| passes[id.id as usize];
+
+        // Derived images always refer to a resource added before them, so
+        // their sources are remapped by the time they are reached.
+        let mut resources = Vec::with_capacity(subgraph.resources.len());
+        for (i, sub_res) in subgraph.resources.iter().enumerate() {
+            let id = match mapped[i] {
+                Some(id) => id,
+                None => {
+                    let mut ty = sub_res.ty.clone();
+                    if let ResourceType::Image(ImageInfo {
+                        size: ImageSize::RelativeToImage(source, _),
+                        ..
+                    }) = &mut ty
+                    {
+                        *source = resources[source.id as usize];
+                    }
+
+                    let name = format!("{}/{}", label, subgraph.resource_names[i]);
+                    self.add_resource(name, ty)
+                }
+            };
+
+            let res = &mut self.resources[id.id as usize];
+            if let Some(pass) = sub_res.produced_by {
+                res.produced_by = Some(remap_pass(pass));
+            }
+            res.read_by
+                .extend(sub_res.read_by.iter().copied().map(remap_pass));
+            if let Some(pass) = sub_res.consumed_by {
+                res.consumed_by = Some(remap_pass(pass));
+            }
+            res.history_read_by
+                .extend(sub_res.history_read_by.iter().copied().map(remap_pass));
+
+            resources.push(id);
+        }
+        let remap = |id: ResourceId| resources[id.id as usize];
+
+        let pass_names = subgraph.pass_names.drain(..);
+        for (mut node, name) in subgraph.passes.drain(..).zip(pass_names) {
+            for att in node.input_attachments.iter_mut() {
+                att.resource = remap(att.resource);
+            }
+            for att in node.color_attachments.iter_mut() {
+                att.consumed = att.consumed.map(remap);
+                att.produced = remap(att.produced);
+                att.resolve = att.resolve.map(remap);
+            }
+            for img in node.storage_images.iter_mut() {
+                img.consumed = img.consumed.map(remap);
+                img.produced = remap(img.produced);
+            }
+            for img in node.sampled_images.iter_mut() {
+                img.resource = remap(img.resource);
+            }
+            for id in node
+                .buffer_inputs
+                .iter_mut()
+                .chain(node.storage_inputs.iter_mut())
+                .chain(node.history_reads.iter_mut())
+            {
+                *id = remap(*id);
+            }
+            for out in node.buffer_outputs.iter_mut() {
+                out.consumed = out.consumed.map(remap);
+                out.produced = remap(out.produced);
+            }
+
+            for set in [&mut node.consumes, &mut node.reads, &mut node.produces] {
+                let ids = set.drain().map(remap).collect::<Vec<_>>();
+                for id in ids {
+                    set.insert(id);
+                }
+            }
+
+            node.node_idx = None;
+
+            self.passes.push(node);
+            self.pass_names.push(format!("{}/{}", label, name));
+        }
+
+        Ok(Subgraph { resources, passes })
+    }
+
+    /// Checks that a render pass may be skipped: each resource it produces
+    /// must either be passed through from a consumed resource or be unused.
+    fn check_can_disable(&self, id: RenderPassId) -> Result<(), RenderGraphError> {
+        let pass = self.render_pass(id)?;
+
+        let passed_through = pass
+            .color_attachments
+            .iter()
+            .filter(|att| att.consumed.is_some())
+            .map(|att| att.produced)
+            .chain(
+                pass.storage_images
+                    .iter()
+                    .filter(|img| img.consumed.is_some())
+                    .map(|img| img.produced),
+            )
+            .chain(
+                pass.buffer_outputs
+                    .iter()
+                    .filter(|out| out.consumed.is_some())
+                    .map(|out| out.produced),
+            )
+            .collect::<Vec<_>>();
+
+        for res_id in pass.produces.iter().copied() {
+            if passed_through.contains(&res_id) {
+                continue;
+            }
+
+            let res = self.resource(res_id)?;
+            let used = !res.read_by.is_empty()
+                || res.consumed_by.is_some()
+                || !res.history_read_by.is_empty()
+                || self.final_image == Some(res_id);
+            if used {
+                return Err(RenderGraphError::RequiredPass {
+                    pass_name: self.render_pass_name(id).unwrap().to_owned(),
+                    res_name: self.resource_name(res_id).unwrap().to_owned(),
+                    res_id,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the viewport and scissor of a render pass.
+    pub fn pass_viewport(&self, id: RenderPassId) -> Result<PassViewport, RenderGraphError> {
+        Ok(self.render_pass(id)?.viewport)
+    }
+
+    /// Declares the color encoding the graph's final image is written with.
+    ///
+    /// The display presenting the graph must be created with a matching
+    /// surface format, via `DisplayConfig::output_encoding`; this is checked
+    /// when the graph is built.
+    pub fn set_output_encoding(&mut self, encoding: OutputEncoding) {
+        self.output_encoding = Some(encoding);
+    }
+
+    /// Returns the color encoding of the graph's final image.
+    ///
+    /// Defaults to [`OutputEncoding::LinearToSrgb`].
+    pub fn output_encoding(&self) -> OutputEncoding {
+        self.output_encoding.unwrap_or(OutputEncoding::LinearToSrgb)
+    }
+
     pub fn set_final_image(&mut self, id: ResourceId) -> Result<(), RenderGraphError> {
         if let Some(old_id) = self.final_image {
             return Err(RenderGraphError::AlreadySetSwapchainImage {
@@ -589,21 +2633,49 @@ impl RenderGraphBuilder {
         S: AsRef<str>,
         R: RenderPass + 'static,
     {
+        self.pass_builder(name.as_ref(), PassKind::Graphics(Box::new(pass)))
+    }
+
+    /// Adds a compute pass, which dispatches compute shaders against graph
+    /// images and buffers.
+    #[inline]
+    pub fn add_compute_pass<'a, S, C>(&'a mut self, name: S, pass: C) -> ComputePassBuilder<'a>
+    where
+        S: AsRef<str>,
+        C: ComputePipeline + 'static,
+    {
+        ComputePassBuilder {
+            inner: self.pass_builder(name.as_ref(), PassKind::Compute(Box::new(pass))),
+        }
+    }
+
+    fn pass_builder(&mut self, name: &str, pass: PassKind) -> RenderPassBuilder<'_> {
         RenderPassBuilder {
-            name: name.as_ref().to_owned(),
+            name: name.to_owned(),
             base_resource_id: ResourceId {
                 id: self.resources.len().try_into().unwrap(),
             },
             produced: ArrayVec::new(),
             produced_names: ArrayVec::new(),
+            partial_reads: SmallSet::new(),
             graph: self,
             pass: RenderPassNode {
-                pass: Box::new(pass),
+                pass,
                 input_attachments: TinyVec::new(),
                 color_attachments: TinyVec::new(),
+                storage_images: TinyVec::new(),
+                storage_inputs: TinyVec::new(),
+                sampled_images: TinyVec::new(),
+                buffer_inputs: TinyVec::new(),
+                history_reads: TinyVec::new(),
+                buffer_outputs: TinyVec::new(),
                 consumes: SmallSet::new(),
                 reads: SmallSet::new(),
                 produces: SmallSet::new(),
+                priority: 0,
+                enabled: true,
+                root: false,
+                viewport: PassViewport::default(),
                 node_idx: None,
             },
         }
@@ -657,166 +2729,836 @@ impl RenderGraphBuilder {
         format!("{:?}", dot)
     }
 
-    pub fn build(mut self, frame: &mut FrameContext) -> Result<(), RenderGraphError> {
-        let final_image_id = self
+    /// Returns the passes which are built: the producer of the final image,
+    /// every root pass, and all passes they depend on.
+    fn live_passes(&self) -> Result<Vec<bool>, RenderGraphError> {
+        let final_image = self
             .final_image
-            .clone()
             .ok_or(RenderGraphError::MissingSwapchainImage)?;
-        let final_image = self.resource(final_image_id).unwrap();
-
-        // Build a dependency graph of passes.
-        //
-        // - Each node is a render pass.
-        // - Each edge is a resource dependency. There are two kinds of dependency:
-        //   - Produce-dependencies, in which pass A produces resource R and
-        //     pass B reads or consumes R. This requires a memory barrier
-        //     to ensure A happens-before B and to ensure the data written by A
-        //     is made visible.
-        //   - Consume-dependencies, in which pass A reads resource R and pass B
-        //     consumes R. This only requires an execution barrier to ensure A
-        //     happens-before B (write-after-read hazards are precluded by
-        //     execution barriers in Vulkan).
-        let mut graph = PassGraph::with_capacity(self.passes.len(), self.resources.len());
-
-        // Get the pass which produces the final image.
-        let final_pass_id = final_image
+        let final_pass = self
+            .resource(final_image)?
             .produced_by
             .ok_or(RenderGraphError::SwapchainNotWritten)?;
 
-        // Enqueue all render passes that introduce produce-dependencies.
-        let mut next_depth: TinyVec<[RenderPassId; 8]> = TinyVec::new();
-        let mut cur_depth: SmallSet<RenderPassId, 16> = SmallSet::new();
-
-        // Walk the graph in reverse, breadth-first. Process all nodes at depth N at once.
-        next_depth.push(final_pass_id);
-
-        let start_produce_insert = Instant::now();
-        while !next_depth.is_empty() {
-            assert!(cur_depth.is_empty());
-
-            for pass_id in next_depth.drain(..) {
-                if !cur_depth.contains(&pass_id) {
-                    let pass = self.render_pass_mut(pass_id).unwrap();
-                    if pass.node_idx.is_none() {
-                        // Only consider passes that have not already been visited.
-                        cur_depth.insert(pass_id);
-
-                        pass.node_idx = Some(graph.add_node(pass_id));
-                    }
-                }
+        let mut live = vec![false; self.passes.len()];
+        let mut stack = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| pass.root)
+            .map(|(idx, _)| RenderPassId { id: idx as u16 })
+            .chain(std::iter::once(final_pass))
+            .collect::<Vec<_>>();
+
+        while let Some(pass_id) = stack.pop() {
+            if std::mem::replace(&mut live[pass_id.id as usize], true) {
+                continue;
             }
 
-            for pass_id in cur_depth.drain() {
-                let pass = self.render_pass(pass_id).unwrap();
-                let pass_idx = pass.node_idx.unwrap();
-
-                // Insert produce-dependencies. Since entire depths are inserted
-                // into the graph at once, this will not miss lateral edges (i.e.,
-                // edges from one depth-N node to another). Any node at this depth
-                // which does not already exist in the graph is not a dependency of
-                // the terminal node.
-                for produce_id in pass.produces.iter().copied() {
-                    let produce = self.resource(produce_id).unwrap();
-
-                    for dependent_id in produce
-                        .read_by
-                        .iter()
-                        .copied()
-                        .chain(produce.consumed_by.iter().copied())
-                    {
-                        let dependent = self.render_pass(dependent_id).unwrap();
-                        if let Some(dependent_idx) = dependent.node_idx {
-                            // If the dependent has not been inserted, it is not
-                            // a dependency.
-                            graph.add_edge(
-                                pass_idx,
-                                dependent_idx,
-                                DependencyType::Produce(produce_id),
-                            );
-                        }
-                    }
-                }
-
-                // Enqueue next depth of the graph.
-                for input_id in pass.reads.iter().chain(pass.consumes.iter()).copied() {
-                    let input = self.resource(input_id).unwrap();
-                    let producer = input.produced_by.unwrap();
-                    next_depth.push(producer);
+            let pass = self.render_pass(pass_id)?;
+            for input_id in pass.reads.iter().chain(pass.consumes.iter()).copied() {
+                if let Some(producer) = self.resource(input_id)?.produced_by {
+                    stack.push(producer);
                 }
             }
         }
 
-        log::debug!(
-            "Produce-dependencies inserted in {}μs",
-            start_produce_insert.elapsed().as_micros()
-        );
+        Ok(live)
+    }
 
-        // The graph now contains all necessary render passes and all
-        // produce-dependencies between them.
+    /// Returns the render passes which are culled when the graph is built,
+    /// because none of their outputs reach the final image or a root pass.
+    ///
+    /// See [`RenderPassBuilder::mark_root`].
+    pub fn culled_passes(&self) -> Result<Vec<RenderPassId>, RenderGraphError> {
+        Ok(self
+            .live_passes()?
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, live)| !live)
+            .map(|(idx, _)| RenderPassId { id: idx as u16 })
+            .collect())
+    }
 
-        let start_consume_insert = Instant::now();
-        // TODO: this avoids a mutable borrow error on the graph, but it
-        // shouldn't be necessary -- no nodes are added or removed, only edges.
-        let mut passes = next_depth;
-        passes.extend(graph.node_weights().copied());
+    /// Builds a dependency graph containing every render pass.
+    fn full_dependency_graph(&self) -> PassGraph {
+        let mut graph = PassGraph::with_capacity(self.passes.len(), self.resources.len());
+        let nodes = (0..self.passes.len())
+            .map(|id| graph.add_node(RenderPassId { id: id as u16 }))
+            .collect::<Vec<_>>();
+        let node = |id: RenderPassId| nodes[id.id as usize];
 
-        // Insert consume-dependencies.
-        // TODO: cache a list of consumers (expected to be relatively small) and
-        //   insert these dependencies to any readers of the consumed resource,
-        //   rather than scanning every read resource.
-        for pass_id in passes.iter().copied() {
-            let pass = self.render_pass(pass_id).unwrap();
+        for (res_idx, res) in self.resources.iter().enumerate() {
+            let res_id = ResourceId { id: res_idx as u16 };
 
-            for read_id in pass.reads.iter().copied() {
-                let read = self.resource(read_id).unwrap();
+            if let Some(producer) = res.produced_by {
+                for &dependent in res.read_by.iter().chain(res.consumed_by.iter()) {
+                    graph.add_edge(
+                        node(producer),
+                        node(dependent),
+                        DependencyType::Produce(res_id),
+                    );
+                }
+            }
 
-                if let Some(consumed_by_id) = read.consumed_by {
-                    let consumed_by = self.render_pass(consumed_by_id).unwrap();
+            if let Some(consumer) = res.consumed_by {
+                for &reader in res.read_by.iter().filter(|&&r| r != consumer) {
                     graph.add_edge(
-                        pass.node_idx.unwrap(),
-                        consumed_by.node_idx.unwrap(),
-                        DependencyType::Consume(read_id),
+                        node(reader),
+                        node(consumer),
+                        DependencyType::Consume(res_id),
                     );
                 }
             }
         }
 
-        log::debug!(
-            "Consume-dependencies inserted in {}μs",
-            start_consume_insert.elapsed().as_micros()
-        );
-
-        println!("{}", self.gen_dotgraph(&graph));
+        graph
+    }
 
-        let start_dep_resolve = Instant::now();
-        let ordered = match petgraph::algo::toposort(&graph, None) {
-            Ok(o) => o,
-            Err(cycle) => {
-                let pass_id = *graph.node_weight(cycle.node_id()).unwrap();
-                return Err(RenderGraphError::DependencyCycle {
-                    pass_name: self.render_pass_name(pass_id).unwrap().into(),
-                });
+    /// Orders every render pass in the graph as `build` would.
+    fn sort_all_passes(&self, graph: &PassGraph) -> Result<Vec<NodeIndex<u16>>, RenderGraphError> {
+        prioritized_toposort(graph, |id| self.render_pass(id).unwrap().priority).map_err(|cycle| {
+            RenderGraphError::DependencyCycle {
+                pass_name: self.render_pass_name(graph[cycle]).unwrap().into(),
             }
-        };
-        log::debug!(
-            "Dependencies resolved in {}μs",
-            start_dep_resolve.elapsed().as_micros()
-        );
+        })
+    }
 
-        todo!("physical resource assignment");
+    /// Returns the barrier needed between the passes at either end of a
+    /// dependency.
+    ///
+    /// A produce-dependency makes the producer's writes visible to the
+    /// dependent's accesses. A consume-dependency only orders the consumer's
+    /// writes after the reader's accesses, which needs no memory dependency.
+    ///
+    /// The layout transition assumes `src` was the last pass to access the
+    /// resource. [`RenderGraphBuilder::collect_barriers`] corrects this once
+    /// the passes are ordered.
+    fn dependency_barrier(
+        &self,
+        src: RenderPassId,
+        dst: RenderPassId,
+        dependency: DependencyType,
+    ) -> Result<DependencyBarrier, RenderGraphError> {
+        let res_id = dependency.resource();
+        let access = |pass_id| -> Result<ResourceAccess, RenderGraphError> {
+            Ok(self
+                .render_pass(pass_id)?
+                .access(res_id)
+                .expect("dependency on a resource the pass does not access"))
+        };
+        let src_access = access(src)?;
+        let dst_access = access(dst)?;
 
-        // Maintain a mapping from virtual resources to physical resources.
-        let mut virt_to_phys: Vec<Option<PhysResourceId>> =
-            Vec::with_capacity(self.resources.len());
-        virt_to_phys.resize(self.resources.len(), None);
+        let (src_mask, dst_mask) = match dependency {
+            DependencyType::Produce(_) => (src_access.access & WRITE_ACCESS, dst_access.access),
+            DependencyType::Consume(_) => (vk::AccessFlags::empty(), vk::AccessFlags::empty()),
+        };
 
-        for pass_id in ordered.iter().map(|&idx| *graph.node_weight(idx).unwrap()) {
-            let pass = self.render_pass(pass_id).unwrap();
-            let pass_complete_event = frame.alloc_event();
+        Ok(DependencyBarrier {
+            src_stages: src_access.stages,
+            src_access: src_mask,
+            dst_stages: dst_access.stages,
+            dst_access: dst_mask,
+            old_layout: src_access.layout,
+            new_layout: dst_access.layout,
+        })
+    }
 
-            // Input attachments have already been acquired from the frame context.
-            for in_att in pass.input_attachments.iter().copied() {
-                let phys_id = virt_to_phys[in_att.id as usize].unwrap();
+    /// Collects the barriers to record before each pass, in execution order,
+    /// and the layout each resource is left in.
+    ///
+    /// An image is transitioned at most once before each pass, from the
+    /// layout left by the last pass to access it. This may not be the source
+    /// of the dependency, e.g. when one pass reads an image and a later pass
+    /// consumes it.
+    fn collect_barriers(
+        &self,
+        graph: &PassGraph,
+        ordered: &[NodeIndex<u16>],
+    ) -> Result<(Vec<Vec<DependencyBarrier>>, Vec<vk::ImageLayout>), RenderGraphError> {
+        let mut layouts = vec![vk::ImageLayout::UNDEFINED; self.resources.len()];
+        let mut barriers = Vec::with_capacity(ordered.len());
+
+        for &idx in ordered {
+            let pass_id = graph[idx];
+            let pass = self.render_pass(pass_id)?;
+
+            let mut transitioned: SmallSet<ResourceId, EXPECTED_READS> = SmallSet::new();
+            let mut pass_barriers = Vec::new();
+            for edge in graph.edges_directed(idx, Direction::Incoming) {
+                let dependency = *edge.weight();
+                let mut barrier =
+                    self.dependency_barrier(graph[edge.source()], pass_id, dependency)?;
+
+                let res_id = dependency.resource();
+                barrier.old_layout = if transitioned.insert(res_id) {
+                    layouts[res_id.id as usize]
+                } else {
+                    barrier.new_layout
+                };
+                pass_barriers.push(barrier);
+            }
+            barriers.push(pass_barriers);
+
+            for id in pass
+                .reads
+                .iter()
+                .chain(pass.consumes.iter())
+                .chain(pass.produces.iter())
+                .copied()
+            {
+                if let Some(access) = pass.access(id) {
+                    layouts[id.id as usize] = access.layout;
+                }
+            }
+        }
+
+        Ok((barriers, layouts))
+    }
+
+    /// Reports the span of render passes over which each transient resource
+    /// is live, given the current swapchain extent.
+    ///
+    /// Transient resources are those produced by a render pass and not read
+    /// as history. A resource which consumes another is reported separately,
+    /// though the two share storage.
+    pub fn lifetime_report(
+        &self,
+        swapchain_extent: vk::Extent2D,
+    ) -> Result<LifetimeReport, RenderGraphError> {
+        let graph = self.full_dependency_graph();
+        let ordered = self.sort_all_passes(&graph)?;
+
+        let mut position = vec![0; self.passes.len()];
+        for (pos, &idx) in ordered.iter().enumerate() {
+            position[graph[idx].id as usize] = pos;
+        }
+
+        let mut resources = Vec::new();
+        for (res_idx, res) in self.resources.iter().enumerate() {
+            let producer = match res.produced_by {
+                Some(p) if res.history_read_by.is_empty() => p,
+                _ => continue,
+            };
+
+            let id = ResourceId { id: res_idx as u16 };
+            let uses = res
+                .read_by
+                .iter()
+                .chain(res.consumed_by.iter())
+                .map(|pass| position[pass.id as usize]);
+            let first_use = position[producer.id as usize];
+            let last_use = uses.fold(first_use, usize::max);
+
+            let size = match &res.ty {
+                ResourceType::Buffer(info) => Some(info.size),
+                ResourceType::Image(info) => self
+                    .image_extent(id, swapchain_extent)?
+                    .and_then(|extent| estimate_image_size(info, extent)),
+            };
+
+            resources.push(ResourceLifetime {
+                id,
+                name: self.resource_names[res_idx].clone(),
+                first_use,
+                last_use,
+                size,
+            });
+        }
+
+        Ok(LifetimeReport {
+            passes: ordered
+                .iter()
+                .map(|&idx| self.pass_names[graph[idx].id as usize].clone())
+                .collect(),
+            resources,
+        })
+    }
+
+    /// Analyzes the graph for render passes which could execute concurrently.
+    pub fn overlap_report(&self) -> Result<OverlapReport, RenderGraphError> {
+        let graph = self.full_dependency_graph();
+        let ordered = self.sort_all_passes(&graph)?;
+
+        let count = graph.node_count();
+
+        // Compute the longest chain ending at each pass, and the set of passes
+        // each pass transitively depends on.
+        let mut chain_len = vec![1; count];
+        let mut ancestors = vec![FixedBitSet::with_capacity(count); count];
+        for &idx in ordered.iter() {
+            // Predecessors come earlier in the order, so their sets are
+            // complete and distinct from this pass's.
+            let mut idx_ancestors = std::mem::take(&mut ancestors[idx.index()]);
+            for pred in graph.neighbors_directed(idx, Direction::Incoming) {
+                chain_len[idx.index()] = chain_len[idx.index()].max(chain_len[pred.index()] + 1);
+
+                idx_ancestors.union_with(&ancestors[pred.index()]);
+                idx_ancestors.insert(pred.index());
+            }
+            ancestors[idx.index()] = idx_ancestors;
+        }
+
+        let mut independent_pairs = Vec::new();
+        for (a, a_ancestors) in ancestors.iter().enumerate() {
+            for (b, b_ancestors) in ancestors.iter().enumerate().skip(a + 1) {
+                if !a_ancestors.contains(b) && !b_ancestors.contains(a) {
+                    independent_pairs
+                        .push((self.pass_names[a].clone(), self.pass_names[b].clone()));
+                }
+            }
+        }
+
+        Ok(OverlapReport {
+            pass_count: count,
+            critical_path_len: chain_len.into_iter().max().unwrap_or(0),
+            independent_pairs,
+        })
+    }
+
+    /// Checks the graph's render passes and resources against the device's
+    /// limits, given the current swapchain extent.
+    pub fn validate_limits(
+        &self,
+        limits: &DeviceLimits,
+        swapchain_extent: vk::Extent2D,
+    ) -> Result<(), RenderGraphError> {
+        let named = |name: &str| {
+            let name = name.to_owned();
+            move |source| RenderGraphError::LimitExceeded { name, source }
+        };
+
+        for (pass, name) in self.passes.iter().zip(self.pass_names.iter()) {
+            LimitExceeded::check(
+                "color attachment count",
+                pass.color_attachments.len() as u64,
+                limits.max_color_attachments,
+            )
+            .map_err(named(name))?;
+        }
+
+        for (idx, (res, name)) in self
+            .resources
+            .iter()
+            .zip(self.resource_names.iter())
+            .enumerate()
+        {
+            match &res.ty {
+                ResourceType::Image(info) => {
+                    LimitExceeded::check(
+                        "image array layers",
+                        info.array_layers,
+                        limits.max_image_array_layers,
+                    )
+                    .map_err(named(name))?;
+
+                    let id = ResourceId { id: idx as u16 };
+                    let extent = match self.image_extent(id, swapchain_extent)? {
+                        Some(extent) => extent,
+                        None => continue,
+                    };
+
+                    if info.image_type(extent) == vk::ImageType::_3D && info.array_layers > 1 {
+                        return Err(RenderGraphError::LayeredVolume {
+                            r_name: name.clone(),
+                            r_id: id,
+                        });
+                    }
+
+                    let max_dimension = if extent.depth > 1 {
+                        limits.max_image_dimension_3d
+                    } else {
+                        limits.max_image_dimension_2d
+                    };
+                    let largest = extent.width.max(extent.height).max(extent.depth);
+                    LimitExceeded::check("image dimension", largest, max_dimension)
+                        .map_err(named(name))?;
+                }
+
+                // Buffers are bound as a whole, so their size is limited by
+                // the descriptor range.
+                ResourceType::Buffer(info) => {
+                    LimitExceeded::check(
+                        "buffer size",
+                        info.size,
+                        limits.max_memory_allocation_size,
+                    )
+                    .map_err(named(name))?;
+
+                    if info.usage.contains(vk::BufferUsageFlags::UNIFORM_BUFFER) {
+                        LimitExceeded::check(
+                            "uniform buffer size",
+                            info.size,
+                            limits.max_uniform_buffer_range,
+                        )
+                        .map_err(named(name))?;
+                    }
+
+                    if info.usage.contains(vk::BufferUsageFlags::STORAGE_BUFFER) {
+                        LimitExceeded::check(
+                            "storage buffer size",
+                            info.size,
+                            limits.max_storage_buffer_range,
+                        )
+                        .map_err(named(name))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the final image can be presented with `surface_format`.
+    fn check_final_format(
+        &self,
+        surface_format: vk::SurfaceFormatKHR,
+    ) -> Result<(), RenderGraphError> {
+        let final_image = self
+            .final_image
+            .ok_or(RenderGraphError::MissingSwapchainImage)?;
+        let info = self.resource(final_image)?.image_info()?;
+
+        if info.format != surface_format.format {
+            return Err(RenderGraphError::SwapchainFormatMismatch {
+                r_name: self.resource_name(final_image).unwrap().to_owned(),
+                format: info.format,
+                swapchain_format: surface_format.format,
+            });
+        }
+
+        let encoding = self.output_encoding();
+        let provides_encoding = encoding.surface_formats().iter().any(|pref| {
+            pref.format == surface_format.format && pref.color_space == surface_format.color_space
+        });
+        if !provides_encoding {
+            return Err(RenderGraphError::OutputEncodingMismatch {
+                encoding,
+                surface_format,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks that swapchain images with `image_usage` can be written the way
+    /// the final image's producer writes it.
+    fn check_final_usage(&self, image_usage: vk::ImageUsageFlags) -> Result<(), RenderGraphError> {
+        let final_image = self
+            .final_image
+            .ok_or(RenderGraphError::MissingSwapchainImage)?;
+        let producer = self
+            .resource(final_image)?
+            .produced_by
+            .ok_or(RenderGraphError::SwapchainNotWritten)?;
+        let access = self
+            .render_pass(producer)?
+            .access(final_image)
+            .expect("producer does not access the final image");
+
+        let usage = match access.layout {
+            vk::ImageLayout::GENERAL => vk::ImageUsageFlags::STORAGE,
+            _ => vk::ImageUsageFlags::COLOR_ATTACHMENT,
+        };
+        if !image_usage.contains(usage) {
+            return Err(RenderGraphError::SwapchainUsageMissing {
+                r_name: self.resource_name(final_image).unwrap().to_owned(),
+                usage,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the barrier handing the final image from its producer to the
+    /// presentation engine.
+    fn present_barrier(&self) -> Result<DependencyBarrier, RenderGraphError> {
+        let final_image = self
+            .final_image
+            .ok_or(RenderGraphError::MissingSwapchainImage)?;
+        let producer = self
+            .resource(final_image)?
+            .produced_by
+            .ok_or(RenderGraphError::SwapchainNotWritten)?;
+        let access = self
+            .render_pass(producer)?
+            .access(final_image)
+            .expect("producer does not access the final image");
+
+        // Presentation is synchronized by a semaphore, so only the layout
+        // transition needs to wait for the producer.
+        Ok(DependencyBarrier {
+            src_stages: access.stages,
+            src_access: access.access & WRITE_ACCESS,
+            dst_stages: vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            dst_access: vk::AccessFlags::empty(),
+            old_layout: access.layout,
+            new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        })
+    }
+
+    /// Checks that no render pass reads the contents of an attachment which
+    /// an earlier render pass discarded, and that the final image is not
+    /// discarded.
+    fn check_store_ops(&self) -> Result<(), RenderGraphError> {
+        for (writer_idx, writer) in self.passes.iter().enumerate() {
+            for att in writer.color_attachments.iter().filter(|att| att.discard) {
+                if self.final_image == Some(att.produced) {
+                    return Err(RenderGraphError::DiscardedFinalImage {
+                        r_name: self.resource_name(att.produced).unwrap().to_owned(),
+                        r_id: att.produced,
+                        writer: self.pass_names[writer_idx].clone(),
+                    });
+                }
+
+                let res = self.resource(att.produced)?;
+                let reader = res
+                    .read_by
+                    .iter()
+                    .chain(res.consumed_by.iter())
+                    .chain(res.history_read_by.iter())
+                    .next();
+
+                if let Some(&reader) = reader {
+                    return Err(RenderGraphError::DiscardedContentsRead {
+                        r_name: self.resource_name(att.produced).unwrap().to_owned(),
+                        r_id: att.produced,
+                        writer: self.pass_names[writer_idx].clone(),
+                        reader: self.render_pass_name(reader).unwrap().to_owned(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that no pass writes the subresources of an image it reads.
+    fn check_subresource_access(&self) -> Result<(), RenderGraphError> {
+        for (pass, pass_name) in self.passes.iter().zip(self.pass_names.iter()) {
+            for img in pass.sampled_images.iter() {
+                let read = match &img.subresources {
+                    Some(read) => read,
+                    None => continue,
+                };
+
+                let info = self.resource(img.resource)?.image_info()?;
+                let color_writes = pass
+                    .color_attachments
+                    .iter()
+                    .filter(|att| att.consumed == Some(img.resource))
+                    .map(|att| Subresources {
+                        mip_levels: att.mip_level..att.mip_level + 1,
+                        array_layers: att.layers.clone().unwrap_or(0..info.array_layers),
+                    });
+                // Storage images may be written anywhere by the shader.
+                let storage_writes = pass
+                    .storage_images
+                    .iter()
+                    .filter(|img_write| img_write.consumed == Some(img.resource))
+                    .map(|_| info.all_subresources());
+
+                if color_writes
+                    .chain(storage_writes)
+                    .any(|written| read.overlaps(&written))
+                {
+                    return Err(RenderGraphError::OverlappingSubresources {
+                        pass_name: pass_name.clone(),
+                        r_name: self.resource_name(img.resource).unwrap().to_owned(),
+                        r_id: img.resource,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that each pass's viewport and scissor lie within its
+    /// attachments.
+    fn check_viewports(&self) -> Result<(), RenderGraphError> {
+        for (idx, pass) in self.passes.iter().enumerate() {
+            if !pass.viewport.is_valid() {
+                return Err(RenderGraphError::InvalidViewport {
+                    pass_name: self.pass_names[idx].clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that each image's view mask only selects existing layers.
+    fn check_view_masks(&self) -> Result<(), RenderGraphError> {
+        for (idx, res) in self.resources.iter().enumerate() {
+            if let ResourceType::Image(info) = &res.ty {
+                if !info.view_mask_is_valid() {
+                    return Err(RenderGraphError::InvalidViewMask {
+                        r_name: self.resource_names[idx].clone(),
+                        r_id: ResourceId { id: idx as u16 },
+                        view_mask: info.view_mask,
+                        array_layers: info.array_layers,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the graph for rendering to a display described by `target`.
+    ///
+    /// The graph is validated against `limits` at the display's current
+    /// extent, and its final image against the display's surface format.
+    pub fn build(
+        mut self,
+        target: &DisplayInfo,
+        limits: &DeviceLimits,
+    ) -> Result<RenderGraph, RenderGraphError> {
+        self.validate_limits(limits, target.image_extent)?;
+        self.check_final_format(target.surface_format)?;
+        self.check_final_usage(target.image_usage)?;
+        self.check_store_ops()?;
+        self.check_view_masks()?;
+        self.check_viewports()?;
+        self.check_subresource_access()?;
+
+        // Build a dependency graph of passes.
+        //
+        // - Each node is a render pass.
+        // - Each edge is a resource dependency. There are two kinds of dependency:
+        //   - Produce-dependencies, in which pass A produces resource R and
+        //     pass B reads or consumes R. This requires a memory barrier
+        //     to ensure A happens-before B and to ensure the data written by A
+        //     is made visible.
+        //   - Consume-dependencies, in which pass A reads resource R and pass B
+        //     consumes R. This only requires an execution barrier to ensure A
+        //     happens-before B (write-after-read hazards are precluded by
+        //     execution barriers in Vulkan).
+        let mut graph = PassGraph::with_capacity(self.passes.len(), self.resources.len());
+
+        // Passes which do not lead to the final image or to a root pass are
+        // never added to the graph, and are thereby culled. This is the same
+        // set reported by `culled_passes`.
+        let live = self.live_passes()?;
+        for (idx, pass) in self.passes.iter_mut().enumerate() {
+            if live[idx] {
+                pass.node_idx = Some(graph.add_node(RenderPassId { id: idx as u16 }));
+            }
+        }
+
+        let start_produce_insert = Instant::now();
+        for pass in self.passes.iter() {
+            let pass_idx = match pass.node_idx {
+                Some(idx) => idx,
+                None => continue,
+            };
+
+            // Insert produce-dependencies.
+            for produce_id in pass.produces.iter().copied() {
+                let produce = self.resource(produce_id).unwrap();
+
+                for dependent_id in produce
+                    .read_by
+                    .iter()
+                    .copied()
+                    .chain(produce.consumed_by.iter().copied())
+                {
+                    let dependent = self.render_pass(dependent_id).unwrap();
+                    if let Some(dependent_idx) = dependent.node_idx {
+                        // If the dependent has not been inserted, it was culled.
+                        graph.add_edge(
+                            pass_idx,
+                            dependent_idx,
+                            DependencyType::Produce(produce_id),
+                        );
+                    }
+                }
+            }
+        }
+
+        log::debug!(
+            "Produce-dependencies inserted in {}μs",
+            start_produce_insert.elapsed().as_micros()
+        );
+
+        // The graph now contains all necessary render passes and all
+        // produce-dependencies between them.
+
+        let start_consume_insert = Instant::now();
+        // TODO: this avoids a mutable borrow error on the graph, but it
+        // shouldn't be necessary -- no nodes are added or removed, only edges.
+        let passes = graph.node_weights().copied().collect::<Vec<_>>();
+
+        // Insert consume-dependencies.
+        // TODO: cache a list of consumers (expected to be relatively small) and
+        //   insert these dependencies to any readers of the consumed resource,
+        //   rather than scanning every read resource.
+        for pass_id in passes.iter().copied() {
+            let pass = self.render_pass(pass_id).unwrap();
+
+            for read_id in pass.reads.iter().copied() {
+                let read = self.resource(read_id).unwrap();
+
+                if let Some(consumed_by_id) = read.consumed_by {
+                    // The consumer may have been culled.
+                    let consumed_by = self.render_pass(consumed_by_id).unwrap();
+                    if let Some(consumed_by_idx) = consumed_by.node_idx {
+                        graph.add_edge(
+                            pass.node_idx.unwrap(),
+                            consumed_by_idx,
+                            DependencyType::Consume(read_id),
+                        );
+                    }
+                }
+            }
+        }
+
+        log::debug!(
+            "Consume-dependencies inserted in {}μs",
+            start_consume_insert.elapsed().as_micros()
+        );
+
+        println!("{}", self.gen_dotgraph(&graph));
+
+        let start_dep_resolve = Instant::now();
+        let ordered =
+            match prioritized_toposort(&graph, |id| self.render_pass(id).unwrap().priority) {
+                Ok(o) => o,
+                Err(cycle) => {
+                    let pass_id = *graph.node_weight(cycle).unwrap();
+                    return Err(RenderGraphError::DependencyCycle {
+                        pass_name: self.render_pass_name(pass_id).unwrap().into(),
+                    });
+                }
+            };
+        log::debug!(
+            "Dependencies resolved in {}μs",
+            start_dep_resolve.elapsed().as_micros()
+        );
+
+        let (barriers, layouts) = self.collect_barriers(&graph, &ordered)?;
+        let order = ordered.iter().map(|&idx| graph[idx]).collect();
+
+        // Passes after the producer may have read the final image in another
+        // layout.
+        let mut present_barrier = self.present_barrier()?;
+        if let Some(final_image) = self.final_image {
+            present_barrier.old_layout = layouts[final_image.id as usize];
+        }
+
+        Ok(RenderGraph {
+            builder: self,
+            order,
+            barriers,
+            present_barrier,
+        })
+    }
+}
+
+/// A render graph built by [`RenderGraphBuilder::build`].
+///
+/// Passes may be enabled and disabled between executions without rebuilding
+/// the graph.
+pub struct RenderGraph {
+    builder: RenderGraphBuilder,
+    // Built passes in execution order.
+    order: Vec<RenderPassId>,
+    // The barriers to record before each pass in `order`.
+    barriers: Vec<Vec<DependencyBarrier>>,
+    // The barrier to record after the final image is produced.
+    present_barrier: DependencyBarrier,
+}
+
+impl RenderGraph {
+    /// Enables or disables a render pass.
+    ///
+    /// A disabled pass is skipped when the graph executes, without changing
+    /// the graph's structure. Each resource it produces by consuming another
+    /// takes on the consumed resource's contents unchanged. A pass may only be
+    /// disabled if every other resource it produces is unused, so that e.g. a
+    /// debug overlay drawn onto an image can be toggled freely.
+    pub fn set_pass_enabled(
+        &mut self,
+        id: RenderPassId,
+        enabled: bool,
+    ) -> Result<(), RenderGraphError> {
+        if !enabled {
+            self.builder.check_can_disable(id)?;
+        }
+
+        self.builder.render_pass_mut(id)?.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn is_pass_enabled(&self, id: RenderPassId) -> Result<bool, RenderGraphError> {
+        Ok(self.builder.render_pass(id)?.enabled)
+    }
+
+    /// Returns the built render passes in execution order.
+    pub fn passes(&self) -> &[RenderPassId] {
+        &self.order
+    }
+
+    /// Checks that every disabled pass may still be skipped.
+    fn check_disabled_passes(&self) -> Result<(), RenderGraphError> {
+        for &pass_id in self.order.iter() {
+            if !self.builder.render_pass(pass_id)?.enabled {
+                self.builder.check_can_disable(pass_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the graph's passes into the current frame.
+    pub fn execute(&mut self, frame: &mut FrameContext) -> Result<(), RenderGraphError> {
+        self.check_disabled_passes()?;
+
+        todo!("physical resource assignment");
+
+        // Maintain a mapping from virtual resources to physical resources.
+        let mut virt_to_phys: Vec<Option<PhysResourceId>> =
+            Vec::with_capacity(self.builder.resources.len());
+        virt_to_phys.resize(self.builder.resources.len(), None);
+
+        for (pass_id, barriers) in self.order.iter().copied().zip(self.barriers.iter()) {
+            let pass = self.builder.render_pass(pass_id).unwrap();
+
+            if !pass.enabled {
+                // Pass consumed resources through to their successors.
+                let outputs = pass
+                    .color_attachments
+                    .iter()
+                    .map(|att| (att.consumed, att.produced))
+                    .chain(
+                        pass.storage_images
+                            .iter()
+                            .map(|img| (img.consumed, img.produced)),
+                    )
+                    .chain(
+                        pass.buffer_outputs
+                            .iter()
+                            .map(|out| (out.consumed, out.produced)),
+                    );
+                for (consumed, produced) in outputs {
+                    if let Some(old_id) = consumed {
+                        virt_to_phys[produced.id as usize] =
+                            virt_to_phys[old_id.id as usize].take();
+                    }
+                }
+
+                continue;
+            }
+
+            let pass_complete_event = frame.alloc_event();
+
+            // Input attachments have already been acquired from the frame context.
+            for in_att in pass.input_attachments.iter() {
+                let phys_id = virt_to_phys[in_att.resource.id as usize].unwrap();
+                let info = self.builder.resource(in_att.resource)?.image_info()?;
+                let view_format = in_att.view_format(info);
             }
 
             for col_att in pass.color_attachments.iter() {
@@ -829,7 +3571,50 @@ impl RenderGraphBuilder {
             }
         }
 
-        todo!();
+        Ok(())
+    }
+
+    /// Records the transition of the final image for presentation.
+    ///
+    /// This must be recorded after the graph's passes, in the same command
+    /// buffer or a later one submitted before presentation.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - `image` must be the swapchain image the graph's final image was
+    ///   rendered to.
+    pub unsafe fn record_present_barrier(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        image: vk::Image,
+    ) -> Result<(), RenderGraphError> {
+        let final_image = self
+            .builder
+            .final_image
+            .ok_or(RenderGraphError::MissingSwapchainImage)?;
+        let range = self
+            .builder
+            .resource(final_image)?
+            .image_info()?
+            .subresource_range();
+
+        let barrier = self.present_barrier;
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                barrier.src_stages,
+                barrier.dst_stages,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier.image_barrier(image, range)],
+            );
+        }
+
+        Ok(())
     }
 }
 
@@ -864,9 +3649,55 @@ mod tests {
         fn record(&self, device: &vks::Device, cmdbuf: &mut vks::CommandBuffer) {}
     }
 
+    struct DummyCompute;
+
+    impl ComputePipeline for DummyCompute {
+        fn record(&self, device: &vks::Device, cmdbuf: &mut vks::CommandBuffer) {}
+    }
+
     const DUMMY_COLOR: ImageInfo = ImageInfo {
         size: ImageSize::SAME_AS_SWAPCHAIN,
         format: vk::Format::B8G8R8A8_SRGB,
+        view_formats: Vec::new(),
+        samples: vk::SampleCountFlagBits::_1,
+        mip_levels: 1,
+        array_layers: 1,
+        view_mask: 0,
+    };
+
+    /// A display whose surface format matches `DUMMY_COLOR`.
+    fn test_display() -> DisplayInfo {
+        DisplayInfo {
+            min_image_count: 2,
+            surface_format: vk::SurfaceFormatKHR {
+                format: DUMMY_COLOR.format,
+                color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR_KHR,
+            },
+            supported_surface_formats: Vec::new(),
+            image_extent: vk::Extent2D {
+                width: 1920,
+                height: 1080,
+            },
+            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            samples: vk::SampleCountFlagBits::_1,
+            present_mode: vk::PresentModeKHR::FIFO_KHR,
+            supported_present_modes: Vec::new(),
+        }
+    }
+
+    fn test_limits() -> DeviceLimits {
+        DeviceLimits {
+            max_image_dimension_2d: 4096,
+            max_image_array_layers: 256,
+            max_color_attachments: 8,
+            max_storage_buffer_range: 1 << 20,
+            ..DeviceLimits::from(&vk::PhysicalDeviceLimits::default())
+        }
+    }
+
+    const DUMMY_BUFFER: BufferInfo = BufferInfo {
+        size: 1024,
+        usage: vk::BufferUsageFlags::STORAGE_BUFFER,
     };
 
     // Invariants:
@@ -897,4 +3728,1291 @@ mod tests {
         let res = graph.resource(color_attachment).unwrap();
         assert_eq!(res.produced_by, Some(pass));
     }
+
+    #[test]
+    fn resolve_relative_to_swapchain() {
+        let swapchain = vk::Extent2D {
+            width: 1920,
+            height: 1080,
+        };
+
+        let full = ImageSize::SAME_AS_SWAPCHAIN
+            .resolve(swapchain, None)
+            .unwrap();
+        assert_eq!((full.width, full.height, full.depth), (1920, 1080, 1));
+
+        let half = ImageSize::RelativeToSwapchain(RelativeExtent::scale(0.5))
+            .resolve(swapchain, None)
+            .unwrap();
+        assert_eq!((half.width, half.height, half.depth), (960, 540, 1));
+
+        let tiny = ImageSize::RelativeToSwapchain(RelativeExtent::scale(0.0001))
+            .resolve(swapchain, None)
+            .unwrap();
+        assert_eq!((tiny.width, tiny.height), (1, 1));
+    }
+
+    #[test]
+    fn resolve_relative_to_input_requires_input() {
+        let swapchain = vk::Extent2D {
+            width: 800,
+            height: 600,
+        };
+        let size = ImageSize::RelativeToInput(RelativeExtent::scale(2.0));
+
+        assert!(size.resolve(swapchain, None).is_none());
+
+        let input = vk::Extent3D {
+            width: 64,
+            height: 32,
+            depth: 1,
+        };
+        let resolved = size.resolve(swapchain, Some(input)).unwrap();
+        assert_eq!((resolved.width, resolved.height), (128, 64));
+    }
+
+    #[test]
+    fn images_lists_producers() {
+        let mut graph = RenderGraphBuilder::new();
+        let external = graph.add_image("external", DUMMY_COLOR);
+        graph.add_buffer("buffer", DUMMY_BUFFER);
+
+        let mut pass = graph.add_render_pass("main pass", DummyPass);
+        let color = pass
+            .add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        pass.finish();
+
+        let images = graph.images().collect::<Vec<_>>();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].id, external);
+        assert_eq!(images[0].producer, None);
+        assert_eq!(images[1].id, color);
+        assert_eq!(images[1].name, "color");
+        assert_eq!(images[1].producer, Some("main pass"));
+    }
+
+    #[test]
+    fn buffer_output_is_read_by_later_pass() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut producer = graph.add_render_pass("producer", DummyPass);
+        let buffer = producer
+            .add_buffer_output("buffer", DUMMY_BUFFER, None)
+            .unwrap();
+        let producer = producer.finish();
+
+        let mut reader = graph.add_render_pass("reader", DummyPass);
+        reader.add_buffer_input(buffer).unwrap();
+        let reader = reader.finish();
+
+        let res = graph.resource(buffer).unwrap();
+        assert_eq!(res.produced_by, Some(producer));
+        assert_eq!(res.read_by.as_slice(), &[reader]);
+    }
+
+    #[test]
+    fn sampled_image_is_read_by_later_pass() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut producer = graph.add_render_pass("producer", DummyPass);
+        let image = producer
+            .add_color_attachment("image", DUMMY_COLOR, None)
+            .unwrap();
+        let producer = producer.finish();
+
+        let mut reader = graph.add_render_pass("reader", DummyPass);
+        reader.add_sampled_image(image).unwrap();
+        let reader = reader.finish();
+
+        let res = graph.resource(image).unwrap();
+        assert_eq!(res.produced_by, Some(producer));
+        assert_eq!(res.read_by.as_slice(), &[reader]);
+
+        let buffer = graph.add_buffer("buffer", DUMMY_BUFFER);
+        let mut pass = graph.add_render_pass("pass", DummyPass);
+        assert!(matches!(
+            pass.add_sampled_image(buffer),
+            Err(RenderGraphError::IncompatibleResourceType {
+                expected: ResourceTypeTag::Image,
+                actual: ResourceTypeTag::Buffer,
+            })
+        ));
+    }
+
+    #[test]
+    fn history_read_is_not_a_dependency() {
+        let mut graph = RenderGraphBuilder::new();
+
+        // A pass which accumulates into an image, reading its previous value.
+        let mut accumulate = graph.add_render_pass("accumulate", DummyPass);
+        let accum = accumulate
+            .add_color_attachment("accum", DUMMY_COLOR, None)
+            .unwrap();
+        accumulate.add_history_image(accum).unwrap();
+        assert!(matches!(
+            accumulate.add_history_image(accum),
+            Err(RenderGraphError::AlreadyAccessed { .. })
+        ));
+        let accumulate = accumulate.finish();
+
+        let mut reader = graph.add_render_pass("reader", DummyPass);
+        reader.add_history_image(accum).unwrap();
+        let reader = reader.finish();
+
+        let res = graph.resource(accum).unwrap();
+        assert_eq!(res.produced_by, Some(accumulate));
+        assert!(res.read_by.is_empty());
+        assert_eq!(res.history_read_by.as_slice(), &[accumulate, reader]);
+        assert_eq!(graph.persistent_images().collect::<Vec<_>>(), vec![accum]);
+    }
+
+    #[test]
+    fn motion_vector_input_checks_format() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut geometry = graph.add_render_pass("geometry", DummyPass);
+        let motion = geometry.add_motion_vector_output("motion").unwrap();
+        geometry.finish();
+
+        let color = graph.add_image("color", DUMMY_COLOR);
+
+        let mut taa = graph.add_render_pass("taa", DummyPass);
+        taa.add_motion_vector_input(motion).unwrap();
+        assert!(matches!(
+            taa.add_motion_vector_input(color),
+            Err(RenderGraphError::NotMotionVectors { .. })
+        ));
+    }
+
+    #[test]
+    fn resolve_multisampled_attachment() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut pass = graph.add_render_pass("pass", DummyPass);
+        let msaa = pass
+            .add_color_attachment(
+                "msaa",
+                ImageInfo {
+                    samples: vk::SampleCountFlagBits::_4,
+                    ..DUMMY_COLOR
+                },
+                None,
+            )
+            .unwrap();
+        let single = pass
+            .add_color_attachment("single", DUMMY_COLOR, None)
+            .unwrap();
+
+        let resolved = pass.add_resolve_attachment("resolved", msaa).unwrap();
+        assert!(matches!(
+            pass.add_resolve_attachment("bad", single),
+            Err(RenderGraphError::NotMultisampled { .. })
+        ));
+        pass.finish();
+
+        let info = graph.resource(resolved).unwrap().image_info().unwrap();
+        assert_eq!(info.samples, vk::SampleCountFlagBits::_1);
+        assert_eq!(info.format, DUMMY_COLOR.format);
+    }
+
+    #[test]
+    fn transient_attachments_are_not_sampled() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut gbuffer = graph.add_render_pass("gbuffer", DummyPass);
+        let albedo = gbuffer
+            .add_color_attachment("albedo", DUMMY_COLOR, None)
+            .unwrap();
+        let normal = gbuffer
+            .add_color_attachment("normal", DUMMY_COLOR, None)
+            .unwrap();
+        gbuffer.finish();
+
+        let mut lighting = graph.add_render_pass("lighting", DummyPass);
+        lighting.add_input_attachment(albedo).unwrap();
+        lighting.add_input_attachment(normal).unwrap();
+        let lit = lighting
+            .add_color_attachment("lit", DUMMY_COLOR, None)
+            .unwrap();
+        lighting.finish();
+
+        let mut overlay = graph.add_render_pass("overlay", DummyPass);
+        overlay.add_sampled_image(normal).unwrap();
+        let output = overlay
+            .add_color_attachment("output", DUMMY_COLOR, Some(lit))
+            .unwrap();
+        overlay.finish();
+
+        graph.set_final_image(output).unwrap();
+
+        // `lit` is consumed by the final image, so it shares its storage.
+        assert_eq!(
+            graph.transient_attachments().collect::<Vec<_>>(),
+            vec![albedo]
+        );
+    }
+
+    #[test]
+    fn storage_images_are_not_transient() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut compute = graph.add_compute_pass("mask", DummyCompute);
+        let mask = compute
+            .add_storage_image("mask", DUMMY_COLOR, None)
+            .unwrap();
+        compute.finish();
+
+        let mut pass = graph.add_render_pass("composite", DummyPass);
+        pass.add_input_attachment(mask).unwrap();
+        let output = pass
+            .add_color_attachment("output", DUMMY_COLOR, None)
+            .unwrap();
+        pass.finish();
+
+        graph.set_final_image(output).unwrap();
+        assert_eq!(graph.transient_attachments().count(), 0);
+    }
+
+    #[test]
+    fn read_then_consumed_image_is_transitioned_once() {
+        use vk::ImageLayout as L;
+
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut scene = graph.add_render_pass("scene", DummyPass);
+        let color = scene
+            .add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        scene.finish();
+
+        let mut blur = graph.add_render_pass("blur", DummyPass);
+        blur.add_sampled_image(color).unwrap();
+        let output = blur
+            .add_color_attachment("output", DUMMY_COLOR, None)
+            .unwrap();
+        let blur = blur.finish();
+        graph.set_final_image(output).unwrap();
+
+        let mut tonemap = graph.add_compute_pass("tonemap", DummyCompute);
+        tonemap
+            .add_storage_image("tonemapped", DUMMY_COLOR, Some(color))
+            .unwrap();
+        tonemap.mark_root();
+        let tonemap = tonemap.finish();
+
+        let built = graph.build(&test_display(), &test_limits()).unwrap();
+        let pos = |id| built.order.iter().position(|&p| p == id).unwrap();
+        assert!(pos(blur) < pos(tonemap));
+
+        // The tonemap pass depends on `color` through both its producer and
+        // its reader, but only transitions it once, from the reader's layout.
+        let transitions = built.barriers[pos(tonemap)]
+            .iter()
+            .filter(|b| b.old_layout != b.new_layout)
+            .map(|b| (b.old_layout, b.new_layout))
+            .collect::<Vec<_>>();
+        assert_eq!(transitions, [(L::SHADER_READ_ONLY_OPTIMAL, L::GENERAL)]);
+    }
+
+    #[test]
+    fn discarded_attachment_must_not_be_read() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut pass = graph.add_render_pass("msaa", DummyPass);
+        let msaa = pass
+            .add_color_attachment(
+                "msaa",
+                ImageInfo {
+                    samples: vk::SampleCountFlagBits::_4,
+                    ..DUMMY_COLOR
+                },
+                None,
+            )
+            .unwrap();
+        let resolved = pass.add_resolve_attachment("resolved", msaa).unwrap();
+        pass.discard_color_attachment(msaa).unwrap();
+        assert!(matches!(
+            pass.discard_color_attachment(ResourceId { id: 100 }),
+            Err(RenderGraphError::NoSuchResource(_))
+        ));
+        pass.finish();
+
+        let mut reader = graph.add_render_pass("reader", DummyPass);
+        reader.add_sampled_image(resolved).unwrap();
+        reader.finish();
+        graph.check_store_ops().unwrap();
+
+        let mut bad = graph.add_render_pass("bad", DummyPass);
+        bad.add_input_attachment(msaa).unwrap();
+        bad.finish();
+
+        match graph.check_store_ops() {
+            Err(RenderGraphError::DiscardedContentsRead {
+                r_id,
+                writer,
+                reader,
+                ..
+            }) => {
+                assert_eq!(r_id, msaa);
+                assert_eq!(writer, "msaa");
+                assert_eq!(reader, "bad");
+            }
+            other => panic!("expected DiscardedContentsRead, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn final_image_matches_surface_format() {
+        let mut graph = RenderGraphBuilder::new();
+        let mut pass = graph.add_render_pass("present", DummyPass);
+        let output = pass
+            .add_color_attachment("output", DUMMY_COLOR, None)
+            .unwrap();
+        pass.finish();
+        graph.set_final_image(output).unwrap();
+
+        let srgb = vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_SRGB,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR_KHR,
+        };
+        graph.check_final_format(srgb).unwrap();
+
+        let unorm = vk::SurfaceFormatKHR {
+            format: vk::Format::B8G8R8A8_UNORM,
+            ..srgb
+        };
+        assert!(matches!(
+            graph.check_final_format(unorm),
+            Err(RenderGraphError::SwapchainFormatMismatch { swapchain_format, .. })
+                if swapchain_format == vk::Format::B8G8R8A8_UNORM
+        ));
+
+        graph.set_output_encoding(OutputEncoding::EncodedSrgb);
+        assert!(matches!(
+            graph.check_final_format(srgb),
+            Err(RenderGraphError::OutputEncodingMismatch {
+                encoding: OutputEncoding::EncodedSrgb,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn compute_pass_writes_final_image() {
+        use vk::{AccessFlags as A, ImageLayout as L, PipelineStageFlags as S};
+
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut scene = graph.add_render_pass("scene", DummyPass);
+        let color = scene
+            .add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        scene.finish();
+
+        let mut upscale = graph.add_compute_pass("upscale", DummyCompute);
+        upscale.add_sampled_image(color).unwrap();
+        let output = upscale
+            .add_storage_image("output", DUMMY_COLOR, None)
+            .unwrap();
+        upscale.finish();
+        graph.set_final_image(output).unwrap();
+
+        assert!(matches!(
+            graph.check_final_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT),
+            Err(RenderGraphError::SwapchainUsageMissing { usage, .. })
+                if usage == vk::ImageUsageFlags::STORAGE
+        ));
+        graph
+            .check_final_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::STORAGE)
+            .unwrap();
+
+        let barrier = graph.present_barrier().unwrap();
+        assert_eq!(barrier.src_stages, S::COMPUTE_SHADER);
+        assert_eq!(barrier.src_access, A::SHADER_WRITE);
+        assert_eq!(barrier.dst_stages, S::BOTTOM_OF_PIPE);
+        assert_eq!(barrier.dst_access, A::empty());
+        assert_eq!(barrier.old_layout, L::GENERAL);
+        assert_eq!(barrier.new_layout, L::PRESENT_SRC_KHR);
+
+        let range = DUMMY_COLOR.subresource_range();
+        let image_barrier = barrier.image_barrier(vk::Image::null(), range);
+        assert_eq!(image_barrier.src_access_mask, A::SHADER_WRITE);
+        assert_eq!(image_barrier.old_layout, L::GENERAL);
+        assert_eq!(image_barrier.new_layout, L::PRESENT_SRC_KHR);
+        assert_eq!(image_barrier.subresource_range.level_count, 1);
+    }
+
+    #[test]
+    fn final_image_cannot_be_discarded() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut pass = graph.add_render_pass("present", DummyPass);
+        let output = pass
+            .add_color_attachment("output", DUMMY_COLOR, None)
+            .unwrap();
+        pass.discard_color_attachment(output).unwrap();
+        pass.finish();
+        graph.check_store_ops().unwrap();
+
+        graph.set_final_image(output).unwrap();
+        assert!(matches!(
+            graph.check_store_ops(),
+            Err(RenderGraphError::DiscardedFinalImage { r_id, writer, .. })
+                if r_id == output && writer == "present"
+        ));
+    }
+
+    #[test]
+    fn toposort_respects_priority() {
+        let mut graph = PassGraph::default();
+        let pass = |id| RenderPassId { id };
+
+        // 0 -> 2, 1 -> 2, 3 is independent.
+        let nodes = (0..4)
+            .map(|id| graph.add_node(pass(id)))
+            .collect::<Vec<_>>();
+        graph.add_edge(
+            nodes[0],
+            nodes[2],
+            DependencyType::Produce(ResourceId { id: 0 }),
+        );
+        graph.add_edge(
+            nodes[1],
+            nodes[2],
+            DependencyType::Produce(ResourceId { id: 1 }),
+        );
+
+        let order = |priorities: [i32; 4]| {
+            prioritized_toposort(&graph, |id| priorities[id.id as usize])
+                .unwrap()
+                .into_iter()
+                .map(|idx| graph[idx].id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order([0, 0, 0, 0]), vec![0, 1, 2, 3]);
+        assert_eq!(order([0, 0, 0, 1]), vec![3, 0, 1, 2]);
+        assert_eq!(order([0, 1, 0, -1]), vec![1, 0, 2, 3]);
+        // The dependencies of a high-priority pass share its priority.
+        assert_eq!(order([0, 0, 5, 1]), vec![0, 1, 2, 3]);
+        assert_eq!(order([0, 0, 5, 6]), vec![3, 0, 1, 2]);
+
+        graph.add_edge(
+            nodes[2],
+            nodes[0],
+            DependencyType::Consume(ResourceId { id: 2 }),
+        );
+        let cycle = prioritized_toposort(&graph, |_| 0).unwrap_err();
+        assert!(cycle == nodes[0] || cycle == nodes[2]);
+    }
+
+    #[test]
+    fn overlap_report_finds_independent_passes() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut shadow = graph.add_render_pass("shadow", DummyPass);
+        let shadow_map = shadow
+            .add_color_attachment("shadow_map", DUMMY_COLOR, None)
+            .unwrap();
+        shadow.finish();
+
+        let mut gbuffer = graph.add_render_pass("gbuffer", DummyPass);
+        let albedo = gbuffer
+            .add_color_attachment("albedo", DUMMY_COLOR, None)
+            .unwrap();
+        gbuffer.finish();
+
+        let mut lighting = graph.add_render_pass("lighting", DummyPass);
+        lighting.add_sampled_image(shadow_map).unwrap();
+        lighting.add_input_attachment(albedo).unwrap();
+        let lit = lighting
+            .add_color_attachment("lit", DUMMY_COLOR, None)
+            .unwrap();
+        lighting.finish();
+
+        let mut post = graph.add_render_pass("post", DummyPass);
+        post.add_sampled_image(lit).unwrap();
+        post.finish();
+
+        let report = graph.overlap_report().unwrap();
+        assert_eq!(report.pass_count, 4);
+        assert_eq!(report.critical_path_len, 3);
+        assert_eq!(
+            report.independent_pairs,
+            vec![("shadow".to_owned(), "gbuffer".to_owned())]
+        );
+    }
+
+    #[test]
+    fn lifetime_report_spans_uses() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut gbuffer = graph.add_render_pass("gbuffer", DummyPass);
+        let albedo = gbuffer
+            .add_color_attachment("albedo", DUMMY_COLOR, None)
+            .unwrap();
+        gbuffer.finish();
+
+        let mut lighting = graph.add_render_pass("lighting", DummyPass);
+        lighting.add_input_attachment(albedo).unwrap();
+        let lit = lighting
+            .add_color_attachment("lit \"hdr\"", DUMMY_COLOR, None)
+            .unwrap();
+        lighting.finish();
+
+        let mut post = graph.add_render_pass("post", DummyPass);
+        post.add_sampled_image(albedo).unwrap();
+        post.add_sampled_image(lit).unwrap();
+        post.finish();
+
+        let extent = vk::Extent2D {
+            width: 4,
+            height: 2,
+        };
+        let report = graph.lifetime_report(extent).unwrap();
+        assert_eq!(report.passes, vec!["gbuffer", "lighting", "post"]);
+        assert_eq!(
+            report
+                .resources
+                .iter()
+                .map(|res| (res.id, res.first_use, res.last_use, res.size))
+                .collect::<Vec<_>>(),
+            vec![(albedo, 0, 2, Some(32)), (lit, 1, 2, Some(32))]
+        );
+        assert_eq!(report.peak_size(), 64);
+        assert_eq!(
+            report.to_json(),
+            "{\"passes\":[\"gbuffer\",\"lighting\",\"post\"],\"resources\":[\
+             {\"id\":0,\"name\":\"albedo\",\"first_use\":0,\"last_use\":2,\"size\":32},\
+             {\"id\":1,\"name\":\"lit \\\"hdr\\\"\",\"first_use\":1,\"last_use\":2,\"size\":32}]}"
+        );
+    }
+
+    #[test]
+    fn validate_limits_names_offender() {
+        let limits = DeviceLimits {
+            max_image_dimension_2d: 4096,
+            max_image_array_layers: 256,
+            max_color_attachments: 1,
+            max_storage_buffer_range: 1 << 20,
+            ..DeviceLimits::from(&vk::PhysicalDeviceLimits::default())
+        };
+        let extent = vk::Extent2D {
+            width: 1920,
+            height: 1080,
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let color = graph.add_image("color", DUMMY_COLOR);
+        graph
+            .add_derived_image(
+                "double",
+                color,
+                RelativeExtent::scale(2.0),
+                DUMMY_COLOR.format,
+            )
+            .unwrap();
+        graph.validate_limits(&limits, extent).unwrap();
+
+        graph
+            .add_derived_image(
+                "quad",
+                color,
+                RelativeExtent::scale(4.0),
+                DUMMY_COLOR.format,
+            )
+            .unwrap();
+        match graph.validate_limits(&limits, extent) {
+            Err(RenderGraphError::LimitExceeded { name, source }) => {
+                assert_eq!(name, "quad");
+                assert_eq!(source.requested, 7680);
+                assert_eq!(source.max, 4096);
+            }
+            other => panic!("expected LimitExceeded, got {:?}", other),
+        }
+
+        let mut graph = RenderGraphBuilder::new();
+        graph.add_buffer("storage", DUMMY_BUFFER);
+        let mut pass = graph.add_render_pass("mrt", DummyPass);
+        pass.add_color_attachment("a", DUMMY_COLOR, None).unwrap();
+        pass.add_color_attachment("b", DUMMY_COLOR, None).unwrap();
+        pass.finish();
+        assert!(matches!(
+            graph.validate_limits(&limits, extent),
+            Err(RenderGraphError::LimitExceeded { name, .. }) if name == "mrt"
+        ));
+    }
+
+    #[test]
+    fn alias_plan_reuses_dead_memory() {
+        let mut graph = RenderGraphBuilder::new();
+        let extent = vk::Extent2D {
+            width: 4,
+            height: 2,
+        };
+
+        // A chain of post-processing passes, each reading only the previous
+        // pass's output.
+        let mut scene = graph.add_render_pass("scene", DummyPass);
+        let a = scene.add_color_attachment("a", DUMMY_COLOR, None).unwrap();
+        scene.finish();
+
+        let mut prev = a;
+        let mut outputs = vec![a];
+        for name in ["b", "c", "d"].iter() {
+            let mut pass = graph.add_render_pass(*name, DummyPass);
+            pass.add_sampled_image(prev).unwrap();
+            prev = pass.add_color_attachment(*name, DUMMY_COLOR, None).unwrap();
+            pass.finish();
+            outputs.push(prev);
+        }
+
+        let mut fin = graph.add_render_pass("final", DummyPass);
+        fin.add_sampled_image(prev).unwrap();
+        let swapchain = fin
+            .add_color_attachment("swapchain", DUMMY_COLOR, None)
+            .unwrap();
+        fin.finish();
+        graph.set_final_image(swapchain).unwrap();
+
+        for alignment in [0, 3, 384] {
+            assert!(matches!(
+                graph.alias_plan(extent, alignment),
+                Err(RenderGraphError::InvalidAlignment(a)) if a == alignment
+            ));
+        }
+
+        let plan = graph.alias_plan(extent, 256).unwrap();
+        assert_eq!(plan.placements.len(), 4);
+        // Each image is live alongside at most one neighbor, so two slots of
+        // 32 bytes (aligned to 256) suffice.
+        assert_eq!(plan.size, 256 + 32);
+
+        let offset_of = |id| {
+            plan.placements
+                .iter()
+                .find(|p| p.resources == vec![id])
+                .unwrap()
+                .offset
+        };
+        for pair in outputs.windows(2) {
+            assert_ne!(offset_of(pair[0]), offset_of(pair[1]));
+        }
+        assert_eq!(offset_of(outputs[0]), offset_of(outputs[2]));
+    }
+
+    #[test]
+    fn volume_and_array_view_types() {
+        let flat = vk::Extent3D {
+            width: 64,
+            height: 64,
+            depth: 1,
+        };
+        let volume = vk::Extent3D { depth: 32, ..flat };
+
+        let array = ImageInfo {
+            array_layers: 4,
+            ..DUMMY_COLOR
+        };
+        assert_eq!(array.view_type(flat), vk::ImageViewType::_2D_ARRAY);
+        assert_eq!(array.subresource_range().layer_count, 4);
+        assert_eq!(array.subresource_range().level_count, 1);
+        assert_eq!(DUMMY_COLOR.view_type(flat), vk::ImageViewType::_2D);
+        assert_eq!(DUMMY_COLOR.image_type(volume), vk::ImageType::_3D);
+        assert_eq!(DUMMY_COLOR.view_type(volume), vk::ImageViewType::_3D);
+
+        let limits = DeviceLimits {
+            max_image_dimension_2d: 4096,
+            max_image_dimension_3d: 256,
+            max_image_array_layers: 256,
+            max_color_attachments: 8,
+            ..DeviceLimits::from(&vk::PhysicalDeviceLimits::default())
+        };
+        let swapchain = vk::Extent2D {
+            width: 64,
+            height: 64,
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let fog = ImageInfo {
+            size: ImageSize::Absolute(volume),
+            ..DUMMY_COLOR
+        };
+        graph.add_image("fog", fog.clone());
+        graph.validate_limits(&limits, swapchain).unwrap();
+
+        let layered = graph.add_image(
+            "layered fog",
+            ImageInfo {
+                array_layers: 2,
+                ..fog
+            },
+        );
+        assert!(matches!(
+            graph.validate_limits(&limits, swapchain),
+            Err(RenderGraphError::LayeredVolume { r_id, .. }) if r_id == layered
+        ));
+    }
+
+    #[test]
+    fn downsample_within_one_image() {
+        let chain = ImageInfo {
+            mip_levels: 4,
+            ..DUMMY_COLOR
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let mut pass = graph.add_render_pass("base", DummyPass);
+        let mut img = pass
+            .add_color_attachment("mip 0", chain.clone(), None)
+            .unwrap();
+        let mut prev = pass.finish();
+
+        for level in 1..4 {
+            let mut pass = graph.add_render_pass(format!("downsample {}", level), DummyPass);
+            pass.add_sampled_subresources(
+                img,
+                Subresources::mip(level - 1, 1),
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )
+            .unwrap();
+            let next = pass
+                .add_color_attachment(format!("mip {}", level), chain.clone(), Some(img))
+                .unwrap();
+            pass.select_color_attachment_mip(next, level).unwrap();
+            let id = pass.finish();
+
+            let res = graph.resource(img).unwrap();
+            assert_eq!(res.produced_by, Some(prev));
+            assert_eq!(res.consumed_by, Some(id));
+            assert!(res.read_by.is_empty());
+
+            img = next;
+            prev = id;
+        }
+        graph.check_subresource_access().unwrap();
+
+        let mut pass = graph.add_render_pass("feedback", DummyPass);
+        pass.add_sampled_subresources(
+            img,
+            Subresources::mip(2, 1),
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+        )
+        .unwrap();
+        let out = pass
+            .add_color_attachment("mip 2 again", chain.clone(), Some(img))
+            .unwrap();
+        pass.select_color_attachment_mip(out, 2).unwrap();
+        assert!(matches!(
+            pass.select_color_attachment_mip(out, 4),
+            Err(RenderGraphError::SubresourcesOutOfRange { .. })
+        ));
+        pass.finish();
+        assert!(matches!(
+            graph.check_subresource_access(),
+            Err(RenderGraphError::OverlappingSubresources { r_id, .. }) if r_id == img
+        ));
+    }
+
+    #[test]
+    fn partial_read_independent_of_consume_order() {
+        let chain = ImageInfo {
+            mip_levels: 2,
+            ..DUMMY_COLOR
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let mut pass = graph.add_render_pass("base", DummyPass);
+        let img = pass
+            .add_color_attachment("mip 0", chain.clone(), None)
+            .unwrap();
+        pass.finish();
+
+        // Consume first, then read.
+        let mut pass = graph.add_render_pass("downsample", DummyPass);
+        let next = pass
+            .add_color_attachment("mip 1", chain.clone(), Some(img))
+            .unwrap();
+        pass.select_color_attachment_mip(next, 1).unwrap();
+        pass.add_sampled_subresources(
+            img,
+            Subresources::mip(0, 1),
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+        )
+        .unwrap();
+        assert!(matches!(
+            pass.add_sampled_subresources(
+                img,
+                Subresources::mip(0, 1),
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+            Err(RenderGraphError::AlreadyAccessed {
+                ty: AccessType::Read,
+                ..
+            })
+        ));
+        let id = pass.finish();
+
+        let res = graph.resource(img).unwrap();
+        assert_eq!(res.consumed_by, Some(id));
+        assert!(res.read_by.is_empty());
+        graph.check_subresource_access().unwrap();
+
+        // A whole read cannot be combined with a consume in either order.
+        let mut pass = graph.add_render_pass("whole", DummyPass);
+        pass.add_sampled_image(next).unwrap();
+        assert!(matches!(
+            pass.add_color_attachment("out", chain.clone(), Some(next)),
+            Err(RenderGraphError::AlreadyAccessed {
+                ty: AccessType::Read,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn storage_write_overlaps_partial_read() {
+        let chain = ImageInfo {
+            mip_levels: 2,
+            ..DUMMY_COLOR
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let mut pass = graph.add_render_pass("base", DummyPass);
+        let img = pass
+            .add_color_attachment("mip 0", chain.clone(), None)
+            .unwrap();
+        pass.finish();
+
+        let mut pass = graph.add_compute_pass("downsample", DummyCompute);
+        pass.add_sampled_subresources(img, Subresources::mip(0, 1))
+            .unwrap();
+        pass.add_storage_image("mips", chain, Some(img)).unwrap();
+        pass.finish();
+
+        assert!(matches!(
+            graph.check_subresource_access(),
+            Err(RenderGraphError::OverlappingSubresources { r_id, .. }) if r_id == img
+        ));
+    }
+
+    #[test]
+    fn layered_attachments() {
+        let cube = ImageInfo {
+            array_layers: 6,
+            ..DUMMY_COLOR
+        };
+
+        let mut graph = RenderGraphBuilder::new();
+        let mut pass = graph.add_render_pass("shadow face", DummyPass);
+        let shadow = pass.add_color_attachment("shadow", cube, None).unwrap();
+        pass.select_color_attachment_layers(shadow, 2..3).unwrap();
+        assert!(matches!(
+            pass.select_color_attachment_layers(shadow, 5..7),
+            Err(RenderGraphError::LayersOutOfRange {
+                array_layers: 6,
+                ..
+            })
+        ));
+        assert!(matches!(
+            pass.select_color_attachment_layers(shadow, 1..1),
+            Err(RenderGraphError::LayersOutOfRange { .. })
+        ));
+        pass.finish();
+        graph.check_view_masks().unwrap();
+
+        let stereo = graph.add_image(
+            "stereo",
+            ImageInfo {
+                array_layers: 2,
+                view_mask: 0b111,
+                ..DUMMY_COLOR
+            },
+        );
+        assert!(matches!(
+            graph.check_view_masks(),
+            Err(RenderGraphError::InvalidViewMask { r_id, .. }) if r_id == stereo
+        ));
+    }
+
+    #[test]
+    fn subgraph_is_spliced_onto_inputs() {
+        let mut bloom = RenderGraphBuilder::new();
+        let bloom_in = bloom.add_image("input", DUMMY_COLOR);
+
+        let mut blur = bloom.add_render_pass("blur", DummyPass);
+        blur.add_sampled_image(bloom_in).unwrap();
+        let blurred = blur
+            .add_color_attachment("blurred", DUMMY_COLOR, None)
+            .unwrap();
+        let blur = blur.finish();
+
+        let mut composite = bloom.add_render_pass("composite", DummyPass);
+        composite.add_sampled_image(blurred).unwrap();
+        let bloom_out = composite
+            .add_color_attachment("output", DUMMY_COLOR, Some(bloom_in))
+            .unwrap();
+        let composite = composite.finish();
+
+        let mut graph = RenderGraphBuilder::new();
+        let mut scene = graph.add_render_pass("scene", DummyPass);
+        let color = scene
+            .add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        let scene = scene.finish();
+
+        let sub = graph
+            .add_subgraph("bloom", bloom, &[(bloom_in, color)])
+            .unwrap();
+        assert_eq!(sub.resource(bloom_in), Some(color));
+
+        let blur = sub.render_pass(blur).unwrap();
+        let composite = sub.render_pass(composite).unwrap();
+        let output = sub.resource(bloom_out).unwrap();
+        assert_eq!(graph.render_pass_name(blur), Some("bloom/blur"));
+        assert_eq!(graph.resource_name(output), Some("bloom/output"));
+
+        let res = graph.resource(color).unwrap();
+        assert_eq!(res.produced_by, Some(scene));
+        assert_eq!(res.read_by.as_slice(), &[blur]);
+        assert_eq!(res.consumed_by, Some(composite));
+
+        let node = graph.render_pass(composite).unwrap();
+        assert!(node.consumes.contains(&color));
+        assert!(node.reads.contains(&sub.resource(blurred).unwrap()));
+        assert!(node.produces.contains(&output));
+
+        graph.set_final_image(output).unwrap();
+        let deps = graph.full_dependency_graph();
+        assert_eq!(graph.sort_all_passes(&deps).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn subgraph_inputs_are_not_consumed_twice() {
+        let mut sub = RenderGraphBuilder::new();
+        let input = sub.add_image("input", DUMMY_COLOR);
+        let mut pass = sub.add_render_pass("overlay", DummyPass);
+        pass.add_color_attachment("output", DUMMY_COLOR, Some(input))
+            .unwrap();
+        pass.finish();
+
+        let mut graph = RenderGraphBuilder::new();
+        let color = graph.add_image("color", DUMMY_COLOR);
+        let mut pass = graph.add_render_pass("tonemap", DummyPass);
+        pass.add_color_attachment("output", DUMMY_COLOR, Some(color))
+            .unwrap();
+        pass.finish();
+
+        assert!(matches!(
+            graph.add_subgraph("debug", sub, &[(input, color)]),
+            Err(RenderGraphError::AlreadyConsumed { r_id, .. }) if r_id == color
+        ));
+    }
+
+    #[test]
+    fn only_pass_through_passes_can_be_disabled() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut scene = graph.add_render_pass("scene", DummyPass);
+        let color = scene
+            .add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        let scene = scene.finish();
+
+        let mut overlay = graph.add_render_pass("overlay", DummyPass);
+        let overlaid = overlay
+            .add_color_attachment("overlaid", DUMMY_COLOR, Some(color))
+            .unwrap();
+        let overlay = overlay.finish();
+        graph.set_final_image(overlaid).unwrap();
+
+        let present_barrier = graph.present_barrier().unwrap();
+        let mut graph = RenderGraph {
+            builder: graph,
+            order: vec![scene, overlay],
+            barriers: vec![Vec::new(); 2],
+            present_barrier,
+        };
+
+        graph.set_pass_enabled(overlay, false).unwrap();
+        assert!(!graph.is_pass_enabled(overlay).unwrap());
+        graph.check_disabled_passes().unwrap();
+        graph.set_pass_enabled(overlay, true).unwrap();
+
+        assert!(matches!(
+            graph.set_pass_enabled(scene, false),
+            Err(RenderGraphError::RequiredPass { res_id, .. }) if res_id == color
+        ));
+        assert!(graph.is_pass_enabled(scene).unwrap());
+
+        // The invariant is checked again before execution.
+        graph.builder.render_pass_mut(scene).unwrap().enabled = false;
+        assert!(matches!(
+            graph.check_disabled_passes(),
+            Err(RenderGraphError::RequiredPass { res_id, .. }) if res_id == color
+        ));
+    }
+
+    #[test]
+    fn derived_image_follows_source() {
+        let mut graph = RenderGraphBuilder::new();
+        let color = graph.add_image("color", DUMMY_COLOR);
+        let half = graph
+            .add_derived_image(
+                "half",
+                color,
+                RelativeExtent::scale(0.5),
+                vk::Format::R16G16B16A16_SFLOAT,
+            )
+            .unwrap();
+        let quarter = graph
+            .add_derived_image(
+                "quarter",
+                half,
+                RelativeExtent::scale(0.5),
+                vk::Format::R16G16B16A16_SFLOAT,
+            )
+            .unwrap();
+
+        for &(width, height) in &[(1920, 1080), (800, 600)] {
+            let swapchain = vk::Extent2D { width, height };
+            let extent = graph.image_extent(quarter, swapchain).unwrap().unwrap();
+            assert_eq!(
+                (extent.width, extent.height, extent.depth),
+                (width / 4, height / 4, 1)
+            );
+        }
+
+        let buffer = graph.add_buffer("buffer", DUMMY_BUFFER);
+        assert!(matches!(
+            graph.add_derived_image("bad", buffer, RelativeExtent::ONE, vk::Format::R8_UNORM),
+            Err(RenderGraphError::IncompatibleResourceType { .. })
+        ));
+    }
+
+    #[test]
+    fn input_attachment_view_format() {
+        let mut graph = RenderGraphBuilder::new();
+        let mutable = graph.add_image(
+            "mutable",
+            ImageInfo {
+                view_formats: vec![vk::Format::B8G8R8A8_UNORM],
+                ..DUMMY_COLOR
+            },
+        );
+        assert_eq!(
+            graph
+                .resource(mutable)
+                .unwrap()
+                .image_info()
+                .unwrap()
+                .create_flags(),
+            vk::ImageCreateFlags::MUTABLE_FORMAT
+        );
+
+        let plain = graph.add_image("plain", DUMMY_COLOR);
+        let mut pass = graph.add_render_pass("pass", DummyPass);
+        pass.add_input_attachment_with_format(mutable, vk::Format::B8G8R8A8_UNORM)
+            .unwrap();
+        pass.add_input_attachment(plain).unwrap();
+        let views = pass
+            .pass
+            .input_attachments
+            .iter()
+            .map(|att| att.view_format(&DUMMY_COLOR))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            views,
+            vec![vk::Format::B8G8R8A8_UNORM, vk::Format::B8G8R8A8_SRGB]
+        );
+
+        let fixed = graph.add_image("fixed", DUMMY_COLOR);
+        let mut pass = graph.add_render_pass("pass", DummyPass);
+        assert!(matches!(
+            pass.add_input_attachment_with_format(fixed, vk::Format::B8G8R8A8_UNORM),
+            Err(RenderGraphError::IncompatibleViewFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn image_is_not_a_buffer_input() {
+        let mut graph = RenderGraphBuilder::new();
+        let image = graph.add_image("image", DUMMY_COLOR);
+
+        let mut pass = graph.add_render_pass("pass", DummyPass);
+        assert!(matches!(
+            pass.add_buffer_input(image),
+            Err(RenderGraphError::IncompatibleResourceType {
+                expected: ResourceTypeTag::Buffer,
+                actual: ResourceTypeTag::Image,
+            })
+        ));
+    }
+
+    #[test]
+    fn unused_passes_are_culled_unless_root() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut main = graph.add_render_pass("main", DummyPass);
+        let output = main
+            .add_color_attachment("output", DUMMY_COLOR, None)
+            .unwrap();
+        let main = main.finish();
+        graph.set_final_image(output).unwrap();
+
+        let mut orphan = graph.add_render_pass("orphan", DummyPass);
+        orphan
+            .add_buffer_output("orphan buffer", DUMMY_BUFFER, None)
+            .unwrap();
+        let orphan = orphan.finish();
+
+        let mut upload = graph.add_render_pass("upload", DummyPass);
+        let data = upload
+            .add_buffer_output("data", DUMMY_BUFFER, None)
+            .unwrap();
+        let upload = upload.finish();
+
+        let mut readback = graph.add_render_pass("readback", DummyPass);
+        readback.add_buffer_input(data).unwrap();
+        readback.mark_root();
+        let readback = readback.finish();
+
+        let culled = graph.culled_passes().unwrap();
+        assert_eq!(culled, vec![orphan]);
+        assert!(!culled.contains(&main));
+        assert!(!culled.contains(&upload));
+        assert!(!culled.contains(&readback));
+
+        // Building culls the same passes.
+        let built = graph.build(&test_display(), &test_limits()).unwrap();
+        let mut passes = built.passes().to_vec();
+        passes.sort_by_key(|pass| pass.id);
+        assert_eq!(passes, vec![main, upload, readback]);
+    }
+
+    #[test]
+    fn split_screen_halves_tile_exactly() {
+        let extent = vk::Extent2D {
+            width: 1279,
+            height: 720,
+        };
+        let left = RelativeRect::new(0.0, 0.0, 0.5, 1.0).apply(extent);
+        let right = RelativeRect::new(0.5, 0.0, 0.5, 1.0).apply(extent);
+
+        assert_eq!(left.offset.x, 0);
+        assert_eq!(left.extent.width as i32, right.offset.x);
+        assert_eq!(right.offset.x + right.extent.width as i32, 1279);
+        assert_eq!(right.extent.height, 720);
+    }
+
+    #[test]
+    fn scissor_is_clipped_to_viewport() {
+        let extent = vk::Extent2D {
+            width: 100,
+            height: 100,
+        };
+        let viewport = PassViewport::new(RelativeRect::new(0.5, 0.5, 0.5, 0.5))
+            .scissor(RelativeRect::new(0.0, 0.0, 0.75, 0.75));
+
+        let (area, scissor) = viewport.render_area(extent);
+        assert_eq!((area.offset.x, area.offset.y), (50, 50));
+        assert_eq!((scissor.offset.x, scissor.offset.y), (50, 50));
+        assert_eq!((scissor.extent.width, scissor.extent.height), (25, 25));
+
+        let vp = viewport.viewport(extent);
+        assert_eq!((vp.x, vp.width, vp.max_depth), (50.0, 50.0, 1.0));
+    }
+
+    #[test]
+    fn viewport_outside_attachments_is_rejected() {
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut pass = graph.add_render_pass("inset", DummyPass);
+        pass.add_color_attachment("color", DUMMY_COLOR, None)
+            .unwrap();
+        pass.set_viewport(PassViewport::new(RelativeRect::new(0.75, 0.0, 0.5, 1.0)));
+        let pass = pass.finish();
+
+        assert_eq!(graph.pass_viewport(pass).unwrap().rect.x, 0.75);
+        assert!(matches!(
+            graph.check_viewports(),
+            Err(RenderGraphError::InvalidViewport { .. })
+        ));
+    }
+
+    #[test]
+    fn compute_output_is_synchronized_with_reader() {
+        use vk::{AccessFlags as A, ImageLayout as L, PipelineStageFlags as S};
+
+        let mut graph = RenderGraphBuilder::new();
+
+        let mut color = graph.add_render_pass("color", DummyPass);
+        let scene = color
+            .add_color_attachment("scene", DUMMY_COLOR, None)
+            .unwrap();
+        let color = color.finish();
+
+        let mut compute = graph.add_compute_pass("tonemap", DummyCompute);
+        let tonemapped = compute
+            .add_storage_image("tonemapped", DUMMY_COLOR, Some(scene))
+            .unwrap();
+        let compute = compute.finish();
+
+        let mut reader = graph.add_render_pass("reader", DummyPass);
+        reader.add_sampled_image(tonemapped).unwrap();
+        let reader = reader.finish();
+
+        let res = graph.resource(tonemapped).unwrap();
+        assert_eq!(res.produced_by, Some(compute));
+        assert_eq!(res.read_by.as_slice(), &[reader]);
+
+        let barrier = graph
+            .dependency_barrier(color, compute, DependencyType::Produce(scene))
+            .unwrap();
+        assert_eq!(barrier.src_stages, S::COLOR_ATTACHMENT_OUTPUT);
+        assert_eq!(barrier.src_access, A::COLOR_ATTACHMENT_WRITE);
+        assert_eq!(barrier.dst_stages, S::COMPUTE_SHADER);
+        assert_eq!(barrier.dst_access, A::SHADER_READ | A::SHADER_WRITE);
+        assert_eq!(barrier.new_layout, L::GENERAL);
+
+        let barrier = graph
+            .dependency_barrier(compute, reader, DependencyType::Produce(tonemapped))
+            .unwrap();
+        assert_eq!(barrier.src_stages, S::COMPUTE_SHADER);
+        assert_eq!(barrier.src_access, A::SHADER_WRITE);
+        assert_eq!(barrier.dst_stages, S::FRAGMENT_SHADER);
+        assert_eq!(barrier.dst_access, A::SHADER_READ);
+        assert_eq!(
+            (barrier.old_layout, barrier.new_layout),
+            (L::GENERAL, L::SHADER_READ_ONLY_OPTIMAL)
+        );
+    }
+
+    #[test]
+    fn consume_dependency_is_execution_only() {
+        let mut graph = RenderGraphBuilder::new();
+        let buffer = graph.add_buffer("particles", DUMMY_BUFFER);
+
+        let mut draw = graph.add_render_pass("draw", DummyPass);
+        draw.add_buffer_input(buffer).unwrap();
+        let draw = draw.finish();
+
+        let mut simulate = graph.add_compute_pass("simulate", DummyCompute);
+        simulate
+            .add_buffer_output("particles'", DUMMY_BUFFER, Some(buffer))
+            .unwrap();
+        let simulate = simulate.finish();
+
+        let barrier = graph
+            .dependency_barrier(draw, simulate, DependencyType::Consume(buffer))
+            .unwrap();
+        assert_eq!(
+            barrier.src_stages,
+            vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER
+        );
+        assert_eq!(barrier.dst_stages, vk::PipelineStageFlags::COMPUTE_SHADER);
+        assert!(barrier.src_access.is_empty());
+        assert!(barrier.dst_access.is_empty());
+    }
 }
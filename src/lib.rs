@@ -3,35 +3,100 @@
 #![feature(int_log)]
 #![feature(once_cell)]
 
+mod buffer;
+mod clear;
+mod compile;
+mod copy;
 mod debug_utils;
+mod descriptor;
 mod display;
+mod error;
+mod format;
 mod frame;
 pub mod graph;
+mod limits;
 mod mem;
+mod mip;
 pub mod pass;
+mod profiler;
+mod query;
+mod readback;
+#[cfg(feature = "renderdoc")]
+mod renderdoc;
+mod sampler;
 mod shader;
-mod util;
+pub mod util;
 pub mod vks;
 
 use std::{
     cell::{Ref, RefCell, RefMut},
     ffi::{CStr, CString},
+    fmt,
+    ops::Range,
     sync::Arc,
 };
 
 use arrayvec::ArrayVec;
-use erupt::vk;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use erupt::{vk, ExtendableFromConst};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use raw_window_handle::RawWindowHandle;
 use thread_local::ThreadLocal;
 
-pub use debug_utils::DebugMessenger;
-pub use display::Display;
-pub use mem::{MemoryConfig, MemoryTypes};
+use crate::{descriptor::DescriptorSetLayoutCache, vks::VkObject};
+
+pub use buffer::{
+    Buffer, BufferCreateInfo, FrameArrayBuffer, IndexElement, MemoryLocation, StreamAllocation,
+    StreamBuffer, TypedBuffer,
+};
+pub use clear::{
+    clear_color_image, clear_depth_stencil_image, fill_buffer, update_buffer, AccessScope,
+};
+pub use compile::{PendingPipeline, PipelineSlot};
+pub use copy::{
+    blit_image, copy_buffer_to_image, copy_image, copy_image_to_buffer, BufferToImageCopyInfo,
+    ImageBlitInfo, ImageCopyInfo, ImageToBufferCopyInfo,
+};
+pub use debug_utils::{DebugCallback, DebugMessage, DebugMessenger, DebugMessengerConfig};
+pub use descriptor::{
+    BindlessIndex, BindlessTable, DescriptorAllocator, DescriptorArena, DescriptorBinding,
+    DescriptorSetLayoutInfo, BINDLESS_BUFFER_BINDING, BINDLESS_IMAGE_BINDING,
+};
+pub use display::{
+    Capture, DirectDisplay, DirectDisplayMode, Display, DisplayConfig, HdrMetadata, OutputEncoding,
+    PresentModePreference, SurfaceFormatPreference,
+};
+pub use error::Error;
+pub use format::FormatExt;
+pub use limits::{DeviceLimits, LimitExceeded};
+pub use mem::{
+    MemoryBudget, MemoryConfig, MemoryHeapReport, MemoryReport, MemoryTypeReport, MemoryTypes,
+};
+pub use mip::{generate_mips, mip_level_count, mip_level_count_3d, MipChainInfo};
+pub use profiler::GpuProfiler;
+pub use query::{QueryKind, QueryPool};
+pub use readback::{ReadbackId, ReadbackPool};
+#[cfg(feature = "renderdoc")]
+pub use renderdoc::RenderDoc;
+pub use sampler::{SamplerError, SamplerInfo};
 
 const LAYER_NAME_VALIDATION: &[u8] = b"VK_LAYER_KHRONOS_validation\0";
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Tears down `device`, the debug messenger and the instance, in that order.
+///
+/// Each object is destroyed only if no other handles to it remain; objects
+/// still holding a reference are reported in the returned error. Once
+/// shutdown succeeds, a new instance may be created to resume rendering.
+pub fn shutdown(device: Device, messenger: Option<DebugMessenger>) -> Result<(), Error> {
+    let instance = device.read_inner().instance.clone();
+
+    let device_result = device.destroy();
+    drop(messenger);
+    let instance_result = instance.destroy();
+
+    device_result.and(instance_result)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ApiVersion {
     inner: u32,
 }
@@ -79,6 +144,36 @@ fn i8_slice_to_cstr(slice: &[i8]) -> Option<&CStr> {
     }
 }
 
+/// Creates a `VK_EXT_metal_surface` surface backed by `layer`.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `instance` must have been created with `VK_EXT_metal_surface` enabled.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+unsafe fn create_metal_surface(
+    instance: &vks::Instance,
+    layer: raw_window_metal::Layer,
+) -> vks::SurfaceKHR {
+    use raw_window_metal::Layer;
+
+    let layer = match layer {
+        Layer::Existing(layer) | Layer::Allocated(layer) => layer as *const vk::CAMetalLayer,
+        Layer::None => panic!("window has no view to attach a CAMetalLayer to"),
+    };
+
+    // Safety: the layer pointer is non-null for both `Existing` and `Allocated`.
+    let create_info = vk::MetalSurfaceCreateInfoEXTBuilder::new()
+        .flags(vk::MetalSurfaceCreateFlagsEXT::empty())
+        .layer(unsafe { &*layer });
+
+    unsafe {
+        instance
+            .create_metal_surface_ext(&create_info)
+            .expect("failed to create Metal window surface")
+    }
+}
+
 /// A type representing functionality which may be either an extension or part
 /// of the core Vulkan API, depending on version.
 pub enum ExtensionFn<T> {
@@ -86,15 +181,206 @@ pub enum ExtensionFn<T> {
     Extension(T),
 }
 
+/// A user-installed handler for device memory leak reports.
+///
+/// Called with the memory report of a device which is destroyed while
+/// allocations made through it are still live.
+pub type LeakCallback = Arc<dyn Fn(&MemoryReport) + Send + Sync>;
+
+/// Parameters for creating a [`Device`].
+#[derive(Clone, Default)]
+pub struct DeviceDescriptor {
+    /// Device extensions to enable in addition to those used by the crate.
+    /// Unsupported extensions are skipped with a warning.
+    pub extra_extensions: Vec<CString>,
+    /// Features to enable in addition to those used by the crate.
+    /// Unsupported features are skipped with a warning.
+    pub extra_features: vk::PhysicalDeviceFeatures,
+    /// Whether to enable the descriptor indexing features needed by
+    /// [`BindlessTable`]. Skipped with a warning if unsupported.
+    pub bindless: bool,
+    /// Called with a report of leaked allocations when the device is
+    /// destroyed. If `None`, leaks are written to the `log` crate.
+    pub leak_callback: Option<LeakCallback>,
+}
+
+impl fmt::Debug for DeviceDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceDescriptor")
+            .field("extra_extensions", &self.extra_extensions)
+            .field("extra_features", &self.extra_features)
+            .field("bindless", &self.bindless)
+            .field("leak_callback", &self.leak_callback.is_some())
+            .finish()
+    }
+}
+
+/// Returns the descriptor indexing features required by [`BindlessTable`].
+fn bindless_features() -> vk::PhysicalDeviceDescriptorIndexingFeatures {
+    vk::PhysicalDeviceDescriptorIndexingFeatures {
+        shader_sampled_image_array_non_uniform_indexing: vk::TRUE,
+        shader_storage_buffer_array_non_uniform_indexing: vk::TRUE,
+        descriptor_binding_sampled_image_update_after_bind: vk::TRUE,
+        descriptor_binding_storage_buffer_update_after_bind: vk::TRUE,
+        descriptor_binding_update_unused_while_pending: vk::TRUE,
+        descriptor_binding_partially_bound: vk::TRUE,
+        runtime_descriptor_array: vk::TRUE,
+        ..Default::default()
+    }
+}
+
+/// Returns the name of the first feature in [`bindless_features`] which
+/// `supported` lacks, or `None` if all are supported.
+fn missing_bindless_feature(
+    supported: &vk::PhysicalDeviceDescriptorIndexingFeatures,
+) -> Option<&'static str> {
+    let required = bindless_features();
+    [
+        (
+            "shaderSampledImageArrayNonUniformIndexing",
+            required.shader_sampled_image_array_non_uniform_indexing,
+            supported.shader_sampled_image_array_non_uniform_indexing,
+        ),
+        (
+            "shaderStorageBufferArrayNonUniformIndexing",
+            required.shader_storage_buffer_array_non_uniform_indexing,
+            supported.shader_storage_buffer_array_non_uniform_indexing,
+        ),
+        (
+            "descriptorBindingSampledImageUpdateAfterBind",
+            required.descriptor_binding_sampled_image_update_after_bind,
+            supported.descriptor_binding_sampled_image_update_after_bind,
+        ),
+        (
+            "descriptorBindingStorageBufferUpdateAfterBind",
+            required.descriptor_binding_storage_buffer_update_after_bind,
+            supported.descriptor_binding_storage_buffer_update_after_bind,
+        ),
+        (
+            "descriptorBindingUpdateUnusedWhilePending",
+            required.descriptor_binding_update_unused_while_pending,
+            supported.descriptor_binding_update_unused_while_pending,
+        ),
+        (
+            "descriptorBindingPartiallyBound",
+            required.descriptor_binding_partially_bound,
+            supported.descriptor_binding_partially_bound,
+        ),
+        (
+            "runtimeDescriptorArray",
+            required.runtime_descriptor_array,
+            supported.runtime_descriptor_array,
+        ),
+    ]
+    .iter()
+    .find(|&&(_, req, sup)| req != vk::FALSE && sup == vk::FALSE)
+    .map(|&(name, _, _)| name)
+}
+
+/// Views the flags of `features` as a slice.
+fn feature_flags(features: &vk::PhysicalDeviceFeatures) -> &[vk::Bool32] {
+    let len = std::mem::size_of::<vk::PhysicalDeviceFeatures>() / std::mem::size_of::<vk::Bool32>();
+
+    // Safety: `PhysicalDeviceFeatures` is a `repr(C)` struct of `Bool32`s.
+    unsafe { std::slice::from_raw_parts(features as *const _ as *const vk::Bool32, len) }
+}
+
+/// Views the flags of `features` as a mutable slice.
+fn feature_flags_mut(features: &mut vk::PhysicalDeviceFeatures) -> &mut [vk::Bool32] {
+    let len = std::mem::size_of::<vk::PhysicalDeviceFeatures>() / std::mem::size_of::<vk::Bool32>();
+
+    // Safety: `PhysicalDeviceFeatures` is a `repr(C)` struct of `Bool32`s.
+    unsafe { std::slice::from_raw_parts_mut(features as *mut _ as *mut vk::Bool32, len) }
+}
+
+/// Selects which optional instance functionality is enabled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InstanceProfile {
+    /// Enables the surface and display extensions needed to present to a
+    /// window.
+    Presentation,
+    /// Skips all window system integration extensions.
+    ///
+    /// Instances created with this profile cannot create surfaces, and
+    /// physical devices must be enumerated without a surface. This allows the
+    /// crate to be used for offscreen and compute workloads on systems
+    /// without a windowing system.
+    ComputeOnly,
+}
+
+/// Parameters for creating an [`Instance`].
+#[derive(Clone, Debug)]
+pub struct InstanceDescriptor {
+    pub app_name: String,
+    pub app_version: u32,
+    pub engine_name: String,
+    pub engine_version: u32,
+    /// The highest Vulkan version to use.
+    ///
+    /// The version is further limited by the driver and by the newest
+    /// version supported by this crate.
+    pub api_version: ApiVersion,
+    pub profile: InstanceProfile,
+    /// Instance extensions to enable in addition to those required by the
+    /// profile. Unsupported extensions are skipped with a warning.
+    pub extra_extensions: Vec<CString>,
+    /// Whether to enable the Khronos validation layer, if it is installed.
+    ///
+    /// Enabled by default in debug builds.
+    pub validation: bool,
+    /// The message severities reported by debug messengers created for the
+    /// instance.
+    pub debug_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+}
+
+impl InstanceDescriptor {
+    pub fn new<S>(app_name: S, app_version: u32) -> InstanceDescriptor
+    where
+        S: Into<String>,
+    {
+        InstanceDescriptor {
+            app_name: app_name.into(),
+            app_version,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for InstanceDescriptor {
+    fn default() -> Self {
+        InstanceDescriptor {
+            app_name: String::new(),
+            app_version: 0,
+            engine_name: "reify".into(),
+            engine_version: 1,
+            api_version: ApiVersion::V1_2_0,
+            profile: InstanceProfile::Presentation,
+            extra_extensions: Vec::new(),
+            validation: cfg!(debug_assertions),
+            debug_severity: vk::DebugUtilsMessageSeverityFlagsEXT::all()
+                - vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE_EXT,
+        }
+    }
+}
+
 pub(crate) struct InstanceInner {
     // Underlying instance. Destroys the instance when dropped.
     handle: vks::Instance,
+    // The API version the instance was created with.
+    api_version: ApiVersion,
+    profile: InstanceProfile,
+    validation: bool,
+    debug_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
 }
 
 impl InstanceInner {
     pub fn handle(&self) -> &vks::Instance {
         &self.handle
     }
+
+    pub fn debug_severity(&self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        self.debug_severity
+    }
 }
 
 #[derive(Clone)]
@@ -103,8 +389,13 @@ pub struct Instance {
 }
 
 impl Instance {
-    /// Lists the set of required extensions for the current platform.
-    fn required_extensions(api_version: ApiVersion) -> Vec<&'static CStr> {
+    /// Lists the supported extensions among those required for the current
+    /// platform and `extra`.
+    fn required_extensions(
+        api_version: ApiVersion,
+        profile: InstanceProfile,
+        extra: &[CString],
+    ) -> Vec<&CStr> {
         let entry = vks::entry();
         let instance_extensions =
             unsafe { entry.enumerate_instance_extension_properties(None, None) }
@@ -112,32 +403,45 @@ impl Instance {
 
         let mut extensions = Vec::new();
 
-        extensions.push(vk::KHR_SURFACE_EXTENSION_NAME);
-        if cfg!(all(
-            unix,
-            not(target_os = "android"),
-            not(target_os = "macos")
-        )) {
-            extensions.push(vk::KHR_WAYLAND_SURFACE_EXTENSION_NAME);
-            extensions.push(vk::KHR_XCB_SURFACE_EXTENSION_NAME);
-            extensions.push(vk::KHR_XLIB_SURFACE_EXTENSION_NAME);
-        } else {
-            unimplemented!("only tested on linux at the moment, sorry :(");
+        if profile == InstanceProfile::Presentation {
+            extensions.push(vk::KHR_SURFACE_EXTENSION_NAME);
+            if cfg!(any(target_os = "macos", target_os = "ios")) {
+                extensions.push(vk::EXT_METAL_SURFACE_EXTENSION_NAME);
+            } else if cfg!(target_os = "android") {
+                extensions.push(vk::KHR_ANDROID_SURFACE_EXTENSION_NAME);
+            } else if cfg!(all(unix, not(target_os = "android"))) {
+                extensions.push(vk::KHR_WAYLAND_SURFACE_EXTENSION_NAME);
+                extensions.push(vk::KHR_XCB_SURFACE_EXTENSION_NAME);
+                extensions.push(vk::KHR_XLIB_SURFACE_EXTENSION_NAME);
+            } else if cfg!(windows) {
+                extensions.push(vk::KHR_WIN32_SURFACE_EXTENSION_NAME);
+            }
         }
 
         extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION_NAME);
-        extensions.push(vk::EXT_DISPLAY_SURFACE_COUNTER_EXTENSION_NAME);
-        extensions.push(vk::KHR_DISPLAY_EXTENSION_NAME);
+
+        if profile == InstanceProfile::Presentation {
+            extensions.push(vk::EXT_DISPLAY_SURFACE_COUNTER_EXTENSION_NAME);
+            extensions.push(vk::KHR_DISPLAY_EXTENSION_NAME);
+            // Required for HDR swapchain color spaces.
+            extensions.push(vk::EXT_SWAPCHAIN_COLOR_SPACE_EXTENSION_NAME);
+        }
 
         if api_version < ApiVersion::V1_1_0 {
             extensions.push(vk::KHR_GET_PHYSICAL_DEVICE_PROPERTIES_2_EXTENSION_NAME);
             extensions.push(vk::KHR_EXTERNAL_MEMORY_CAPABILITIES_EXTENSION_NAME);
         }
 
-        let mut extension_cstrs = extensions
+        let mut extension_cstrs = Vec::<&CStr>::new();
+        let requested = extensions
             .into_iter()
             .map(|ptr| unsafe { CStr::from_ptr(ptr) })
-            .collect::<Vec<_>>();
+            .chain(extra.iter().map(CString::as_c_str));
+        for ext in requested {
+            if !extension_cstrs.contains(&ext) {
+                extension_cstrs.push(ext);
+            }
+        }
 
         extension_cstrs.retain(|&wanted| {
             for inst_ext in instance_extensions.iter() {
@@ -157,13 +461,13 @@ impl Instance {
     }
 
     /// Lists the set of required layers.
-    fn required_layers() -> Vec<&'static CStr> {
+    fn required_layers(validation: bool) -> Vec<&'static CStr> {
         let entry = vks::entry();
         let instance_layers = unsafe { entry.enumerate_instance_layer_properties(None) }
             .expect("failed to enumerate instance layer properties");
 
         let mut layers = Vec::new();
-        if cfg!(debug_assertions) {
+        if validation {
             layers.push(CStr::from_bytes_with_nul(LAYER_NAME_VALIDATION).unwrap());
         }
 
@@ -184,27 +488,55 @@ impl Instance {
         layers
     }
 
+    /// Creates an instance with the `Presentation` profile.
     pub fn create<S>(app_name: S, app_version: u32) -> Instance
     where
         S: AsRef<str>,
     {
+        Self::create_with_profile(app_name, app_version, InstanceProfile::Presentation)
+    }
+
+    pub fn create_with_profile<S>(
+        app_name: S,
+        app_version: u32,
+        profile: InstanceProfile,
+    ) -> Instance
+    where
+        S: AsRef<str>,
+    {
+        Self::create_with_descriptor(&InstanceDescriptor {
+            profile,
+            ..InstanceDescriptor::new(app_name.as_ref(), app_version)
+        })
+    }
+
+    /// Creates an instance with the given parameters.
+    pub fn create_with_descriptor(descriptor: &InstanceDescriptor) -> Instance {
         let entry = vks::entry();
 
         let driver_api_version = match unsafe { entry.enumerate_instance_version() }.result() {
             Ok(version) => ApiVersion::from_u32(version),
             Err(e) => panic!("failed to query instance version: {}", e),
         };
+        let api_version = driver_api_version
+            .min(descriptor.api_version)
+            .min(ApiVersion::V1_2_0);
 
-        let app_name = CString::new(app_name.as_ref()).unwrap();
+        let app_name = CString::new(descriptor.app_name.as_str()).unwrap();
+        let engine_name = CString::new(descriptor.engine_name.as_str()).unwrap();
         let app_info = vk::ApplicationInfoBuilder::new()
             .application_name(app_name.as_c_str())
-            .application_version(app_version)
-            .engine_name(CStr::from_bytes_with_nul(b"reify\0").unwrap())
-            .engine_version(1)
-            .api_version({ std::cmp::min(driver_api_version, ApiVersion::V1_2_0) }.as_u32());
-
-        let extensions = Self::required_extensions(driver_api_version);
-        let layers = Self::required_layers();
+            .application_version(descriptor.app_version)
+            .engine_name(engine_name.as_c_str())
+            .engine_version(descriptor.engine_version)
+            .api_version(api_version.as_u32());
+
+        let extensions = Self::required_extensions(
+            api_version,
+            descriptor.profile,
+            &descriptor.extra_extensions,
+        );
+        let layers = Self::required_layers(descriptor.validation);
 
         let instance_handle = {
             let ext_ptrs = extensions.iter().map(|&s| s.as_ptr()).collect::<Vec<_>>();
@@ -227,6 +559,10 @@ impl Instance {
         Instance {
             inner: Arc::new(RwLock::new(InstanceInner {
                 handle: instance_handle,
+                api_version,
+                profile: descriptor.profile,
+                validation: descriptor.validation,
+                debug_severity: descriptor.debug_severity,
             })),
         }
     }
@@ -235,6 +571,31 @@ impl Instance {
         self.inner.read()
     }
 
+    /// Destroys the instance.
+    ///
+    /// Physical devices, devices, surfaces and debug messengers hold a
+    /// reference to their instance, and must be dropped first. If any remain,
+    /// the instance is destroyed once they are dropped and
+    /// [`Error::StillInUse`] is returned.
+    pub fn destroy(self) -> Result<(), Error> {
+        let references = Arc::strong_count(&self.inner) - 1;
+        drop(self);
+
+        if references > 0 {
+            log::error!(
+                "Instance destruction deferred: {} references remain.",
+                references
+            );
+            return Err(Error::StillInUse {
+                object: "instance",
+                references,
+            });
+        }
+
+        log::info!("Destroyed instance.");
+        Ok(())
+    }
+
     /// Initializes a debug messenger for this instance.
     ///
     /// # Safety
@@ -246,17 +607,42 @@ impl Instance {
         DebugMessenger::new(self.clone())
     }
 
+    /// Initializes a debug messenger for this instance which reports
+    /// messages as configured.
+    pub fn create_debug_messenger_with_config(
+        &self,
+        config: DebugMessengerConfig,
+    ) -> DebugMessenger {
+        DebugMessenger::with_config(self.clone(), config)
+    }
+
     #[cfg(any(
         target_os = "linux",
         target_os = "dragonfly",
         target_os = "freebsd",
         target_os = "netbsd",
         target_os = "openbsd",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "android",
+        windows,
     ))]
     pub fn create_surface(&self, window: RawWindowHandle) -> vks::SurfaceKHR {
         let read_lock = self.inner.read();
 
+        assert_eq!(
+            read_lock.profile,
+            InstanceProfile::Presentation,
+            "cannot create a surface with a compute-only instance"
+        );
+
         let surface = match window {
+            #[cfg(not(any(
+                windows,
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "android"
+            )))]
             RawWindowHandle::Xlib(xlib) => {
                 let create_info = vk::XlibSurfaceCreateInfoKHRBuilder::new()
                     .flags(vk::XlibSurfaceCreateFlagsKHR::empty())
@@ -270,6 +656,12 @@ impl Instance {
                         .expect("failed to create Xlib window surface")
                 }
             }
+            #[cfg(not(any(
+                windows,
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "android"
+            )))]
             RawWindowHandle::Xcb(xcb) => {
                 let create_info = vk::XcbSurfaceCreateInfoKHRBuilder::new()
                     .flags(vk::XcbSurfaceCreateFlagsKHR::empty())
@@ -283,7 +675,55 @@ impl Instance {
                         .expect("failed to create XCB window surface")
                 }
             }
+            #[cfg(not(any(
+                windows,
+                target_os = "macos",
+                target_os = "ios",
+                target_os = "android"
+            )))]
             RawWindowHandle::Wayland(_) => todo!(),
+            #[cfg(windows)]
+            RawWindowHandle::Windows(win) => {
+                let create_info = vk::Win32SurfaceCreateInfoKHRBuilder::new()
+                    .flags(vk::Win32SurfaceCreateFlagsKHR::empty())
+                    .hinstance(win.hinstance)
+                    .hwnd(win.hwnd);
+
+                unsafe {
+                    read_lock
+                        .handle
+                        .create_win32_surface_khr(&create_info)
+                        .expect("failed to create Win32 window surface")
+                }
+            }
+            #[cfg(target_os = "macos")]
+            RawWindowHandle::MacOS(macos) => {
+                let layer = raw_window_metal::macos::metal_layer_from_handle(macos);
+                unsafe { create_metal_surface(&read_lock.handle, layer) }
+            }
+            #[cfg(target_os = "ios")]
+            RawWindowHandle::IOS(ios) => {
+                let layer = raw_window_metal::ios::metal_layer_from_handle(ios);
+                unsafe { create_metal_surface(&read_lock.handle, layer) }
+            }
+            #[cfg(target_os = "android")]
+            RawWindowHandle::Android(android) => {
+                assert!(
+                    !android.a_native_window.is_null(),
+                    "window has no ANativeWindow; it may not have been resumed yet"
+                );
+
+                let create_info = vk::AndroidSurfaceCreateInfoKHRBuilder::new()
+                    .flags(vk::AndroidSurfaceCreateFlagsKHR::empty())
+                    .window(unsafe { &mut *android.a_native_window });
+
+                unsafe {
+                    read_lock
+                        .handle
+                        .create_android_surface_khr(&create_info)
+                        .expect("failed to create Android window surface")
+                }
+            }
             _ => panic!("unrecognized window handle"),
         };
 
@@ -296,14 +736,26 @@ impl Instance {
         target_os = "freebsd",
         target_os = "netbsd",
         target_os = "openbsd",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "android",
+        windows,
     )))]
     pub fn create_surface(&self, window: RawWindowHandle) -> Surface {
-        compile_error!("Unsupported platform (only linux is supported).");
+        compile_error!(
+            "Unsupported platform (only linux, windows, android and apple are supported)."
+        );
     }
 
+    /// Enumerates the physical devices available to this instance.
+    ///
+    /// If `surface` is `None`, no presentation queue is selected, and devices
+    /// created from the returned physical devices cannot present. Otherwise,
+    /// devices which cannot present to `surface` are skipped, as are devices
+    /// without graphics or transfer queues.
     pub fn enumerate_physical_devices(
         &self,
-        surface: &vks::SurfaceKHR,
+        surface: Option<&vks::SurfaceKHR>,
         memory_config: MemoryConfig,
     ) -> Vec<PhysicalDevice> {
         let read_lock = self.inner.read();
@@ -320,24 +772,44 @@ impl Instance {
 
         devices
             .into_iter()
-            .map(|raw| {
+            .filter_map(|raw| {
                 // Safety: Physical device handle comes from the correct instance.
                 unsafe { PhysicalDevice::new(self.clone(), raw, surface, memory_config) }
             })
             .collect()
     }
+
+    /// Enumerates the physical devices available to this instance and returns
+    /// the one preferred by `selector`.
+    ///
+    /// Returns `None` if no device satisfies the selector's requirements.
+    pub fn select_physical_device(
+        &self,
+        surface: Option<&vks::SurfaceKHR>,
+        memory_config: MemoryConfig,
+        selector: &DeviceSelector,
+    ) -> Option<PhysicalDevice> {
+        let selected = selector.select(self.enumerate_physical_devices(surface, memory_config));
+
+        match &selected {
+            Some(phys) => log::info!("Selected physical device {:?}", phys.name()),
+            None => log::error!("No physical device satisfies the selector."),
+        }
+
+        selected
+    }
 }
 
 pub struct PhysicalDeviceInner {
     instance: Instance,
     raw: vks::PhysicalDevice,
 
-    // TODO: allow querying properties
-    _queue_families: Vec<vk::QueueFamilyProperties>,
+    queue_families: Vec<vk::QueueFamilyProperties>,
     graphics_queue_family: u32,
     transfer_queue_family: u32,
-    present_queue_family: u32,
+    present_queue_family: Option<u32>,
 
+    extensions: Vec<vk::ExtensionProperties>,
     memory_types: MemoryTypes,
 }
 
@@ -358,6 +830,150 @@ pub struct PhysicalDevice {
     inner: Arc<PhysicalDeviceInner>,
 }
 
+/// A user-supplied predicate on physical devices.
+type DeviceFilter = Box<dyn Fn(&PhysicalDevice) -> bool>;
+
+/// The properties of a physical device which a [`DeviceSelector`] checks.
+trait DeviceCandidate {
+    fn name(&self) -> String;
+    fn device_type(&self) -> vk::PhysicalDeviceType;
+    fn supports_presentation(&self) -> bool;
+    fn supports_extension(&self, name: &CStr) -> bool;
+}
+
+impl DeviceCandidate for PhysicalDevice {
+    fn name(&self) -> String {
+        PhysicalDevice::name(self)
+    }
+
+    fn device_type(&self) -> vk::PhysicalDeviceType {
+        self.properties().device_type
+    }
+
+    fn supports_presentation(&self) -> bool {
+        PhysicalDevice::supports_presentation(self)
+    }
+
+    fn supports_extension(&self, name: &CStr) -> bool {
+        PhysicalDevice::supports_extension(self, name)
+    }
+}
+
+/// A strategy for choosing among the available physical devices.
+///
+/// Devices which do not meet every requirement are rejected. Among the
+/// remaining devices, the preferred device type wins, and ties are broken by
+/// enumeration order.
+pub struct DeviceSelector {
+    prefer_discrete: bool,
+    name: Option<String>,
+    require_presentation: bool,
+    required_extensions: Vec<CString>,
+    filters: Vec<DeviceFilter>,
+}
+
+impl Default for DeviceSelector {
+    fn default() -> Self {
+        DeviceSelector {
+            prefer_discrete: true,
+            name: None,
+            require_presentation: false,
+            required_extensions: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl DeviceSelector {
+    pub fn new() -> DeviceSelector {
+        DeviceSelector::default()
+    }
+
+    /// Sets whether discrete GPUs are preferred over integrated GPUs.
+    ///
+    /// If `false`, integrated GPUs are preferred instead, which typically
+    /// saves power on hybrid systems. Defaults to `true`.
+    pub fn prefer_discrete(mut self, prefer_discrete: bool) -> DeviceSelector {
+        self.prefer_discrete = prefer_discrete;
+        self
+    }
+
+    /// Requires the device name to contain `name`, ignoring case.
+    pub fn name(mut self, name: impl Into<String>) -> DeviceSelector {
+        self.name = Some(name.into().to_lowercase());
+        self
+    }
+
+    /// Requires that the device can present to the surface used during
+    /// enumeration.
+    pub fn require_presentation(mut self) -> DeviceSelector {
+        self.require_presentation = true;
+        let swapchain = unsafe { CStr::from_ptr(vk::KHR_SWAPCHAIN_EXTENSION_NAME) };
+        self.require_extension(swapchain)
+    }
+
+    /// Requires the device to support the named extension.
+    pub fn require_extension(mut self, name: &CStr) -> DeviceSelector {
+        self.required_extensions.push(name.to_owned());
+        self
+    }
+
+    /// Requires the device to satisfy an arbitrary predicate, such as
+    /// supporting a set of features.
+    pub fn require(mut self, filter: impl Fn(&PhysicalDevice) -> bool + 'static) -> DeviceSelector {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Returns `true` if the device meets every requirement other than the
+    /// user-supplied filters.
+    fn accepts<D: DeviceCandidate>(&self, device: &D) -> bool {
+        if let Some(name) = &self.name {
+            if !device.name().to_lowercase().contains(name.as_str()) {
+                return false;
+            }
+        }
+
+        if self.require_presentation && !device.supports_presentation() {
+            return false;
+        }
+
+        self.required_extensions
+            .iter()
+            .all(|ext| device.supports_extension(ext))
+    }
+
+    fn rank(&self, device_type: vk::PhysicalDeviceType) -> u32 {
+        match device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU if self.prefer_discrete => 0,
+            vk::PhysicalDeviceType::INTEGRATED_GPU if !self.prefer_discrete => 0,
+            vk::PhysicalDeviceType::DISCRETE_GPU | vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+            vk::PhysicalDeviceType::CPU => 3,
+            _ => 4,
+        }
+    }
+
+    /// Returns the preferred device among `devices`, if any are acceptable.
+    pub fn select(&self, devices: Vec<PhysicalDevice>) -> Option<PhysicalDevice> {
+        self.select_with(devices, |phys| {
+            self.filters.iter().all(|filter| filter(phys))
+        })
+    }
+
+    fn select_with<D: DeviceCandidate>(
+        &self,
+        devices: Vec<D>,
+        filter: impl Fn(&D) -> bool,
+    ) -> Option<D> {
+        devices
+            .into_iter()
+            .filter(|device| self.accepts(device) && filter(device))
+            // min_by_key returns the first minimum, preserving enumeration order.
+            .min_by_key(|device| self.rank(device.device_type()))
+    }
+}
+
 impl PhysicalDevice {
     /// Create a new physical device with the provided instance and raw physical
     /// device handle.
@@ -365,12 +981,14 @@ impl PhysicalDevice {
     /// # Safety
     ///
     /// The raw physical device handle must have been provided by `instance`.
+    ///
+    /// Returns `None` if the device lacks a required queue family.
     unsafe fn new(
         instance: Instance,
         phys_device: vks::PhysicalDevice,
-        surface: &vks::SurfaceKHR,
+        surface: Option<&vks::SurfaceKHR>,
         memory_config: MemoryConfig,
-    ) -> PhysicalDevice {
+    ) -> Option<PhysicalDevice> {
         let instance_read = instance.inner.read();
 
         let queue_families = unsafe {
@@ -430,16 +1048,22 @@ impl PhysicalDevice {
             // Safety:
             // - Queue family index provided by physical device.
             // - No external synchronization requirement.
-            if unsafe {
-                instance_read
-                    .handle()
-                    .get_physical_device_surface_support_khr(&phys_device, index as u32, &surface)
-                    .expect(&format!(
-                        "failed to query queue family {} for surface support",
-                        index
-                    ))
-            } {
-                present_queue = Some(index);
+            if let Some(surface) = surface {
+                if unsafe {
+                    instance_read
+                        .handle()
+                        .get_physical_device_surface_support_khr(
+                            &phys_device,
+                            index as u32,
+                            surface,
+                        )
+                        .expect(&format!(
+                            "failed to query queue family {} for surface support",
+                            index
+                        ))
+                } {
+                    present_queue = Some(index);
+                }
             }
         }
 
@@ -450,6 +1074,13 @@ impl PhysicalDevice {
 
         let memory_types = memory_properties.select_memory_types(memory_config);
 
+        let extensions = unsafe {
+            instance_read
+                .handle()
+                .enumerate_device_extension_properties(&phys_device)
+        }
+        .expect("failed to enumerate device extension properties");
+
         drop(instance_read);
 
         let graphics_queue_family = match graphics_queue {
@@ -464,7 +1095,10 @@ impl PhysicalDevice {
                 );
                 g
             }
-            None => panic!("No queue families support graphics operations."),
+            None => {
+                log::warn!("Skipping device: no queue families support graphics operations.");
+                return None;
+            }
         };
 
         let transfer_queue_family = match transfer_queue {
@@ -479,28 +1113,55 @@ impl PhysicalDevice {
                 );
                 g
             }
-            None => panic!("No queue families support transfer operations."),
+            None => {
+                log::warn!("Skipping device: no queue families support transfer operations.");
+                return None;
+            }
         };
 
-        let present_queue_family = match present_queue {
-            Some(p) => {
+        let present_queue_family = match (surface, present_queue) {
+            (Some(_), Some(p)) => {
                 log::info!("Using queue family {} for presentation", p);
-                p as u32
+                Some(p as u32)
+            }
+            (Some(_), None) => {
+                log::warn!(
+                    "Skipping device: no queue families support presenting to the window surface."
+                );
+                return None;
+            }
+            (None, _) => {
+                log::info!("No surface provided; presentation is disabled");
+                None
             }
-            None => panic!("No queue families support presenting to the window surface"),
         };
 
-        PhysicalDevice {
+        Some(PhysicalDevice {
             inner: Arc::new(PhysicalDeviceInner {
                 instance,
                 raw: phys_device,
-                _queue_families: queue_families,
+                queue_families,
                 graphics_queue_family,
                 transfer_queue_family,
                 present_queue_family,
+                extensions,
                 memory_types,
             }),
-        }
+        })
+    }
+
+    /// Returns the name of the device as reported by the driver.
+    pub fn name(&self) -> String {
+        let properties = self.properties();
+        i8_slice_to_cstr(&properties.device_name)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether devices created from this physical device can present
+    /// to the surface it was enumerated with.
+    pub fn supports_presentation(&self) -> bool {
+        self.inner.present_queue_family.is_some()
     }
 
     pub fn properties(&self) -> vk::PhysicalDeviceProperties {
@@ -514,6 +1175,46 @@ impl PhysicalDevice {
         }
     }
 
+    /// Returns the API version usable with this device, which is the lower of
+    /// the versions supported by the device and the instance.
+    pub fn api_version(&self) -> ApiVersion {
+        let device_version = ApiVersion::from_u32(self.properties().api_version);
+        device_version.min(self.inner.instance.read_inner().api_version)
+    }
+
+    /// Returns the device limits most relevant to user-supplied parameters.
+    pub fn limits(&self) -> DeviceLimits {
+        let limits = DeviceLimits::from(&self.properties().limits);
+
+        let maintenance3 = unsafe { CStr::from_ptr(vk::KHR_MAINTENANCE3_EXTENSION_NAME) };
+        if self.api_version() < ApiVersion::V1_1_0 && !self.supports_extension(maintenance3) {
+            return limits;
+        }
+
+        // Safety: the device supports Vulkan 1.1 or VK_KHR_maintenance3.
+        let properties = unsafe {
+            self.inner
+                .instance
+                .read_inner()
+                .handle
+                .get_physical_device_maintenance3_properties(&self.inner.raw)
+        };
+        match properties {
+            Some(properties) => limits.with_maintenance3(&properties),
+            None => limits,
+        }
+    }
+
+    /// Returns the timestamp period of the graphics queue, or `None` if the
+    /// graphics queue does not support timestamps.
+    pub fn graphics_timestamp_period(&self) -> Option<util::TimestampPeriod> {
+        let family = &self.inner.queue_families[self.inner.graphics_queue_family as usize];
+        util::TimestampPeriod::new(
+            self.properties().limits.timestamp_period,
+            family.timestamp_valid_bits,
+        )
+    }
+
     pub fn features(&self) -> vk::PhysicalDeviceFeatures {
         // Safety: No external synchronization requirement.
         unsafe {
@@ -525,11 +1226,69 @@ impl PhysicalDevice {
         }
     }
 
+    pub fn format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        // Safety: No external synchronization requirement.
+        unsafe {
+            self.inner
+                .instance
+                .read_inner()
+                .handle
+                .get_physical_device_format_properties(&self.inner.raw, format)
+        }
+    }
+
+    /// Checks that images of the given formats with optimal tiling can be
+    /// blitted from `src` to `dst` with `filter`.
+    ///
+    /// Returns [`Error::BlitUnsupported`] naming the first format which lacks
+    /// a required feature.
+    pub fn check_blit_support(
+        &self,
+        src: vk::Format,
+        dst: vk::Format,
+        filter: vk::Filter,
+    ) -> Result<(), Error> {
+        let (src_missing, dst_missing) = copy::missing_blit_features(
+            self.format_properties(src).optimal_tiling_features,
+            self.format_properties(dst).optimal_tiling_features,
+            filter,
+        );
+
+        if !src_missing.is_empty() {
+            Err(Error::BlitUnsupported {
+                format: src,
+                missing: src_missing,
+            })
+        } else if !dst_missing.is_empty() {
+            Err(Error::BlitUnsupported {
+                format: dst,
+                missing: dst_missing,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn memory_types(&self) -> mem::MemoryTypes {
         self.inner.memory_types
     }
 
+    /// Returns whether the physical device supports the named device
+    /// extension.
+    pub fn supports_extension(&self, name: &CStr) -> bool {
+        self.inner.extensions.iter().any(|ext| {
+            i8_slice_to_cstr(&ext.extension_name)
+                .map(|ext_name| ext_name == name)
+                .unwrap_or(false)
+        })
+    }
+
     pub fn create_device(&self) -> Device {
+        self.create_device_with_descriptor(&DeviceDescriptor::default())
+    }
+
+    /// Creates a logical device with the given parameters.
+    pub fn create_device_with_descriptor(&self, descriptor: &DeviceDescriptor) -> Device {
         let mut unique_queue_families = UniqueQueueFamilies::default();
         let graphics = unique_queue_families
             .get_or_insert(self.inner.graphics_queue_family, SINGLE_QUEUE_PRIORITY)
@@ -537,19 +1296,135 @@ impl PhysicalDevice {
         let transfer = unique_queue_families
             .get_or_insert(self.inner.transfer_queue_family, SINGLE_QUEUE_PRIORITY)
             as u8;
-        let present = unique_queue_families
-            .get_or_insert(self.inner.present_queue_family, SINGLE_QUEUE_PRIORITY)
-            as u8;
+        // Without a presentation queue family, the present queue aliases the
+        // graphics queue and is never used for presentation.
+        let present = match self.inner.present_queue_family {
+            Some(family) => unique_queue_families.get_or_insert(family, SINGLE_QUEUE_PRIORITY),
+            None => graphics as usize,
+        } as u8;
+
+        let mut extensions = Vec::new();
+        if self.inner.present_queue_family.is_some() {
+            // TODO: need to check ahead of time that this is available
+            extensions.push(vk::KHR_SWAPCHAIN_EXTENSION_NAME);
+
+            let hdr_metadata = unsafe { CStr::from_ptr(vk::EXT_HDR_METADATA_EXTENSION_NAME) };
+            if self.supports_extension(hdr_metadata) {
+                extensions.push(vk::EXT_HDR_METADATA_EXTENSION_NAME);
+            }
+        }
+
+        // Non-conformant implementations such as MoltenVK require this
+        // extension to be enabled whenever it is advertised.
+        let portability_subset =
+            unsafe { CStr::from_ptr(vk::KHR_PORTABILITY_SUBSET_EXTENSION_NAME) };
+        if self.supports_extension(portability_subset) {
+            extensions.push(vk::KHR_PORTABILITY_SUBSET_EXTENSION_NAME);
+        }
+
+        // Used to report heap budgets in `Device::memory_report`.
+        let memory_budget = unsafe { CStr::from_ptr(vk::EXT_MEMORY_BUDGET_EXTENSION_NAME) };
+        if self.supports_extension(memory_budget) {
+            extensions.push(vk::EXT_MEMORY_BUDGET_EXTENSION_NAME);
+        }
+
+        for ext in descriptor.extra_extensions.iter() {
+            if !self.supports_extension(ext) {
+                log::warn!("Device extension not found: {}", ext.to_string_lossy());
+            } else if !extensions
+                .iter()
+                .any(|&ptr| unsafe { CStr::from_ptr(ptr) } == ext.as_c_str())
+            {
+                extensions.push(ext.as_ptr());
+            }
+        }
+
+        let supported_features = self.features();
+        let mut enabled_features = vk::PhysicalDeviceFeatures {
+            sampler_anisotropy: supported_features.sampler_anisotropy,
+            occlusion_query_precise: supported_features.occlusion_query_precise,
+            pipeline_statistics_query: supported_features.pipeline_statistics_query,
+            ..Default::default()
+        };
+        let mut unsupported_features = 0;
+        for ((enabled, &requested), &supported) in feature_flags_mut(&mut enabled_features)
+            .iter_mut()
+            .zip(feature_flags(&descriptor.extra_features))
+            .zip(feature_flags(&supported_features))
+        {
+            if requested != vk::FALSE {
+                if supported != vk::FALSE {
+                    *enabled = vk::TRUE;
+                } else {
+                    unsupported_features += 1;
+                }
+            }
+        }
+        if unsupported_features > 0 {
+            log::warn!(
+                "{} requested device features are not supported; ignoring.",
+                unsupported_features
+            );
+        }
+
+        let bindless = descriptor.bindless && {
+            // Descriptor indexing is core in Vulkan 1.2.
+            let core = self.api_version() >= ApiVersion::V1_2_0;
+            let indexing_ext =
+                unsafe { CStr::from_ptr(vk::EXT_DESCRIPTOR_INDEXING_EXTENSION_NAME) };
+
+            // The features must be queried before they are enabled, as
+            // enabling an unsupported feature fails device creation.
+            let missing = if core || self.supports_extension(indexing_ext) {
+                let supported = unsafe {
+                    self.inner
+                        .instance
+                        .read_inner()
+                        .handle
+                        .get_physical_device_descriptor_indexing_features(&self.inner.raw)
+                };
+                match supported {
+                    Some(supported) => missing_bindless_feature(&supported),
+                    None => Some("vkGetPhysicalDeviceFeatures2"),
+                }
+            } else {
+                Some("VK_EXT_descriptor_indexing")
+            };
 
-        let phys_device_features = vk::PhysicalDeviceFeaturesBuilder::new();
-        let enabled_layer_names = &[LAYER_NAME_VALIDATION.as_ptr() as *const i8];
-        let device_create_info = vk::DeviceCreateInfoBuilder::new()
+            match missing {
+                Some(missing) => {
+                    log::warn!(
+                        "Bindless descriptors require {}, which is not supported; ignoring.",
+                        missing
+                    );
+                    false
+                }
+                None => {
+                    if !core {
+                        extensions.push(vk::EXT_DESCRIPTOR_INDEXING_EXTENSION_NAME);
+                    }
+                    true
+                }
+            }
+        };
+        let indexing_features = bindless_features();
+
+        // Device layers are deprecated, but are still honored by older
+        // loaders.
+        let enabled_layer_names: &[*const i8] = if self.inner.instance.read_inner().validation {
+            &[LAYER_NAME_VALIDATION.as_ptr() as *const i8]
+        } else {
+            &[]
+        };
+        let mut device_create_info = vk::DeviceCreateInfoBuilder::new()
             .flags(vk::DeviceCreateFlags::empty())
             .queue_create_infos(unique_queue_families.infos())
             .enabled_layer_names(enabled_layer_names)
-            // TODO: need to check ahead of time that this is available
-            .enabled_extension_names(&[vk::KHR_SWAPCHAIN_EXTENSION_NAME])
-            .enabled_features(&phys_device_features);
+            .enabled_extension_names(&extensions)
+            .enabled_features(&enabled_features);
+        if bindless {
+            device_create_info = device_create_info.extend_from(&indexing_features);
+        }
 
         // Safety: no external synchronization requirement.
         let raw_device = unsafe {
@@ -563,8 +1438,20 @@ impl PhysicalDevice {
 
         log::info!("Successfully created logical device.");
 
+        let enabled_extensions = extensions
+            .iter()
+            .map(|&ptr| unsafe { CStr::from_ptr(ptr) }.to_owned())
+            .collect();
+
         let inner = Arc::new(RwLock::new(DeviceInner {
             raw: raw_device,
+            enabled_extensions,
+            enabled_features,
+            bindless,
+            memory_stats: mem::MemoryStats::default(),
+            leak_callback: descriptor.leak_callback.clone(),
+            descriptor_set_layouts: Mutex::new(DescriptorSetLayoutCache::default()),
+            compile_pool: Mutex::new(None),
             phys_device: self.clone(),
             instance: self.inner.instance.clone(),
         }));
@@ -690,7 +1577,9 @@ impl ThreadLocalCommandPool {
     fn init(&self) -> vks::VkResult<vks::CommandPool> {
         let command_pool_info = vk::CommandPoolCreateInfoBuilder::new()
             .queue_family_index(self.queue_family_id)
-            .flags(vk::CommandPoolCreateFlags::empty());
+            // Swapchain command buffers are re-recorded when the display is
+            // resized.
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
 
         let command_pool = unsafe {
             self.device
@@ -772,26 +1661,229 @@ pub struct DeviceInner {
     //command_buffer: Option<vks::CommandBuffer>,
     //command_pool: Option<vks::CommandPool>,
     raw: vks::Device,
+    enabled_extensions: Vec<CString>,
+    enabled_features: vk::PhysicalDeviceFeatures,
+    // Whether the descriptor indexing features for bindless tables are enabled.
+    bindless: bool,
+    memory_stats: mem::MemoryStats,
+    leak_callback: Option<LeakCallback>,
+    descriptor_set_layouts: Mutex<DescriptorSetLayoutCache>,
+    // Spawned on the first background pipeline compilation.
+    compile_pool: Mutex<Option<compile::CompilePool>>,
     phys_device: PhysicalDevice,
     instance: Instance,
 }
 
-#[derive(Clone)]
-pub struct Device {
-    // NOTE: sensitive drop order.
-    queues: DeviceQueues,
-    inner: Arc<RwLock<DeviceInner>>,
-}
+impl DeviceInner {
+    /// Returns whether the named device extension was enabled at device
+    /// creation.
+    pub fn is_extension_enabled(&self, name: &CStr) -> bool {
+        self.enabled_extensions
+            .iter()
+            .any(|ext| ext.as_c_str() == name)
+    }
 
-impl Device {
-    pub fn read_inner(&self) -> RwLockReadGuard<'_, DeviceInner> {
-        self.inner.read()
+    /// Returns the features enabled at device creation.
+    pub fn enabled_features(&self) -> &vk::PhysicalDeviceFeatures {
+        &self.enabled_features
+    }
+
+    /// Returns whether bindless descriptor tables may be created.
+    pub fn is_bindless_enabled(&self) -> bool {
+        self.bindless
+    }
+}
+
+impl Drop for DeviceInner {
+    fn drop(&mut self) {
+        unsafe {
+            // Safety: all pipelines referencing the layouts hold a reference to
+            // the device, so none remain.
+            self.descriptor_set_layouts
+                .get_mut()
+                .destroy_with(&self.raw);
+        }
+    }
+}
+
+/// Creates a shader module from SPIR-V code.
+unsafe fn create_shader_module(
+    device: &vks::Device,
+    code: &[u32],
+) -> vks::VkResult<vks::ShaderModule> {
+    unsafe {
+        device.create_shader_module(
+            &vk::ShaderModuleCreateInfoBuilder::new()
+                .flags(vk::ShaderModuleCreateFlags::empty())
+                .code(code),
+        )
+    }
+}
+
+/// Logs the device memory allocations which outlive their device.
+fn log_leaks(report: &MemoryReport) {
+    log::error!(
+        "Device destroyed with live allocations: {} buffers, {} images, {} transient images, {} pool blocks.",
+        report.buffers,
+        report.images,
+        report.transient_images,
+        report.pool_blocks,
+    );
+    for (index, ty) in report.types.iter().enumerate() {
+        if ty.allocations > 0 {
+            log::error!(
+                "  Memory type {}: {} allocations, {} bytes.",
+                index,
+                ty.allocations,
+                ty.allocated
+            );
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Device {
+    // NOTE: sensitive drop order.
+    queues: DeviceQueues,
+    inner: Arc<RwLock<DeviceInner>>,
+}
+
+impl Device {
+    pub fn read_inner(&self) -> RwLockReadGuard<'_, DeviceInner> {
+        self.inner.read()
+    }
+
+    /// Blocks until all queues of the device are idle.
+    pub fn wait_idle(&self) -> Result<(), Error> {
+        let device_read = self.inner.read();
+
+        // Every queue must be externally synchronized for the duration.
+        let _queue_locks = self
+            .queues
+            .queues
+            .iter()
+            .map(|q| q.write_inner())
+            .collect::<ArrayVec<_, MAX_DEVICE_QUEUES>>();
+
+        unsafe { device_read.raw.device_wait_idle()? };
+        Ok(())
+    }
+
+    /// Returns the number of handles to the device held outside of this one,
+    /// e.g. by other clones, displays, pipelines or queues.
+    fn outstanding_references(&self) -> usize {
+        // References held by this handle's own queues and command pools are
+        // not outstanding.
+        let queue_refs = self
+            .queues
+            .queues
+            .iter()
+            .filter(|q| Arc::ptr_eq(&q.read_inner()._device, &self.inner))
+            .count();
+        let pool_refs = self
+            .queues
+            .cmd_pools
+            .iter()
+            .filter(|p| Arc::ptr_eq(&p.device, &self.inner))
+            .count();
+        let internal = 1 + queue_refs + pool_refs;
+        let device = Arc::strong_count(&self.inner) - internal;
+        let queues = self
+            .queues
+            .queues
+            .iter()
+            .map(|q| Arc::strong_count(&q.inner) - 1)
+            .sum::<usize>();
+        let pools = self
+            .queues
+            .cmd_pools
+            .iter()
+            .map(|p| Arc::strong_count(p) - 1)
+            .sum::<usize>();
+
+        device + queues + pools
+    }
+
+    /// Waits for the device to become idle and destroys it, along with its
+    /// command pools and cached descriptor set layouts.
+    ///
+    /// Objects created from the device hold a reference to it. If any remain,
+    /// the device is destroyed once they are dropped and
+    /// [`Error::StillInUse`] is returned.
+    ///
+    /// Device memory which is still allocated when the device is destroyed,
+    /// e.g. by buffers that were never destroyed, is reported as a leak.
+    pub fn destroy(self) -> Result<(), Error> {
+        // Finish background compilations, which hold references to the device.
+        let compile_pool = self.inner.read().compile_pool.lock().take();
+        if let Some(pool) = compile_pool {
+            pool.shutdown();
+        }
+
+        if let Err(e) = self.wait_idle() {
+            log::error!("Failed to wait for device idle: {}", e);
+        }
+
+        let references = self.outstanding_references();
+        if references == 0 {
+            let report = self.memory_report();
+            if report.has_live_allocations() {
+                match &self.inner.read().leak_callback {
+                    Some(callback) => callback(&report),
+                    None => log_leaks(&report),
+                }
+            }
+        }
+        drop(self);
+
+        if references > 0 {
+            log::error!(
+                "Device destruction deferred: {} references remain.",
+                references
+            );
+            return Err(Error::StillInUse {
+                object: "device",
+                references,
+            });
+        }
+
+        log::info!("Destroyed device.");
+        Ok(())
     }
 
     pub fn physical_device(&self) -> PhysicalDevice {
         self.inner.read().phys_device.clone()
     }
 
+    /// Returns a snapshot of the device memory allocated through this device.
+    ///
+    /// Heap budgets are included if the device supports
+    /// `VK_EXT_memory_budget`, which is enabled automatically.
+    pub fn memory_report(&self) -> MemoryReport {
+        let device_read = self.inner.read();
+        let phys_device = &device_read.phys_device.inner.raw;
+        let instance_read = device_read.instance.read_inner();
+
+        let properties = instance_read
+            .handle
+            .get_physical_device_memory_properties(phys_device);
+        let memory_budget = unsafe { CStr::from_ptr(vk::EXT_MEMORY_BUDGET_EXTENSION_NAME) };
+        let budget = if device_read.is_extension_enabled(memory_budget) {
+            // Safety: the extension is supported by the physical device.
+            unsafe {
+                instance_read
+                    .handle
+                    .get_physical_device_memory_budget(phys_device)
+            }
+        } else {
+            None
+        };
+
+        device_read
+            .memory_stats
+            .report(&properties, budget.as_ref())
+    }
+
     pub fn graphics_queue(&self) -> Queue {
         self.queues.graphics_queue()
     }
@@ -829,11 +1921,12 @@ impl Device {
         &self,
         surface: vks::SurfaceKHR,
         phys_window_extent: vk::Extent2D,
-    ) -> Display {
-        unsafe { Display::create(self, surface, phys_window_extent) }
+        config: DisplayConfig,
+    ) -> Result<Display, Error> {
+        unsafe { Display::create(self, surface, phys_window_extent, config) }
     }
 
-    unsafe fn create_render_pass(&self, target: &Display) -> vks::RenderPass {
+    unsafe fn create_render_pass(&self, target: PipelineTarget) -> vks::VkResult<vks::RenderPass> {
         let graphics_present_differ = self.graphics_family_id() != self.present_family_id();
 
         let initial_layout = if graphics_present_differ {
@@ -856,26 +1949,52 @@ impl Device {
             vk::ImageLayout::PRESENT_SRC_KHR
         };
 
-        let color_attachment = vk::AttachmentDescriptionBuilder::new()
-            .format(target.info().surface_format.format)
+        let PipelineTarget { format, samples } = target;
+        let multisampled = samples != vk::SampleCountFlagBits::_1;
+
+        let swapchain_attachment = vk::AttachmentDescriptionBuilder::new()
+            .format(format)
             .samples(vk::SampleCountFlagBits::_1)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
+            // When multisampling, the swapchain image is entirely overwritten
+            // by the resolve.
+            .load_op(if multisampled {
+                vk::AttachmentLoadOp::DONT_CARE
+            } else {
+                vk::AttachmentLoadOp::CLEAR
+            })
             .store_op(vk::AttachmentStoreOp::STORE)
             .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
             .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
             .initial_layout(initial_layout)
             .final_layout(final_layout);
 
-        let attachment_reference = vk::AttachmentReferenceBuilder::new()
+        // The multisampled image is only used within the render pass, so its
+        // contents are neither loaded nor stored.
+        let multisample_attachment = vk::AttachmentDescriptionBuilder::new()
+            .format(format)
+            .samples(samples)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        // The driver automatically transitions the attachments to the correct
+        // layout.
+        let color_attachments = &[vk::AttachmentReferenceBuilder::new()
             .attachment(0)
-            // The driver automatically transitions the color attachment to the
-            // correct layout.
-            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let resolve_attachments = &[vk::AttachmentReferenceBuilder::new()
+            .attachment(1)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
 
-        let color_attachments = &[attachment_reference];
-        let subpass = vk::SubpassDescriptionBuilder::new()
+        let mut subpass = vk::SubpassDescriptionBuilder::new()
             .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
             .color_attachments(color_attachments);
+        if multisampled {
+            subpass = subpass.resolve_attachments(resolve_attachments);
+        }
 
         // If graphics and present operations are performed on the same queue
         // family, then a subpass dependency is sufficient to synchronize access
@@ -908,80 +2027,247 @@ impl Device {
             );
         };
 
-        let attachments = &[color_attachment];
+        let attachments = if multisampled {
+            vec![multisample_attachment, swapchain_attachment]
+        } else {
+            vec![swapchain_attachment]
+        };
         let subpasses = &[subpass];
         let render_pass_info = vk::RenderPassCreateInfoBuilder::new()
-            .attachments(attachments)
+            .attachments(&attachments)
             .subpasses(subpasses)
             .dependencies(&dependencies);
 
-        let render_pass = unsafe { self.inner.read().raw.create_render_pass(&render_pass_info) }
-            .expect("failed to create render pass");
+        unsafe { self.inner.read().raw.create_render_pass(&render_pass_info) }
+    }
+
+    unsafe fn create_pipeline_layout(
+        &self,
+        info: &PipelineLayoutInfo,
+    ) -> vks::VkResult<vks::PipelineLayout> {
+        let device_read = self.inner.read();
+        let mut layout_cache = device_read.descriptor_set_layouts.lock();
+
+        let set_layouts = info
+            .descriptor_sets
+            .iter()
+            .map(|set| unsafe { layout_cache.get_or_create(&device_read.raw, set) })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let push_constant_ranges = info
+            .push_constant_ranges
+            .iter()
+            .map(|range| range.into_builder())
+            .collect::<Vec<_>>();
+
+        let pipeline_layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+
+        unsafe {
+            device_read
+                .raw
+                .create_pipeline_layout(&pipeline_layout_info)
+        }
+    }
+
+    /// Creates a buffer and allocates its memory.
+    ///
+    /// Host-visible buffers are persistently mapped. The buffer must be
+    /// destroyed with [`Buffer::destroy_with`].
+    ///
+    /// Returns [`Error::LimitExceeded`] if the buffer is larger than a single
+    /// allocation may be, or if the device's allocation count limit has been
+    /// reached.
+    pub fn create_buffer(&self, info: &BufferCreateInfo) -> Result<Buffer, Error> {
+        let limits = self.physical_device().limits();
+        LimitExceeded::check("buffer size", info.size, limits.max_memory_allocation_size)?;
+        LimitExceeded::check(
+            "memory allocation count",
+            u64::from(self.inner.read().memory_stats.allocation_count()) + 1,
+            limits.max_memory_allocation_count,
+        )?;
+
+        Ok(Buffer::create(self, info)?)
+    }
+
+    /// Creates a pool of `count` queries of the given kind.
+    ///
+    /// The pool must be destroyed with [`QueryPool::destroy_with`].
+    pub fn create_query_pool(&self, kind: QueryKind, count: u32) -> Result<QueryPool, Error> {
+        QueryPool::create(self, kind, count)
+    }
+
+    /// Creates a sampler, after validating it against the device's limits.
+    pub fn create_sampler(&self, info: &SamplerInfo) -> Result<vks::Sampler, Error> {
+        let device_read = self.inner.read();
+        info.validate(
+            &device_read.phys_device.properties().limits,
+            device_read.enabled_features.sampler_anisotropy != 0,
+        )?;
+
+        Ok(unsafe { device_read.raw.create_sampler(&info.to_vk())? })
+    }
+
+    /// Destroys a sampler.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `sampler` must have been created with this device.
+    /// - All submitted commands that refer to `sampler` must have completed
+    ///   execution.
+    pub unsafe fn destroy_sampler(&self, sampler: vks::Sampler) {
+        unsafe { self.inner.read().raw.destroy_sampler(sampler) };
+    }
+
+    /// Allocates a descriptor set with the layout described by `info`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `allocator` must only be used with this device.
+    /// - The returned descriptor set must not be used after `allocator` is
+    ///   reset or destroyed.
+    pub unsafe fn allocate_descriptor_set(
+        &self,
+        allocator: &mut DescriptorAllocator,
+        info: &DescriptorSetLayoutInfo,
+    ) -> vks::VkResult<vks::DescriptorSet> {
+        let device_read = self.inner.read();
+        let layout = unsafe {
+            device_read
+                .descriptor_set_layouts
+                .lock()
+                .get_or_create(&device_read.raw, info)?
+        };
+
+        unsafe { allocator.allocate_raw(&device_read.raw, layout) }
+    }
 
-        render_pass
+    /// Writes resource bindings into descriptor sets.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - The destination descriptor sets must not be in use by any pending
+    ///   command buffer.
+    /// - All handles referenced by `writes` must be associated with this
+    ///   device.
+    pub unsafe fn update_descriptor_sets(&self, writes: &[vk::WriteDescriptorSetBuilder<'_>]) {
+        unsafe { self.inner.read().raw.update_descriptor_sets(writes, &[]) };
     }
 
+    /// Creates a graphics pipeline from SPIR-V vertex and fragment shaders,
+    /// compatible with the render pass of `target`.
+    ///
+    /// The shaders' entry points must be named `main`. Returns
+    /// [`Error::LimitExceeded`] if `info` exceeds the device's limits.
     pub unsafe fn create_pipeline(
         &self,
         vert_spv: &[u32],
         frag_spv: &[u32],
+        info: &GraphicsPipelineInfo,
         target: &Display,
-    ) -> Pipeline {
+    ) -> Result<Pipeline, Error> {
+        unsafe {
+            self.create_pipeline_for_target(vert_spv, frag_spv, info, target.pipeline_target())
+        }
+    }
+
+    /// Compiles a graphics pipeline on a background thread.
+    ///
+    /// This is the asynchronous counterpart of [`Device::create_pipeline`],
+    /// for a display described by `target`. The device spawns its compile
+    /// threads on first use. See [`PipelineSlot`] for drawing with a previous
+    /// pipeline until the new one is ready.
+    pub fn compile_pipeline(
+        &self,
+        vert_spv: Vec<u32>,
+        frag_spv: Vec<u32>,
+        info: GraphicsPipelineInfo,
+        target: PipelineTarget,
+    ) -> PendingPipeline {
+        PendingPipeline::spawn(self, vert_spv, frag_spv, info, target)
+    }
+
+    unsafe fn create_pipeline_for_target(
+        &self,
+        vert_spv: &[u32],
+        frag_spv: &[u32],
+        info: &GraphicsPipelineInfo,
+        target: PipelineTarget,
+    ) -> Result<Pipeline, Error> {
         let device_read = self.inner.read();
+        info.validate(&device_read.phys_device.limits())?;
 
-        let vert_module = unsafe {
-            device_read.raw.create_shader_module(
-                &vk::ShaderModuleCreateInfoBuilder::new()
-                    .flags(vk::ShaderModuleCreateFlags::empty())
-                    .code(vert_spv),
-            )
-        }
-        .expect("failed to create vertex shader module");
+        let vert_module = unsafe { create_shader_module(&device_read.raw, vert_spv)? };
+        let frag_module = match unsafe { create_shader_module(&device_read.raw, frag_spv) } {
+            Ok(module) => module,
+            Err(e) => {
+                unsafe { device_read.raw.destroy_shader_module(vert_module) };
+                return Err(e.into());
+            }
+        };
+
+        let pipeline =
+            unsafe { self.create_graphics_pipeline(&vert_module, &frag_module, info, target) };
 
-        let frag_module = unsafe {
-            device_read.raw.create_shader_module(
-                &vk::ShaderModuleCreateInfoBuilder::new()
-                    .flags(vk::ShaderModuleCreateFlags::empty())
-                    .code(frag_spv),
-            )
+        // The shader modules are no longer needed once the pipeline is created.
+        unsafe {
+            device_read.raw.destroy_shader_module(vert_module);
+            device_read.raw.destroy_shader_module(frag_module);
         }
-        .expect("failed to create fragment shader module");
+
+        pipeline
+    }
+
+    unsafe fn create_graphics_pipeline(
+        &self,
+        vert_module: &vks::ShaderModule,
+        frag_module: &vks::ShaderModule,
+        info: &GraphicsPipelineInfo,
+        target: PipelineTarget,
+    ) -> Result<Pipeline, Error> {
+        let device_read = self.inner.read();
 
         let vert_stage = vks::PipelineShaderStageCreateInfoBuilder::new()
             .stage(vk::ShaderStageFlagBits::VERTEX)
             .name(&CStr::from_bytes_with_nul(b"main\0").unwrap())
-            .module(&vert_module);
+            .module(vert_module);
 
         let frag_stage = vks::PipelineShaderStageCreateInfoBuilder::new()
             .stage(vk::ShaderStageFlagBits::FRAGMENT)
             .name(&CStr::from_bytes_with_nul(b"main\0").unwrap())
-            .module(&frag_module);
+            .module(frag_module);
 
+        let vertex_bindings = info
+            .vertex_bindings
+            .iter()
+            .map(|binding| binding.into_builder())
+            .collect::<Vec<_>>();
+        let vertex_attributes = info
+            .vertex_attributes
+            .iter()
+            .map(|attribute| attribute.into_builder())
+            .collect::<Vec<_>>();
         let vertex_input = vk::PipelineVertexInputStateCreateInfoBuilder::new()
-            .vertex_binding_descriptions(&[])
-            .vertex_attribute_descriptions(&[]);
+            .vertex_binding_descriptions(&vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes);
 
         let input_assembly = vk::PipelineInputAssemblyStateCreateInfoBuilder::new()
             .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
             .primitive_restart_enable(false);
 
-        let viewport = vk::ViewportBuilder::new()
-            .x(0.0)
-            .y(0.0)
-            .width(target.info().image_extent.width as f32)
-            .height(target.info().image_extent.height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0);
-
-        let scissor = vk::Rect2DBuilder::new()
-            .offset(vk::Offset2D { x: 0, y: 0 })
-            .extent(target.info().image_extent);
-
-        let viewports = &[viewport];
-        let scissors = &[scissor];
+        // The viewport and scissor are dynamic so that the pipeline remains
+        // valid when the display is resized.
         let viewport_state = vk::PipelineViewportStateCreateInfoBuilder::new()
-            .viewports(viewports)
-            .scissors(scissors);
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let dynamic_states = info.dynamic_states.to_vk();
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfoBuilder::new().dynamic_states(&dynamic_states);
 
         let rasterization_state = vk::PipelineRasterizationStateCreateInfoBuilder::new()
             .depth_clamp_enable(false)
@@ -990,45 +2276,33 @@ impl Device {
             .line_width(1.0)
             .cull_mode(vk::CullModeFlags::BACK)
             .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-            .depth_bias_enable(false);
+            .depth_bias_enable(info.dynamic_states.depth_bias);
 
         let multisample_state = vk::PipelineMultisampleStateCreateInfoBuilder::new()
             .sample_shading_enable(false)
-            .rasterization_samples(vk::SampleCountFlagBits::_1)
+            .rasterization_samples(target.samples)
             .min_sample_shading(1.0)
             .sample_mask(&[])
             .alpha_to_coverage_enable(false)
             .alpha_to_one_enable(false);
 
-        let color_blend_attachment = vk::PipelineColorBlendAttachmentStateBuilder::new()
-            .color_write_mask(
-                vk::ColorComponentFlags::R
-                    | vk::ColorComponentFlags::G
-                    | vk::ColorComponentFlags::B
-                    | vk::ColorComponentFlags::A,
-            )
-            .blend_enable(false);
-
-        let attachments = &[color_blend_attachment];
+        // The display render pass has a single color attachment.
+        let attachments = info.color_blend_states(1)?;
         let color_blend = vk::PipelineColorBlendStateCreateInfoBuilder::new()
-            .attachments(attachments)
-            .logic_op_enable(false);
-
-        let pipeline_layout_info = vk::PipelineLayoutCreateInfoBuilder::new()
-            .set_layouts(&[])
-            .push_constant_ranges(&[]);
+            .attachments(&attachments)
+            .logic_op_enable(false)
+            .blend_constants(info.blend_constants);
 
-        let pipeline_layout = unsafe {
-            self.inner
-                .read()
-                .raw
-                .create_pipeline_layout(&pipeline_layout_info)
-        }
-        .expect("failed to create pipeline layout");
-
-        let render_pass = unsafe { self.create_render_pass(target) };
+        let pipeline_layout = unsafe { self.create_pipeline_layout(&info.layout)? };
+        let render_pass = match unsafe { self.create_render_pass(target) } {
+            Ok(render_pass) => render_pass,
+            Err(e) => {
+                unsafe { device_read.raw.destroy_pipeline_layout(pipeline_layout) };
+                return Err(e.into());
+            }
+        };
 
-        let pipeline = unsafe {
+        let created = unsafe {
             // Safety: copied handles do not outlive the block.
             let stages = &[vert_stage.into_inner(), frag_stage.into_inner()];
 
@@ -1040,28 +2314,97 @@ impl Device {
                 .rasterization_state(&rasterization_state)
                 .multisample_state(&multisample_state)
                 .color_blend_state(&color_blend)
+                .dynamic_state(&dynamic_state)
                 .layout(&pipeline_layout)
                 .render_pass(&render_pass);
 
             // Safety: copied handles do not outlive the block.
             let pipeline_infos = &[pipeline_info.into_inner()];
-            device_read
-                .raw
-                .create_graphics_pipelines(pipeline_infos)
-                .expect("failed to create pipeline")
-                .into_iter()
-                .next()
-                .unwrap()
+            device_read.raw.create_graphics_pipelines(pipeline_infos)
+        };
+        let pipeline = match created {
+            Ok(pipelines) => pipelines.into_iter().next().unwrap(),
+            Err(e) => {
+                unsafe {
+                    device_read.raw.destroy_render_pass(render_pass);
+                    device_read.raw.destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
         };
 
-        Pipeline {
+        Ok(Pipeline {
             inner: Arc::new(RwLock::new(PipelineInner {
                 pipeline: Some(pipeline),
                 layout: Some(pipeline_layout),
                 pass: Some(render_pass),
+                dynamic_states: info.dynamic_states,
                 device: self.clone(),
             })),
-        }
+        })
+    }
+
+    /// Creates a compute pipeline from a SPIR-V compute shader.
+    ///
+    /// The shader's entry point must be named `main`. The returned pipeline
+    /// has no associated render pass and should be bound with
+    /// `vk::PipelineBindPoint::COMPUTE`.
+    ///
+    /// Returns [`Error::LimitExceeded`] if `layout` exceeds the device's
+    /// limits.
+    pub unsafe fn create_compute_pipeline(
+        &self,
+        comp_spv: &[u32],
+        layout: &PipelineLayoutInfo,
+    ) -> Result<Pipeline, Error> {
+        let device_read = self.inner.read();
+        layout.validate(&device_read.phys_device.limits())?;
+
+        let comp_module = unsafe { create_shader_module(&device_read.raw, comp_spv)? };
+
+        let comp_stage = vks::PipelineShaderStageCreateInfoBuilder::new()
+            .stage(vk::ShaderStageFlagBits::COMPUTE)
+            .name(&CStr::from_bytes_with_nul(b"main\0").unwrap())
+            .module(&comp_module);
+
+        let pipeline_layout = match unsafe { self.create_pipeline_layout(layout) } {
+            Ok(pipeline_layout) => pipeline_layout,
+            Err(e) => {
+                unsafe { device_read.raw.destroy_shader_module(comp_module) };
+                return Err(e.into());
+            }
+        };
+
+        let created = unsafe {
+            // Safety: copied handles do not outlive the block.
+            let pipeline_info = vks::ComputePipelineCreateInfoBuilder::new()
+                .stage(comp_stage.into_inner().build())
+                .layout(&pipeline_layout);
+
+            let pipeline_infos = &[pipeline_info.into_inner()];
+            device_read.raw.create_compute_pipelines(pipeline_infos)
+        };
+
+        // The shader module is no longer needed once the pipeline is created.
+        unsafe { device_read.raw.destroy_shader_module(comp_module) };
+
+        let pipeline = match created {
+            Ok(pipelines) => pipelines.into_iter().next().unwrap(),
+            Err(e) => {
+                unsafe { device_read.raw.destroy_pipeline_layout(pipeline_layout) };
+                return Err(e.into());
+            }
+        };
+
+        Ok(Pipeline {
+            inner: Arc::new(RwLock::new(PipelineInner {
+                pipeline: Some(pipeline),
+                layout: Some(pipeline_layout),
+                pass: None,
+                dynamic_states: DynamicStates::default(),
+                device: self.clone(),
+            })),
+        })
     }
 }
 
@@ -1158,10 +2501,369 @@ pub struct Swapchain {
     inner: Arc<RwLock<SwapchainInner>>,
 }
 
+/// Describes the resource interface of a pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineLayoutInfo {
+    /// The layouts of the descriptor sets used by the pipeline, in set order.
+    pub descriptor_sets: Vec<DescriptorSetLayoutInfo>,
+    /// The push constant ranges accessible to the pipeline.
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+}
+
+impl PipelineLayoutInfo {
+    pub fn new() -> PipelineLayoutInfo {
+        PipelineLayoutInfo::default()
+    }
+
+    /// Checks the layout against the device's limits.
+    pub fn validate(&self, limits: &DeviceLimits) -> Result<(), LimitExceeded> {
+        LimitExceeded::check(
+            "descriptor set count",
+            self.descriptor_sets.len() as u64,
+            limits.max_bound_descriptor_sets,
+        )?;
+
+        for set in self.descriptor_sets.iter() {
+            LimitExceeded::check(
+                "descriptors per set",
+                set.bindings()
+                    .iter()
+                    .map(|b| u64::from(b.count))
+                    .sum::<u64>(),
+                limits.max_per_set_descriptors,
+            )?;
+        }
+
+        for range in self.push_constant_ranges.iter() {
+            // A range ending past u32::MAX exceeds any limit.
+            let end = range
+                .offset
+                .checked_add(range.size)
+                .map_or(u64::from(u32::MAX) + 1, u64::from);
+            LimitExceeded::check(
+                "push constant range end",
+                end,
+                limits.max_push_constants_size,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends a descriptor set layout at the next set index.
+    pub fn descriptor_set(mut self, set: DescriptorSetLayoutInfo) -> PipelineLayoutInfo {
+        self.descriptor_sets.push(set);
+        self
+    }
+
+    /// Declares a range of push constants accessible from `stages`.
+    pub fn push_constant_range(
+        mut self,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    ) -> PipelineLayoutInfo {
+        self.push_constant_ranges.push(vk::PushConstantRange {
+            stage_flags: stages,
+            offset,
+            size,
+        });
+        self
+    }
+}
+
+/// The color attachment a graphics pipeline renders to.
+///
+/// For a display, use [`Display::pipeline_target`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PipelineTarget {
+    /// The format of the resolved color attachment.
+    pub format: vk::Format,
+    /// The number of samples rendered per pixel.
+    pub samples: vk::SampleCountFlagBits,
+}
+
+/// Describes the fixed-function configuration of a graphics pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct GraphicsPipelineInfo {
+    pub layout: PipelineLayoutInfo,
+    /// The vertex buffer bindings consumed by the pipeline.
+    pub vertex_bindings: Vec<vk::VertexInputBindingDescription>,
+    /// The vertex attributes read from the vertex buffer bindings.
+    pub vertex_attributes: Vec<vk::VertexInputAttributeDescription>,
+    /// The blend state of each color attachment, in attachment order.
+    ///
+    /// If empty, all attachments are written without blending.
+    pub color_blend_attachments: Vec<ColorBlendAttachment>,
+    /// The constant color used by the `CONSTANT_*` blend factors.
+    ///
+    /// Ignored if blend constants are dynamic.
+    pub blend_constants: [f32; 4],
+    /// Additional pipeline state which is set while recording commands.
+    pub dynamic_states: DynamicStates,
+}
+
+/// Pipeline state which may be set per draw rather than at pipeline creation.
+///
+/// The viewport and scissor are always dynamic. Each state enabled here must
+/// be set with the corresponding `PipelineInner` method before drawing.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DynamicStates {
+    /// Enables depth bias, set with `PipelineInner::set_depth_bias`.
+    pub depth_bias: bool,
+    /// Set with `PipelineInner::set_line_width`. Widths other than 1.0
+    /// require the `wideLines` feature.
+    pub line_width: bool,
+    /// Set with `PipelineInner::set_blend_constants`.
+    pub blend_constants: bool,
+    /// Set with `PipelineInner::set_stencil_reference`.
+    pub stencil_reference: bool,
+}
+
+impl DynamicStates {
+    fn to_vk(self) -> ArrayVec<vk::DynamicState, 6> {
+        let mut states = ArrayVec::new();
+        states.push(vk::DynamicState::VIEWPORT);
+        states.push(vk::DynamicState::SCISSOR);
+
+        if self.depth_bias {
+            states.push(vk::DynamicState::DEPTH_BIAS);
+        }
+        if self.line_width {
+            states.push(vk::DynamicState::LINE_WIDTH);
+        }
+        if self.blend_constants {
+            states.push(vk::DynamicState::BLEND_CONSTANTS);
+        }
+        if self.stencil_reference {
+            states.push(vk::DynamicState::STENCIL_REFERENCE);
+        }
+
+        states
+    }
+}
+
+/// The blend state of a single color attachment.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorBlendAttachment {
+    /// The blend equation, or `None` to write fragment colors unmodified.
+    pub blend: Option<BlendEquation>,
+    /// The color components written to the attachment.
+    pub write_mask: vk::ColorComponentFlags,
+}
+
+impl ColorBlendAttachment {
+    /// Writes all components without blending.
+    pub const OPAQUE: ColorBlendAttachment = ColorBlendAttachment {
+        blend: None,
+        write_mask: vk::ColorComponentFlags::all(),
+    };
+
+    /// Blends with straight (non-premultiplied) alpha.
+    pub const ALPHA: ColorBlendAttachment = ColorBlendAttachment {
+        blend: Some(BlendEquation {
+            src_color: vk::BlendFactor::SRC_ALPHA,
+            dst_color: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_op: vk::BlendOp::ADD,
+            src_alpha: vk::BlendFactor::ONE,
+            dst_alpha: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_op: vk::BlendOp::ADD,
+        }),
+        write_mask: vk::ColorComponentFlags::all(),
+    };
+
+    /// Blends with premultiplied alpha.
+    pub const PREMULTIPLIED_ALPHA: ColorBlendAttachment = ColorBlendAttachment {
+        blend: Some(BlendEquation {
+            src_color: vk::BlendFactor::ONE,
+            dst_color: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_op: vk::BlendOp::ADD,
+            src_alpha: vk::BlendFactor::ONE,
+            dst_alpha: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_op: vk::BlendOp::ADD,
+        }),
+        write_mask: vk::ColorComponentFlags::all(),
+    };
+
+    /// Adds fragment colors to the attachment.
+    pub const ADDITIVE: ColorBlendAttachment = ColorBlendAttachment {
+        blend: Some(BlendEquation {
+            src_color: vk::BlendFactor::ONE,
+            dst_color: vk::BlendFactor::ONE,
+            color_op: vk::BlendOp::ADD,
+            src_alpha: vk::BlendFactor::ONE,
+            dst_alpha: vk::BlendFactor::ONE,
+            alpha_op: vk::BlendOp::ADD,
+        }),
+        write_mask: vk::ColorComponentFlags::all(),
+    };
+
+    fn to_vk(self) -> vk::PipelineColorBlendAttachmentStateBuilder<'static> {
+        let builder =
+            vk::PipelineColorBlendAttachmentStateBuilder::new().color_write_mask(self.write_mask);
+
+        match self.blend {
+            Some(eq) => builder
+                .blend_enable(true)
+                .src_color_blend_factor(eq.src_color)
+                .dst_color_blend_factor(eq.dst_color)
+                .color_blend_op(eq.color_op)
+                .src_alpha_blend_factor(eq.src_alpha)
+                .dst_alpha_blend_factor(eq.dst_alpha)
+                .alpha_blend_op(eq.alpha_op),
+            None => builder.blend_enable(false),
+        }
+    }
+}
+
+impl Default for ColorBlendAttachment {
+    fn default() -> Self {
+        ColorBlendAttachment::OPAQUE
+    }
+}
+
+/// Blend factors and operations for the color and alpha components.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlendEquation {
+    pub src_color: vk::BlendFactor,
+    pub dst_color: vk::BlendFactor,
+    pub color_op: vk::BlendOp,
+    pub src_alpha: vk::BlendFactor,
+    pub dst_alpha: vk::BlendFactor,
+    pub alpha_op: vk::BlendOp,
+}
+
+impl GraphicsPipelineInfo {
+    pub fn new() -> GraphicsPipelineInfo {
+        GraphicsPipelineInfo::default()
+    }
+
+    /// Checks the pipeline's parameters against the device's limits.
+    pub fn validate(&self, limits: &DeviceLimits) -> Result<(), LimitExceeded> {
+        self.layout.validate(limits)?;
+
+        LimitExceeded::check(
+            "vertex binding count",
+            self.vertex_bindings.len() as u64,
+            limits.max_vertex_input_bindings,
+        )?;
+        LimitExceeded::check(
+            "vertex attribute count",
+            self.vertex_attributes.len() as u64,
+            limits.max_vertex_input_attributes,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns one blend state per color attachment of the render pass.
+    ///
+    /// If no blend states were given, every attachment is opaque.
+    fn color_blend_states(
+        &self,
+        color_attachment_count: usize,
+    ) -> Result<Vec<vk::PipelineColorBlendAttachmentStateBuilder<'static>>, Error> {
+        if self.color_blend_attachments.is_empty() {
+            return Ok(vec![
+                ColorBlendAttachment::OPAQUE.to_vk();
+                color_attachment_count
+            ]);
+        }
+
+        if self.color_blend_attachments.len() != color_attachment_count {
+            return Err(Error::ColorBlendAttachmentCount {
+                expected: color_attachment_count,
+                got: self.color_blend_attachments.len(),
+            });
+        }
+
+        Ok(self
+            .color_blend_attachments
+            .iter()
+            .map(|attachment| attachment.to_vk())
+            .collect())
+    }
+
+    pub fn layout(mut self, layout: PipelineLayoutInfo) -> GraphicsPipelineInfo {
+        self.layout = layout;
+        self
+    }
+
+    /// Declares a vertex buffer binding with the given stride.
+    pub fn vertex_binding(
+        mut self,
+        binding: u32,
+        stride: u32,
+        input_rate: vk::VertexInputRate,
+    ) -> GraphicsPipelineInfo {
+        self.vertex_bindings
+            .push(vk::VertexInputBindingDescription {
+                binding,
+                stride,
+                input_rate,
+            });
+        self
+    }
+
+    /// Appends the blend state of the next color attachment.
+    pub fn color_blend_attachment(
+        mut self,
+        attachment: ColorBlendAttachment,
+    ) -> GraphicsPipelineInfo {
+        self.color_blend_attachments.push(attachment);
+        self
+    }
+
+    pub fn blend_constants(mut self, constants: [f32; 4]) -> GraphicsPipelineInfo {
+        self.blend_constants = constants;
+        self
+    }
+
+    pub fn dynamic_states(mut self, states: DynamicStates) -> GraphicsPipelineInfo {
+        self.dynamic_states = states;
+        self
+    }
+
+    /// Declares a vertex attribute read from `binding` at `offset` bytes.
+    pub fn vertex_attribute(
+        mut self,
+        location: u32,
+        binding: u32,
+        format: vk::Format,
+        offset: u32,
+    ) -> GraphicsPipelineInfo {
+        self.vertex_attributes
+            .push(vk::VertexInputAttributeDescription {
+                location,
+                binding,
+                format,
+                offset,
+            });
+        self
+    }
+}
+
+/// The integer type of the elements of an index buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexType {
+    U16,
+    U32,
+}
+
+impl From<IndexType> for vk::IndexType {
+    fn from(ty: IndexType) -> Self {
+        match ty {
+            IndexType::U16 => vk::IndexType::UINT16,
+            IndexType::U32 => vk::IndexType::UINT32,
+        }
+    }
+}
+
 pub struct PipelineInner {
     pipeline: Option<vks::Pipeline>,
     layout: Option<vks::PipelineLayout>,
     pass: Option<vks::RenderPass>,
+    dynamic_states: DynamicStates,
     device: Device,
 }
 
@@ -1194,6 +2896,228 @@ impl PipelineInner {
         self.pipeline.as_ref().unwrap()
     }
 
+    /// Returns the pipeline layout, for use when binding descriptor sets.
+    pub fn layout(&self) -> &vks::PipelineLayout {
+        self.layout.as_ref().unwrap()
+    }
+
+    /// Binds vertex buffers to the pipeline's vertex input bindings, starting
+    /// at `first_binding`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - Each buffer must have been created with `VERTEX_BUFFER` usage and be
+    ///   bound to memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffers` and `offsets` have different lengths.
+    pub unsafe fn bind_vertex_buffers(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        first_binding: u32,
+        buffers: &[&vks::Buffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        // Safety: copied handles do not outlive the call.
+        let raw_buffers = buffers
+            .iter()
+            .map(|buf| unsafe { *buf.handle() })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            device.cmd_bind_vertex_buffers(command_buffer, first_binding, &raw_buffers, offsets);
+        }
+    }
+
+    /// Binds an index buffer for subsequent indexed draws.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - `buffer` must have been created with `INDEX_BUFFER` usage and be
+    ///   bound to memory.
+    /// - `offset` must be a multiple of the size of `index_type`.
+    pub unsafe fn bind_index_buffer(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        buffer: &vks::Buffer,
+        offset: vk::DeviceSize,
+        index_type: IndexType,
+    ) {
+        unsafe {
+            device.cmd_bind_index_buffer(command_buffer, buffer, offset, index_type.into());
+        }
+    }
+
+    /// Records an indexed draw using the bound index and vertex buffers.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be inside a render pass compatible with this
+    ///   pipeline, with this pipeline bound.
+    /// - An index buffer must be bound, and all indices read by the draw must
+    ///   refer to vertices within the bound vertex buffers.
+    pub unsafe fn draw_indexed(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        indices: Range<u32>,
+        vertex_offset: i32,
+        instances: Range<u32>,
+    ) {
+        unsafe {
+            device.cmd_draw_indexed(
+                command_buffer,
+                indices.end - indices.start,
+                instances.end - instances.start,
+                indices.start,
+                vertex_offset,
+                instances.start,
+            );
+        }
+    }
+
+    /// Returns the additional dynamic states enabled for the pipeline.
+    pub fn dynamic_states(&self) -> DynamicStates {
+        self.dynamic_states
+    }
+
+    /// Sets the depth bias for subsequent draws.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - If `clamp` is nonzero, the `depthBiasClamp` feature must be enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if depth bias is not a dynamic state of the pipeline.
+    pub unsafe fn set_depth_bias(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        constant_factor: f32,
+        clamp: f32,
+        slope_factor: f32,
+    ) {
+        assert!(self.dynamic_states.depth_bias, "depth bias is not dynamic");
+        unsafe {
+            device.cmd_set_depth_bias(command_buffer, constant_factor, clamp, slope_factor);
+        }
+    }
+
+    /// Sets the rasterized line width for subsequent draws.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - If `line_width` is not 1.0, the `wideLines` feature must be enabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if line width is not a dynamic state of the pipeline.
+    pub unsafe fn set_line_width(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        line_width: f32,
+    ) {
+        assert!(self.dynamic_states.line_width, "line width is not dynamic");
+        unsafe {
+            device.cmd_set_line_width(command_buffer, line_width);
+        }
+    }
+
+    /// Sets the constant color used by the `CONSTANT_*` blend factors.
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` must be in the recording state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if blend constants are not a dynamic state of the pipeline.
+    pub unsafe fn set_blend_constants(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        blend_constants: [f32; 4],
+    ) {
+        assert!(
+            self.dynamic_states.blend_constants,
+            "blend constants are not dynamic"
+        );
+        unsafe {
+            device.cmd_set_blend_constants(command_buffer, blend_constants);
+        }
+    }
+
+    /// Sets the stencil reference value of the faces in `face_mask`.
+    ///
+    /// # Safety
+    ///
+    /// `command_buffer` must be in the recording state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stencil reference is not a dynamic state of the pipeline.
+    pub unsafe fn set_stencil_reference(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        face_mask: vk::StencilFaceFlags,
+        reference: u32,
+    ) {
+        assert!(
+            self.dynamic_states.stencil_reference,
+            "stencil reference is not dynamic"
+        );
+        unsafe {
+            device.cmd_set_stencil_reference(command_buffer, face_mask, reference);
+        }
+    }
+
+    /// Records an update of push constant values into a command buffer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_buffer` must be in the recording state.
+    /// - The range `offset..offset + size_of::<T>()` must lie within a push
+    ///   constant range declared for all of `stages` in the pipeline layout.
+    pub unsafe fn push_constants<T: bytemuck::Pod>(
+        &self,
+        device: &vks::Device,
+        command_buffer: &mut vks::CommandBuffer,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        value: &T,
+    ) {
+        unsafe {
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout(),
+                stages,
+                offset,
+                bytemuck::bytes_of(value),
+            );
+        }
+    }
+
+    /// Returns the render pass the pipeline was created against.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a compute pipeline.
     pub fn render_pass(&self) -> &vks::RenderPass {
         self.pass.as_ref().unwrap()
     }
@@ -1208,3 +3132,206 @@ impl Pipeline {
         self.inner.read()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_selector_ranks_preferred_type_first() {
+        use vk::PhysicalDeviceType as T;
+
+        let discrete = DeviceSelector::new();
+        assert!(discrete.rank(T::DISCRETE_GPU) < discrete.rank(T::INTEGRATED_GPU));
+        assert!(discrete.rank(T::INTEGRATED_GPU) < discrete.rank(T::VIRTUAL_GPU));
+        assert!(discrete.rank(T::VIRTUAL_GPU) < discrete.rank(T::CPU));
+        assert!(discrete.rank(T::CPU) < discrete.rank(T::OTHER));
+
+        let integrated = DeviceSelector::new().prefer_discrete(false);
+        assert!(integrated.rank(T::INTEGRATED_GPU) < integrated.rank(T::DISCRETE_GPU));
+        assert!(integrated.rank(T::DISCRETE_GPU) < integrated.rank(T::VIRTUAL_GPU));
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct FakeDevice {
+        name: &'static str,
+        device_type: vk::PhysicalDeviceType,
+        presentation: bool,
+        extensions: Vec<CString>,
+    }
+
+    impl FakeDevice {
+        fn new(name: &'static str, device_type: vk::PhysicalDeviceType) -> FakeDevice {
+            FakeDevice {
+                name,
+                device_type,
+                presentation: false,
+                extensions: Vec::new(),
+            }
+        }
+
+        fn presentable(mut self) -> FakeDevice {
+            self.presentation = true;
+            let swapchain = unsafe { CStr::from_ptr(vk::KHR_SWAPCHAIN_EXTENSION_NAME) };
+            self.extensions.push(swapchain.to_owned());
+            self
+        }
+    }
+
+    impl DeviceCandidate for FakeDevice {
+        fn name(&self) -> String {
+            self.name.to_owned()
+        }
+
+        fn device_type(&self) -> vk::PhysicalDeviceType {
+            self.device_type
+        }
+
+        fn supports_presentation(&self) -> bool {
+            self.presentation
+        }
+
+        fn supports_extension(&self, name: &CStr) -> bool {
+            self.extensions.iter().any(|ext| ext.as_c_str() == name)
+        }
+    }
+
+    #[test]
+    fn device_selector_selects_preferred_acceptable_device() {
+        use vk::PhysicalDeviceType as T;
+
+        let igpu = FakeDevice::new("Intel UHD Graphics", T::INTEGRATED_GPU).presentable();
+        let dgpu = FakeDevice::new("NVIDIA GeForce RTX", T::DISCRETE_GPU);
+        let dgpu2 = FakeDevice::new("AMD Radeon RX", T::DISCRETE_GPU).presentable();
+        let cpu = FakeDevice::new("llvmpipe", T::CPU).presentable();
+        let devices = vec![igpu.clone(), dgpu.clone(), dgpu2.clone(), cpu.clone()];
+        let select = |selector: DeviceSelector| selector.select_with(devices.clone(), |_| true);
+
+        // Ties are broken by enumeration order.
+        assert_eq!(select(DeviceSelector::new()), Some(dgpu.clone()));
+        assert_eq!(
+            select(DeviceSelector::new().prefer_discrete(false)),
+            Some(igpu.clone())
+        );
+        assert_eq!(
+            select(DeviceSelector::new().name("geforce")),
+            Some(dgpu.clone())
+        );
+
+        // Devices missing a requirement are rejected, even if preferred.
+        assert_eq!(
+            select(DeviceSelector::new().require_presentation()),
+            Some(dgpu2.clone())
+        );
+        assert_eq!(
+            select(
+                DeviceSelector::new()
+                    .require_presentation()
+                    .name("llvmpipe")
+            ),
+            Some(cpu)
+        );
+        assert_eq!(
+            select(DeviceSelector::new().name("GeForce").require_presentation()),
+            None
+        );
+        let ray_query = CString::new("VK_KHR_ray_query").unwrap();
+        assert_eq!(
+            select(DeviceSelector::new().require_extension(&ray_query)),
+            None
+        );
+
+        // User-supplied filters apply alongside the built-in requirements.
+        assert_eq!(
+            DeviceSelector::new().select_with(devices, |dev| dev.name.starts_with("AMD")),
+            Some(dgpu2)
+        );
+    }
+
+    #[test]
+    fn pipeline_layout_validation() {
+        let limits = DeviceLimits {
+            max_bound_descriptor_sets: 4,
+            max_per_set_descriptors: 4,
+            max_push_constants_size: 128,
+            ..DeviceLimits::from(&vk::PhysicalDeviceLimits::default())
+        };
+
+        let layout = PipelineLayoutInfo::new()
+            .descriptor_set(DescriptorSetLayoutInfo::new().binding(
+                0,
+                vk::DescriptorType::SAMPLED_IMAGE,
+                4,
+                vk::ShaderStageFlags::FRAGMENT,
+            ))
+            .push_constant_range(vk::ShaderStageFlags::VERTEX, 64, 64);
+        layout.validate(&limits).unwrap();
+
+        let too_many = PipelineLayoutInfo::new().descriptor_set(
+            DescriptorSetLayoutInfo::new()
+                .binding(
+                    0,
+                    vk::DescriptorType::SAMPLED_IMAGE,
+                    4,
+                    vk::ShaderStageFlags::FRAGMENT,
+                )
+                .binding(
+                    1,
+                    vk::DescriptorType::SAMPLER,
+                    1,
+                    vk::ShaderStageFlags::FRAGMENT,
+                ),
+        );
+        let err = too_many.validate(&limits).unwrap_err();
+        assert_eq!(err.limit, "descriptors per set");
+        assert_eq!(err.requested, 5);
+
+        // The end of this range does not fit in a u32.
+        let overflowing = PipelineLayoutInfo::new().push_constant_range(
+            vk::ShaderStageFlags::VERTEX,
+            u32::MAX,
+            4,
+        );
+        let err = overflowing.validate(&limits).unwrap_err();
+        assert_eq!(err.limit, "push constant range end");
+    }
+
+    #[test]
+    fn color_blend_states_match_attachment_count() {
+        let opaque = GraphicsPipelineInfo::new();
+        assert_eq!(opaque.color_blend_states(2).unwrap().len(), 2);
+
+        let blended = GraphicsPipelineInfo::new()
+            .color_blend_attachment(ColorBlendAttachment::ALPHA)
+            .color_blend_attachment(ColorBlendAttachment::ADDITIVE);
+        let states = blended.color_blend_states(2).unwrap();
+        assert_eq!(states[0].blend_enable, vk::TRUE);
+        assert_eq!(states[1].dst_color_blend_factor, vk::BlendFactor::ONE);
+
+        assert!(matches!(
+            blended.color_blend_states(1),
+            Err(Error::ColorBlendAttachmentCount {
+                expected: 1,
+                got: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn bindless_reports_missing_feature() {
+        let mut supported = bindless_features();
+        assert_eq!(missing_bindless_feature(&supported), None);
+
+        supported.descriptor_binding_partially_bound = vk::FALSE;
+        assert_eq!(
+            missing_bindless_feature(&supported),
+            Some("descriptorBindingPartiallyBound")
+        );
+
+        supported.descriptor_binding_sampled_image_update_after_bind = vk::FALSE;
+        assert_eq!(
+            missing_bindless_feature(&supported),
+            Some("descriptorBindingSampledImageUpdateAfterBind")
+        );
+    }
+}
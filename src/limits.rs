@@ -0,0 +1,111 @@
+//! Device limits relevant to user-supplied parameters.
+
+use erupt::vk;
+use thiserror::Error;
+
+/// A subset of the device's implementation limits.
+///
+/// These are the limits most likely to be exceeded by render graph and
+/// pipeline parameters. The full set is available from
+/// `PhysicalDevice::properties`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceLimits {
+    pub max_image_dimension_2d: u32,
+    pub max_image_dimension_3d: u32,
+    pub max_image_array_layers: u32,
+    pub max_framebuffer_width: u32,
+    pub max_framebuffer_height: u32,
+    pub max_color_attachments: u32,
+    pub max_bound_descriptor_sets: u32,
+    /// The maximum number of descriptors in a single descriptor set layout.
+    pub max_per_set_descriptors: u32,
+    pub max_per_stage_resources: u32,
+    pub max_push_constants_size: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_storage_buffer_range: u32,
+    pub max_memory_allocation_count: u32,
+    /// The maximum size of a single memory allocation.
+    ///
+    /// Each buffer has its own allocation, so this also bounds buffer sizes.
+    pub max_memory_allocation_size: vk::DeviceSize,
+    pub max_vertex_input_bindings: u32,
+    pub max_vertex_input_attributes: u32,
+}
+
+impl DeviceLimits {
+    /// The minimum `maxPerSetDescriptors` required by the specification.
+    const MIN_PER_SET_DESCRIPTORS: u32 = 1024;
+    /// The minimum `maxMemoryAllocationSize` required by the specification.
+    const MIN_MEMORY_ALLOCATION_SIZE: vk::DeviceSize = 1 << 30;
+
+    /// Takes the limits which Vulkan 1.1 reports separately from the core
+    /// limits.
+    pub(crate) fn with_maintenance3(
+        mut self,
+        properties: &vk::PhysicalDeviceMaintenance3Properties,
+    ) -> DeviceLimits {
+        self.max_per_set_descriptors = properties.max_per_set_descriptors;
+        self.max_memory_allocation_size = properties.max_memory_allocation_size;
+        self
+    }
+}
+
+/// Converts the core limits. The limits reported by
+/// `VkPhysicalDeviceMaintenance3Properties` are set to their guaranteed
+/// minimums.
+impl From<&vk::PhysicalDeviceLimits> for DeviceLimits {
+    fn from(limits: &vk::PhysicalDeviceLimits) -> Self {
+        DeviceLimits {
+            max_image_dimension_2d: limits.max_image_dimension2_d,
+            max_image_dimension_3d: limits.max_image_dimension3_d,
+            max_image_array_layers: limits.max_image_array_layers,
+            max_framebuffer_width: limits.max_framebuffer_width,
+            max_framebuffer_height: limits.max_framebuffer_height,
+            max_color_attachments: limits.max_color_attachments,
+            max_bound_descriptor_sets: limits.max_bound_descriptor_sets,
+            max_per_set_descriptors: DeviceLimits::MIN_PER_SET_DESCRIPTORS,
+            max_per_stage_resources: limits.max_per_stage_resources,
+            max_push_constants_size: limits.max_push_constants_size,
+            max_uniform_buffer_range: limits.max_uniform_buffer_range,
+            max_storage_buffer_range: limits.max_storage_buffer_range,
+            max_memory_allocation_count: limits.max_memory_allocation_count,
+            max_memory_allocation_size: DeviceLimits::MIN_MEMORY_ALLOCATION_SIZE,
+            max_vertex_input_bindings: limits.max_vertex_input_bindings,
+            max_vertex_input_attributes: limits.max_vertex_input_attributes,
+        }
+    }
+}
+
+/// A parameter which exceeds a device limit.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("{limit} of {requested} exceeds the device limit of {max}")]
+pub struct LimitExceeded {
+    /// A description of the limited quantity.
+    pub limit: &'static str,
+    pub requested: u64,
+    pub max: u64,
+}
+
+impl LimitExceeded {
+    /// Returns an error if `requested` exceeds `max`.
+    pub(crate) fn check<R, M>(
+        limit: &'static str,
+        requested: R,
+        max: M,
+    ) -> Result<(), LimitExceeded>
+    where
+        R: Into<u64>,
+        M: Into<u64>,
+    {
+        let (requested, max) = (requested.into(), max.into());
+        if requested > max {
+            return Err(LimitExceeded {
+                limit,
+                requested,
+                max,
+            });
+        }
+
+        Ok(())
+    }
+}
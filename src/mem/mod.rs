@@ -1,5 +1,8 @@
 pub mod buddy;
 pub use buddy::{BuddyAllocator, BuddyBlock, BuddyBuilder, BuddyError};
+mod stats;
+pub(crate) use stats::{AllocationKind, MemoryStats};
+pub use stats::{MemoryBudget, MemoryHeapReport, MemoryReport, MemoryTypeReport};
 
 use std::{
     collections::LinkedList,
@@ -180,9 +183,20 @@ impl PhysicalDeviceMemoryProperties {
             }
         }
 
+        // Transient memory backs attachments whose contents never leave the
+        // render pass. LAZILY_ALLOCATED memory may not be backed at all on
+        // tiled GPUs, so it is used when available. Per the ordering rules
+        // above, the first matching type has the fewest extra properties.
+        let transient = (0u32..self.types.len() as u32).find(|&ty_id| {
+            self.type_properties(ty_id).contains(
+                vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::LAZILY_ALLOCATED,
+            )
+        });
+
         MemoryTypes {
             host: host.expect("No suitable host-visible memory type found."),
             device: device.expect("No suitable device-local memory type found."),
+            transient,
         }
     }
 }
@@ -191,6 +205,37 @@ impl PhysicalDeviceMemoryProperties {
 pub struct MemoryTypes {
     host: HostVisible,
     device: DeviceLocal,
+    transient: Option<u32>,
+}
+
+impl MemoryTypes {
+    /// Returns the index of the memory type used for host-visible memory.
+    pub(crate) fn host_type_index(&self) -> u32 {
+        self.host.type_index
+    }
+
+    /// Returns the index of the memory type used for device-local memory.
+    pub(crate) fn device_type_index(&self) -> u32 {
+        self.device.type_index
+    }
+
+    /// Returns the index of the memory type used for transient attachments.
+    ///
+    /// This is a lazily allocated memory type if one is compatible with
+    /// `memory_type_bits`, or the device-local memory type otherwise.
+    pub(crate) fn transient_type_index(&self, memory_type_bits: u32) -> u32 {
+        match self.transient {
+            Some(idx) if memory_type_bits & (1 << idx) != 0 => idx,
+            _ => self.device.type_index,
+        }
+    }
+
+    /// Returns whether host-visible memory is host-coherent.
+    ///
+    /// Writes to non-coherent memory must be flushed explicitly.
+    pub(crate) fn host_is_coherent(&self) -> bool {
+        self.host.is_host_coherent
+    }
 }
 
 pub trait MemoryType: private::Sealed {
@@ -267,6 +312,11 @@ impl<T: MemoryType> Drop for MemoryPoolInner<T> {
             let mut block_write = block.inner.write();
             if let Some(mem) = block_write.memory.take() {
                 unsafe { device_read.raw.free_memory(mem) }
+                device_read.memory_stats.release(
+                    AllocationKind::PoolBlock,
+                    self.ty.type_index(),
+                    self.config.block_size.into(),
+                );
             };
         }
     }
@@ -287,6 +337,11 @@ impl<T: MemoryType> MemoryPoolInner<T> {
         self.blocks.reserve(num_blocks as usize);
         for _ in 0..num_blocks {
             let memory = unsafe { device_read.raw.allocate_memory(&info)? };
+            device_read.memory_stats.record(
+                AllocationKind::PoolBlock,
+                self.ty.type_index(),
+                self.config.block_size.into(),
+            );
 
             let block = Block {
                 inner: Arc::new(RwLock::new(BlockInner {
@@ -0,0 +1,238 @@
+//! Accounting of live device memory allocations.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use erupt::vk;
+
+/// The kind of object backed by a device memory allocation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum AllocationKind {
+    Buffer,
+    Image,
+    /// An attachment whose contents never leave its render pass.
+    TransientImage,
+    /// A block owned by a [`MemoryPool`](super::MemoryPool).
+    PoolBlock,
+}
+
+const ALLOCATION_KINDS: usize = 4;
+
+#[derive(Default)]
+struct TypeCounters {
+    bytes: AtomicU64,
+    allocations: AtomicU32,
+}
+
+/// Live allocation counters of a device, updated as memory is allocated and
+/// freed.
+#[derive(Default)]
+pub(crate) struct MemoryStats {
+    types: [TypeCounters; vk::MAX_MEMORY_TYPES as usize],
+    kinds: [AtomicU32; ALLOCATION_KINDS],
+}
+
+impl MemoryStats {
+    /// Records an allocation of `size` bytes from memory type `type_index`.
+    pub fn record(&self, kind: AllocationKind, type_index: u32, size: vk::DeviceSize) {
+        let counters = &self.types[type_index as usize];
+        counters.bytes.fetch_add(size, Ordering::Relaxed);
+        counters.allocations.fetch_add(1, Ordering::Relaxed);
+        self.kinds[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the release of an allocation previously passed to `record`.
+    pub fn release(&self, kind: AllocationKind, type_index: u32, size: vk::DeviceSize) {
+        let counters = &self.types[type_index as usize];
+        counters.bytes.fetch_sub(size, Ordering::Relaxed);
+        counters.allocations.fetch_sub(1, Ordering::Relaxed);
+        self.kinds[kind as usize].fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of live allocations of all memory types.
+    pub fn allocation_count(&self) -> u32 {
+        self.types
+            .iter()
+            .map(|counters| counters.allocations.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    fn count(&self, kind: AllocationKind) -> u32 {
+        self.kinds[kind as usize].load(Ordering::Relaxed)
+    }
+
+    /// Builds a report of the current counters against the device's memory
+    /// properties and, if available, its memory budget.
+    pub fn report(
+        &self,
+        properties: &vk::PhysicalDeviceMemoryProperties,
+        budget: Option<&vk::PhysicalDeviceMemoryBudgetPropertiesEXT>,
+    ) -> MemoryReport {
+        let types = properties.memory_types[..properties.memory_type_count as usize]
+            .iter()
+            .zip(self.types.iter())
+            .map(|(ty, counters)| MemoryTypeReport {
+                heap_index: ty.heap_index,
+                flags: ty.property_flags,
+                allocated: counters.bytes.load(Ordering::Relaxed),
+                allocations: counters.allocations.load(Ordering::Relaxed),
+            })
+            .collect::<Vec<_>>();
+
+        let heaps = properties.memory_heaps[..properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, heap)| MemoryHeapReport {
+                size: heap.size,
+                flags: heap.flags,
+                allocated: types
+                    .iter()
+                    .filter(|ty| ty.heap_index as usize == index)
+                    .map(|ty| ty.allocated)
+                    .sum(),
+                budget: budget.map(|b| MemoryBudget {
+                    usage: b.heap_usage[index],
+                    budget: b.heap_budget[index],
+                }),
+            })
+            .collect();
+
+        MemoryReport {
+            heaps,
+            types,
+            buffers: self.count(AllocationKind::Buffer),
+            images: self.count(AllocationKind::Image),
+            transient_images: self.count(AllocationKind::TransientImage),
+            pool_blocks: self.count(AllocationKind::PoolBlock),
+        }
+    }
+}
+
+/// A driver-provided estimate of a heap's usage and budget, reported by
+/// `VK_EXT_memory_budget`.
+///
+/// Both values are process-wide, so they include memory not allocated through
+/// this crate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryBudget {
+    /// The estimated number of bytes of the heap used by this process.
+    pub usage: vk::DeviceSize,
+    /// The estimated number of bytes this process can use from the heap
+    /// before allocations fail or performance degrades.
+    pub budget: vk::DeviceSize,
+}
+
+/// Memory usage of a single memory heap.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryHeapReport {
+    /// The total size of the heap in bytes.
+    pub size: vk::DeviceSize,
+    pub flags: vk::MemoryHeapFlags,
+    /// The number of bytes allocated from the heap by this device.
+    pub allocated: vk::DeviceSize,
+    /// The heap's budget, if `VK_EXT_memory_budget` is enabled.
+    pub budget: Option<MemoryBudget>,
+}
+
+/// Memory usage of a single memory type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryTypeReport {
+    /// The index of the heap the memory type allocates from.
+    pub heap_index: u32,
+    pub flags: vk::MemoryPropertyFlags,
+    /// The number of bytes allocated from the memory type by this device.
+    pub allocated: vk::DeviceSize,
+    /// The number of live allocations from the memory type.
+    pub allocations: u32,
+}
+
+/// A snapshot of a device's memory usage, returned by
+/// [`Device::memory_report`](crate::Device::memory_report).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Usage per memory heap, indexed by heap index.
+    pub heaps: Vec<MemoryHeapReport>,
+    /// Usage per memory type, indexed by memory type index.
+    pub types: Vec<MemoryTypeReport>,
+    /// The number of live buffers.
+    pub buffers: u32,
+    /// The number of live images, excluding transient images.
+    pub images: u32,
+    /// The number of live transient attachment images.
+    pub transient_images: u32,
+    /// The number of blocks held by memory pools.
+    pub pool_blocks: u32,
+}
+
+impl MemoryReport {
+    /// Returns whether any allocation made through the device is still live.
+    pub fn has_live_allocations(&self) -> bool {
+        self.types.iter().any(|ty| ty.allocations > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties() -> vk::PhysicalDeviceMemoryProperties {
+        let mut props = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 3,
+            memory_heap_count: 2,
+            ..Default::default()
+        };
+        props.memory_types[0].heap_index = 0;
+        props.memory_types[1].heap_index = 1;
+        props.memory_types[2].heap_index = 0;
+        props.memory_heaps[0].size = 1 << 30;
+        props.memory_heaps[1].size = 1 << 28;
+        props
+    }
+
+    #[test]
+    fn report_aggregates_types_into_heaps() {
+        let stats = MemoryStats::default();
+        stats.record(AllocationKind::Buffer, 0, 100);
+        stats.record(AllocationKind::Image, 2, 50);
+        stats.record(AllocationKind::Buffer, 1, 10);
+
+        let report = stats.report(&properties(), None);
+        assert_eq!(report.types.len(), 3);
+        assert_eq!(report.types[2].allocated, 50);
+        assert_eq!(report.heaps.len(), 2);
+        assert_eq!(report.heaps[0].allocated, 150);
+        assert_eq!(report.heaps[1].allocated, 10);
+        assert_eq!(report.heaps[0].budget, None);
+        assert_eq!(report.buffers, 2);
+        assert_eq!(report.images, 1);
+        assert!(report.has_live_allocations());
+    }
+
+    #[test]
+    fn release_undoes_record() {
+        let stats = MemoryStats::default();
+        stats.record(AllocationKind::TransientImage, 1, 64);
+        stats.release(AllocationKind::TransientImage, 1, 64);
+
+        let report = stats.report(&properties(), None);
+        assert_eq!(report.types[1].allocated, 0);
+        assert_eq!(report.types[1].allocations, 0);
+        assert_eq!(report.transient_images, 0);
+        assert!(!report.has_live_allocations());
+    }
+
+    #[test]
+    fn report_includes_budget() {
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+        budget.heap_usage[1] = 7;
+        budget.heap_budget[1] = 9;
+
+        let report = MemoryStats::default().report(&properties(), Some(&budget));
+        assert_eq!(
+            report.heaps[1].budget,
+            Some(MemoryBudget {
+                usage: 7,
+                budget: 9
+            })
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Mipmap generation.
+
+use erupt::vk;
+
+use crate::{clear::AccessScope, vks};
+
+/// Returns the number of mip levels in a full mip chain for `extent`.
+pub fn mip_level_count(extent: vk::Extent2D) -> u32 {
+    32 - extent.width.max(extent.height).max(1).leading_zeros()
+}
+
+/// Returns the number of mip levels in a full mip chain for a 3D `extent`.
+///
+/// Unlike array layers, the depth of a 3D image is halved at each level.
+pub fn mip_level_count_3d(extent: vk::Extent3D) -> u32 {
+    32 - extent
+        .width
+        .max(extent.height)
+        .max(extent.depth)
+        .max(1)
+        .leading_zeros()
+}
+
+fn level_offset(extent: vk::Extent3D, level: u32) -> vk::Offset3D {
+    vk::Offset3D {
+        x: (extent.width >> level).max(1) as i32,
+        y: (extent.height >> level).max(1) as i32,
+        z: (extent.depth >> level).max(1) as i32,
+    }
+}
+
+fn level_barrier<'a>(
+    image: &vks::Image,
+    level: u32,
+    array_layers: u32,
+    (src_access_mask, old_layout): (vk::AccessFlags, vk::ImageLayout),
+    (dst_access_mask, new_layout): (vk::AccessFlags, vk::ImageLayout),
+) -> vks::ImageMemoryBarrierBuilder<'a> {
+    vks::ImageMemoryBarrierBuilder::new()
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .old_layout(old_layout)
+        .new_layout(new_layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: level,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: array_layers,
+        })
+}
+
+/// Describes the mip chain filled by [`generate_mips`].
+#[derive(Copy, Clone)]
+pub struct MipChainInfo<'a> {
+    pub image: &'a vks::Image,
+    /// The extent of level 0. For 2D images and arrays its depth is 1; 3D
+    /// images are downsampled in depth as well, and must have one array layer.
+    pub extent: vk::Extent3D,
+    /// The number of mip levels in the chain, including level 0.
+    pub mip_levels: u32,
+    pub array_layers: u32,
+    /// The layout every level is left in.
+    pub final_layout: vk::ImageLayout,
+}
+
+/// Records commands to fill mip levels `1..mip_levels` of a color image by
+/// repeatedly downsampling the previous level with a linear filter.
+///
+/// On completion, every level is in `final_layout`, and the writes are made
+/// available to `dst_scope`.
+///
+/// # Safety
+///
+/// The caller must uphold the following invariants:
+/// - `image` must have been created with `TRANSFER_SRC` and `TRANSFER_DST`
+///   usage, a format supporting linear blits, and at least `mip_levels` mip
+///   levels and `array_layers` array layers.
+/// - All mip levels of `image` must be in `TRANSFER_DST_OPTIMAL` layout.
+/// - Level 0 must have been written by a transfer operation which happens
+///   before this command in submission order, with no intervening barrier
+///   other than one into the transfer stage.
+pub unsafe fn generate_mips(
+    device: &vks::Device,
+    cmdbuf: &mut vks::CommandBuffer,
+    info: &MipChainInfo<'_>,
+    dst_scope: AccessScope,
+) {
+    let MipChainInfo {
+        image,
+        extent,
+        mip_levels,
+        array_layers,
+        final_layout,
+    } = *info;
+    let transfer_dst = (
+        vk::AccessFlags::TRANSFER_WRITE,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+    );
+    let transfer_src = (
+        vk::AccessFlags::TRANSFER_READ,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+    let done = (dst_scope.access_mask, final_layout);
+
+    let subresource = |level| vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: level,
+        base_array_layer: 0,
+        layer_count: array_layers,
+    };
+
+    for level in 1..mip_levels {
+        let src = level - 1;
+
+        // Wait for the source level to be written before reading it.
+        let to_src = level_barrier(image, src, array_layers, transfer_dst, transfer_src);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                cmdbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                // Safety: Produced raw handles do not outlive the block.
+                &[to_src.into_inner()],
+            );
+        }
+
+        let blit = vk::ImageBlitBuilder::new()
+            .src_subresource(subresource(src))
+            .src_offsets([vk::Offset3D::default(), level_offset(extent, src)])
+            .dst_subresource(subresource(level))
+            .dst_offsets([vk::Offset3D::default(), level_offset(extent, level)]);
+
+        unsafe {
+            device.cmd_blit_image(
+                cmdbuf,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::LINEAR,
+            );
+        }
+
+        // The source level is complete.
+        let to_final = level_barrier(image, src, array_layers, transfer_src, done);
+        unsafe {
+            device.cmd_pipeline_barrier(
+                cmdbuf,
+                vk::PipelineStageFlags::TRANSFER,
+                dst_scope.stage_mask,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_final.into_inner()],
+            );
+        }
+    }
+
+    // The last level is only ever written.
+    let last = level_barrier(
+        image,
+        mip_levels.saturating_sub(1),
+        array_layers,
+        transfer_dst,
+        done,
+    );
+    unsafe {
+        device.cmd_pipeline_barrier(
+            cmdbuf,
+            vk::PipelineStageFlags::TRANSFER,
+            dst_scope.stage_mask,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[last.into_inner()],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_chain_level_count() {
+        let count = |width, height| mip_level_count(vk::Extent2D { width, height });
+
+        assert_eq!(count(1, 1), 1);
+        assert_eq!(count(2, 1), 2);
+        assert_eq!(count(256, 256), 9);
+        assert_eq!(count(1920, 1080), 11);
+        assert_eq!(count(0, 0), 1);
+    }
+
+    #[test]
+    fn volume_levels_shrink_in_depth() {
+        let extent = vk::Extent3D {
+            width: 64,
+            height: 16,
+            depth: 128,
+        };
+        assert_eq!(mip_level_count_3d(extent), 8);
+
+        let offset = level_offset(extent, 5);
+        assert_eq!((offset.x, offset.y, offset.z), (2, 1, 4));
+
+        let flat = vk::Extent3D { depth: 1, ..extent };
+        assert_eq!(level_offset(flat, 2).z, 1);
+    }
+}
@@ -45,6 +45,12 @@ pub struct ClearDepthStencilValue {
     stencil: u32,
 }
 
+impl ClearDepthStencilValue {
+    pub const fn new(depth: f32, stencil: u32) -> ClearDepthStencilValue {
+        ClearDepthStencilValue { depth, stencil }
+    }
+}
+
 impl From<ClearDepthStencilValue> for vk::ClearDepthStencilValue {
     fn from(val: ClearDepthStencilValue) -> Self {
         vk::ClearDepthStencilValue {
@@ -74,3 +80,12 @@ pub trait RenderPass {
     /// This excludes the actual beginning and ending of the render pass, as well as subpass transitions.
     fn record(&self, device: &vks::Device, cmdbuf: &mut vks::CommandBuffer);
 }
+
+/// The compute counterpart of [`RenderPass`].
+pub trait ComputePipeline {
+    /// Records the dispatches of the compute pass to a command buffer.
+    ///
+    /// The implementation binds its own pipeline and descriptor sets. Storage
+    /// images written by the pass are in `GENERAL` layout.
+    fn record(&self, device: &vks::Device, cmdbuf: &mut vks::CommandBuffer);
+}
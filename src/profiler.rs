@@ -0,0 +1,336 @@
+//! GPU timing of command buffer scopes with timestamp queries.
+
+use std::{ffi::CString, fmt, time::Duration};
+
+use erupt::vk;
+
+use crate::{
+    util::{ErrorOnDrop, TimestampPeriod},
+    vks, Device, Error,
+};
+
+#[derive(Default)]
+pub struct DroppedGpuProfiler;
+
+impl fmt::Display for DroppedGpuProfiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GpuProfiler must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// The timestamp queries of one frame in flight.
+struct FrameQueries {
+    pool: vks::QueryPool,
+    // Scope `i` writes its start and end timestamps to queries `2i` and `2i + 1`.
+    labels: Vec<String>,
+}
+
+/// Returns the number of timestamp queries needed for `max_scopes` scopes.
+fn query_count(max_scopes: u32) -> Result<u32, Error> {
+    match max_scopes.checked_mul(2) {
+        Some(count) if count > 0 => Ok(count),
+        _ => Err(Error::InvalidProfilerCapacity(max_scopes)),
+    }
+}
+
+/// Pairs each scope's label with the time between its start and end
+/// timestamps.
+fn scope_timings<'a>(
+    period: TimestampPeriod,
+    labels: impl Iterator<Item = String> + 'a,
+    timestamps: &'a [u64],
+) -> impl Iterator<Item = (String, Duration)> + 'a {
+    labels
+        .zip(timestamps.chunks_exact(2))
+        .map(move |(label, ts)| (label, period.elapsed(ts[0], ts[1])))
+}
+
+/// Measures the GPU time taken by labeled scopes of a frame's commands.
+///
+/// Each frame in flight has its own query pool, so a frame's timings are read
+/// back when its slot is next reused, after the caller has waited for it to
+/// complete. Reading results never stalls the GPU; timings are reported one
+/// lap of the frames in flight late.
+///
+/// Timestamps are written on the graphics queue, and scopes may be nested.
+pub struct GpuProfiler {
+    bomb: ErrorOnDrop<DroppedGpuProfiler>,
+
+    period: TimestampPeriod,
+    max_scopes: u32,
+
+    frames: Vec<FrameQueries>,
+    current_frame: Option<usize>,
+    // The queries of each open scope, or `None` if the scope did not fit in
+    // the query pool.
+    open_scopes: Vec<Option<u32>>,
+
+    last_frame: Vec<(String, Duration)>,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler with room for `max_scopes` scopes per frame.
+    ///
+    /// Returns [`Error::TimestampsUnsupported`] if the graphics queue cannot
+    /// write timestamps, or [`Error::InvalidProfilerCapacity`] if
+    /// `max_scopes` is zero or too large for a query pool.
+    pub fn new(
+        device: &Device,
+        frames_in_flight: usize,
+        max_scopes: u32,
+    ) -> Result<GpuProfiler, Error> {
+        let query_count = query_count(max_scopes)?;
+        let period = device
+            .physical_device()
+            .graphics_timestamp_period()
+            .ok_or(Error::TimestampsUnsupported)?;
+
+        let device_read = device.read_inner();
+        let create_info = vk::QueryPoolCreateInfoBuilder::new()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(query_count);
+
+        let mut frames = Vec::with_capacity(frames_in_flight);
+        for _ in 0..frames_in_flight {
+            match unsafe { device_read.raw.create_query_pool(&create_info) } {
+                Ok(pool) => frames.push(FrameQueries {
+                    pool,
+                    labels: Vec::new(),
+                }),
+                Err(e) => {
+                    for frame in frames {
+                        unsafe { device_read.raw.destroy_query_pool(frame.pool) };
+                    }
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Ok(GpuProfiler {
+            bomb: ErrorOnDrop::new(DroppedGpuProfiler),
+            period,
+            max_scopes,
+            frames,
+            current_frame: None,
+            open_scopes: Vec::new(),
+            last_frame: Vec::new(),
+        })
+    }
+
+    /// Begins profiling frame slot `frame_index`.
+    ///
+    /// The timings last recorded in this slot are read back and become the
+    /// result of [`GpuProfiler::last_frame_timings`]. The slot's queries are
+    /// then reset in `command_buffer`, which must precede all scopes of the
+    /// frame in submission order.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the profiler was created with.
+    /// - The previous submission of this frame slot must have completed
+    ///   execution.
+    /// - `command_buffer` must be in the recording state and be submitted to
+    ///   the graphics queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a scope of the previous frame is still open.
+    pub unsafe fn begin_frame(
+        &mut self,
+        device: &Device,
+        frame_index: usize,
+        command_buffer: &mut vks::CommandBuffer,
+    ) -> Result<(), Error> {
+        assert!(
+            self.open_scopes.is_empty(),
+            "{} profiler scopes left open",
+            self.open_scopes.len()
+        );
+
+        let device_read = device.read_inner();
+        let frame = &mut self.frames[frame_index];
+
+        if !frame.labels.is_empty() {
+            let mut timestamps = vec![0; 2 * frame.labels.len()];
+            let ready = unsafe {
+                device_read.raw.get_query_pool_results_u64(
+                    &frame.pool,
+                    0,
+                    timestamps.len() as u32,
+                    &mut timestamps,
+                )?
+            };
+
+            // Results are only unavailable if the frame was never submitted.
+            if ready {
+                self.last_frame.clear();
+                self.last_frame.extend(scope_timings(
+                    self.period,
+                    frame.labels.drain(..),
+                    &timestamps,
+                ));
+            }
+            frame.labels.clear();
+        }
+
+        unsafe {
+            device_read.raw.cmd_reset_query_pool(
+                command_buffer,
+                &frame.pool,
+                0,
+                2 * self.max_scopes,
+            );
+        }
+
+        self.current_frame = Some(frame_index);
+        Ok(())
+    }
+
+    /// Opens a scope, writing its start timestamp.
+    ///
+    /// The scope is also marked with a debug label, so it appears in captures
+    /// taken by graphics debuggers. Scopes beyond the profiler's capacity are
+    /// labeled, but not measured.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the profiler was created with.
+    /// - `command_buffer` must be in the recording state and be submitted to
+    ///   the graphics queue after the frame's reset.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no frame has begun.
+    pub unsafe fn begin_scope<S: Into<String>>(
+        &mut self,
+        device: &Device,
+        command_buffer: &mut vks::CommandBuffer,
+        label: S,
+    ) {
+        let frame_index = self.current_frame.expect("no profiler frame has begun");
+        let frame = &mut self.frames[frame_index];
+        let label = label.into();
+
+        let device_read = device.read_inner();
+        let label_cstr = CString::new(label.replace('\0', "")).unwrap();
+        unsafe {
+            device_read
+                .raw
+                .cmd_begin_debug_utils_label(command_buffer, &label_cstr, [0.0; 4]);
+        }
+
+        let scope = frame.labels.len() as u32;
+        if scope >= self.max_scopes {
+            log::warn!("GPU profiler scope limit of {} exceeded", self.max_scopes);
+            self.open_scopes.push(None);
+            return;
+        }
+
+        frame.labels.push(label);
+        self.open_scopes.push(Some(scope));
+
+        unsafe {
+            device_read.raw.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlagBits::TOP_OF_PIPE,
+                &frame.pool,
+                2 * scope,
+            );
+        }
+    }
+
+    /// Closes the most recently opened scope, writing its end timestamp.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the profiler was created with.
+    /// - `command_buffer` must be in the recording state and be submitted to
+    ///   the graphics queue after the scope's start.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no scope is open.
+    pub unsafe fn end_scope(&mut self, device: &Device, command_buffer: &mut vks::CommandBuffer) {
+        let scope = self.open_scopes.pop().expect("no profiler scope is open");
+
+        let device_read = device.read_inner();
+        unsafe { device_read.raw.cmd_end_debug_utils_label(command_buffer) };
+
+        let scope = match scope {
+            Some(s) => s,
+            None => return,
+        };
+
+        let frame = &self.frames[self.current_frame.unwrap()];
+        unsafe {
+            device_read.raw.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlagBits::BOTTOM_OF_PIPE,
+                &frame.pool,
+                2 * scope + 1,
+            );
+        }
+    }
+
+    /// Returns the label and GPU duration of each scope of the most recently
+    /// read back frame, in the order the scopes were opened.
+    pub fn last_frame_timings(&self) -> &[(String, Duration)] {
+        &self.last_frame
+    }
+
+    /// Destroys the profiler's query pools.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the profiler was created with.
+    /// - All submitted commands that write the profiler's timestamps must have
+    ///   completed execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+
+        let device_read = device.read_inner();
+        for frame in self.frames.drain(..) {
+            unsafe { device_read.raw.destroy_query_pool(frame.pool) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_count_rejects_invalid_capacity() {
+        assert_eq!(query_count(1).unwrap(), 2);
+        assert_eq!(query_count(64).unwrap(), 128);
+        assert!(matches!(
+            query_count(0),
+            Err(Error::InvalidProfilerCapacity(0))
+        ));
+        assert!(matches!(
+            query_count(u32::MAX / 2 + 1),
+            Err(Error::InvalidProfilerCapacity(_))
+        ));
+    }
+
+    #[test]
+    fn nested_scope_timings() {
+        let period = TimestampPeriod::new(2.0, 64).unwrap();
+        let labels = vec!["frame".to_owned(), "shadows".to_owned()];
+        // The inner scope's timestamps lie within the outer scope's.
+        let timestamps = [100, 200, 120, 150];
+
+        let timings = scope_timings(period, labels.into_iter(), &timestamps).collect::<Vec<_>>();
+        assert_eq!(
+            timings,
+            [
+                ("frame".to_owned(), Duration::from_nanos(200)),
+                ("shadows".to_owned(), Duration::from_nanos(60)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Occlusion and pipeline statistics queries.
+
+use std::fmt;
+
+use erupt::vk;
+
+use crate::{util::ErrorOnDrop, vks, Device, Error};
+
+#[derive(Default)]
+pub struct DroppedQueryPool;
+
+impl fmt::Display for DroppedQueryPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("QueryPool must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// The kind of queries held by a [`QueryPool`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueryKind {
+    /// Counts the samples which pass the depth and stencil tests.
+    ///
+    /// If `precise` is false, the result may only be relied upon to be zero
+    /// or non-zero. Precise counts require the `occlusionQueryPrecise`
+    /// feature.
+    Occlusion { precise: bool },
+    /// Counts the statistics selected by the flags, such as the number of
+    /// fragment shader invocations. Requires the `pipelineStatisticsQuery`
+    /// feature.
+    PipelineStatistics(vk::QueryPipelineStatisticFlags),
+}
+
+impl QueryKind {
+    /// Returns the number of values produced by each query of this kind.
+    ///
+    /// Pipeline statistics are reported in order of increasing flag bit.
+    pub fn values_per_query(&self) -> usize {
+        match self {
+            QueryKind::Occlusion { .. } => 1,
+            QueryKind::PipelineStatistics(flags) => flags.bits().count_ones() as usize,
+        }
+    }
+
+    fn is_supported(&self, features: &vk::PhysicalDeviceFeatures) -> bool {
+        match self {
+            QueryKind::Occlusion { precise } => !precise || features.occlusion_query_precise != 0,
+            QueryKind::PipelineStatistics(flags) => {
+                !flags.is_empty() && features.pipeline_statistics_query != 0
+            }
+        }
+    }
+
+    fn query_type(&self) -> vk::QueryType {
+        match self {
+            QueryKind::Occlusion { .. } => vk::QueryType::OCCLUSION,
+            QueryKind::PipelineStatistics(_) => vk::QueryType::PIPELINE_STATISTICS,
+        }
+    }
+}
+
+/// A pool of occlusion or pipeline statistics queries.
+///
+/// Queries must be reset before each use. Results are read with
+/// [`QueryPool::results`] once the commands which wrote them have completed,
+/// e.g. after waiting on the frame's fence.
+pub struct QueryPool {
+    bomb: ErrorOnDrop<DroppedQueryPool>,
+
+    raw: vks::QueryPool,
+    kind: QueryKind,
+    count: u32,
+}
+
+impl QueryPool {
+    pub(crate) fn create(device: &Device, kind: QueryKind, count: u32) -> Result<QueryPool, Error> {
+        let device_read = device.read_inner();
+        if !kind.is_supported(device_read.enabled_features()) {
+            return Err(Error::QueryUnsupported(kind));
+        }
+
+        let mut create_info = vk::QueryPoolCreateInfoBuilder::new()
+            .query_type(kind.query_type())
+            .query_count(count);
+        if let QueryKind::PipelineStatistics(flags) = kind {
+            create_info = create_info.pipeline_statistics(flags);
+        }
+
+        let raw = unsafe { device_read.raw.create_query_pool(&create_info)? };
+
+        Ok(QueryPool {
+            bomb: ErrorOnDrop::new(DroppedQueryPool),
+            raw,
+            kind,
+            count,
+        })
+    }
+
+    /// Returns the underlying query pool handle.
+    pub fn raw(&self) -> &vks::QueryPool {
+        &self.raw
+    }
+
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    /// Returns the number of queries in the pool.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Records a reset of `query_count` queries starting at `first_query`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool was created with.
+    /// - `command_buffer` must be in the recording state, outside of a render
+    ///   pass instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the queries are out of the pool's range.
+    pub unsafe fn cmd_reset(
+        &self,
+        device: &Device,
+        command_buffer: &mut vks::CommandBuffer,
+        first_query: u32,
+        query_count: u32,
+    ) {
+        assert!(first_query
+            .checked_add(query_count)
+            .map_or(false, |end| end <= self.count));
+
+        let device_read = device.read_inner();
+        unsafe {
+            device_read.raw.cmd_reset_query_pool(
+                command_buffer,
+                &self.raw,
+                first_query,
+                query_count,
+            );
+        }
+    }
+
+    /// Records the commands of `f` within query `query`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool was created with.
+    /// - `command_buffer` must be in the recording state.
+    /// - `query` must have been reset, and not used since.
+    /// - `f` must not end the current render pass instance, if any.
+    pub unsafe fn scope<R, F>(
+        &self,
+        device: &Device,
+        command_buffer: &mut vks::CommandBuffer,
+        query: u32,
+        f: F,
+    ) -> R
+    where
+        F: FnOnce(&mut vks::CommandBuffer) -> R,
+    {
+        assert!(query < self.count);
+
+        let flags = match self.kind {
+            QueryKind::Occlusion { precise: true } => vk::QueryControlFlags::PRECISE,
+            _ => vk::QueryControlFlags::empty(),
+        };
+
+        unsafe {
+            device
+                .read_inner()
+                .raw
+                .cmd_begin_query(command_buffer, &self.raw, query, flags);
+        }
+
+        let result = f(command_buffer);
+
+        unsafe {
+            device
+                .read_inner()
+                .raw
+                .cmd_end_query(command_buffer, &self.raw, query);
+        }
+
+        result
+    }
+
+    /// Reads the results of `query_count` queries starting at `first_query`.
+    ///
+    /// Each query contributes [`QueryKind::values_per_query`] values. Does not
+    /// wait for results; returns `Ok(None)` if any query is not yet available.
+    ///
+    /// Returns [`Error::Vulkan`] with `ERROR_VALIDATION_FAILED_EXT` if the
+    /// queries are out of the pool's range.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool was created with.
+    /// - Each query must have been reset and written since.
+    pub unsafe fn results(
+        &self,
+        device: &Device,
+        first_query: u32,
+        query_count: u32,
+    ) -> Result<Option<Vec<u64>>, Error> {
+        if first_query
+            .checked_add(query_count)
+            .map_or(true, |end| end > self.count)
+        {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT.into());
+        }
+
+        let mut data = vec![0; query_count as usize * self.kind.values_per_query()];
+        if data.is_empty() {
+            return Ok(Some(data));
+        }
+
+        let device_read = device.read_inner();
+        let ready = unsafe {
+            device_read.raw.get_query_pool_results_u64(
+                &self.raw,
+                first_query,
+                query_count,
+                &mut data,
+            )?
+        };
+
+        Ok(if ready { Some(data) } else { None })
+    }
+
+    /// Destroys the query pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool was created with.
+    /// - All submitted commands that refer to the pool must have completed
+    ///   execution.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+
+        let device_read = device.read_inner();
+        unsafe { device_read.raw.destroy_query_pool(self.raw) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipeline_statistics_values_per_query() {
+        assert_eq!(QueryKind::Occlusion { precise: true }.values_per_query(), 1);
+
+        let flags = vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES
+            | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS
+            | vk::QueryPipelineStatisticFlags::COMPUTE_SHADER_INVOCATIONS;
+        assert_eq!(QueryKind::PipelineStatistics(flags).values_per_query(), 3);
+
+        let features = vk::PhysicalDeviceFeatures::default();
+        assert!(QueryKind::Occlusion { precise: false }.is_supported(&features));
+        assert!(!QueryKind::Occlusion { precise: true }.is_supported(&features));
+        assert!(!QueryKind::PipelineStatistics(flags).is_supported(&features));
+    }
+}
@@ -0,0 +1,465 @@
+//! Readback of device buffers and images to the host.
+
+use std::fmt;
+
+use erupt::vk;
+
+use crate::{
+    clear::AccessScope,
+    copy::{self, ImageToBufferCopyInfo},
+    util::{self, ErrorOnDrop},
+    vks::{self, VkObject},
+    Buffer, BufferCreateInfo, Device, Error, FormatExt, MemoryLocation,
+};
+
+#[derive(Default)]
+pub struct DroppedReadbackPool;
+
+impl fmt::Display for DroppedReadbackPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReadbackPool must be manually destroyed with .destroy_with()")
+    }
+}
+
+/// Identifies a readback submitted to a [`ReadbackPool`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReadbackId(u64);
+
+/// The command buffer and fence of a single submission.
+struct Submission {
+    commands: vks::CommandBuffer,
+    // Signaled when the submission completes.
+    fence: vks::Fence,
+}
+
+impl Submission {
+    fn create(device: &Device) -> Result<Submission, Error> {
+        let device_read = device.read_inner();
+        let fence = unsafe {
+            device_read
+                .raw
+                .create_fence(&vk::FenceCreateInfoBuilder::new())?
+        };
+
+        let graphics_command_pool = device.graphics_command_pool();
+        let allocated = graphics_command_pool.get_mut().and_then(|mut pool| {
+            let allocate_info = vks::CommandBufferAllocateInfoBuilder::new()
+                .command_pool(&mut pool)
+                .level(vk::CommandBufferLevel::PRIMARY)
+                .command_buffer_count(1);
+            unsafe { device_read.raw.allocate_command_buffers(&allocate_info) }
+        });
+
+        match allocated {
+            Ok(mut allocated) => Ok(Submission {
+                commands: allocated.pop().unwrap(),
+                fence,
+            }),
+            Err(e) => {
+                unsafe { device_read.raw.destroy_fence(fence) };
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Records the commands written by `record` and submits them to the
+    /// graphics queue.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - The previous submission of `self`, if any, must have completed
+    ///   execution.
+    /// - The commands written by `record` must be valid to submit.
+    unsafe fn submit<F>(&mut self, device: &Device, record: F) -> Result<(), Error>
+    where
+        F: FnOnce(&vks::Device, &mut vks::CommandBuffer),
+    {
+        let device_read = device.read_inner();
+        let begin_info = vk::CommandBufferBeginInfoBuilder::new()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+
+        unsafe {
+            device_read
+                .raw
+                .begin_command_buffer(&mut self.commands, &begin_info)?;
+            record(&device_read.raw, &mut self.commands);
+            device_read.raw.end_command_buffer(&mut self.commands)?;
+
+            let command_buffers = [*self.commands.handle_mut()];
+            let submit_info = vk::SubmitInfoBuilder::new().command_buffers(&command_buffers);
+
+            device_read.raw.reset_fences(&[*self.fence.handle_mut()])?;
+
+            let graphics_queue = device.graphics_queue();
+            let mut graphics_queue_write = graphics_queue.write_inner();
+            device_read.raw.queue_submit(
+                &mut graphics_queue_write.raw,
+                &[submit_info],
+                Some(&mut self.fence),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+struct PendingReadback {
+    id: ReadbackId,
+    submission: Submission,
+    staging: Buffer,
+}
+
+/// Copies device buffers and images into host memory.
+///
+/// Each readback is recorded into its own command buffer and submitted to
+/// the graphics queue immediately, so it observes all work submitted to the
+/// queue before it. Its data is retrieved with [`ReadbackPool::poll`], which
+/// never blocks, or [`ReadbackPool::wait`].
+///
+/// The command buffers of completed readbacks are reused. They are allocated
+/// from the calling thread's graphics command pool, so a pool should be used
+/// from a single thread.
+pub struct ReadbackPool {
+    bomb: ErrorOnDrop<DroppedReadbackPool>,
+
+    next_id: u64,
+    pending: Vec<PendingReadback>,
+    // Submissions which have completed and may be reused.
+    idle: Vec<Submission>,
+}
+
+impl Default for ReadbackPool {
+    fn default() -> ReadbackPool {
+        ReadbackPool::new()
+    }
+}
+
+impl ReadbackPool {
+    pub fn new() -> ReadbackPool {
+        ReadbackPool {
+            bomb: ErrorOnDrop::new(DroppedReadbackPool),
+            next_id: 0,
+            pending: Vec::new(),
+            idle: Vec::new(),
+        }
+    }
+
+    /// Returns the number of readbacks whose data has not yet been retrieved.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Submits a readback of `size` bytes of `src`, starting at byte `offset`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool is used with.
+    /// - `src` must have been created with `TRANSFER_SRC` usage, and contain
+    ///   the range read.
+    /// - `src` must not be destroyed until the readback completes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub unsafe fn read_buffer(
+        &mut self,
+        device: &Device,
+        src: &vks::Buffer,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> Result<ReadbackId, Error> {
+        assert!(size > 0, "readback of 0 bytes");
+
+        unsafe {
+            self.submit(device, size, |raw, cmdbuf, staging| {
+                let before_read = vk::MemoryBarrierBuilder::new()
+                    .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+                    .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+                raw.cmd_pipeline_barrier(
+                    cmdbuf,
+                    vk::PipelineStageFlags::ALL_COMMANDS,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[before_read],
+                    &[],
+                    &[],
+                );
+
+                let region = vk::BufferCopyBuilder::new()
+                    .src_offset(offset)
+                    .dst_offset(0)
+                    .size(size);
+                raw.cmd_copy_buffer(cmdbuf, src, staging, &[region]);
+
+                let after_write = vk::MemoryBarrierBuilder::new()
+                    .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                    .dst_access_mask(AccessScope::HOST_READ.access_mask);
+                raw.cmd_pipeline_barrier(
+                    cmdbuf,
+                    vk::PipelineStageFlags::TRANSFER,
+                    AccessScope::HOST_READ.stage_mask,
+                    vk::DependencyFlags::empty(),
+                    &[after_write],
+                    &[],
+                    &[],
+                );
+            })
+        }
+    }
+
+    /// Submits a readback of the first `extent` texels of the layers in
+    /// `subresource` of `src`.
+    ///
+    /// The data is tightly packed, with each layer following the previous
+    /// one. `src` is returned to `src_layout` after the copy.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool is used with.
+    /// - `src` must have been created with `format` and `TRANSFER_SRC` usage,
+    ///   and contain the region read.
+    /// - The read subresource of `src` must be in `src_layout`, which must not
+    ///   be `UNDEFINED`, when the readback executes.
+    /// - `src` must not be destroyed until the readback completes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `format` has no known texel size, or if the region is empty.
+    pub unsafe fn read_image(
+        &mut self,
+        device: &Device,
+        src: &vks::Image,
+        src_layout: vk::ImageLayout,
+        format: vk::Format,
+        subresource: vk::ImageSubresourceLayers,
+        extent: vk::Extent3D,
+    ) -> Result<ReadbackId, Error> {
+        let size = packed_size(format, extent, subresource.layer_count)
+            .expect("format has no known texel size");
+        assert!(size > 0, "readback of an empty image region");
+
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: subresource,
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: extent,
+        };
+
+        unsafe {
+            self.submit(device, size, |raw, cmdbuf, staging| {
+                let info = ImageToBufferCopyInfo {
+                    src,
+                    src_layout,
+                    dst: staging,
+                    region,
+                };
+                copy::copy_image_to_buffer(raw, cmdbuf, &info, AccessScope::HOST_READ);
+            })
+        }
+    }
+
+    /// Allocates a staging buffer of `size` bytes and submits the commands
+    /// written by `record`, which copy into it.
+    unsafe fn submit<F>(
+        &mut self,
+        device: &Device,
+        size: vk::DeviceSize,
+        record: F,
+    ) -> Result<ReadbackId, Error>
+    where
+        F: FnOnce(&vks::Device, &mut vks::CommandBuffer, &vks::Buffer),
+    {
+        let buffer_info = BufferCreateInfo::new(
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::Host,
+        )
+        .label("readback");
+        let staging = device.create_buffer(&buffer_info)?;
+
+        let mut submission = match self.idle.pop() {
+            Some(submission) => submission,
+            None => match Submission::create(device) {
+                Ok(submission) => submission,
+                Err(e) => {
+                    unsafe { staging.destroy_with(device) };
+                    return Err(e);
+                }
+            },
+        };
+
+        let submitted =
+            unsafe { submission.submit(device, |raw, cmdbuf| record(raw, cmdbuf, staging.raw())) };
+        if let Err(e) = submitted {
+            // Nothing was submitted, so both can be reused or freed at once.
+            self.idle.push(submission);
+            unsafe { staging.destroy_with(device) };
+            return Err(e);
+        }
+
+        let id = ReadbackId(self.next_id);
+        self.next_id += 1;
+        self.pending.push(PendingReadback {
+            id,
+            submission,
+            staging,
+        });
+
+        Ok(id)
+    }
+
+    fn pending_index(&self, id: ReadbackId) -> usize {
+        self.pending
+            .iter()
+            .position(|pending| pending.id == id)
+            .expect("readback is not pending")
+    }
+
+    /// Returns the data of readback `id` if it has completed.
+    ///
+    /// Once returned, the data is no longer held by the pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool is used with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not pending, e.g. if its data was already returned.
+    pub unsafe fn poll(
+        &mut self,
+        device: &Device,
+        id: ReadbackId,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let index = self.pending_index(id);
+        let status = {
+            let device_read = device.read_inner();
+            unsafe {
+                device_read
+                    .raw
+                    .get_fence_status(&self.pending[index].submission.fence)?
+            }
+        };
+
+        match status {
+            vks::FenceStatus::Signaled => unsafe { self.finish(device, index) }.map(Some),
+            vks::FenceStatus::Unsignaled => Ok(None),
+        }
+    }
+
+    /// Blocks until readback `id` completes and returns its data.
+    ///
+    /// Once returned, the data is no longer held by the pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool is used with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not pending, e.g. if its data was already returned.
+    pub unsafe fn wait(&mut self, device: &Device, id: ReadbackId) -> Result<Vec<u8>, Error> {
+        let index = self.pending_index(id);
+        {
+            let device_read = device.read_inner();
+            let fence = unsafe { *self.pending[index].submission.fence.handle() };
+            unsafe { device_read.raw.wait_for_fences(&[fence], true, None)? };
+        }
+
+        unsafe { self.finish(device, index) }
+    }
+
+    /// Reads back the data of the completed readback at `index` in `pending`.
+    unsafe fn finish(&mut self, device: &Device, index: usize) -> Result<Vec<u8>, Error> {
+        let pending = self.pending.swap_remove(index);
+        self.idle.push(pending.submission);
+
+        let len = pending.staging.size() as usize;
+        let data = unsafe { pending.staging.read(device, 0, len) };
+        unsafe { pending.staging.destroy_with(device) };
+
+        Ok(data?)
+    }
+
+    /// Waits for all pending readbacks, discards their data, and destroys the
+    /// pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `device` must be the device the pool is used with.
+    pub unsafe fn destroy_with(mut self, device: &Device) {
+        self.bomb.disarm();
+
+        let device_read = device.read_inner();
+        let fences = self
+            .pending
+            .iter()
+            .map(|pending| unsafe { *pending.submission.fence.handle() })
+            .collect::<Vec<_>>();
+        if !fences.is_empty() {
+            if let Err(e) = unsafe { device_read.raw.wait_for_fences(&fences, true, None) } {
+                log::error!("Failed to wait for pending readbacks: {}", e);
+            }
+        }
+
+        for pending in self.pending.drain(..) {
+            unsafe { pending.staging.destroy_with(device) };
+            self.idle.push(pending.submission);
+        }
+
+        // Command buffers are destroyed automatically along with their owning
+        // pools.
+        for submission in self.idle.drain(..) {
+            unsafe { device_read.raw.destroy_fence(submission.fence) };
+        }
+    }
+}
+
+/// Returns the size in bytes of `layers` layers of `extent` texels of
+/// `format`, tightly packed.
+fn packed_size(format: vk::Format, extent: vk::Extent3D, layers: u32) -> Option<vk::DeviceSize> {
+    let block_size = format.block_size()?;
+    let (block_width, block_height) = format.block_extent()?;
+    let blocks = |texels: u32, block: u32| util::ceil_div(texels.into(), block.into());
+
+    Some(
+        block_size as vk::DeviceSize
+            * blocks(extent.width, block_width)
+            * blocks(extent.height, block_height)
+            * extent.depth as vk::DeviceSize
+            * layers as vk::DeviceSize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_size_rounds_up_to_whole_blocks() {
+        let extent = vk::Extent3D {
+            width: 5,
+            height: 5,
+            depth: 1,
+        };
+
+        assert_eq!(
+            packed_size(vk::Format::R8G8B8A8_UNORM, extent, 2),
+            Some(200)
+        );
+        // BC1 stores 4x4 blocks of 8 bytes.
+        assert_eq!(
+            packed_size(vk::Format::BC1_RGB_UNORM_BLOCK, extent, 1),
+            Some(32)
+        );
+        assert_eq!(packed_size(vk::Format::UNDEFINED, extent, 1), None);
+    }
+}
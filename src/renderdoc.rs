@@ -0,0 +1,130 @@
+//! Programmatic frame captures with RenderDoc.
+//!
+//! RenderDoc must inject itself into the process before the Vulkan instance is
+//! created, e.g. by launching the application from the RenderDoc UI or with
+//! `renderdoccmd capture`. This module only attaches to an injected RenderDoc;
+//! it never loads the library itself.
+
+use std::{os::raw::c_void, ptr};
+
+use libloading::Library;
+
+// eRENDERDOC_API_Version_1_1_2.
+const API_VERSION_1_1_2: i32 = 10102;
+
+type GetApiFn = unsafe extern "C" fn(version: i32, out_api_pointers: *mut *mut c_void) -> i32;
+type VoidFn = unsafe extern "C" fn();
+type CaptureFn = unsafe extern "C" fn(device: *mut c_void, window: *mut c_void);
+type EndCaptureFn = unsafe extern "C" fn(device: *mut c_void, window: *mut c_void) -> u32;
+type IsCapturingFn = unsafe extern "C" fn() -> u32;
+
+/// The function table of `RENDERDOC_API_1_1_2`.
+///
+/// Entries which are not used are kept as untyped pointers to preserve the
+/// layout.
+#[repr(C)]
+struct ApiTable {
+    _get_api_version: *const c_void,
+    _set_capture_option_u32: *const c_void,
+    _set_capture_option_f32: *const c_void,
+    _get_capture_option_u32: *const c_void,
+    _get_capture_option_f32: *const c_void,
+    _set_focus_toggle_keys: *const c_void,
+    _set_capture_keys: *const c_void,
+    _get_overlay_bits: *const c_void,
+    _mask_overlay_bits: *const c_void,
+    _shutdown: *const c_void,
+    _unload_crash_handler: *const c_void,
+    _set_capture_file_path_template: *const c_void,
+    _get_capture_file_path_template: *const c_void,
+    _get_num_captures: *const c_void,
+    _get_capture: *const c_void,
+    trigger_capture: VoidFn,
+    _is_target_control_connected: *const c_void,
+    _launch_replay_ui: *const c_void,
+    _set_active_window: *const c_void,
+    start_frame_capture: CaptureFn,
+    is_frame_capturing: IsCapturingFn,
+    end_frame_capture: EndCaptureFn,
+}
+
+/// A handle to a RenderDoc instance injected into the process.
+pub struct RenderDoc {
+    api: *const ApiTable,
+    // Keeps the library loaded while the function table is in use.
+    _library: Library,
+}
+
+impl RenderDoc {
+    /// Attaches to RenderDoc, if it has been injected into the process.
+    ///
+    /// Returns `None` if RenderDoc is not loaded or does not support the
+    /// required API version.
+    pub fn attach() -> Option<RenderDoc> {
+        let library = unsafe { Self::open_loaded()? };
+
+        let api = unsafe {
+            let get_api = library.get::<GetApiFn>(b"RENDERDOC_GetAPI\0").ok()?;
+            let mut api: *mut c_void = ptr::null_mut();
+            if get_api(API_VERSION_1_1_2, &mut api) != 1 || api.is_null() {
+                return None;
+            }
+            api as *const ApiTable
+        };
+
+        Some(RenderDoc {
+            api,
+            _library: library,
+        })
+    }
+
+    #[cfg(unix)]
+    unsafe fn open_loaded() -> Option<Library> {
+        use libloading::os::unix::{Library, RTLD_NOW};
+
+        // Not exposed by libloading. The value is shared by glibc and musl.
+        const RTLD_NOLOAD: i32 = 0x4;
+
+        let library = unsafe { Library::open(Some("librenderdoc.so"), RTLD_NOW | RTLD_NOLOAD) };
+        library.ok().map(Into::into)
+    }
+
+    #[cfg(windows)]
+    unsafe fn open_loaded() -> Option<Library> {
+        libloading::os::windows::Library::open_already_loaded("renderdoc.dll")
+            .ok()
+            .map(Into::into)
+    }
+
+    fn api(&self) -> &ApiTable {
+        // Safety: RenderDoc keeps the table alive while the library is loaded.
+        unsafe { &*self.api }
+    }
+
+    /// Captures the next frame presented by any swapchain.
+    pub fn capture_next_frame(&self) {
+        unsafe { (self.api().trigger_capture)() }
+    }
+
+    /// Begins capturing all work submitted until
+    /// [`RenderDoc::end_frame_capture`], regardless of presentation.
+    ///
+    /// This allows capturing work which is never presented, such as
+    /// offscreen rendering.
+    pub fn start_frame_capture(&self) {
+        unsafe { (self.api().start_frame_capture)(ptr::null_mut(), ptr::null_mut()) }
+    }
+
+    /// Ends a capture begun with [`RenderDoc::start_frame_capture`].
+    ///
+    /// Returns `false` if the capture failed.
+    pub fn end_frame_capture(&self) -> bool {
+        unsafe { (self.api().end_frame_capture)(ptr::null_mut(), ptr::null_mut()) == 1 }
+    }
+
+    /// Returns `true` if a capture begun with
+    /// [`RenderDoc::start_frame_capture`] is in progress.
+    pub fn is_frame_capturing(&self) -> bool {
+        unsafe { (self.api().is_frame_capturing)() == 1 }
+    }
+}
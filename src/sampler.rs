@@ -0,0 +1,214 @@
+//! Sampler configuration.
+
+use erupt::vk;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SamplerError {
+    #[error("Anisotropic filtering requires the samplerAnisotropy feature.")]
+    AnisotropyNotEnabled,
+    #[error("Max anisotropy {requested} is less than 1.0.")]
+    AnisotropyTooLow { requested: f32 },
+    #[error("Max anisotropy {requested} exceeds the device limit of {limit}.")]
+    AnisotropyTooHigh { requested: f32, limit: f32 },
+    #[error("LOD bias {requested} exceeds the device limit of ±{limit}.")]
+    LodBiasTooHigh { requested: f32, limit: f32 },
+    #[error("Minimum LOD {min} is greater than maximum LOD {max}.")]
+    InvalidLodRange { min: f32, max: f32 },
+}
+
+/// Describes how a sampler filters and addresses an image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SamplerInfo {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    /// The addressing modes for the U, V and W coordinates.
+    pub address_modes: [vk::SamplerAddressMode; 3],
+    /// The maximum anisotropy, or `None` to disable anisotropic filtering.
+    ///
+    /// Requires the `samplerAnisotropy` feature.
+    pub max_anisotropy: Option<f32>,
+    /// The bias added to the computed level of detail.
+    pub mip_lod_bias: f32,
+    pub min_lod: f32,
+    /// The maximum level of detail. `vk::LOD_CLAMP_NONE` does not clamp.
+    pub max_lod: f32,
+    /// The color returned when sampling outside a `CLAMP_TO_BORDER` image.
+    pub border_color: vk::BorderColor,
+    /// The comparison applied to fetched texels, for depth comparison
+    /// samplers.
+    pub compare_op: Option<vk::CompareOp>,
+}
+
+impl Default for SamplerInfo {
+    fn default() -> Self {
+        SamplerInfo {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_modes: [vk::SamplerAddressMode::REPEAT; 3],
+            max_anisotropy: None,
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+            border_color: vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+            compare_op: None,
+        }
+    }
+}
+
+impl SamplerInfo {
+    /// Returns a sampler which uses nearest-neighbor filtering.
+    pub fn nearest() -> SamplerInfo {
+        SamplerInfo {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            ..Default::default()
+        }
+    }
+
+    pub fn address_mode(mut self, mode: vk::SamplerAddressMode) -> SamplerInfo {
+        self.address_modes = [mode; 3];
+        self
+    }
+
+    pub fn anisotropy(mut self, max_anisotropy: f32) -> SamplerInfo {
+        self.max_anisotropy = Some(max_anisotropy);
+        self
+    }
+
+    pub fn lod(mut self, bias: f32, min: f32, max: f32) -> SamplerInfo {
+        self.mip_lod_bias = bias;
+        self.min_lod = min;
+        self.max_lod = max;
+        self
+    }
+
+    pub fn border_color(mut self, color: vk::BorderColor) -> SamplerInfo {
+        self.border_color = color;
+        self
+    }
+
+    /// Checks the sampler against the device's limits and enabled features.
+    pub fn validate(
+        &self,
+        limits: &vk::PhysicalDeviceLimits,
+        anisotropy_enabled: bool,
+    ) -> Result<(), SamplerError> {
+        if let Some(requested) = self.max_anisotropy {
+            if !anisotropy_enabled {
+                return Err(SamplerError::AnisotropyNotEnabled);
+            }
+
+            if requested.is_nan() || requested < 1.0 {
+                return Err(SamplerError::AnisotropyTooLow { requested });
+            }
+
+            if requested > limits.max_sampler_anisotropy {
+                return Err(SamplerError::AnisotropyTooHigh {
+                    requested,
+                    limit: limits.max_sampler_anisotropy,
+                });
+            }
+        }
+
+        if self.mip_lod_bias.abs() > limits.max_sampler_lod_bias {
+            return Err(SamplerError::LodBiasTooHigh {
+                requested: self.mip_lod_bias,
+                limit: limits.max_sampler_lod_bias,
+            });
+        }
+
+        if self.min_lod > self.max_lod {
+            return Err(SamplerError::InvalidLodRange {
+                min: self.min_lod,
+                max: self.max_lod,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn to_vk(self) -> vk::SamplerCreateInfoBuilder<'static> {
+        vk::SamplerCreateInfoBuilder::new()
+            .mag_filter(self.mag_filter)
+            .min_filter(self.min_filter)
+            .mipmap_mode(self.mipmap_mode)
+            .address_mode_u(self.address_modes[0])
+            .address_mode_v(self.address_modes[1])
+            .address_mode_w(self.address_modes[2])
+            .anisotropy_enable(self.max_anisotropy.is_some())
+            .max_anisotropy(self.max_anisotropy.unwrap_or(1.0))
+            .mip_lod_bias(self.mip_lod_bias)
+            .min_lod(self.min_lod)
+            .max_lod(self.max_lod)
+            .border_color(self.border_color)
+            .compare_enable(self.compare_op.is_some())
+            .compare_op(self.compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+            .unnormalized_coordinates(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> vk::PhysicalDeviceLimits {
+        vk::PhysicalDeviceLimits {
+            max_sampler_anisotropy: 16.0,
+            max_sampler_lod_bias: 4.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_against_limits() {
+        let limits = limits();
+
+        assert_eq!(SamplerInfo::default().validate(&limits, false), Ok(()));
+        assert_eq!(
+            SamplerInfo::default()
+                .anisotropy(8.0)
+                .validate(&limits, true),
+            Ok(())
+        );
+        assert_eq!(
+            SamplerInfo::default()
+                .anisotropy(8.0)
+                .validate(&limits, false),
+            Err(SamplerError::AnisotropyNotEnabled)
+        );
+        assert!(matches!(
+            SamplerInfo::default()
+                .anisotropy(32.0)
+                .validate(&limits, true),
+            Err(SamplerError::AnisotropyTooHigh { .. })
+        ));
+        assert!(matches!(
+            SamplerInfo::default()
+                .anisotropy(0.5)
+                .validate(&limits, true),
+            Err(SamplerError::AnisotropyTooLow { .. })
+        ));
+        assert!(matches!(
+            SamplerInfo::default()
+                .anisotropy(f32::NAN)
+                .validate(&limits, true),
+            Err(SamplerError::AnisotropyTooLow { .. })
+        ));
+        assert!(matches!(
+            SamplerInfo::default()
+                .lod(-5.0, 0.0, vk::LOD_CLAMP_NONE)
+                .validate(&limits, false),
+            Err(SamplerError::LodBiasTooHigh { .. })
+        ));
+        assert!(matches!(
+            SamplerInfo::default()
+                .lod(0.0, 4.0, 2.0)
+                .validate(&limits, false),
+            Err(SamplerError::InvalidLodRange { .. })
+        ));
+    }
+}
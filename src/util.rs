@@ -1,13 +1,133 @@
-use std::{cmp, collections::HashSet, fmt, hash::Hash, iter::FromIterator, mem, ops::Range};
+//! Miscellaneous helpers for working with the Vulkan API.
+
+use std::{
+    cmp, collections::HashSet, convert::TryInto, fmt, hash::Hash, iter::FromIterator, mem,
+    ops::Range, time::Duration,
+};
 
 use arrayvec::ArrayVec;
+use erupt::vk;
+
+/// Returns a subresource range covering all mip levels and array layers of
+/// the given aspects of an image.
+pub const fn subresource_range_full(
+    aspect_mask: vk::ImageAspectFlags,
+) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange {
+        aspect_mask,
+        base_mip_level: 0,
+        level_count: vk::REMAINING_MIP_LEVELS,
+        base_array_layer: 0,
+        layer_count: vk::REMAINING_ARRAY_LAYERS,
+    }
+}
+
+/// A subresource range covering the entire color aspect of an image.
+pub const IMAGE_SUBRESOURCE_RANGE_FULL_COLOR: vk::ImageSubresourceRange =
+    subresource_range_full(vk::ImageAspectFlags::COLOR);
+
+/// A subresource range covering the entire depth aspect of an image.
+pub const IMAGE_SUBRESOURCE_RANGE_FULL_DEPTH: vk::ImageSubresourceRange =
+    subresource_range_full(vk::ImageAspectFlags::DEPTH);
+
+/// A subresource range covering the entire depth and stencil aspects of an
+/// image.
+pub const IMAGE_SUBRESOURCE_RANGE_FULL_DEPTH_STENCIL: vk::ImageSubresourceRange =
+    subresource_range_full(vk::ImageAspectFlags::from_bits_truncate(
+        vk::ImageAspectFlags::DEPTH.bits() | vk::ImageAspectFlags::STENCIL.bits(),
+    ));
+
+/// Converts an optional timeout to the nanosecond count expected by Vulkan.
+///
+/// `None` maps to `u64::MAX`, which waits indefinitely. Durations too long to
+/// represent also saturate to `u64::MAX`.
+pub fn timeout_u64(timeout: Option<Duration>) -> u64 {
+    timeout
+        .and_then(|t| t.as_nanos().try_into().ok())
+        .unwrap_or(u64::MAX)
+}
+
+/// Divides `value` by `divisor`, which must be nonzero, rounding up.
+pub const fn ceil_div(value: u64, divisor: u64) -> u64 {
+    value / divisor + (value % divisor != 0) as u64
+}
+
+/// Rounds `value` up to the next multiple of `align`, which must be nonzero.
+///
+/// Returns `None` if the result does not fit in a `u64`.
+pub const fn align_up(value: u64, align: u64) -> Option<u64> {
+    ceil_div(value, align).checked_mul(align)
+}
+
+/// Extends a 2D extent to a 3D extent with a depth of 1.
+pub const fn extent_2d_to_3d(extent: vk::Extent2D) -> vk::Extent3D {
+    vk::Extent3D {
+        width: extent.width,
+        height: extent.height,
+        depth: 1,
+    }
+}
+
+/// Truncates a 3D extent to its width and height.
+pub const fn extent_3d_to_2d(extent: vk::Extent3D) -> vk::Extent2D {
+    vk::Extent2D {
+        width: extent.width,
+        height: extent.height,
+    }
+}
+
+/// The resolution and width of the timestamps written by a queue.
+///
+/// Timestamps are raw tick counts. This converts them to durations using the
+/// device's `timestampPeriod` limit, accounting for the number of valid bits
+/// so that differences remain correct when the counter wraps.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimestampPeriod {
+    nanos_per_tick: f32,
+    valid_bits: u32,
+}
+
+impl TimestampPeriod {
+    /// Creates a timestamp period from `VkPhysicalDeviceLimits::timestampPeriod`
+    /// and `VkQueueFamilyProperties::timestampValidBits`.
+    ///
+    /// Returns `None` if `valid_bits` is zero, i.e. the queue does not support
+    /// timestamps.
+    pub fn new(nanos_per_tick: f32, valid_bits: u32) -> Option<TimestampPeriod> {
+        (valid_bits > 0).then(|| TimestampPeriod {
+            nanos_per_tick,
+            valid_bits: valid_bits.min(64),
+        })
+    }
+
+    /// Returns the number of nanoseconds per timestamp tick.
+    pub fn nanos_per_tick(&self) -> f32 {
+        self.nanos_per_tick
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.valid_bits)
+    }
+
+    /// Converts a tick count to a duration.
+    pub fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        Duration::from_nanos((ticks as f64 * self.nanos_per_tick as f64) as u64)
+    }
+
+    /// Returns the time elapsed between two timestamps written by the same
+    /// queue, where `end` was written after `start`.
+    pub fn elapsed(&self, start: u64, end: u64) -> Duration {
+        let ticks = (end & self.mask()).wrapping_sub(start & self.mask()) & self.mask();
+        self.ticks_to_duration(ticks)
+    }
+}
 
 /// A dummy type which emits an error message when dropped.
 ///
 /// This is useful for drawing attention to incorrect use of types which require
 /// explicit destruction.
 #[derive(Default)]
-pub struct ErrorOnDrop<T>
+pub(crate) struct ErrorOnDrop<T>
 where
     T: fmt::Display + Default,
 {
@@ -34,12 +154,22 @@ where
         ErrorOnDrop { error, armed: true }
     }
 
+    /// Arms the bomb. Has no effect if it is already armed.
+    pub fn arm(&mut self) {
+        self.armed = true;
+    }
+
     pub fn disarm(&mut self) {
         self.armed = false;
     }
+
+    #[cfg(test)]
+    pub fn is_armed(&self) -> bool {
+        self.armed
+    }
 }
 
-pub enum SmallSet<T: PartialEq, const CAP: usize> {
+pub(crate) enum SmallSet<T: PartialEq, const CAP: usize> {
     Inline(ArrayVec<T, CAP>),
     Heap(HashSet<T>),
 }
@@ -169,7 +299,7 @@ where
     // TODO: extend()
 }
 
-pub enum SmallSetIter<'a, T> {
+pub(crate) enum SmallSetIter<'a, T> {
     Inline(std::slice::Iter<'a, T>),
     Heap(std::collections::hash_set::Iter<'a, T>),
 }
@@ -192,7 +322,7 @@ impl<'a, T> Iterator for SmallSetIter<'a, T> {
     }
 }
 
-pub enum SmallSetDrain<'a, T, const CAP: usize> {
+pub(crate) enum SmallSetDrain<'a, T, const CAP: usize> {
     Inline(arrayvec::Drain<'a, T, CAP>),
     Heap(std::collections::hash_set::Drain<'a, T>),
 }
@@ -219,6 +349,65 @@ impl<'a, T, const CAP: usize> Iterator for SmallSetDrain<'a, T, CAP> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn timeout_saturates() {
+        assert_eq!(timeout_u64(None), u64::MAX);
+        assert_eq!(timeout_u64(Some(Duration::from_millis(5))), 5_000_000);
+        assert_eq!(timeout_u64(Some(Duration::MAX)), u64::MAX);
+    }
+
+    #[test]
+    fn align_up_rounds_to_multiple() {
+        assert_eq!(align_up(0, 256), Some(0));
+        assert_eq!(align_up(1, 256), Some(256));
+        assert_eq!(align_up(256, 256), Some(256));
+        assert_eq!(align_up(257, 256), Some(512));
+        assert_eq!(align_up(12, 12), Some(12));
+        assert_eq!(align_up(13, 12), Some(24));
+        assert_eq!(align_up(u64::MAX - 1, 1), Some(u64::MAX - 1));
+        assert_eq!(align_up(u64::MAX - 1, 256), None);
+    }
+
+    #[test]
+    fn ceil_div_rounds_up() {
+        assert_eq!(ceil_div(0, 4), 0);
+        assert_eq!(ceil_div(1, 4), 1);
+        assert_eq!(ceil_div(4, 4), 1);
+        assert_eq!(ceil_div(5, 4), 2);
+        assert_eq!(ceil_div(u64::MAX, 2), 1 << 63);
+    }
+
+    #[test]
+    fn timestamp_elapsed_wraps() {
+        assert_eq!(TimestampPeriod::new(1.0, 0), None);
+
+        let period = TimestampPeriod::new(2.5, 36).unwrap();
+        assert_eq!(period.ticks_to_duration(4), Duration::from_nanos(10));
+        assert_eq!(period.elapsed(100, 140), Duration::from_nanos(100));
+
+        // The counter wraps at 2^36.
+        let start = (1 << 36) - 10;
+        assert_eq!(period.elapsed(start, 30), Duration::from_nanos(100));
+
+        let full = TimestampPeriod::new(1.0, 64).unwrap();
+        assert_eq!(full.elapsed(u64::MAX - 1, 1), Duration::from_nanos(3));
+    }
+
+    #[test]
+    fn extent_round_trip() {
+        let extent = vk::Extent2D {
+            width: 640,
+            height: 480,
+        };
+        let extent_3d = extent_2d_to_3d(extent);
+        assert_eq!(extent_3d.depth, 1);
+        let extent_2d = extent_3d_to_2d(extent_3d);
+        assert_eq!(
+            (extent_2d.width, extent_2d.height),
+            (extent.width, extent.height)
+        );
+    }
+
     #[test]
     fn init_cap_is_array_cap() {
         let set = SmallSet::<(), 8>::new();
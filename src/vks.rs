@@ -7,10 +7,15 @@
 //! that a parameter be externally synchronized accept the object type either by
 //! mutable reference or by value (in case the function may destroy the object).
 
-use erupt::{utils::VulkanResult, vk, DeviceLoader, EntryLoader, InstanceLoader, LoaderError};
+use erupt::{
+    utils::VulkanResult, vk, DeviceLoader, EntryLoader, ExtendableFromMut, InstanceLoader,
+    LoaderError,
+};
 
 use std::{convert::TryInto, ffi::CStr, lazy::SyncOnceCell, time::Duration};
 
+use crate::util;
+
 static ENTRY: SyncOnceCell<EntryLoader> = SyncOnceCell::new();
 
 pub fn entry() -> &'static EntryLoader {
@@ -106,7 +111,7 @@ macro_rules! define_handle {
         }
 
         impl $defty {
-            unsafe fn new(raw: $raw) -> $defty {
+            pub(crate) unsafe fn new(raw: $raw) -> $defty {
                 $defty { raw }
             }
         }
@@ -243,6 +248,145 @@ impl Instance {
         }
     }
 
+    /// Reports the descriptor indexing capabilities of a physical device.
+    ///
+    /// Returns `None` if neither Vulkan 1.1 nor
+    /// `VK_KHR_get_physical_device_properties2` is available.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    pub unsafe fn get_physical_device_descriptor_indexing_features(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceDescriptorIndexingFeatures> {
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingFeaturesBuilder::new();
+        let features2 = vk::PhysicalDeviceFeatures2Builder::new()
+            .extend_from(&mut indexing)
+            .build();
+
+        unsafe {
+            if self.loader.get_physical_device_features2.is_some() {
+                self.loader
+                    .get_physical_device_features2(*phys_device.handle(), Some(features2));
+            } else if self.loader.get_physical_device_features2_khr.is_some() {
+                self.loader
+                    .get_physical_device_features2_khr(*phys_device.handle(), Some(features2));
+            } else {
+                return None;
+            }
+        }
+
+        let mut indexing = indexing.build();
+        indexing.p_next = std::ptr::null_mut();
+        Some(indexing)
+    }
+
+    /// Reports the heap usage and budget of a physical device as estimated by
+    /// `VK_EXT_memory_budget`.
+    ///
+    /// Returns `None` if neither Vulkan 1.1 nor
+    /// `VK_KHR_get_physical_device_properties2` is available.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - `phys_device` must support `VK_EXT_memory_budget`.
+    pub unsafe fn get_physical_device_memory_budget(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMemoryBudgetPropertiesEXT> {
+        let mut budget = vk::PhysicalDeviceMemoryBudgetPropertiesEXTBuilder::new();
+        let properties2 = vk::PhysicalDeviceMemoryProperties2Builder::new()
+            .extend_from(&mut budget)
+            .build();
+
+        unsafe {
+            if self.loader.get_physical_device_memory_properties2.is_some() {
+                self.loader.get_physical_device_memory_properties2(
+                    *phys_device.handle(),
+                    Some(properties2),
+                );
+            } else if self
+                .loader
+                .get_physical_device_memory_properties2_khr
+                .is_some()
+            {
+                self.loader.get_physical_device_memory_properties2_khr(
+                    *phys_device.handle(),
+                    Some(properties2),
+                );
+            } else {
+                return None;
+            }
+        }
+
+        let mut budget = budget.build();
+        budget.p_next = std::ptr::null_mut();
+        Some(budget)
+    }
+
+    /// Reports the descriptor and allocation limits of a physical device
+    /// introduced by `VK_KHR_maintenance3`.
+    ///
+    /// Returns `None` if neither Vulkan 1.1 nor
+    /// `VK_KHR_get_physical_device_properties2` is available.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - `phys_device` must support Vulkan 1.1 or `VK_KHR_maintenance3`.
+    pub unsafe fn get_physical_device_maintenance3_properties(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> Option<vk::PhysicalDeviceMaintenance3Properties> {
+        let mut maintenance3 = vk::PhysicalDeviceMaintenance3PropertiesBuilder::new();
+        let properties2 = vk::PhysicalDeviceProperties2Builder::new()
+            .extend_from(&mut maintenance3)
+            .build();
+
+        unsafe {
+            if self.loader.get_physical_device_properties2.is_some() {
+                self.loader
+                    .get_physical_device_properties2(*phys_device.handle(), Some(properties2));
+            } else if self.loader.get_physical_device_properties2_khr.is_some() {
+                self.loader
+                    .get_physical_device_properties2_khr(*phys_device.handle(), Some(properties2));
+            } else {
+                return None;
+            }
+        }
+
+        let mut maintenance3 = maintenance3.build();
+        maintenance3.p_next = std::ptr::null_mut();
+        Some(maintenance3)
+    }
+
+    /// Reports the capabilities of a physical device for a format.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    #[inline]
+    pub unsafe fn get_physical_device_format_properties(
+        &self,
+        phys_device: &PhysicalDevice,
+        format: vk::Format,
+    ) -> vk::FormatProperties {
+        unsafe {
+            self.loader
+                .get_physical_device_format_properties(*phys_device.handle(), format)
+        }
+    }
+
     /// Returns properties of a physical device.
     ///
     /// # Safety
@@ -291,6 +435,25 @@ impl Instance {
         }
     }
 
+    /// Returns the device extensions supported by a physical device.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    #[inline]
+    pub unsafe fn enumerate_device_extension_properties(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> VkResult<Vec<vk::ExtensionProperties>> {
+        unsafe {
+            self.loader
+                .enumerate_device_extension_properties(*phys_device.handle(), None, None)
+                .result()
+        }
+    }
+
     /// Queries if presentation is supported.
     ///
     /// # Safety
@@ -391,6 +554,121 @@ impl Instance {
         }
     }
 
+    /// Queries the displays attached to a physical device.
+    ///
+    /// Fails with `ERROR_EXTENSION_NOT_PRESENT` if `VK_KHR_display` is not
+    /// enabled.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    pub unsafe fn get_physical_device_display_properties_khr(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> VkResult<Vec<vk::DisplayPropertiesKHR>> {
+        if self
+            .loader
+            .get_physical_device_display_properties_khr
+            .is_none()
+        {
+            return Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT);
+        }
+
+        unsafe {
+            self.loader
+                .get_physical_device_display_properties_khr(*phys_device.handle(), None)
+                .result()
+        }
+    }
+
+    /// Queries the display planes of a physical device.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - The `VK_KHR_display` extension must be enabled on this instance.
+    pub unsafe fn get_physical_device_display_plane_properties_khr(
+        &self,
+        phys_device: &PhysicalDevice,
+    ) -> VkResult<Vec<vk::DisplayPlanePropertiesKHR>> {
+        unsafe {
+            self.loader
+                .get_physical_device_display_plane_properties_khr(*phys_device.handle(), None)
+                .result()
+        }
+    }
+
+    /// Queries the displays which a display plane can present to.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - `plane_index` must be less than the number of display planes of
+    ///   `phys_device`.
+    /// - The `VK_KHR_display` extension must be enabled on this instance.
+    pub unsafe fn get_display_plane_supported_displays_khr(
+        &self,
+        phys_device: &PhysicalDevice,
+        plane_index: u32,
+    ) -> VkResult<Vec<vk::DisplayKHR>> {
+        unsafe {
+            self.loader
+                .get_display_plane_supported_displays_khr(*phys_device.handle(), plane_index, None)
+                .result()
+        }
+    }
+
+    /// Queries the built-in modes of a display.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - `display` must be a display of `phys_device`.
+    /// - The `VK_KHR_display` extension must be enabled on this instance.
+    pub unsafe fn get_display_mode_properties_khr(
+        &self,
+        phys_device: &PhysicalDevice,
+        display: vk::DisplayKHR,
+    ) -> VkResult<Vec<vk::DisplayModePropertiesKHR>> {
+        unsafe {
+            self.loader
+                .get_display_mode_properties_khr(*phys_device.handle(), display, None)
+                .result()
+        }
+    }
+
+    /// Queries the capabilities of a display plane when using a display mode.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `phys_device` must be a physical device handle associated with this
+    ///   instance.
+    /// - `mode` must be a mode of a display of `phys_device`.
+    /// - `plane_index` must be less than the number of display planes of
+    ///   `phys_device`.
+    /// - The `VK_KHR_display` extension must be enabled on this instance.
+    pub unsafe fn get_display_plane_capabilities_khr(
+        &self,
+        phys_device: &PhysicalDevice,
+        mode: vk::DisplayModeKHR,
+        plane_index: u32,
+    ) -> VkResult<vk::DisplayPlaneCapabilitiesKHR> {
+        unsafe {
+            self.loader
+                .get_display_plane_capabilities_khr(*phys_device.handle(), mode, plane_index)
+                .result()
+        }
+    }
+
     /// Creates a new device instance.
     ///
     /// # Safety
@@ -458,6 +736,54 @@ impl Instance {
         }
     }
 
+    pub unsafe fn create_win32_surface_khr(
+        &self,
+        create_info: &vk::Win32SurfaceCreateInfoKHR,
+    ) -> VkResult<SurfaceKHR> {
+        unsafe {
+            self.loader
+                .create_win32_surface_khr(create_info, None)
+                .result()
+                .map(|s| SurfaceKHR::new(s))
+        }
+    }
+
+    pub unsafe fn create_metal_surface_ext(
+        &self,
+        create_info: &vk::MetalSurfaceCreateInfoEXT,
+    ) -> VkResult<SurfaceKHR> {
+        unsafe {
+            self.loader
+                .create_metal_surface_ext(create_info, None)
+                .result()
+                .map(|s| SurfaceKHR::new(s))
+        }
+    }
+
+    pub unsafe fn create_android_surface_khr(
+        &self,
+        create_info: &vk::AndroidSurfaceCreateInfoKHR,
+    ) -> VkResult<SurfaceKHR> {
+        unsafe {
+            self.loader
+                .create_android_surface_khr(create_info, None)
+                .result()
+                .map(|s| SurfaceKHR::new(s))
+        }
+    }
+
+    pub unsafe fn create_display_plane_surface_khr(
+        &self,
+        create_info: &vk::DisplaySurfaceCreateInfoKHR,
+    ) -> VkResult<SurfaceKHR> {
+        unsafe {
+            self.loader
+                .create_display_plane_surface_khr(create_info, None)
+                .result()
+                .map(|s| SurfaceKHR::new(s))
+        }
+    }
+
     pub unsafe fn destroy_surface(&self, mut surface: SurfaceKHR) {
         // Safety:
         // - Access to surface is externally synchronized via ownership.
@@ -560,6 +886,16 @@ impl Device {
         unsafe { self.loader.queue_wait_idle(*queue.handle_mut()).result() }
     }
 
+    /// Blocks until all queues of the device are idle.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - Access to all queues of the device must be externally synchronized.
+    pub unsafe fn device_wait_idle(&self) -> VkResult<()> {
+        unsafe { self.loader.device_wait_idle().result() }
+    }
+
     // ------------------------------------------------------------------------
 
     /// Creates an image view from an existing image.
@@ -597,6 +933,34 @@ impl Device {
 
     // ------------------------------------------------------------------------
 
+    pub unsafe fn create_sampler(
+        &self,
+        create_info: &vk::SamplerCreateInfoBuilder<'_>,
+    ) -> VkResult<Sampler> {
+        unsafe {
+            self.loader
+                .create_sampler(create_info, None)
+                .result()
+                .map(|s| Sampler::new(s))
+        }
+    }
+
+    /// Destroys a sampler object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - All submitted commands that refer to `sampler` must have completed
+    ///   execution.
+    pub unsafe fn destroy_sampler(&self, mut sampler: Sampler) {
+        unsafe {
+            self.loader
+                .destroy_sampler(Some(*sampler.handle_mut()), None)
+        }
+    }
+
+    // ------------------------------------------------------------------------
+
     /// Creates a framebuffer from an existing image.
     ///
     /// # Safety
@@ -727,65 +1091,220 @@ impl Device {
 
     // ------------------------------------------------------------------------
 
-    /// Creates graphics pipelines.
+    /// Creates a new descriptor set layout object.
     ///
     /// # Safety
     ///
-    /// - TODO: lots of handles
-    pub unsafe fn create_graphics_pipelines(
+    /// - TODO: destroy before destroying parent
+    pub unsafe fn create_descriptor_set_layout(
         &self,
-        create_infos: &[vk::GraphicsPipelineCreateInfoBuilder<'_>],
-    ) -> VkResult<Vec<Pipeline>> {
+        create_info: &vk::DescriptorSetLayoutCreateInfoBuilder<'_>,
+    ) -> VkResult<DescriptorSetLayout> {
         unsafe {
-            Ok(self
-                .loader
-                .create_graphics_pipelines(None, create_infos, None)
-                .result()?
-                .into_iter()
-                .map(|p| Pipeline::new(p))
-                .collect())
+            self.loader
+                .create_descriptor_set_layout(create_info, None)
+                .result()
+                .map(|dsl| DescriptorSetLayout::new(dsl))
         }
     }
 
-    /// Destroys a pipeline object.
+    /// Destroys a descriptor set layout object.
     ///
     /// # Safety
     ///
-    /// - TODO: destroy before parent device and depended handles
-    pub unsafe fn destroy_pipeline(&self, mut pipeline: Pipeline) {
+    /// - `descriptor_set_layout` must be a handle to a descriptor set layout
+    ///   object associated with this device.
+    pub unsafe fn destroy_descriptor_set_layout(
+        &self,
+        mut descriptor_set_layout: DescriptorSetLayout,
+    ) {
         unsafe {
             self.loader
-                .destroy_pipeline(Some(*pipeline.handle_mut()), None);
+                .destroy_descriptor_set_layout(Some(*descriptor_set_layout.handle_mut()), None)
         }
     }
 
     // ------------------------------------------------------------------------
 
-    /// Creates a new command pool object.
+    /// Creates a new descriptor pool object.
     ///
     /// # Safety
     ///
-    /// - TODO: destroy before parent device
-    pub unsafe fn create_command_pool(
+    /// - TODO: destroy before destroying parent
+    pub unsafe fn create_descriptor_pool(
         &self,
-        create_info: &vk::CommandPoolCreateInfo,
-    ) -> VkResult<CommandPool> {
+        create_info: &vk::DescriptorPoolCreateInfoBuilder<'_>,
+    ) -> VkResult<DescriptorPool> {
         unsafe {
             self.loader
-                .create_command_pool(create_info, None)
+                .create_descriptor_pool(create_info, None)
                 .result()
-                .map(|c| CommandPool::new(c))
+                .map(|dp| DescriptorPool::new(dp))
         }
     }
 
-    /// Destroys a command pool object.
+    /// Destroys a descriptor pool object.
+    ///
+    /// Any descriptor sets allocated from the pool are implicitly freed.
     ///
     /// # Safety
     ///
     /// The caller must uphold the following invariants:
-    /// - `command_pool` must be a handle to a command pool object associated
-    ///   with this device.
-    /// - No command buffer allocated from this command pool may be in the
+    /// - `descriptor_pool` must be a handle to a descriptor pool object
+    ///   associated with this device.
+    /// - All submitted commands that refer to descriptor sets allocated from
+    ///   `descriptor_pool` must have completed execution.
+    pub unsafe fn destroy_descriptor_pool(&self, mut descriptor_pool: DescriptorPool) {
+        unsafe {
+            self.loader
+                .destroy_descriptor_pool(Some(*descriptor_pool.handle_mut()), None)
+        }
+    }
+
+    /// Returns all descriptor sets allocated from a pool to the pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `descriptor_pool` must be a handle to a descriptor pool object
+    ///   associated with this device.
+    /// - All submitted commands that refer to descriptor sets allocated from
+    ///   `descriptor_pool` must have completed execution.
+    /// - Descriptor sets allocated from `descriptor_pool` must not be used
+    ///   after this call.
+    pub unsafe fn reset_descriptor_pool(
+        &self,
+        descriptor_pool: &mut DescriptorPool,
+    ) -> VkResult<()> {
+        unsafe {
+            self.loader
+                .reset_descriptor_pool(*descriptor_pool.handle_mut(), None)
+                .result()
+        }
+    }
+
+    /// Allocates descriptor sets from an existing descriptor pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `allocate_info.descriptor_pool` must be a handle to a descriptor pool
+    ///   object associated with this device.
+    pub unsafe fn allocate_descriptor_sets(
+        &self,
+        allocate_info: &DescriptorSetAllocateInfoBuilder<'_>,
+    ) -> VkResult<Vec<DescriptorSet>> {
+        unsafe {
+            Ok(self
+                .loader
+                .allocate_descriptor_sets(&allocate_info.inner)
+                .result()?
+                .into_iter()
+                .map(|ds| DescriptorSet::new(ds))
+                .collect())
+        }
+    }
+
+    /// Updates the contents of descriptor sets.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - The descriptor sets referenced by `descriptor_writes` and
+    ///   `descriptor_copies` must not be in use by any pending command buffer.
+    /// - The destination descriptor sets must be externally synchronized.
+    pub unsafe fn update_descriptor_sets(
+        &self,
+        descriptor_writes: &[vk::WriteDescriptorSetBuilder<'_>],
+        descriptor_copies: &[vk::CopyDescriptorSetBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader
+                .update_descriptor_sets(descriptor_writes, descriptor_copies);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+
+    /// Creates graphics pipelines.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: lots of handles
+    pub unsafe fn create_graphics_pipelines(
+        &self,
+        create_infos: &[vk::GraphicsPipelineCreateInfoBuilder<'_>],
+    ) -> VkResult<Vec<Pipeline>> {
+        unsafe {
+            Ok(self
+                .loader
+                .create_graphics_pipelines(None, create_infos, None)
+                .result()?
+                .into_iter()
+                .map(|p| Pipeline::new(p))
+                .collect())
+        }
+    }
+
+    /// Creates compute pipelines.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: lots of handles
+    pub unsafe fn create_compute_pipelines(
+        &self,
+        create_infos: &[vk::ComputePipelineCreateInfoBuilder<'_>],
+    ) -> VkResult<Vec<Pipeline>> {
+        unsafe {
+            Ok(self
+                .loader
+                .create_compute_pipelines(None, create_infos, None)
+                .result()?
+                .into_iter()
+                .map(|p| Pipeline::new(p))
+                .collect())
+        }
+    }
+
+    /// Destroys a pipeline object.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: destroy before parent device and depended handles
+    pub unsafe fn destroy_pipeline(&self, mut pipeline: Pipeline) {
+        unsafe {
+            self.loader
+                .destroy_pipeline(Some(*pipeline.handle_mut()), None);
+        }
+    }
+
+    // ------------------------------------------------------------------------
+
+    /// Creates a new command pool object.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: destroy before parent device
+    pub unsafe fn create_command_pool(
+        &self,
+        create_info: &vk::CommandPoolCreateInfo,
+    ) -> VkResult<CommandPool> {
+        unsafe {
+            self.loader
+                .create_command_pool(create_info, None)
+                .result()
+                .map(|c| CommandPool::new(c))
+        }
+    }
+
+    /// Destroys a command pool object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `command_pool` must be a handle to a command pool object associated
+    ///   with this device.
+    /// - No command buffer allocated from this command pool may be in the
     ///   pending state.
     pub unsafe fn destroy_command_pool(&self, mut command_pool: CommandPool) {
         unsafe {
@@ -875,76 +1394,546 @@ impl Device {
         }
     }
 
-    #[inline]
-    pub unsafe fn cmd_pipeline_barrier(
+    #[inline]
+    pub unsafe fn cmd_pipeline_barrier(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_stage_mask: vk::PipelineStageFlags,
+        dst_stage_mask: vk::PipelineStageFlags,
+        dependency_flags: vk::DependencyFlags,
+        memory_barriers: &[vk::MemoryBarrierBuilder<'_>],
+        buffer_memory_barriers: &[vk::BufferMemoryBarrierBuilder<'_>],
+        image_memory_barriers: &[vk::ImageMemoryBarrierBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_pipeline_barrier(
+                *command_buffer.handle_mut(),
+                src_stage_mask,
+                dst_stage_mask,
+                Some(dependency_flags),
+                memory_barriers,
+                buffer_memory_barriers,
+                image_memory_barriers,
+            );
+        }
+    }
+
+    #[inline]
+    pub unsafe fn cmd_begin_render_pass(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        begin_info: &RenderPassBeginInfoBuilder<'_>,
+        contents: vk::SubpassContents,
+    ) {
+        unsafe {
+            self.loader.cmd_begin_render_pass(
+                *command_buffer.handle_mut(),
+                &begin_info.inner,
+                contents,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_bind_pipeline(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        bind_point: vk::PipelineBindPoint,
+        pipeline: &Pipeline,
+    ) {
+        unsafe {
+            self.loader.cmd_bind_pipeline(
+                *command_buffer.handle_mut(),
+                bind_point,
+                *pipeline.handle(),
+            );
+        }
+    }
+
+    pub unsafe fn cmd_bind_descriptor_sets(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        bind_point: vk::PipelineBindPoint,
+        layout: &PipelineLayout,
+        first_set: u32,
+        descriptor_sets: &[vk::DescriptorSet],
+        dynamic_offsets: &[u32],
+    ) {
+        unsafe {
+            self.loader.cmd_bind_descriptor_sets(
+                *command_buffer.handle_mut(),
+                bind_point,
+                *layout.handle(),
+                first_set,
+                descriptor_sets,
+                dynamic_offsets,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_bind_vertex_buffers(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        first_binding: u32,
+        buffers: &[vk::Buffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        // The loader takes the binding count from `buffers` alone.
+        assert_eq!(
+            buffers.len(),
+            offsets.len(),
+            "each vertex buffer needs exactly one offset"
+        );
+
+        unsafe {
+            self.loader.cmd_bind_vertex_buffers(
+                *command_buffer.handle_mut(),
+                first_binding,
+                buffers,
+                offsets,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_bind_index_buffer(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        buffer: &Buffer,
+        offset: vk::DeviceSize,
+        index_type: vk::IndexType,
+    ) {
+        unsafe {
+            self.loader.cmd_bind_index_buffer(
+                *command_buffer.handle_mut(),
+                *buffer.handle(),
+                offset,
+                index_type,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_push_constants(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        layout: &PipelineLayout,
+        stage_flags: vk::ShaderStageFlags,
+        offset: u32,
+        values: &[u8],
+    ) {
+        unsafe {
+            self.loader.cmd_push_constants(
+                *command_buffer.handle_mut(),
+                *layout.handle(),
+                stage_flags,
+                offset,
+                values.len().try_into().unwrap(),
+                values.as_ptr().cast(),
+            );
+        }
+    }
+
+    pub unsafe fn cmd_set_viewport(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        first_viewport: u32,
+        viewports: &[vk::ViewportBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader
+                .cmd_set_viewport(*command_buffer.handle_mut(), first_viewport, viewports);
+        }
+    }
+
+    pub unsafe fn cmd_set_scissor(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        first_scissor: u32,
+        scissors: &[vk::Rect2DBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader
+                .cmd_set_scissor(*command_buffer.handle_mut(), first_scissor, scissors);
+        }
+    }
+
+    pub unsafe fn cmd_set_depth_bias(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        constant_factor: f32,
+        clamp: f32,
+        slope_factor: f32,
+    ) {
+        unsafe {
+            self.loader.cmd_set_depth_bias(
+                *command_buffer.handle_mut(),
+                constant_factor,
+                clamp,
+                slope_factor,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_set_line_width(&self, command_buffer: &mut CommandBuffer, line_width: f32) {
+        unsafe {
+            self.loader
+                .cmd_set_line_width(*command_buffer.handle_mut(), line_width);
+        }
+    }
+
+    pub unsafe fn cmd_set_blend_constants(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        blend_constants: [f32; 4],
+    ) {
+        unsafe {
+            self.loader
+                .cmd_set_blend_constants(*command_buffer.handle_mut(), blend_constants);
+        }
+    }
+
+    pub unsafe fn cmd_set_stencil_reference(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        face_mask: vk::StencilFaceFlags,
+        reference: u32,
+    ) {
+        unsafe {
+            self.loader.cmd_set_stencil_reference(
+                *command_buffer.handle_mut(),
+                face_mask,
+                reference,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_draw(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.loader.cmd_draw(
+                *command_buffer.handle_mut(),
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_draw_indexed(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.loader.cmd_draw_indexed(
+                *command_buffer.handle_mut(),
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_blit_image(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_image: &Image,
+        src_image_layout: vk::ImageLayout,
+        dst_image: &Image,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::ImageBlitBuilder<'_>],
+        filter: vk::Filter,
+    ) {
+        unsafe {
+            self.loader.cmd_blit_image(
+                *command_buffer.handle_mut(),
+                *src_image.handle(),
+                src_image_layout,
+                *dst_image.handle(),
+                dst_image_layout,
+                regions,
+                filter,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_copy_buffer(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_buffer: &Buffer,
+        dst_buffer: &Buffer,
+        regions: &[vk::BufferCopyBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_copy_buffer(
+                *command_buffer.handle_mut(),
+                *src_buffer.handle(),
+                *dst_buffer.handle(),
+                regions,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_copy_image(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_image: &Image,
+        src_image_layout: vk::ImageLayout,
+        dst_image: &Image,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::ImageCopyBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_copy_image(
+                *command_buffer.handle_mut(),
+                *src_image.handle(),
+                src_image_layout,
+                *dst_image.handle(),
+                dst_image_layout,
+                regions,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_copy_buffer_to_image(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_buffer: &Buffer,
+        dst_image: &Image,
+        dst_image_layout: vk::ImageLayout,
+        regions: &[vk::BufferImageCopyBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_copy_buffer_to_image(
+                *command_buffer.handle_mut(),
+                *src_buffer.handle(),
+                *dst_image.handle(),
+                dst_image_layout,
+                regions,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_copy_image_to_buffer(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        src_image: &Image,
+        src_image_layout: vk::ImageLayout,
+        dst_buffer: &Buffer,
+        regions: &[vk::BufferImageCopyBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_copy_image_to_buffer(
+                *command_buffer.handle_mut(),
+                *src_image.handle(),
+                src_image_layout,
+                *dst_buffer.handle(),
+                regions,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_clear_color_image(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        image: &Image,
+        image_layout: vk::ImageLayout,
+        color: &vk::ClearColorValue,
+        ranges: &[vk::ImageSubresourceRangeBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_clear_color_image(
+                *command_buffer.handle_mut(),
+                *image.handle(),
+                image_layout,
+                color,
+                ranges,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_clear_depth_stencil_image(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        image: &Image,
+        image_layout: vk::ImageLayout,
+        depth_stencil: &vk::ClearDepthStencilValue,
+        ranges: &[vk::ImageSubresourceRangeBuilder<'_>],
+    ) {
+        unsafe {
+            self.loader.cmd_clear_depth_stencil_image(
+                *command_buffer.handle_mut(),
+                *image.handle(),
+                image_layout,
+                depth_stencil,
+                ranges,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_fill_buffer(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        dst_buffer: &Buffer,
+        dst_offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+        data: u32,
+    ) {
+        unsafe {
+            self.loader.cmd_fill_buffer(
+                *command_buffer.handle_mut(),
+                *dst_buffer.handle(),
+                dst_offset,
+                size,
+                data,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_update_buffer(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        dst_buffer: &Buffer,
+        dst_offset: vk::DeviceSize,
+        data: &[u8],
+    ) {
+        unsafe {
+            self.loader.cmd_update_buffer(
+                *command_buffer.handle_mut(),
+                *dst_buffer.handle(),
+                dst_offset,
+                data.len() as vk::DeviceSize,
+                data.as_ptr() as *const std::ffi::c_void,
+            );
+        }
+    }
+
+    /// Opens a debug label region, if `VK_EXT_debug_utils` is enabled.
+    ///
+    /// Labels annotate command buffers in validation messages and in
+    /// captures taken by graphics debuggers.
+    pub unsafe fn cmd_begin_debug_utils_label(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        label: &CStr,
+        color: [f32; 4],
+    ) {
+        if self.loader.cmd_begin_debug_utils_label_ext.is_none() {
+            return;
+        }
+
+        let label_info = vk::DebugUtilsLabelEXTBuilder::new()
+            .label_name(label)
+            .color(color);
+        unsafe {
+            self.loader
+                .cmd_begin_debug_utils_label_ext(*command_buffer.handle_mut(), &label_info);
+        }
+    }
+
+    /// Closes the most recently opened debug label region, if
+    /// `VK_EXT_debug_utils` is enabled.
+    pub unsafe fn cmd_end_debug_utils_label(&self, command_buffer: &mut CommandBuffer) {
+        if self.loader.cmd_end_debug_utils_label_ext.is_none() {
+            return;
+        }
+
+        unsafe {
+            self.loader
+                .cmd_end_debug_utils_label_ext(*command_buffer.handle_mut());
+        }
+    }
+
+    /// Resets a range of queries to the unavailable state.
+    pub unsafe fn cmd_reset_query_pool(
+        &self,
+        command_buffer: &mut CommandBuffer,
+        query_pool: &QueryPool,
+        first_query: u32,
+        query_count: u32,
+    ) {
+        unsafe {
+            self.loader.cmd_reset_query_pool(
+                *command_buffer.handle_mut(),
+                *query_pool.handle(),
+                first_query,
+                query_count,
+            );
+        }
+    }
+
+    pub unsafe fn cmd_begin_query(
         &self,
         command_buffer: &mut CommandBuffer,
-        src_stage_mask: vk::PipelineStageFlags,
-        dst_stage_mask: vk::PipelineStageFlags,
-        dependency_flags: vk::DependencyFlags,
-        memory_barriers: &[vk::MemoryBarrierBuilder<'_>],
-        buffer_memory_barriers: &[vk::BufferMemoryBarrierBuilder<'_>],
-        image_memory_barriers: &[vk::ImageMemoryBarrierBuilder<'_>],
+        query_pool: &QueryPool,
+        query: u32,
+        flags: vk::QueryControlFlags,
     ) {
         unsafe {
-            self.loader.cmd_pipeline_barrier(
+            self.loader.cmd_begin_query(
                 *command_buffer.handle_mut(),
-                src_stage_mask,
-                dst_stage_mask,
-                Some(dependency_flags),
-                memory_barriers,
-                buffer_memory_barriers,
-                image_memory_barriers,
+                *query_pool.handle(),
+                query,
+                Some(flags),
             );
         }
     }
 
-    #[inline]
-    pub unsafe fn cmd_begin_render_pass(
+    pub unsafe fn cmd_end_query(
         &self,
         command_buffer: &mut CommandBuffer,
-        begin_info: &RenderPassBeginInfoBuilder<'_>,
-        contents: vk::SubpassContents,
+        query_pool: &QueryPool,
+        query: u32,
     ) {
         unsafe {
-            self.loader.cmd_begin_render_pass(
-                *command_buffer.handle_mut(),
-                &begin_info.inner,
-                contents,
-            );
+            self.loader
+                .cmd_end_query(*command_buffer.handle_mut(), *query_pool.handle(), query);
         }
     }
 
-    pub unsafe fn cmd_bind_pipeline(
+    /// Writes a timestamp to a query once all previous commands have
+    /// completed `pipeline_stage`.
+    pub unsafe fn cmd_write_timestamp(
         &self,
         command_buffer: &mut CommandBuffer,
-        bind_point: vk::PipelineBindPoint,
-        pipeline: &Pipeline,
+        pipeline_stage: vk::PipelineStageFlagBits,
+        query_pool: &QueryPool,
+        query: u32,
     ) {
         unsafe {
-            self.loader.cmd_bind_pipeline(
+            self.loader.cmd_write_timestamp(
                 *command_buffer.handle_mut(),
-                bind_point,
-                *pipeline.handle(),
+                pipeline_stage,
+                *query_pool.handle(),
+                query,
             );
         }
     }
 
-    pub unsafe fn cmd_draw(
+    pub unsafe fn cmd_dispatch(
         &self,
         command_buffer: &mut CommandBuffer,
-        vertex_count: u32,
-        instance_count: u32,
-        first_vertex: u32,
-        first_instance: u32,
+        group_count_x: u32,
+        group_count_y: u32,
+        group_count_z: u32,
     ) {
         unsafe {
-            self.loader.cmd_draw(
+            self.loader.cmd_dispatch(
                 *command_buffer.handle_mut(),
-                vertex_count,
-                instance_count,
-                first_vertex,
-                first_instance,
+                group_count_x,
+                group_count_y,
+                group_count_z,
             );
         }
     }
@@ -958,6 +1947,88 @@ impl Device {
 
     // ------------------------------------------------------------------------
 
+    /// Creates a new query pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `create_info` must be a valid query pool create info.
+    pub unsafe fn create_query_pool(
+        &self,
+        create_info: &vk::QueryPoolCreateInfo,
+    ) -> VkResult<QueryPool> {
+        unsafe {
+            self.loader
+                .create_query_pool(create_info, None)
+                .result()
+                .map(|q| QueryPool::new(q))
+        }
+    }
+
+    /// Destroys a query pool.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `query_pool` must be a handle to a query pool associated with this
+    ///   device.
+    /// - All submitted commands that refer to `query_pool` must have completed
+    ///   execution.
+    pub unsafe fn destroy_query_pool(&self, mut query_pool: QueryPool) {
+        unsafe {
+            self.loader
+                .destroy_query_pool(Some(*query_pool.handle_mut()), None)
+        }
+    }
+
+    /// Reads the 64-bit results of `query_count` queries into `data`.
+    ///
+    /// The results of each query are packed contiguously, so queries which
+    /// produce several values are strided accordingly. Does not wait for
+    /// results. Returns `Ok(false)` if any of the queries are not yet
+    /// available.
+    ///
+    /// Returns `ERROR_VALIDATION_FAILED_EXT` if `query_count` is zero or does
+    /// not evenly divide the length of `data`.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `query_pool` must be a handle to a query pool associated with this
+    ///   device.
+    pub unsafe fn get_query_pool_results_u64(
+        &self,
+        query_pool: &QueryPool,
+        first_query: u32,
+        query_count: u32,
+        data: &mut [u64],
+    ) -> VkResult<bool> {
+        if query_count == 0 || data.len() % query_count as usize != 0 {
+            return Err(vk::Result::ERROR_VALIDATION_FAILED_EXT);
+        }
+        let stride = data.len() / query_count as usize * std::mem::size_of::<u64>();
+
+        let result = unsafe {
+            self.loader.get_query_pool_results(
+                *query_pool.handle(),
+                first_query,
+                query_count,
+                std::mem::size_of_val(data),
+                data.as_mut_ptr().cast(),
+                stride as vk::DeviceSize,
+                Some(vk::QueryResultFlags::_64),
+            )
+        };
+
+        match result.raw {
+            vk::Result::SUCCESS => Ok(true),
+            vk::Result::NOT_READY => Ok(false),
+            e => Err(e),
+        }
+    }
+
+    // ------------------------------------------------------------------------
+
     /// Creates a new fence object.
     ///
     /// # Safety
@@ -1010,13 +2081,7 @@ impl Device {
         wait_all: bool,
         timeout: Option<Duration>,
     ) -> VkResult<FenceWaitStatus> {
-        let timeout_ns: u64 = timeout
-            .map(|dur| {
-                dur.as_nanos()
-                    .try_into()
-                    .expect("wait_for_fences: timeout overflowed u64")
-            })
-            .unwrap_or(u64::MAX);
+        let timeout_ns = util::timeout_u64(timeout);
 
         unsafe {
             match self
@@ -1078,6 +2143,136 @@ impl Device {
 
     // ------------------------------------------------------------------------
 
+    /// Creates a new image object.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: destroy before destroying parent
+    pub unsafe fn create_image(
+        &self,
+        create_info: &vk::ImageCreateInfoBuilder<'_>,
+    ) -> VkResult<Image> {
+        unsafe {
+            self.loader
+                .create_image(create_info, None)
+                .result()
+                .map(|i| Image::new(i))
+        }
+    }
+
+    /// Destroys an image object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `image` must be a handle to an image object created with this device.
+    ///   Swapchain images must not be destroyed with this method.
+    /// - All submitted commands that refer to `image` must have completed
+    ///   execution.
+    pub unsafe fn destroy_image(&self, mut image: Image) {
+        unsafe {
+            self.loader.destroy_image(Some(*image.handle_mut()), None);
+        }
+    }
+
+    /// Returns the memory requirements of an image object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `image` must be a handle to an image object associated with this
+    ///   device.
+    pub unsafe fn get_image_memory_requirements(&self, image: &Image) -> vk::MemoryRequirements {
+        unsafe { self.loader.get_image_memory_requirements(*image.handle()) }
+    }
+
+    /// Binds device memory to an image object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `image` and `memory` must be associated with this device.
+    /// - `image` must not already be bound to memory.
+    /// - `memory_offset` must satisfy the image's memory requirements.
+    pub unsafe fn bind_image_memory(
+        &self,
+        image: &mut Image,
+        memory: &DeviceMemory,
+        memory_offset: vk::DeviceSize,
+    ) -> VkResult<()> {
+        unsafe {
+            self.loader
+                .bind_image_memory(*image.handle_mut(), *memory.handle(), memory_offset)
+                .result()
+        }
+    }
+
+    /// Creates a new buffer object.
+    ///
+    /// # Safety
+    ///
+    /// - TODO: destroy before destroying parent
+    pub unsafe fn create_buffer(
+        &self,
+        create_info: &vk::BufferCreateInfoBuilder<'_>,
+    ) -> VkResult<Buffer> {
+        unsafe {
+            self.loader
+                .create_buffer(create_info, None)
+                .result()
+                .map(|b| Buffer::new(b))
+        }
+    }
+
+    /// Destroys a buffer object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `buffer` must be a handle to a buffer object associated with this
+    ///   device.
+    /// - All submitted commands that refer to `buffer` must have completed
+    ///   execution.
+    pub unsafe fn destroy_buffer(&self, mut buffer: Buffer) {
+        unsafe {
+            self.loader.destroy_buffer(Some(*buffer.handle_mut()), None);
+        }
+    }
+
+    /// Returns the memory requirements of a buffer object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `buffer` must be a handle to a buffer object associated with this
+    ///   device.
+    pub unsafe fn get_buffer_memory_requirements(&self, buffer: &Buffer) -> vk::MemoryRequirements {
+        unsafe { self.loader.get_buffer_memory_requirements(*buffer.handle()) }
+    }
+
+    /// Binds device memory to a buffer object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `buffer` and `memory` must be associated with this device.
+    /// - `buffer` must not already be bound to memory.
+    /// - `memory_offset` must satisfy the buffer's memory requirements.
+    pub unsafe fn bind_buffer_memory(
+        &self,
+        buffer: &mut Buffer,
+        memory: &DeviceMemory,
+        memory_offset: vk::DeviceSize,
+    ) -> VkResult<()> {
+        unsafe {
+            self.loader
+                .bind_buffer_memory(*buffer.handle_mut(), *memory.handle(), memory_offset)
+                .result()
+        }
+    }
+
+    // ------------------------------------------------------------------------
+
     pub unsafe fn allocate_memory(
         &self,
         allocate_info: &vk::MemoryAllocateInfo,
@@ -1096,6 +2291,81 @@ impl Device {
         }
     }
 
+    /// Maps a range of a memory object into host address space.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `memory` must be associated with this device and must have been
+    ///   allocated from a `HOST_VISIBLE` memory type.
+    /// - `memory` must not already be mapped.
+    /// - The returned pointer must not be used after `memory` is unmapped or
+    ///   freed.
+    pub unsafe fn map_memory(
+        &self,
+        memory: &mut DeviceMemory,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> VkResult<*mut std::ffi::c_void> {
+        let mut data = std::ptr::null_mut();
+        unsafe {
+            self.loader
+                .map_memory(*memory.handle_mut(), offset, size, None, &mut data)
+                .result()?;
+        }
+        Ok(data)
+    }
+
+    /// Unmaps a previously mapped memory object.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `memory` must be associated with this device and must be mapped.
+    pub unsafe fn unmap_memory(&self, memory: &mut DeviceMemory) {
+        unsafe { self.loader.unmap_memory(*memory.handle_mut()) }
+    }
+
+    /// Makes host writes to a range of mapped memory available to the device.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `memory` must be associated with this device and must be mapped.
+    /// - `offset` and `size` must be multiples of `nonCoherentAtomSize`, or
+    ///   `size` must reach the end of the allocation.
+    pub unsafe fn flush_mapped_memory_range(
+        &self,
+        memory: &DeviceMemory,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> VkResult<()> {
+        let range = vk::MappedMemoryRangeBuilder::new()
+            .memory(unsafe { *memory.handle() })
+            .offset(offset)
+            .size(size);
+        unsafe { self.loader.flush_mapped_memory_ranges(&[range]).result() }
+    }
+
+    /// Invalidates a range of mapped non-coherent memory, making device writes
+    /// visible to the host.
+    pub unsafe fn invalidate_mapped_memory_range(
+        &self,
+        memory: &DeviceMemory,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> VkResult<()> {
+        let range = vk::MappedMemoryRangeBuilder::new()
+            .memory(unsafe { *memory.handle() })
+            .offset(offset)
+            .size(size);
+        unsafe {
+            self.loader
+                .invalidate_mapped_memory_ranges(&[range])
+                .result()
+        }
+    }
+
     // ------------------------------------------------------------------------
 
     /// Creates a swapchain.
@@ -1107,7 +2377,8 @@ impl Device {
     ///   this instance.
     /// - If `create_info.old_swapchain` is `Some(old)`, then `old` must be a
     ///   handle to a non-retired swapchain associated with
-    ///   `create_info.surface`.
+    ///   `create_info.surface`. `old` is retired by this call and must still be
+    ///   destroyed by the caller.
     #[inline]
     pub unsafe fn create_swapchain_khr(
         &self,
@@ -1130,12 +2401,12 @@ impl Device {
                 .composite_alpha(create_info.composite_alpha)
                 .present_mode(create_info.present_mode)
                 .clipped(create_info.clipped)
-                // Safety: old swapchain, if any, is externally synchronized via ownership.
+                // Safety: old swapchain, if any, is externally synchronized via mutable borrow.
                 .old_swapchain(
                     create_info
                         .old_swapchain
                         .take()
-                        .map(|mut os| *os.handle_mut())
+                        .map(|os| *os.handle_mut())
                         .unwrap_or(vk::SwapchainKHR::null()),
                 );
 
@@ -1193,13 +2464,7 @@ impl Device {
         semaphore: Option<&mut Semaphore>,
         fence: Option<&mut Fence>,
     ) -> VkResult<AcquiredImage> {
-        let timeout_ns: u64 = timeout
-            .map(|t| {
-                t.as_nanos()
-                    .try_into()
-                    .expect("acquire_next_image_khr: timeout overflowed u64")
-            })
-            .unwrap_or(u64::MAX);
+        let timeout_ns = util::timeout_u64(timeout);
 
         let VulkanResult { raw, value } = unsafe {
             self.loader.acquire_next_image_khr(
@@ -1219,6 +2484,25 @@ impl Device {
         }
     }
 
+    /// Sets the HDR metadata of a swapchain.
+    ///
+    /// # Safety
+    ///
+    /// The caller must uphold the following invariants:
+    /// - `swapchain` must be a handle to a swapchain object associated with
+    ///   this logical device.
+    /// - The `VK_EXT_hdr_metadata` extension must be enabled on this device.
+    pub unsafe fn set_hdr_metadata_ext(
+        &self,
+        swapchain: &mut SwapchainKHR,
+        metadata: &vk::HdrMetadataEXTBuilder<'_>,
+    ) {
+        unsafe {
+            self.loader
+                .set_hdr_metadata_ext(&[*swapchain.handle_mut()], &[*metadata]);
+        }
+    }
+
     pub unsafe fn queue_present_khr(
         &self,
         queue: &mut Queue,
@@ -1325,6 +2609,40 @@ define_handle! {
 
 // ============================================================================
 
+define_handle! {
+    /// An opaque handle to a descriptor pool object.
+    pub struct DescriptorPool(vk::DescriptorPool);
+}
+
+// ============================================================================
+
+define_handle! {
+    /// An opaque handle to a descriptor set object.
+    pub struct DescriptorSet(vk::DescriptorSet);
+}
+
+define_delegated_builder! {
+    pub struct DescriptorSetAllocateInfoBuilder<'a> {
+        inner: vk::DescriptorSetAllocateInfoBuilder<'a>,
+    }
+
+    impl DescriptorSetAllocateInfoBuilder {
+        pub fn set_layouts(&'a [vk::DescriptorSetLayout]) -> Self;
+    }
+}
+
+impl<'a> DescriptorSetAllocateInfoBuilder<'a> {
+    pub fn descriptor_pool(mut self, descriptor_pool: &'a mut DescriptorPool) -> Self {
+        // Safety: descriptor_pool is externally synchronized via mutable reference
+        self.inner = self
+            .inner
+            .descriptor_pool(unsafe { *descriptor_pool.handle_mut() });
+        self
+    }
+}
+
+// ============================================================================
+
 define_handle! {
     /// An opaque handle to a pipeline layout object.
     pub struct PipelineLayout(vk::PipelineLayout);
@@ -1427,6 +2745,32 @@ impl<'a> GraphicsPipelineCreateInfoBuilder<'a> {
     }
 }
 
+define_delegated_builder! {
+    pub struct ComputePipelineCreateInfoBuilder<'a> {
+        inner: vk::ComputePipelineCreateInfoBuilder<'a>,
+    }
+
+    impl ComputePipelineCreateInfoBuilder {
+        pub fn flags(vk::PipelineCreateFlags) -> Self;
+        pub fn stage(vk::PipelineShaderStageCreateInfo) -> Self;
+        pub fn base_pipeline_index(i32) -> Self;
+    }
+}
+
+impl<'a> ComputePipelineCreateInfoBuilder<'a> {
+    pub fn layout(mut self, layout: &'a PipelineLayout) -> Self {
+        self.inner = self.inner.layout(unsafe { *layout.handle() });
+        self
+    }
+
+    pub fn base_pipeline_handle(mut self, base_pipeline_handle: &'a Pipeline) -> Self {
+        self.inner = self
+            .inner
+            .base_pipeline_handle(unsafe { *base_pipeline_handle.handle() });
+        self
+    }
+}
+
 // ============================================================================
 
 define_handle! {
@@ -1469,6 +2813,13 @@ define_handle! {
     pub struct Fence(vk::Fence);
 }
 
+// ============================================================================
+
+define_handle! {
+    /// An opaque handle to a query pool object.
+    pub struct QueryPool(vk::QueryPool);
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FenceStatus {
     Unsignaled,
@@ -1547,6 +2898,13 @@ define_handle! {
 
 // ============================================================================
 
+define_handle! {
+    /// An opaque handle to a Vulkan buffer object.
+    pub struct Buffer(vk::Buffer);
+}
+
+// ============================================================================
+
 define_handle! {
     /// An opaque handle to a Vulkan surface object.
     pub struct SurfaceKHR(vk::SurfaceKHR);
@@ -1574,7 +2932,7 @@ pub struct SwapchainCreateInfo<'surf, 'queues> {
     pub composite_alpha: vk::CompositeAlphaFlagBitsKHR,
     pub present_mode: vk::PresentModeKHR,
     pub clipped: bool,
-    pub old_swapchain: Option<SwapchainKHR>,
+    pub old_swapchain: Option<&'surf mut SwapchainKHR>,
 }
 
 pub struct AcquiredImage {